@@ -0,0 +1 @@
+ALTER TABLE board_settings ADD COLUMN context_budget_chars INTEGER NOT NULL DEFAULT 48000;
@@ -0,0 +1 @@
+ALTER TABLE cards ADD COLUMN opencode_url TEXT NOT NULL DEFAULT '';
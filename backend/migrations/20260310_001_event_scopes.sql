@@ -0,0 +1,14 @@
+ALTER TABLE events ADD COLUMN board_id TEXT;
+ALTER TABLE events ADD COLUMN card_id TEXT;
+ALTER TABLE events ADD COLUMN user_id TEXT;
+
+-- Boards without members are visible to every user; once members exist only
+-- they may access the board.
+CREATE TABLE IF NOT EXISTS board_members (
+    board_id TEXT NOT NULL REFERENCES boards(id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (board_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_board_members_user ON board_members(user_id);
@@ -0,0 +1,19 @@
+-- Every stage change, whichever code path made it, for run transcripts.
+CREATE TABLE IF NOT EXISTS card_stage_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    card_id TEXT NOT NULL REFERENCES cards(id) ON DELETE CASCADE,
+    from_stage TEXT NOT NULL,
+    to_stage TEXT NOT NULL,
+    ai_status TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_card_stage_history_card ON card_stage_history(card_id);
+
+CREATE TRIGGER IF NOT EXISTS trg_cards_stage_history
+AFTER UPDATE OF stage ON cards
+WHEN OLD.stage <> NEW.stage
+BEGIN
+    INSERT INTO card_stage_history (card_id, from_stage, to_stage, ai_status, created_at)
+    VALUES (NEW.id, OLD.stage, NEW.stage, NEW.ai_status, strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'));
+END;
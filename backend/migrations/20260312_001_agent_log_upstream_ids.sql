@@ -0,0 +1,6 @@
+-- OpenCode message/part ID a log row was derived from, so history backfills
+-- never duplicate rows the relay already wrote.
+ALTER TABLE agent_logs ADD COLUMN upstream_id TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_agent_logs_upstream
+    ON agent_logs(card_id, upstream_id) WHERE upstream_id IS NOT NULL;
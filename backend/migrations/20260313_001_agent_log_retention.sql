@@ -0,0 +1,17 @@
+-- Per-board agent log retention. 0 days keeps logs forever; archived rows are
+-- written to compressed files instead of being dropped.
+ALTER TABLE board_settings ADD COLUMN log_retention_days INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE board_settings ADD COLUMN log_done_summary_only INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE board_settings ADD COLUMN log_archive INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS agent_log_archives (
+    id TEXT PRIMARY KEY,
+    card_id TEXT NOT NULL REFERENCES cards(id) ON DELETE CASCADE,
+    path TEXT NOT NULL,
+    entry_count INTEGER NOT NULL,
+    first_at TEXT NOT NULL,
+    last_at TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_agent_log_archives_card ON agent_log_archives(card_id);
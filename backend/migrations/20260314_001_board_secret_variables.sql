@@ -0,0 +1,11 @@
+-- Secret board variables, encrypted with the app-wide key in app_secrets
+-- (board_secrets_key). Only names are ever returned through the API.
+CREATE TABLE IF NOT EXISTS board_secret_variables (
+    board_id TEXT NOT NULL REFERENCES boards(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    nonce BLOB NOT NULL,
+    ciphertext BLOB NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (board_id, name)
+);
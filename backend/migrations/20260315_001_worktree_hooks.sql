@@ -0,0 +1,3 @@
+-- Per-board steps run after a card worktree is created and before it is
+-- removed: {"setup": [...], "teardown": [...]}.
+ALTER TABLE board_settings ADD COLUMN worktree_hooks TEXT NOT NULL DEFAULT '{}';
@@ -0,0 +1,3 @@
+-- Set when a human sends the running agent a message; cleared once the
+-- agent has read it through the MCP tool.
+ALTER TABLE cards ADD COLUMN human_message_pending INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,33 @@
+-- One row per AI run on a card (plan, work, retry, resume), so retries and
+-- re-dispatches no longer overwrite the history of earlier attempts.
+CREATE TABLE IF NOT EXISTS card_runs (
+    id TEXT PRIMARY KEY,
+    card_id TEXT NOT NULL REFERENCES cards(id) ON DELETE CASCADE,
+    run_number INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    session_id TEXT NOT NULL DEFAULT '',
+    agent TEXT,
+    plan_path TEXT,
+    plan_version_id TEXT,
+    branch_name TEXT NOT NULL DEFAULT '',
+    worktree_path TEXT NOT NULL DEFAULT '',
+    base_commit TEXT NOT NULL DEFAULT '',
+    head_commit TEXT NOT NULL DEFAULT '',
+    started_at TEXT NOT NULL,
+    ended_at TEXT,
+    outcome TEXT NOT NULL DEFAULT 'running',
+    files_changed INTEGER NOT NULL DEFAULT 0,
+    additions INTEGER NOT NULL DEFAULT 0,
+    deletions INTEGER NOT NULL DEFAULT 0,
+    input_tokens INTEGER NOT NULL DEFAULT 0,
+    output_tokens INTEGER NOT NULL DEFAULT 0,
+    reasoning_tokens INTEGER NOT NULL DEFAULT 0,
+    cache_read_tokens INTEGER NOT NULL DEFAULT 0,
+    cache_write_tokens INTEGER NOT NULL DEFAULT 0,
+    cost REAL NOT NULL DEFAULT 0,
+    -- DiffResult JSON captured when the run ended.
+    diff TEXT,
+    UNIQUE(card_id, run_number)
+);
+
+CREATE INDEX IF NOT EXISTS idx_card_runs_card ON card_runs(card_id);
@@ -0,0 +1,30 @@
+-- Competing attempts: several independent sessions working on the same card,
+-- each in its own worktree and branch, until review selects a winner.
+CREATE TABLE IF NOT EXISTS card_attempts (
+    id TEXT PRIMARY KEY,
+    card_id TEXT NOT NULL REFERENCES cards(id) ON DELETE CASCADE,
+    attempt_number INTEGER NOT NULL,
+    agent TEXT,
+    -- `provider/model`, or NULL for the OpenCode default.
+    model TEXT,
+    -- queued | dispatched | working | completed | failed | cancelled | selected | discarded
+    status TEXT NOT NULL DEFAULT 'queued',
+    session_id TEXT,
+    opencode_url TEXT NOT NULL DEFAULT '',
+    branch_name TEXT NOT NULL DEFAULT '',
+    worktree_path TEXT NOT NULL DEFAULT '',
+    base_commit TEXT NOT NULL DEFAULT '',
+    files_changed INTEGER NOT NULL DEFAULT 0,
+    additions INTEGER NOT NULL DEFAULT 0,
+    deletions INTEGER NOT NULL DEFAULT 0,
+    -- Verification JSON from the board's `verify` hooks; empty when none ran.
+    verification TEXT NOT NULL DEFAULT '',
+    failure_reason TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    finished_at TEXT,
+    UNIQUE(card_id, attempt_number)
+);
+
+CREATE INDEX IF NOT EXISTS idx_card_attempts_card ON card_attempts(card_id);
+CREATE INDEX IF NOT EXISTS idx_card_attempts_session ON card_attempts(session_id);
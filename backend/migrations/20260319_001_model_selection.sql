@@ -0,0 +1,8 @@
+-- Model choice as `provider/model`; empty uses OpenCode's default. Boards
+-- keep one default per purpose, cards may override planning and
+-- implementation.
+ALTER TABLE board_settings ADD COLUMN planning_model TEXT NOT NULL DEFAULT '';
+ALTER TABLE board_settings ADD COLUMN implementation_model TEXT NOT NULL DEFAULT '';
+ALTER TABLE board_settings ADD COLUMN auto_detect_model TEXT NOT NULL DEFAULT '';
+ALTER TABLE cards ADD COLUMN planning_model TEXT;
+ALTER TABLE cards ADD COLUMN implementation_model TEXT;
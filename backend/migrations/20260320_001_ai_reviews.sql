@@ -0,0 +1,45 @@
+-- Automatic AI review when a card reaches Review. The reviewer runs in its
+-- own session with its own agent profile; its findings are kept apart from
+-- ordinary comments.
+ALTER TABLE board_settings ADD COLUMN ai_review_enabled INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE board_settings ADD COLUMN ai_review_agent TEXT NOT NULL DEFAULT 'oracle';
+ALTER TABLE board_settings ADD COLUMN review_model TEXT NOT NULL DEFAULT '';
+-- How many times the reviewer may send a card back before a human decides.
+ALTER TABLE board_settings ADD COLUMN ai_review_max_rounds INTEGER NOT NULL DEFAULT 2;
+
+CREATE TABLE IF NOT EXISTS ai_reviews (
+    id TEXT PRIMARY KEY,
+    card_id TEXT NOT NULL REFERENCES cards(id) ON DELETE CASCADE,
+    round INTEGER NOT NULL,
+    agent TEXT NOT NULL,
+    -- `provider/model`, or NULL for the OpenCode default.
+    model TEXT,
+    session_id TEXT,
+    opencode_url TEXT NOT NULL DEFAULT '',
+    -- running | approved | changes_requested | failed
+    status TEXT NOT NULL DEFAULT 'running',
+    summary TEXT NOT NULL DEFAULT '',
+    failure_reason TEXT,
+    created_at TEXT NOT NULL,
+    finished_at TEXT,
+    UNIQUE(card_id, round)
+);
+
+CREATE INDEX IF NOT EXISTS idx_ai_reviews_card ON ai_reviews(card_id);
+CREATE INDEX IF NOT EXISTS idx_ai_reviews_session ON ai_reviews(session_id);
+
+CREATE TABLE IF NOT EXISTS ai_review_findings (
+    id TEXT PRIMARY KEY,
+    review_id TEXT NOT NULL REFERENCES ai_reviews(id) ON DELETE CASCADE,
+    card_id TEXT NOT NULL REFERENCES cards(id) ON DELETE CASCADE,
+    file TEXT NOT NULL,
+    -- NULL when the finding is about the file as a whole.
+    line INTEGER,
+    -- info | warning | error
+    severity TEXT NOT NULL,
+    message TEXT NOT NULL,
+    position INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_ai_review_findings_review ON ai_review_findings(review_id);
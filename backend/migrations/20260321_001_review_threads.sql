@@ -0,0 +1,32 @@
+-- Review threads anchored to a line of the card's diff at a given commit.
+-- Unresolved threads are what a re-dispatch after review asks the agent to
+-- address.
+CREATE TABLE IF NOT EXISTS review_threads (
+    id TEXT PRIMARY KEY,
+    card_id TEXT NOT NULL REFERENCES cards(id) ON DELETE CASCADE,
+    -- NULL for feedback on the change as a whole.
+    file TEXT,
+    -- NULL for feedback on the whole file.
+    line INTEGER,
+    -- new | old: which side of the diff `line` counts in.
+    side TEXT NOT NULL DEFAULT 'new',
+    commit_sha TEXT NOT NULL DEFAULT '',
+    resolved INTEGER NOT NULL DEFAULT 0,
+    resolved_by TEXT,
+    resolved_at TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_review_threads_card ON review_threads(card_id);
+
+CREATE TABLE IF NOT EXISTS review_thread_comments (
+    id TEXT PRIMARY KEY,
+    thread_id TEXT NOT NULL REFERENCES review_threads(id) ON DELETE CASCADE,
+    author TEXT NOT NULL,
+    user_id TEXT,
+    content TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_review_thread_comments_thread ON review_thread_comments(thread_id);
@@ -0,0 +1,23 @@
+-- Approved cards waiting to land. Each repository drains its queue one card
+-- at a time: rebase onto the current base, verify, merge, clean up.
+CREATE TABLE IF NOT EXISTS merge_queue (
+    id TEXT PRIMARY KEY,
+    card_id TEXT NOT NULL REFERENCES cards(id) ON DELETE CASCADE,
+    board_id TEXT,
+    -- The repository the card merges into; the queue is per repository.
+    codebase_path TEXT NOT NULL,
+    -- queued | rebasing | verifying | merging | merged | failed | cancelled
+    status TEXT NOT NULL DEFAULT 'queued',
+    failure_reason TEXT,
+    -- JSON verification result from the board's `verify` hooks.
+    verification TEXT,
+    merge_commit TEXT,
+    enqueued_by TEXT,
+    created_at TEXT NOT NULL,
+    started_at TEXT,
+    finished_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_merge_queue_repo ON merge_queue(codebase_path, status);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_merge_queue_active_card ON merge_queue(card_id)
+    WHERE status IN ('queued', 'rebasing', 'verifying', 'merging');
@@ -0,0 +1,30 @@
+-- One row per template version; concurrent saves must not share a number.
+-- Templates that already saved a number twice get their versions renumbered
+-- in save order first, so the unique index can be built.
+CREATE TEMP TABLE prompt_template_renumbered AS
+SELECT id, ROW_NUMBER() OVER (PARTITION BY board_id, name ORDER BY version, created_at, id) AS version
+FROM prompt_template_versions
+WHERE (board_id, name) IN (
+    SELECT board_id, name FROM prompt_template_versions
+    GROUP BY board_id, name, version
+    HAVING COUNT(*) > 1
+);
+
+UPDATE prompt_template_versions
+SET version = (SELECT r.version FROM prompt_template_renumbered r WHERE r.id = prompt_template_versions.id)
+WHERE id IN (SELECT id FROM prompt_template_renumbered);
+
+UPDATE prompt_templates
+SET version = (
+    SELECT MAX(v.version) FROM prompt_template_versions v
+    WHERE v.board_id = prompt_templates.board_id AND v.name = prompt_templates.name
+)
+WHERE (board_id, name) IN (
+    SELECT v.board_id, v.name FROM prompt_template_versions v
+    WHERE v.id IN (SELECT id FROM prompt_template_renumbered)
+);
+
+DROP TABLE prompt_template_renumbered;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_prompt_template_versions_unique
+    ON prompt_template_versions(board_id, name, version);
@@ -0,0 +1,7 @@
+-- Messages whose usage has been added to a run. The relay and history
+-- backfills may both see a message; only the first one counts it.
+CREATE TABLE IF NOT EXISTS card_run_messages (
+    message_id TEXT PRIMARY KEY,
+    run_id TEXT NOT NULL REFERENCES card_runs(id) ON DELETE CASCADE,
+    created_at TEXT NOT NULL
+);
@@ -35,6 +35,11 @@ pub struct UpdateCardRequest {
     pub linked_documents: Option<String>,
     #[serde(default)]
     pub ai_agent: Option<String>,
+    /// `provider/model`; an empty string falls back to the board default.
+    #[serde(default)]
+    pub planning_model: Option<String>,
+    #[serde(default)]
+    pub implementation_model: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -62,6 +67,9 @@ pub struct CardResponse {
     pub updated_at: String,
     pub ai_agent: Option<String>,
     pub board_id: Option<String>,
+    pub human_message_pending: bool,
+    pub planning_model: Option<String>,
+    pub implementation_model: Option<String>,
     pub subtasks: Vec<Subtask>,
     pub labels: Vec<Label>,
     pub comments: Vec<Comment>,
@@ -92,6 +100,9 @@ impl CardResponse {
             updated_at: card.updated_at,
             ai_agent: card.ai_agent,
             board_id: card.board_id,
+            human_message_pending: card.human_message_pending,
+            planning_model: card.planning_model,
+            implementation_model: card.implementation_model,
             subtasks,
             labels,
             comments,
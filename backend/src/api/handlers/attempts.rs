@@ -0,0 +1,90 @@
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+
+use crate::api::dto::CardResponse;
+use crate::api::AppState;
+use crate::auth::middleware::AuthUser;
+use crate::domain::KanbanError;
+use crate::services::card_attempts::{AttemptSpec, CardAttempt};
+use crate::services::git_worktree::DiffResult;
+use crate::services::{BoardAccess, CardAttemptService, CardService, ModelSelection};
+
+#[derive(Debug, Deserialize)]
+pub struct CreateAttemptsRequest {
+    pub attempts: Vec<AttemptSpec>,
+}
+
+/// Queues competing attempts on the card, one per entry.
+pub async fn create_attempts(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(card_id): Path<String>,
+    Json(req): Json<CreateAttemptsRequest>,
+) -> Result<(StatusCode, Json<Vec<CardAttempt>>), KanbanError> {
+    let pool = state.require_db()?;
+    let card = CardService::get_card_model(pool, &card_id).await?;
+    if let Some(board_id) = card.board_id.as_deref().filter(|id| !id.is_empty()) {
+        BoardAccess::require_access(pool, &auth_user.user_id, board_id).await?;
+    }
+
+    let models: Vec<&str> = req
+        .attempts
+        .iter()
+        .filter_map(|spec| spec.model.as_deref())
+        .collect();
+    ModelSelection::validate(&state.http_client, state.opencode.primary(), &models).await?;
+
+    let attempts = CardAttemptService::create(pool, &state.sse_tx, &card, &req.attempts).await?;
+    Ok((StatusCode::CREATED, Json(attempts)))
+}
+
+/// Every attempt on the card with its diff stats and verification, for
+/// side-by-side comparison.
+pub async fn list_attempts(
+    State(state): State<AppState>,
+    Path(card_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<Vec<CardAttempt>>, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
+    CardService::get_card_model(pool, &card_id).await?;
+    Ok(Json(CardAttemptService::list(pool, &card_id).await?))
+}
+
+pub async fn get_attempt_diff(
+    State(state): State<AppState>,
+    Path((card_id, attempt_id)): Path<(String, String)>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<DiffResult>, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
+    Ok(Json(CardAttemptService::diff(pool, &card_id, &attempt_id).await?))
+}
+
+/// Keeps the winning attempt as the card's branch and removes the rest.
+pub async fn select_attempt(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path((card_id, attempt_id)): Path<(String, String)>,
+) -> Result<Json<CardResponse>, KanbanError> {
+    let pool = state.require_db()?;
+    let card = CardService::get_card_model(pool, &card_id).await?;
+    if let Some(board_id) = card.board_id.as_deref().filter(|id| !id.is_empty()) {
+        BoardAccess::require_access(pool, &auth_user.user_id, board_id).await?;
+    }
+
+    CardAttemptService::select(
+        pool,
+        &state.sse_tx,
+        &state.http_client,
+        &state.opencode,
+        &card,
+        &attempt_id,
+    )
+    .await?;
+    Ok(Json(CardService::get_card_by_id(pool, &card_id).await?))
+}
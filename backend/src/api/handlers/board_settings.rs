@@ -1,5 +1,5 @@
 use axum::{
-    extract::{Path, Query, State},
+    extract::{Extension, Path, Query, State},
     http::StatusCode,
     Json,
 };
@@ -8,7 +8,17 @@ use serde_json::json;
 
 use crate::api::handlers::sse::WsEvent;
 use crate::api::state::AppState;
+use crate::auth::middleware::AuthUser;
 use crate::domain::KanbanError;
+use crate::services::board_secrets::SecretVariable;
+use crate::services::context_assembler::DEFAULT_CONTEXT_BUDGET_CHARS;
+use crate::services::prompt_templates;
+use crate::services::worktree_hooks::WorktreeHooks;
+use crate::services::ai_dispatch::message_body;
+use crate::services::model_selection::ModelPurpose;
+use crate::services::{
+    BoardAccess, BoardSecretService, ModelSelection, PromptTemplateService, Redactor,
+};
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct BoardSettings {
@@ -26,6 +36,26 @@ pub struct BoardSettings {
     pub api_conventions: String,
     pub infrastructure: String,
     pub ai_concurrency: i64,
+    pub context_budget_chars: i64,
+    /// Agent logs older than this are removed (archived if `log_archive`); 0 keeps them.
+    pub log_retention_days: i64,
+    /// Done cards keep a single summary row instead of their full log.
+    pub log_done_summary_only: bool,
+    pub log_archive: bool,
+    /// JSON `{"setup": [...], "teardown": [...]}`; see [`WorktreeHooks`].
+    pub worktree_hooks: String,
+    /// `provider/model` defaults per purpose; empty uses OpenCode's default.
+    /// Cards can override planning and implementation.
+    pub planning_model: String,
+    pub implementation_model: String,
+    pub auto_detect_model: String,
+    /// Cards entering review get an automatic review by `ai_review_agent`,
+    /// which must not be the implementer's agent.
+    pub ai_review_enabled: bool,
+    pub ai_review_agent: String,
+    pub review_model: String,
+    /// Times the reviewer may send a card back before leaving it to a human.
+    pub ai_review_max_rounds: i64,
     pub auto_detect_status: String,
     pub auto_detect_session_id: String,
     pub auto_detect_started_at: String,
@@ -48,6 +78,18 @@ pub struct UpdateBoardSettingsRequest {
     pub api_conventions: Option<String>,
     pub infrastructure: Option<String>,
     pub ai_concurrency: Option<i64>,
+    pub context_budget_chars: Option<i64>,
+    pub log_retention_days: Option<i64>,
+    pub log_done_summary_only: Option<bool>,
+    pub log_archive: Option<bool>,
+    pub worktree_hooks: Option<String>,
+    pub planning_model: Option<String>,
+    pub implementation_model: Option<String>,
+    pub auto_detect_model: Option<String>,
+    pub ai_review_enabled: Option<bool>,
+    pub ai_review_agent: Option<String>,
+    pub review_model: Option<String>,
+    pub ai_review_max_rounds: Option<i64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -90,11 +132,13 @@ pub struct AutoDetectLogsQuery {
 pub async fn get_board_settings(
     State(state): State<AppState>,
     Path(board_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
 ) -> Result<Json<BoardSettings>, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_access(pool, &auth_user.user_id, &board_id).await?;
 
     let settings: Option<BoardSettings> = sqlx::query_as(
-        "SELECT board_id, codebase_path, github_repo, context_markdown, document_links, variables, tech_stack, communication_patterns, environments, code_conventions, testing_requirements, api_conventions, infrastructure, ai_concurrency, auto_detect_status, auto_detect_session_id, auto_detect_started_at, created_at, updated_at FROM board_settings WHERE board_id = ?",
+        "SELECT board_id, codebase_path, github_repo, context_markdown, document_links, variables, tech_stack, communication_patterns, environments, code_conventions, testing_requirements, api_conventions, infrastructure, ai_concurrency, context_budget_chars, log_retention_days, log_done_summary_only, log_archive, worktree_hooks, planning_model, implementation_model, auto_detect_model, ai_review_enabled, ai_review_agent, review_model, ai_review_max_rounds, auto_detect_status, auto_detect_session_id, auto_detect_started_at, created_at, updated_at FROM board_settings WHERE board_id = ?",
     )
     .bind(&board_id)
     .fetch_optional(pool)
@@ -115,6 +159,18 @@ pub async fn get_board_settings(
         api_conventions: String::new(),
         infrastructure: String::new(),
         ai_concurrency: 1,
+        context_budget_chars: DEFAULT_CONTEXT_BUDGET_CHARS,
+        log_retention_days: 0,
+        log_done_summary_only: false,
+        log_archive: false,
+        worktree_hooks: "{}".to_string(),
+        planning_model: String::new(),
+        implementation_model: String::new(),
+        auto_detect_model: String::new(),
+        ai_review_enabled: false,
+        ai_review_agent: "oracle".to_string(),
+        review_model: String::new(),
+        ai_review_max_rounds: 2,
         auto_detect_status: String::new(),
         auto_detect_session_id: String::new(),
         auto_detect_started_at: String::new(),
@@ -126,20 +182,57 @@ pub async fn get_board_settings(
 pub async fn update_board_settings(
     State(state): State<AppState>,
     Path(board_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
     Json(req): Json<UpdateBoardSettingsRequest>,
 ) -> Result<Json<BoardSettings>, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_access(pool, &auth_user.user_id, &board_id).await?;
     let now = chrono::Utc::now().to_rfc3339();
 
+    if req.context_budget_chars.is_some_and(|budget| budget < 0) {
+        return Err(KanbanError::BadRequest(
+            "context_budget_chars must not be negative".into(),
+        ));
+    }
+    if req.log_retention_days.is_some_and(|days| days < 0) {
+        return Err(KanbanError::BadRequest(
+            "log_retention_days must not be negative".into(),
+        ));
+    }
+    if let Some(hooks) = &req.worktree_hooks {
+        WorktreeHooks::parse(hooks)?;
+    }
+    if req.ai_review_agent.as_deref().is_some_and(|agent| agent.trim().is_empty()) {
+        return Err(KanbanError::BadRequest(
+            "ai_review_agent must not be empty".into(),
+        ));
+    }
+    if req.ai_review_max_rounds.is_some_and(|rounds| rounds < 0) {
+        return Err(KanbanError::BadRequest(
+            "ai_review_max_rounds must not be negative".into(),
+        ));
+    }
+    ModelSelection::validate(
+        &state.http_client,
+        state.opencode.primary(),
+        &[
+            req.planning_model.as_deref().unwrap_or_default(),
+            req.implementation_model.as_deref().unwrap_or_default(),
+            req.auto_detect_model.as_deref().unwrap_or_default(),
+            req.review_model.as_deref().unwrap_or_default(),
+        ],
+    )
+    .await?;
+
     // Fetch existing settings to merge with partial update
     let existing: Option<BoardSettings> = sqlx::query_as(
-        "SELECT board_id, codebase_path, github_repo, context_markdown, document_links, variables, tech_stack, communication_patterns, environments, code_conventions, testing_requirements, api_conventions, infrastructure, ai_concurrency, auto_detect_status, auto_detect_session_id, auto_detect_started_at, created_at, updated_at FROM board_settings WHERE board_id = ?",
+        "SELECT board_id, codebase_path, github_repo, context_markdown, document_links, variables, tech_stack, communication_patterns, environments, code_conventions, testing_requirements, api_conventions, infrastructure, ai_concurrency, context_budget_chars, log_retention_days, log_done_summary_only, log_archive, worktree_hooks, planning_model, implementation_model, auto_detect_model, ai_review_enabled, ai_review_agent, review_model, ai_review_max_rounds, auto_detect_status, auto_detect_session_id, auto_detect_started_at, created_at, updated_at FROM board_settings WHERE board_id = ?",
     )
     .bind(&board_id)
     .fetch_optional(pool)
     .await?;
 
-    let (cb, gr, cm, dl, va, ts, cp, en, cc, tr, ac, inf, aic) = match &existing {
+    let (cb, gr, cm, dl, va, ts, cp, en, cc, tr, ac, inf, aic, cbc, lrd, lds, la, wh, pm, im, adm, are, ara, rm, amr) = match &existing {
         Some(e) => (
             req.codebase_path.unwrap_or_else(|| e.codebase_path.clone()),
             req.github_repo.unwrap_or_else(|| e.github_repo.clone()),
@@ -154,6 +247,18 @@ pub async fn update_board_settings(
             req.api_conventions.unwrap_or_else(|| e.api_conventions.clone()),
             req.infrastructure.unwrap_or_else(|| e.infrastructure.clone()),
             req.ai_concurrency.unwrap_or(e.ai_concurrency),
+            req.context_budget_chars.unwrap_or(e.context_budget_chars),
+            req.log_retention_days.unwrap_or(e.log_retention_days),
+            req.log_done_summary_only.unwrap_or(e.log_done_summary_only),
+            req.log_archive.unwrap_or(e.log_archive),
+            req.worktree_hooks.unwrap_or_else(|| e.worktree_hooks.clone()),
+            req.planning_model.unwrap_or_else(|| e.planning_model.clone()),
+            req.implementation_model.unwrap_or_else(|| e.implementation_model.clone()),
+            req.auto_detect_model.unwrap_or_else(|| e.auto_detect_model.clone()),
+            req.ai_review_enabled.unwrap_or(e.ai_review_enabled),
+            req.ai_review_agent.unwrap_or_else(|| e.ai_review_agent.clone()),
+            req.review_model.unwrap_or_else(|| e.review_model.clone()),
+            req.ai_review_max_rounds.unwrap_or(e.ai_review_max_rounds),
         ),
         None => (
             req.codebase_path.unwrap_or_default(),
@@ -169,12 +274,25 @@ pub async fn update_board_settings(
             req.api_conventions.unwrap_or_default(),
             req.infrastructure.unwrap_or_default(),
             req.ai_concurrency.unwrap_or(1),
+            req.context_budget_chars
+                .unwrap_or(DEFAULT_CONTEXT_BUDGET_CHARS),
+            req.log_retention_days.unwrap_or(0),
+            req.log_done_summary_only.unwrap_or(false),
+            req.log_archive.unwrap_or(false),
+            req.worktree_hooks.unwrap_or_else(|| "{}".to_string()),
+            req.planning_model.unwrap_or_default(),
+            req.implementation_model.unwrap_or_default(),
+            req.auto_detect_model.unwrap_or_default(),
+            req.ai_review_enabled.unwrap_or(false),
+            req.ai_review_agent.unwrap_or_else(|| "oracle".to_string()),
+            req.review_model.unwrap_or_default(),
+            req.ai_review_max_rounds.unwrap_or(2),
         ),
     };
 
     let settings: BoardSettings = sqlx::query_as(
-        "INSERT INTO board_settings (board_id, codebase_path, github_repo, context_markdown, document_links, variables, tech_stack, communication_patterns, environments, code_conventions, testing_requirements, api_conventions, infrastructure, ai_concurrency, created_at, updated_at)
-         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "INSERT INTO board_settings (board_id, codebase_path, github_repo, context_markdown, document_links, variables, tech_stack, communication_patterns, environments, code_conventions, testing_requirements, api_conventions, infrastructure, ai_concurrency, context_budget_chars, log_retention_days, log_done_summary_only, log_archive, worktree_hooks, planning_model, implementation_model, auto_detect_model, ai_review_enabled, ai_review_agent, review_model, ai_review_max_rounds, created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
          ON CONFLICT(board_id) DO UPDATE SET
              codebase_path = excluded.codebase_path,
              github_repo = excluded.github_repo,
@@ -189,8 +307,20 @@ pub async fn update_board_settings(
              api_conventions = excluded.api_conventions,
               infrastructure = excluded.infrastructure,
               ai_concurrency = excluded.ai_concurrency,
+              context_budget_chars = excluded.context_budget_chars,
+              log_retention_days = excluded.log_retention_days,
+              log_done_summary_only = excluded.log_done_summary_only,
+              log_archive = excluded.log_archive,
+              worktree_hooks = excluded.worktree_hooks,
+              planning_model = excluded.planning_model,
+              implementation_model = excluded.implementation_model,
+              auto_detect_model = excluded.auto_detect_model,
+              ai_review_enabled = excluded.ai_review_enabled,
+              ai_review_agent = excluded.ai_review_agent,
+              review_model = excluded.review_model,
+              ai_review_max_rounds = excluded.ai_review_max_rounds,
               updated_at = excluded.updated_at
-         RETURNING board_id, codebase_path, github_repo, context_markdown, document_links, variables, tech_stack, communication_patterns, environments, code_conventions, testing_requirements, api_conventions, infrastructure, ai_concurrency, auto_detect_status, auto_detect_session_id, auto_detect_started_at, created_at, updated_at",
+         RETURNING board_id, codebase_path, github_repo, context_markdown, document_links, variables, tech_stack, communication_patterns, environments, code_conventions, testing_requirements, api_conventions, infrastructure, ai_concurrency, context_budget_chars, log_retention_days, log_done_summary_only, log_archive, worktree_hooks, planning_model, implementation_model, auto_detect_model, ai_review_enabled, ai_review_agent, review_model, ai_review_max_rounds, auto_detect_status, auto_detect_session_id, auto_detect_started_at, created_at, updated_at",
     )
     .bind(&board_id)
     .bind(&cb)
@@ -206,20 +336,76 @@ pub async fn update_board_settings(
     .bind(&ac)
     .bind(&inf)
     .bind(aic)
+    .bind(cbc)
+    .bind(lrd)
+    .bind(lds)
+    .bind(la)
+    .bind(&wh)
+    .bind(pm.trim())
+    .bind(im.trim())
+    .bind(adm.trim())
+    .bind(are)
+    .bind(ara.trim())
+    .bind(rm.trim())
+    .bind(amr)
     .bind(&now)
     .bind(&now)
     .fetch_one(pool)
     .await?;
+    state.redactors.invalidate(&board_id);
 
     Ok(Json(settings))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct SetSecretVariableRequest {
+    pub value: String,
+}
+
+pub async fn list_secret_variables(
+    State(state): State<AppState>,
+    Path(board_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<Vec<SecretVariable>>, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_access(pool, &auth_user.user_id, &board_id).await?;
+    Ok(Json(BoardSecretService::list(pool, &board_id).await?))
+}
+
+/// Write-only: the response echoes the name with a masked value.
+pub async fn set_secret_variable(
+    State(state): State<AppState>,
+    Path((board_id, name)): Path<(String, String)>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(req): Json<SetSecretVariableRequest>,
+) -> Result<Json<SecretVariable>, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_access(pool, &auth_user.user_id, &board_id).await?;
+    let secret = BoardSecretService::set(pool, &board_id, &name, &req.value).await?;
+    state.redactors.invalidate(&board_id);
+    Ok(Json(secret))
+}
+
+pub async fn delete_secret_variable(
+    State(state): State<AppState>,
+    Path((board_id, name)): Path<(String, String)>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<StatusCode, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_access(pool, &auth_user.user_id, &board_id).await?;
+    BoardSecretService::delete(pool, &board_id, &name).await?;
+    state.redactors.invalidate(&board_id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
 pub async fn auto_detect_board_settings(
     State(state): State<AppState>,
     Path(board_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
     Json(req): Json<AutoDetectBoardSettingsRequest>,
 ) -> Result<(StatusCode, Json<AutoDetectBoardSettingsResponse>), KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_access(pool, &auth_user.user_id, &board_id).await?;
     let codebase_path = req.codebase_path;
 
     if !std::path::Path::new(&codebase_path).exists() {
@@ -229,16 +415,25 @@ pub async fn auto_detect_board_settings(
         )));
     }
 
+    let prompt = PromptTemplateService::render_for_board(
+        pool,
+        prompt_templates::AUTO_DETECT,
+        &board_id,
+        &codebase_path,
+    )
+    .await?;
+    let model = ModelSelection::for_board(pool, &board_id, ModelPurpose::AutoDetect).await?;
+
     let _ = state
         .http_client
-        .get(format!("{}/health", state.config.opencode_url))
+        .get(format!("{}/health", state.opencode.primary()))
         .timeout(std::time::Duration::from_secs(10))
         .send()
         .await;
 
     let session_response = state
         .http_client
-        .post(format!("{}/session", state.config.opencode_url))
+        .post(format!("{}/session", state.opencode.primary()))
         .json(&json!({}))
         .send()
         .await
@@ -295,35 +490,8 @@ pub async fn auto_detect_board_settings(
         let _ = state.sse_tx.send(payload);
     }
 
-    let prompt = format!(
-        r#"You are a codebase analysis assistant. Analyze the codebase at "{codebase_path}" and fill in the board settings.
-
-Board ID: {board_id}
-
-## Instructions
-1. Explore the codebase directory structure, config files, package manifests, and source code
-2. Identify: tech stack, frameworks, languages, versions
-3. Identify: communication patterns (REST, gRPC, WebSocket, message queues)
-4. Identify: environments (dev, staging, production) from config files
-5. Identify: code conventions (linter configs, formatting, naming patterns)
-6. Identify: testing frameworks and requirements
-7. Identify: API conventions (REST style, auth patterns, error formats)
-8. Identify: infrastructure (Docker, K8s, CI/CD, cloud provider)
-9. Use the `kanban_update_board_settings` MCP tool to save ALL findings to board_id "{board_id}"
-10. Set each field with a clear, structured summary (use bullet points, be specific with version numbers)
-
-## SAFETY RULES
-- ONLY use kanban MCP tools to save results
-- Do NOT modify any files in the codebase
-- Do NOT run any build or test commands
-- Do NOT access databases directly
-- READ ONLY -- analyze files, do not change them"#,
-        codebase_path = codebase_path,
-        board_id = board_id,
-    );
-
     let http_client = state.http_client.clone();
-    let message_url = format!("{}/session/{}/message", state.config.opencode_url, &session_id);
+    let message_url = format!("{}/session/{}/message", state.opencode.primary(), &session_id);
     let db_clone = pool.clone();
     let board_id_clone = board_id.clone();
     let sse_tx_clone = state.sse_tx.clone();
@@ -332,7 +500,7 @@ Board ID: {board_id}
     tokio::spawn(async move {
         let result = http_client
             .post(&message_url)
-            .json(&json!({"parts": [{"type": "text", "text": prompt}]}))
+            .json(&message_body(&prompt, model.as_deref()))
             .send()
             .await;
 
@@ -396,9 +564,11 @@ Board ID: {board_id}
 pub async fn clone_repo(
     State(state): State<AppState>,
     Path(board_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
     Json(req): Json<CloneRepoRequest>,
 ) -> Result<Json<CloneRepoResponse>, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_access(pool, &auth_user.user_id, &board_id).await?;
 
     let parent = std::path::Path::new(&req.clone_path)
         .parent()
@@ -417,9 +587,12 @@ pub async fn clone_repo(
         req.github_url.clone()
     };
 
-    let output = std::process::Command::new("git")
+    // Run off the worker threads; a dropped request kills the clone.
+    let output = tokio::process::Command::new("git")
         .args(["clone", &clone_url, &req.clone_path])
+        .kill_on_drop(true)
         .output()
+        .await
         .map_err(|e| KanbanError::Internal(format!("Failed to run git clone: {}", e)))?;
 
     if output.status.success() {
@@ -442,7 +615,12 @@ pub async fn clone_repo(
             error: None,
         }))
     } else {
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        // git echoes the remote URL, which carries the PAT.
+        let mut redactor = Redactor::for_board(pool, Some(&board_id)).await?;
+        if let Some(pat) = &req.pat {
+            redactor = redactor.with_secret(pat, "PAT");
+        }
+        let stderr = redactor.redact(&String::from_utf8_lossy(&output.stderr));
         let error = if stderr.contains("Authentication")
             || stderr.contains("403")
             || stderr.contains("fatal: could not read Username")
@@ -462,8 +640,10 @@ pub async fn clone_repo(
 pub async fn get_auto_detect_status(
     State(state): State<AppState>,
     Path(board_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
 ) -> Result<Json<AutoDetectStatusResponse>, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_access(pool, &auth_user.user_id, &board_id).await?;
 
     let result: Option<(String, String, String)> = sqlx::query_as(
         "SELECT auto_detect_status, auto_detect_session_id, auto_detect_started_at FROM board_settings WHERE board_id = ?",
@@ -483,10 +663,13 @@ pub async fn get_auto_detect_status(
 
 pub async fn get_auto_detect_logs(
     State(state): State<AppState>,
-    Path(_board_id): Path<String>,
+    Path(board_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
     Query(query): Query<AutoDetectLogsQuery>,
 ) -> Result<Json<serde_json::Value>, KanbanError> {
-    let url = format!("{}/session/{}", state.config.opencode_url, query.session_id);
+    let pool = state.require_db()?;
+    BoardAccess::require_access(pool, &auth_user.user_id, &board_id).await?;
+    let url = format!("{}/session/{}", state.opencode.primary(), query.session_id);
 
     let response = state
         .http_client
@@ -1,14 +1,18 @@
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
-    Json,
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
 };
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::api::handlers::sse::WsEvent;
 use crate::api::state::AppState;
+use crate::auth::middleware::AuthUser;
 use crate::domain::KanbanError;
+use crate::services::board_access::BoardMember;
+use crate::services::{BoardAccess, LogRetentionService};
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Board {
@@ -34,8 +38,18 @@ pub struct ReorderBoardRequest {
     pub position: i64,
 }
 
-pub async fn list_boards(State(state): State<AppState>) -> Result<Json<Vec<Board>>, KanbanError> {
+#[derive(Debug, Deserialize)]
+pub struct SetBoardMembersRequest {
+    /// An empty list opens the board to every user.
+    pub user_ids: Vec<String>,
+}
+
+pub async fn list_boards(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<Vec<Board>>, KanbanError> {
     let db = state.require_db()?;
+    let denied = BoardAccess::denied_boards(db, &auth_user.user_id).await?;
 
     let boards: Vec<Board> = sqlx::query_as(
         "SELECT id, name, position, created_at, updated_at FROM boards ORDER BY position ASC"
@@ -43,7 +57,12 @@ pub async fn list_boards(State(state): State<AppState>) -> Result<Json<Vec<Board
     .fetch_all(db)
     .await?;
 
-    Ok(Json(boards))
+    Ok(Json(
+        boards
+            .into_iter()
+            .filter(|board| !denied.contains(&board.id))
+            .collect(),
+    ))
 }
 
 pub async fn create_board(
@@ -83,9 +102,11 @@ pub async fn create_board(
 pub async fn update_board(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
     Json(req): Json<UpdateBoardRequest>,
 ) -> Result<Json<Board>, KanbanError> {
     let db = state.require_db()?;
+    BoardAccess::require_access(db, &auth_user.user_id, &id).await?;
     let now = chrono::Utc::now().to_rfc3339();
 
     let board: Board = sqlx::query_as(
@@ -110,8 +131,10 @@ pub async fn update_board(
 pub async fn delete_board(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
 ) -> Result<StatusCode, KanbanError> {
     let db = state.require_db()?;
+    BoardAccess::require_access(db, &auth_user.user_id, &id).await?;
 
     let result = sqlx::query("DELETE FROM boards WHERE id = ?")
         .bind(&id)
@@ -121,6 +144,7 @@ pub async fn delete_board(
     if result.rows_affected() == 0 {
         return Err(KanbanError::NotFound(format!("Board {} not found", id)));
     }
+    state.redactors.invalidate(&id);
 
     let event = WsEvent::BoardDeleted {
         board_id: id.clone(),
@@ -135,9 +159,11 @@ pub async fn delete_board(
 pub async fn reorder_board(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
     Json(req): Json<ReorderBoardRequest>,
 ) -> Result<Json<Board>, KanbanError> {
     let db = state.require_db()?;
+    BoardAccess::require_access(db, &auth_user.user_id, &id).await?;
     let now = chrono::Utc::now().to_rfc3339();
 
     let board: Board = sqlx::query_as(
@@ -158,3 +184,74 @@ pub async fn reorder_board(
 
     Ok(Json(board))
 }
+
+pub async fn list_board_members(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<Vec<BoardMember>>, KanbanError> {
+    let db = state.require_db()?;
+    BoardAccess::require_access(db, &auth_user.user_id, &id).await?;
+
+    Ok(Json(BoardAccess::list_members(db, &id).await?))
+}
+
+pub async fn set_board_members(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(req): Json<SetBoardMembersRequest>,
+) -> Result<Json<Vec<BoardMember>>, KanbanError> {
+    let db = state.require_db()?;
+    BoardAccess::require_access(db, &auth_user.user_id, &id).await?;
+    // Boards have no owner, so whoever restricts one has to stay on it.
+    if !req.user_ids.is_empty() && !req.user_ids.contains(&auth_user.user_id) {
+        return Err(KanbanError::BadRequest(
+            "A restricted board must keep you as a member".into(),
+        ));
+    }
+
+    let members = BoardAccess::set_members(db, &id, &req.user_ids).await?;
+
+    let event = WsEvent::BoardMembersChanged { board_id: id };
+    if let Ok(payload) = serde_json::to_string(&event) {
+        let _ = state.sse_tx.send(payload);
+    }
+
+    Ok(Json(members))
+}
+
+/// Every agent log on the board, archived rows included, as one JSONL
+/// download; take this before enabling a retention policy.
+pub async fn export_board_logs(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<impl IntoResponse, KanbanError> {
+    let db = state.require_db()?;
+    BoardAccess::require_access(db, &auth_user.user_id, &id).await?;
+
+    let card_ids: Vec<String> =
+        sqlx::query_scalar("SELECT id FROM cards WHERE board_id = ? ORDER BY created_at ASC")
+            .bind(&id)
+            .fetch_all(db)
+            .await?;
+
+    let mut body = String::new();
+    for card_id in card_ids {
+        let logs = LogRetentionService::load_logs(db, &card_id).await?;
+        body.push_str(&LogRetentionService::to_jsonl(&logs));
+    }
+
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"board-{}-logs.jsonl\"", id),
+            ),
+        ],
+        body,
+    ))
+}
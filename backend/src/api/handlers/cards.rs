@@ -1,41 +1,40 @@
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
-    Json,
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use sqlx::{Row, SqlitePool};
+use sqlx::SqlitePool;
 use std::collections::HashSet;
-use std::path::Path as FsPath;
 use std::sync::{Arc, Mutex};
 
 use crate::api::dto::{BoardResponse, CardResponse, CreateCardRequest, MoveCardRequest, UpdateCardRequest};
 use crate::api::handlers::sse::WsEvent;
 use crate::api::AppState;
-use crate::domain::{AgentLog, Card, CardVersion, Comment, KanbanError, NotificationType, SessionMapping, Stage};
-use crate::services::git_worktree::{ConflictDetail, DiffResult, MergeResult, ResolveRequest};
-use crate::services::{AiDispatchService, CardService, GitWorktreeService, NotificationService, SessionMappingService};
+use crate::auth::middleware::AuthUser;
+use crate::domain::{AgentLog, Card, CardVersion, KanbanError, NotificationType, SessionMapping, Stage};
+use crate::services::ai_dispatch::message_body;
+use crate::services::git_worktree::{
+    ConflictDetail, DiffResult, FileSelection, MergeResult, PartialMergeResult, ResolveRequest,
+};
+use crate::services::model_selection::ModelPurpose;
+use crate::services::prompt_templates;
+use crate::services::review_threads::{CreateReviewThreadRequest, ReviewThread};
+use crate::services::{
+    AiDispatchService, BoardAccess, CardAttemptService, CardRunService, CardService,
+    ConflictResolutionService, GitWorktreeService, LogBackfillService, LogRetentionService,
+    MergeQueueService, ModelSelection, NotificationService, PromptTemplateService, Redactor,
+    ReviewThreadService, SessionMappingService, TranscriptService, WorktreeHookService,
+};
+use crate::services::log_backfill::BackfillReport;
 
 #[derive(Debug, Deserialize)]
 pub struct BoardQuery {
     pub board_id: Option<String>,
 }
 
-#[derive(sqlx::FromRow)]
-struct BoardContextRow {
-    codebase_path: String,
-    context_markdown: String,
-    tech_stack: String,
-    communication_patterns: String,
-    environments: String,
-    code_conventions: String,
-    testing_requirements: String,
-    api_conventions: String,
-    infrastructure: String,
-    github_repo: String,
-}
-
 async fn get_card_codebase_path(pool: &SqlitePool, card_id: &str) -> Result<String, KanbanError> {
     let board_id = sqlx::query_scalar::<_, String>("SELECT board_id FROM cards WHERE id = ?")
         .bind(card_id)
@@ -116,6 +115,16 @@ impl Drop for MergeLockGuard {
     }
 }
 
+/// Manual merges would race the queue's update-verify-merge of the card.
+async fn ensure_not_queued(pool: &SqlitePool, card_id: &str) -> Result<(), KanbanError> {
+    if MergeQueueService::active_for_card(pool, card_id).await?.is_some() {
+        return Err(KanbanError::Conflict(
+            "Card is in the merge queue; cancel it before merging manually".into(),
+        ));
+    }
+    Ok(())
+}
+
 fn release_merge_lock(state: &AppState, codebase_path: &str) -> Result<(), KanbanError> {
     let mut locks = state
         .merge_locks
@@ -127,9 +136,12 @@ fn release_merge_lock(state: &AppState, codebase_path: &str) -> Result<(), Kanba
 
 pub async fn create_card(
     State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
     Json(req): Json<CreateCardRequest>,
 ) -> Result<(StatusCode, Json<CardResponse>), KanbanError> {
     let pool = state.require_db()?;
+    let board_id = req.board_id.as_deref().unwrap_or("default");
+    BoardAccess::require_access(pool, &auth_user.user_id, board_id).await?;
     let card = CardService::create_card(pool, req).await?;
 
     let event = WsEvent::CardCreated {
@@ -144,18 +156,39 @@ pub async fn create_card(
 
 pub async fn get_board(
     State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
     Query(query): Query<BoardQuery>,
 ) -> Result<Json<BoardResponse>, KanbanError> {
     let pool = state.require_db()?;
-    let board = CardService::get_board(pool, query.board_id.as_deref()).await?;
+    if let Some(board_id) = &query.board_id {
+        BoardAccess::require_access(pool, &auth_user.user_id, board_id).await?;
+    }
+    let mut board = CardService::get_board(pool, query.board_id.as_deref()).await?;
+
+    // Without a board filter every card is listed; drop restricted boards.
+    let denied = BoardAccess::denied_boards(pool, &auth_user.user_id).await?;
+    if !denied.is_empty() {
+        for column in [
+            &mut board.backlog,
+            &mut board.plan,
+            &mut board.todo,
+            &mut board.in_progress,
+            &mut board.review,
+            &mut board.done,
+        ] {
+            column.retain(|card| card.board_id.as_ref().is_none_or(|id| !denied.contains(id)));
+        }
+    }
     Ok(Json(board))
 }
 
 pub async fn get_card(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
 ) -> Result<Json<CardResponse>, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &id).await?;
     let card = CardService::get_card_by_id(pool, &id).await?;
     Ok(Json(card))
 }
@@ -163,18 +196,64 @@ pub async fn get_card(
 pub async fn get_card_logs(
     State(state): State<AppState>,
     Path(card_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
 ) -> Result<Json<Vec<AgentLog>>, KanbanError> {
     let pool = state.require_db()?;
-    let logs: Vec<AgentLog> = sqlx::query_as(
-        "SELECT * FROM agent_logs WHERE card_id = ? ORDER BY created_at ASC",
-    )
-    .bind(&card_id)
-    .fetch_all(pool)
-    .await
-    .map_err(|e| KanbanError::Internal(e.to_string()))?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
+    let logs = LogRetentionService::load_logs(pool, &card_id).await?;
     Ok(Json(logs))
 }
 
+/// The card's full agent log, archived rows included, as a JSONL download.
+pub async fn export_card_logs(
+    State(state): State<AppState>,
+    Path(card_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<impl IntoResponse, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
+    CardService::get_card_model(pool, &card_id).await?;
+    let logs = LogRetentionService::load_logs(pool, &card_id).await?;
+
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"card-{}-logs.jsonl\"", card_id),
+            ),
+        ],
+        LogRetentionService::to_jsonl(&logs),
+    ))
+}
+
+/// Recovers log entries the relay missed (e.g. across a backend restart)
+/// from the OpenCode session history of the card and its sub-agents.
+pub async fn backfill_card_logs(
+    State(state): State<AppState>,
+    Path(card_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<BackfillReport>, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
+    let card = CardService::get_card_model(pool, &card_id).await?;
+    if card.ai_session_id.as_deref().is_none_or(str::is_empty) {
+        return Err(KanbanError::BadRequest("Card has no AI session".into()));
+    }
+
+    let opencode_url = state.opencode.endpoint_for_card(&card);
+    let report = LogBackfillService::backfill_card(
+        pool,
+        &state.http_client,
+        &state.sse_tx,
+        &opencode_url,
+        &card,
+    )
+    .await?;
+    Ok(Json(report))
+}
+
 #[derive(Serialize)]
 pub struct AgentActivityEntry {
     pub agent_type: Option<String>,
@@ -193,8 +272,10 @@ pub struct AgentActivityResponse {
 pub async fn get_agent_activity(
     State(state): State<AppState>,
     Path(card_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
 ) -> Result<Json<AgentActivityResponse>, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
 
     let agents: Vec<AgentActivityEntry> = sqlx::query_as::<_, (Option<String>, i64, String, String)>(
         "SELECT agent, COUNT(*) as event_count, MIN(created_at) as first_seen, MAX(created_at) as last_seen FROM agent_logs WHERE card_id = ? GROUP BY agent ORDER BY first_seen ASC",
@@ -223,11 +304,64 @@ pub async fn get_agent_activity(
     }))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct TranscriptQuery {
+    /// `markdown` (default) or `jsonl`.
+    pub format: Option<String>,
+}
+
+/// The whole AI run as a readable document: parent and sub-agent sessions,
+/// questions and answers, comments and stage changes, in time order.
+pub async fn export_card_transcript(
+    State(state): State<AppState>,
+    Path(card_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<TranscriptQuery>,
+) -> Result<impl IntoResponse, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
+    let card = CardService::get_card_model(pool, &card_id).await?;
+
+    let entries = TranscriptService::entries(pool, &card_id).await?;
+    let (body, content_type, extension) = match query.format.as_deref().unwrap_or("markdown") {
+        "markdown" | "md" => (
+            TranscriptService::to_markdown(&card, &entries),
+            "text/markdown; charset=utf-8",
+            "md",
+        ),
+        "jsonl" => (
+            TranscriptService::to_jsonl(&entries),
+            "application/x-ndjson",
+            "jsonl",
+        ),
+        other => {
+            return Err(KanbanError::BadRequest(format!(
+                "Unsupported transcript format '{}': use markdown or jsonl",
+                other
+            )));
+        }
+    };
+
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"card-{}-transcript.{}\"", card_id, extension),
+            ),
+        ],
+        body,
+    ))
+}
+
 pub async fn list_card_versions(
     State(state): State<AppState>,
     Path(card_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
 ) -> Result<Json<Vec<CardVersion>>, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
     let versions: Vec<CardVersion> = sqlx::query_as(
         "SELECT * FROM card_versions WHERE card_id = ? ORDER BY created_at DESC LIMIT 50",
     )
@@ -241,8 +375,10 @@ pub async fn list_card_versions(
 pub async fn restore_card_version(
     State(state): State<AppState>,
     Path((card_id, version_id)): Path<(String, String)>,
+    Extension(auth_user): Extension<AuthUser>,
 ) -> Result<Json<CardResponse>, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
     let version: CardVersion =
         sqlx::query_as("SELECT * FROM card_versions WHERE id = ? AND card_id = ?")
             .bind(&version_id)
@@ -325,9 +461,20 @@ pub async fn restore_card_version(
 pub async fn update_card(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
     Json(req): Json<UpdateCardRequest>,
 ) -> Result<Json<CardResponse>, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &id).await?;
+    ModelSelection::validate(
+        &state.http_client,
+        state.opencode.primary(),
+        &[
+            req.planning_model.as_deref().unwrap_or_default(),
+            req.implementation_model.as_deref().unwrap_or_default(),
+        ],
+    )
+    .await?;
     let card = CardService::update_card(pool, &id, req).await?;
 
     let event = WsEvent::CardUpdated {
@@ -343,9 +490,11 @@ pub async fn update_card(
 pub async fn move_card(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
     Json(req): Json<MoveCardRequest>,
 ) -> Result<Json<CardResponse>, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &id).await?;
     let previous_card = CardService::get_card_model(pool, &id).await?;
 
     let target_stage: Stage = req.stage.parse()
@@ -376,11 +525,13 @@ pub async fn move_card(
             .fetch_one(pool)
             .await
             {
-                let _ = GitWorktreeService::remove_worktree(
+                let _ = WorktreeHookService::remove_worktree(
+                    pool,
+                    &state.sse_tx,
+                    &previous_card,
                     &codebase_path,
-                    &previous_card.worktree_path,
-                    &previous_card.branch_name,
-                );
+                )
+                .await;
 
                 if let Err(e) = sqlx::query(
                     "UPDATE cards SET branch_name = '', worktree_path = '', working_directory = '.', updated_at = ? WHERE id = ?",
@@ -499,8 +650,10 @@ pub async fn move_card(
 pub async fn generate_plan(
     State(state): State<AppState>,
     Path(card_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
 ) -> Result<Json<CardResponse>, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
     let card = CardService::get_card_model(pool, &card_id).await?;
 
     if card.stage != "plan" {
@@ -509,60 +662,24 @@ pub async fn generate_plan(
         ));
     }
 
-    let subtasks = CardService::get_subtasks(pool, &card_id).await?;
-    let subtask_titles = if subtasks.is_empty() {
-        "None".to_string()
-    } else {
-        subtasks
-            .iter()
-            .map(|s| format!("- {}", s.title))
-            .collect::<Vec<_>>()
-            .join("\n")
-    };
+    let prompt =
+        PromptTemplateService::render_for_card(pool, prompt_templates::PLANNING, &card, &[])
+            .await?;
+    let model = ModelSelection::for_card(pool, &card, ModelPurpose::Planning).await?;
 
-    let linked_docs_formatted = serde_json::from_str::<Vec<String>>(&card.linked_documents)
-        .ok()
-        .filter(|docs| !docs.is_empty())
-        .map(|docs| {
-            docs.iter()
-                .map(|doc| format!("- {}", doc))
-                .collect::<Vec<_>>()
-                .join("\n")
-        })
-        .unwrap_or_else(|| "None".to_string());
-
-    let attached_files_formatted = match sqlx::query(
-        "SELECT original_filename, file_size, mime_type FROM card_files WHERE card_id = ? ORDER BY uploaded_at ASC",
-    )
-    .bind(&card_id)
-    .fetch_all(pool)
-    .await
-    {
-        Ok(rows) if rows.is_empty() => "None".to_string(),
-        Ok(rows) => rows
-            .iter()
-            .map(|row| {
-                let name: String = row.get("original_filename");
-                let size: i64 = row.get("file_size");
-                let mime: String = row.get("mime_type");
-                format!("- {} ({} bytes, {})", name, size, mime)
-            })
-            .collect::<Vec<_>>()
-            .join("\n"),
-        Err(_) => "None".to_string(),
-    };
+    let opencode_url = state.opencode.select_least_loaded(pool).await?;
 
     // Wake up opencode server (it may be sleeping)
     let _ = state
         .http_client
-        .get(format!("{}/health", state.config.opencode_url))
+        .get(format!("{}/health", opencode_url))
         .timeout(std::time::Duration::from_secs(10))
         .send()
         .await;
 
     let session_response = state
         .http_client
-        .post(format!("{}/session", state.config.opencode_url))
+        .post(format!("{}/session", opencode_url))
         .json(&json!({}))
         .send()
         .await
@@ -589,13 +706,15 @@ pub async fn generate_plan(
         .ok_or_else(|| KanbanError::OpenCodeError("OpenCode session response missing id".into()))?;
 
     let now = chrono::Utc::now().to_rfc3339();
-    sqlx::query("UPDATE cards SET ai_session_id = ?, ai_status = ?, updated_at = ? WHERE id = ?")
+    sqlx::query("UPDATE cards SET ai_session_id = ?, ai_status = ?, opencode_url = ?, updated_at = ? WHERE id = ?")
         .bind(&session_id)
         .bind("planning")
+        .bind(&opencode_url)
         .bind(&now)
         .bind(&card_id)
         .execute(pool)
         .await?;
+    CardRunService::start_or_warn(pool, &card, "plan", &session_id, None).await;
 
     let plan_event = WsEvent::AiStatusChanged {
         card_id: card_id.clone(),
@@ -607,119 +726,10 @@ pub async fn generate_plan(
     };
     let _ = state.sse_tx.send(serde_json::to_string(&plan_event).unwrap_or_default());
 
-    let board_id = sqlx::query_scalar::<_, String>("SELECT board_id FROM cards WHERE id = ?")
-        .bind(&card.id)
-        .fetch_optional(pool)
-        .await?
-        .unwrap_or_default();
-
-    let board_context = if !board_id.is_empty() {
-        let settings: Option<BoardContextRow> =
-            sqlx::query_as(
-                "SELECT codebase_path, context_markdown, tech_stack, communication_patterns, environments, code_conventions, testing_requirements, api_conventions, infrastructure, github_repo FROM board_settings WHERE board_id = ?",
-            )
-            .bind(&board_id)
-            .fetch_optional(pool)
-            .await
-            .ok()
-            .flatten();
-
-        if let Some(ctx) = settings
-        {
-            let mut context_sections = Vec::new();
-
-            if !ctx.context_markdown.is_empty() {
-                context_sections.push(format!("{}\n\n", ctx.context_markdown));
-            }
-            if !ctx.codebase_path.trim().is_empty() {
-                context_sections.push(format!("### Codebase Path\n{}\n\n", ctx.codebase_path));
-            }
-            if !ctx.tech_stack.trim().is_empty() {
-                context_sections.push(format!("### Tech Stack\n{}\n\n", ctx.tech_stack));
-            }
-            if !ctx.communication_patterns.trim().is_empty() {
-                context_sections.push(format!(
-                    "### Communication Patterns\n{}\n\n",
-                    ctx.communication_patterns
-                ));
-            }
-            if !ctx.environments.trim().is_empty() {
-                context_sections.push(format!("### Environments\n{}\n\n", ctx.environments));
-            }
-            if !ctx.code_conventions.trim().is_empty() {
-                context_sections.push(format!("### Code Conventions\n{}\n\n", ctx.code_conventions));
-            }
-            if !ctx.testing_requirements.trim().is_empty() {
-                context_sections.push(format!(
-                    "### Testing Requirements\n{}\n\n",
-                    ctx.testing_requirements
-                ));
-            }
-            if !ctx.api_conventions.trim().is_empty() {
-                context_sections.push(format!("### API Conventions\n{}\n\n", ctx.api_conventions));
-            }
-            if !ctx.infrastructure.trim().is_empty() {
-                context_sections.push(format!("### Infrastructure\n{}\n\n", ctx.infrastructure));
-            }
-            if !ctx.github_repo.trim().is_empty() {
-                context_sections.push(format!("### GitHub Repository\n{}\n\n", ctx.github_repo));
-            }
-
-            context_sections.concat()
-        } else {
-            String::new()
-        }
-    } else {
-        String::new()
-    };
-
-    let prompt = format!(
-        "IMPORTANT: You are working on card_id = \"{}\". ALL subtasks must be created on THIS card. Do NOT create new cards.\n\n\
-## SAFETY RULES — MANDATORY\n\
-- ONLY use the provided kanban MCP tools: kanban_create_subtask, kanban_update_card, kanban_add_comment, kanban_get_card\n\
-- Do NOT search the filesystem for database files\n\
-- Do NOT create, open, or modify any .db or .sqlite files\n\
-- Do NOT use Python, sqlite3, shell commands, or any tool to access databases directly\n\
-- Do NOT attempt to fix MCP tool errors by accessing underlying infrastructure\n\
-- If a kanban MCP tool returns an error, STOP and report the error. Do NOT work around it.\n\n\
-You are a project planning assistant. Analyze this card and create a detailed implementation plan.\n\n\
-## Board Context (apply to ALL work on this board)\n\
-{}\n\n\
-## Card Details\n\
-- Card ID: {}\n\
-- Title: {}\n\
-- Description: {}\n\
-- Priority: {}\n\
-- Working Directory: {}\n\
-- Linked Documents:\n{}\n\
-- Attached Files:\n{}\n\
-- Current Subtasks:\n{}\n\n\
-## Instructions\n\
-1. Analyze the card requirements\n\
-2. Break down the work into concrete, actionable subtasks organized by phases\n\
-3. Use the `kanban_create_subtask` MCP tool to add each subtask to card_id \"{}\"\n\
-4. Set appropriate phase names (e.g., \"Design\", \"Implementation\", \"Testing\") and phase_order for grouping\n\
-5. If you create any plan documents or markdown files, update the card's linked_documents using `kanban_update_card`\n\
-6. Add a summary comment using `kanban_add_comment`\n\n\
-CRITICAL: The card_id for ALL tool calls is: {}",
-        card.id,
-        board_context,
-        card.id,
-        card.title,
-        card.description,
-        card.priority,
-        card.working_directory,
-        linked_docs_formatted,
-        attached_files_formatted,
-        subtask_titles,
-        card.id,
-        card.id,
-    );
-
     let http_client = state.http_client.clone();
     let message_url = format!(
         "{}/session/{}/message",
-        state.config.opencode_url,
+        opencode_url,
         session_id.as_str()
     );
     let db_clone = pool.clone();
@@ -730,7 +740,7 @@ CRITICAL: The card_id for ALL tool calls is: {}",
     tokio::spawn(async move {
         let result = http_client
             .post(&message_url)
-            .json(&json!({"parts": [{"type": "text", "text": prompt}]}))
+            .json(&message_body(&prompt, model.as_deref()))
             .send()
             .await;
 
@@ -765,6 +775,7 @@ CRITICAL: The card_id for ALL tool calls is: {}",
                 {
                     tracing::warn!(error = %e, card_id = card_id_clone.as_str(), "Failed to update card status after plan dispatch failure");
                 }
+                CardRunService::finish_or_warn(&db_clone, &card_id_clone, "failed").await;
                 broadcast_failed(&card_id_clone, &card_stage);
             }
             Err(err) => {
@@ -778,6 +789,7 @@ CRITICAL: The card_id for ALL tool calls is: {}",
                 {
                     tracing::warn!(error = %e, card_id = card_id_clone.as_str(), "Failed to update card status after plan message error");
                 }
+                CardRunService::finish_or_warn(&db_clone, &card_id_clone, "failed").await;
                 broadcast_failed(&card_id_clone, &card_stage);
             }
         }
@@ -792,12 +804,7 @@ async fn handle_review_redispatch(
     card: &Card,
     pool: &SqlitePool,
 ) -> Result<(), KanbanError> {
-    let comments: Vec<Comment> = sqlx::query_as(
-        "SELECT * FROM comments WHERE card_id = ? ORDER BY created_at DESC LIMIT 5",
-    )
-    .bind(&card.id)
-    .fetch_all(pool)
-    .await?;
+    let threads = ReviewThreadService::list(pool, &card.id, true).await?;
 
     let plan_path = card
         .plan_path
@@ -807,18 +814,14 @@ async fn handle_review_redispatch(
     let existing_plan = std::fs::read_to_string(plan_path)
         .map_err(|e| KanbanError::Internal(format!("Failed to read plan: {}", e)))?;
 
-    let mut updated_plan = existing_plan;
-    updated_plan.push_str("\n\n---\n\n## Review Feedback\n\n");
-    updated_plan.push_str("The following feedback was provided during review:\n\n");
-    for comment in &comments {
-        updated_plan.push_str(&format!("- **{}**: {}\n", comment.author, comment.content));
+    let feedback = ReviewThreadService::feedback_markdown(&threads);
+    if !feedback.is_empty() {
+        let mut updated_plan = existing_plan;
+        updated_plan.push_str("\n\n---\n\n");
+        updated_plan.push_str(&feedback);
+        std::fs::write(plan_path, &updated_plan)
+            .map_err(|e| KanbanError::Internal(format!("Failed to write plan: {}", e)))?;
     }
-    updated_plan.push_str(
-        "\n**Action Required**: Address the review feedback above and re-verify all acceptance criteria.\n",
-    );
-
-    std::fs::write(plan_path, &updated_plan)
-        .map_err(|e| KanbanError::Internal(format!("Failed to write plan: {}", e)))?;
 
     sqlx::query("UPDATE cards SET ai_status = ?, updated_at = ? WHERE id = ?")
         .bind("queued")
@@ -846,11 +849,23 @@ pub struct RejectCardRequest {
     pub feedback: Option<String>,
 }
 
+#[derive(Debug, Serialize)]
+pub struct CardDiffResponse {
+    #[serde(flatten)]
+    pub diff: DiffResult,
+    /// Branch head the diff was taken at; threads anchored to another
+    /// commit may point at moved lines.
+    pub head_commit: String,
+    pub review_threads: Vec<ReviewThread>,
+}
+
 pub async fn get_card_diff(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<Json<DiffResult>, KanbanError> {
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<CardDiffResponse>, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &id).await?;
     let card = CardService::get_card_model(pool, &id).await?;
 
     if card.branch_name.is_empty() {
@@ -858,15 +873,27 @@ pub async fn get_card_diff(
     }
 
     let codebase_path = get_card_codebase_path(pool, &id).await?;
-    let diff = GitWorktreeService::get_diff(&codebase_path, &card.branch_name)?;
-    Ok(Json(diff))
+    let branch_name = card.branch_name.clone();
+    let (diff, head_commit) = GitWorktreeService::cancellable(move || {
+        let diff = GitWorktreeService::get_diff(&codebase_path, &branch_name)?;
+        let head_commit = GitWorktreeService::branch_head(&codebase_path, &branch_name);
+        Ok((diff, head_commit.unwrap_or_default()))
+    })
+    .await?;
+    Ok(Json(CardDiffResponse {
+        diff,
+        head_commit,
+        review_threads: ReviewThreadService::list(pool, &id, false).await?,
+    }))
 }
 
 pub async fn get_conflicts(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
 ) -> Result<Json<ConflictDetail>, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &id).await?;
     let card = CardService::get_card_model(pool, &id).await?;
 
     if card.stage != "review" {
@@ -876,22 +903,26 @@ pub async fn get_conflicts(
     }
 
     let codebase_path = get_card_codebase_path(pool, &id).await?;
-    if !GitWorktreeService::is_merge_in_progress(&codebase_path) {
-        return Err(KanbanError::BadRequest(
-            "No merge in progress for this card".into(),
-        ));
-    }
-
-    let detail = GitWorktreeService::get_conflict_details(&codebase_path)?;
+    let detail = GitWorktreeService::cancellable(move || {
+        if !GitWorktreeService::is_merge_in_progress(&codebase_path) {
+            return Err(KanbanError::BadRequest(
+                "No merge in progress for this card".into(),
+            ));
+        }
+        GitWorktreeService::get_conflict_details(&codebase_path)
+    })
+    .await?;
     Ok(Json(detail))
 }
 
 pub async fn resolve_conflicts(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
     Json(req): Json<ResolveRequest>,
 ) -> Result<Json<ConflictDetail>, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &id).await?;
     let card = CardService::get_card_model(pool, &id).await?;
 
     if card.stage != "review" {
@@ -901,65 +932,15 @@ pub async fn resolve_conflicts(
     }
 
     let codebase_path = get_card_codebase_path(pool, &id).await?;
-    if !GitWorktreeService::is_merge_in_progress(&codebase_path) {
-        return Err(KanbanError::BadRequest(
-            "No merge in progress for this card".into(),
-        ));
-    }
-
-    for resolution in &req.resolutions {
-        match resolution.choice.as_str() {
-            "ours" => {
-                GitWorktreeService::run_git(
-                    &codebase_path,
-                    &["checkout", "--ours", "--", resolution.file_path.as_str()],
-                )?;
-                GitWorktreeService::run_git(
-                    &codebase_path,
-                    &["add", "--", resolution.file_path.as_str()],
-                )?;
-            }
-            "theirs" => {
-                GitWorktreeService::run_git(
-                    &codebase_path,
-                    &["checkout", "--theirs", "--", resolution.file_path.as_str()],
-                )?;
-                GitWorktreeService::run_git(
-                    &codebase_path,
-                    &["add", "--", resolution.file_path.as_str()],
-                )?;
-            }
-            "manual" => {
-                let manual_content = resolution.manual_content.as_ref().ok_or_else(|| {
-                    KanbanError::BadRequest(format!(
-                        "manual_content is required for manual resolution: {}",
-                        resolution.file_path
-                    ))
-                })?;
-
-                let file_path = FsPath::new(&codebase_path).join(&resolution.file_path);
-                std::fs::write(&file_path, manual_content).map_err(|e| {
-                    KanbanError::Internal(format!(
-                        "Failed to write manual resolution for {}: {}",
-                        resolution.file_path, e
-                    ))
-                })?;
-
-                GitWorktreeService::run_git(
-                    &codebase_path,
-                    &["add", "--", resolution.file_path.as_str()],
-                )?;
-            }
-            _ => {
-                return Err(KanbanError::BadRequest(format!(
-                    "Invalid resolution choice '{}' for file {}",
-                    resolution.choice, resolution.file_path
-                )));
-            }
+    let detail = GitWorktreeService::blocking(move || {
+        if !GitWorktreeService::is_merge_in_progress(&codebase_path) {
+            return Err(KanbanError::BadRequest(
+                "No merge in progress for this card".into(),
+            ));
         }
-    }
-
-    let detail = GitWorktreeService::get_conflict_details(&codebase_path)?;
+        GitWorktreeService::resolve_files(&codebase_path, &req.resolutions)
+    })
+    .await?;
 
     let event = WsEvent::MergeConflictResolved {
         card_id: id,
@@ -973,8 +954,10 @@ pub async fn resolve_conflicts(
 pub async fn complete_merge(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
 ) -> Result<Json<MergeResult>, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &id).await?;
     let card = CardService::get_card_model(pool, &id).await?;
 
     if card.stage != "review" {
@@ -987,25 +970,24 @@ pub async fn complete_merge(
     }
 
     let codebase_path = get_card_codebase_path(pool, &id).await?;
-    if !GitWorktreeService::is_merge_in_progress(&codebase_path) {
-        return Err(KanbanError::BadRequest(
-            "No merge in progress for this card".into(),
-        ));
-    }
-
-    let unmerged = GitWorktreeService::run_git(&codebase_path, &["ls-files", "--unmerged"])?;
-    if !unmerged.trim().is_empty() {
-        return Err(KanbanError::BadRequest(
-            "Cannot complete merge while conflicts remain".into(),
-        ));
-    }
-
-    GitWorktreeService::run_git(&codebase_path, &["commit", "--no-edit"])?;
-    if let Err(error) = GitWorktreeService::run_git(&codebase_path, &["checkout", "-"]) {
-        tracing::warn!(error = %error, card_id = %id, "Failed to return to previous branch after merge completion");
-    }
+    let repo = codebase_path.clone();
+    GitWorktreeService::blocking(move || {
+        if !GitWorktreeService::is_merge_in_progress(&repo) {
+            return Err(KanbanError::BadRequest(
+                "No merge in progress for this card".into(),
+            ));
+        }
+        GitWorktreeService::complete_merge(&repo)
+    })
+    .await?;
 
-    let _ = GitWorktreeService::remove_worktree(&codebase_path, &card.worktree_path, &card.branch_name);
+    let _ = WorktreeHookService::remove_worktree(
+        pool,
+        &state.sse_tx,
+        &card,
+        &codebase_path,
+    )
+    .await;
 
     sqlx::query("UPDATE cards SET stage = 'done', branch_name = '', worktree_path = '', working_directory = '.', updated_at = ? WHERE id = ?")
         .bind(chrono::Utc::now().to_rfc3339())
@@ -1033,38 +1015,87 @@ pub async fn complete_merge(
     }))
 }
 
-pub async fn abort_merge(
+/// Hands the card's merge conflicts to its agent instead of resolving them
+/// file by file. A conflicted merge this card left in the main checkout is
+/// aborted first; the agent redoes the merge in the card's worktree.
+pub async fn ai_resolve_conflicts(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<StatusCode, KanbanError> {
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<MergeResult>, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &id).await?;
     let card = CardService::get_card_model(pool, &id).await?;
 
     if card.stage != "review" {
         return Err(KanbanError::BadRequest(
-            "Card must be in review stage to abort merge".into(),
+            "Card must be in review stage to resolve conflicts".into(),
+        ));
+    }
+    if card.branch_name.is_empty() {
+        return Err(KanbanError::BadRequest("Card has no git branch".into()));
+    }
+    if card.worktree_path.is_empty() || !std::path::Path::new(&card.worktree_path).exists() {
+        return Err(KanbanError::BadRequest(
+            "Card has no worktree for its agent to merge in".into(),
         ));
     }
 
     let codebase_path = get_card_codebase_path(pool, &id).await?;
-
-    if let Err(abort_error) = GitWorktreeService::run_git(&codebase_path, &["merge", "--abort"]) {
-        tracing::warn!(
-            error = %abort_error,
-            card_id = %id,
-            "git merge --abort failed, using fallback reset"
-        );
-        GitWorktreeService::run_git(&codebase_path, &["reset", "--hard", "HEAD"])?;
+    let (repo, branch_name) = (codebase_path.clone(), card.branch_name.clone());
+    let aborted = GitWorktreeService::blocking(move || {
+        if !GitWorktreeService::is_merge_in_progress(&repo) {
+            return Ok(false);
+        }
+        let merging = GitWorktreeService::run_git(&repo, &["rev-parse", "MERGE_HEAD"])
+            .map(|sha| sha.trim().to_string())
+            .ok();
+        if merging != GitWorktreeService::branch_head(&repo, &branch_name) {
+            return Err(KanbanError::Conflict(
+                "Another card's merge is in progress in the main checkout".into(),
+            ));
+        }
+        GitWorktreeService::run_git(&repo, &["merge", "--abort"])?;
+        GitWorktreeService::return_to_previous_branch(&repo);
+        Ok(true)
+    })
+    .await?;
+    if aborted {
+        release_merge_lock(&state, &codebase_path)?;
+        broadcast_event(&state, &WsEvent::MergeAborted { card_id: id.clone() });
     }
 
-    if let Err(checkout_error) = GitWorktreeService::run_git(&codebase_path, &["checkout", "-"]) {
-        tracing::warn!(
-            error = %checkout_error,
-            card_id = %id,
-            "Failed to return to previous branch after abort"
-        );
+    let result = ConflictResolutionService::start(
+        pool,
+        &state.sse_tx,
+        &state.http_client,
+        &state.opencode.endpoint_for_card(&card),
+        &card,
+        &codebase_path,
+    )
+    .await?;
+    Ok(Json(result))
+}
+
+pub async fn abort_merge(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<StatusCode, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &id).await?;
+    let card = CardService::get_card_model(pool, &id).await?;
+
+    if card.stage != "review" {
+        return Err(KanbanError::BadRequest(
+            "Card must be in review stage to abort merge".into(),
+        ));
     }
 
+    let codebase_path = get_card_codebase_path(pool, &id).await?;
+    let repo = codebase_path.clone();
+    GitWorktreeService::blocking(move || GitWorktreeService::abort_merge(&repo)).await?;
+
     release_merge_lock(&state, &codebase_path)?;
 
     broadcast_event(&state, &WsEvent::MergeAborted { card_id: id });
@@ -1075,8 +1106,10 @@ pub async fn abort_merge(
 pub async fn merge_card(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
 ) -> Result<Json<MergeResult>, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &id).await?;
     let card = CardService::get_card_model(pool, &id).await?;
 
     if card.branch_name.is_empty() {
@@ -1088,33 +1121,23 @@ pub async fn merge_card(
         ));
     }
 
+    ensure_not_queued(pool, &id).await?;
+
     let codebase_path = get_card_codebase_path(pool, &id).await?;
     let mut merge_lock = MergeLockGuard::acquire(&state, &codebase_path)?;
-    let result = GitWorktreeService::merge_branch(
-        &codebase_path,
-        &card.branch_name,
-        true,
-        &card.worktree_path,
-        &card.title,
-    )?;
+    let (repo, branch_name, worktree_path, title) = (
+        codebase_path.clone(),
+        card.branch_name.clone(),
+        card.worktree_path.clone(),
+        card.title.clone(),
+    );
+    let result = GitWorktreeService::blocking(move || {
+        GitWorktreeService::merge_branch(&repo, &branch_name, true, &worktree_path, &title)
+    })
+    .await?;
 
     if result.success {
-        let _ = GitWorktreeService::remove_worktree(&codebase_path, &card.worktree_path, &card.branch_name);
-
-        sqlx::query("UPDATE cards SET stage = 'done', branch_name = '', worktree_path = '', working_directory = '.', updated_at = ? WHERE id = ?")
-            .bind(chrono::Utc::now().to_rfc3339())
-            .bind(&id)
-            .execute(pool)
-            .await?;
-
-        broadcast_event(&state, &WsEvent::MergeCompleted { card_id: id.clone() });
-
-        let event = WsEvent::CardMoved {
-            card_id: id.clone(),
-            from_stage: "review".to_string(),
-            to_stage: "done".to_string(),
-        };
-        broadcast_event(&state, &event);
+        MergeQueueService::finish_merged_card(pool, &state.sse_tx, &card, &codebase_path).await?;
     } else {
         merge_lock.keep_lock();
         broadcast_event(
@@ -1129,12 +1152,88 @@ pub async fn merge_card(
     Ok(Json(result))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct PartialMergeRequest {
+    pub files: Vec<FileSelection>,
+    /// Commit message on the base branch; defaults to the card title.
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// Merges only the selected files or hunks. The card stays in review with
+/// the remainder on its branch, or is done when nothing is left.
+pub async fn merge_card_partial(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(req): Json<PartialMergeRequest>,
+) -> Result<Json<PartialMergeResult>, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &id).await?;
+    let card = CardService::get_card_model(pool, &id).await?;
+
+    if card.branch_name.is_empty() {
+        return Err(KanbanError::BadRequest("Card has no git branch".into()));
+    }
+    if card.stage != "review" {
+        return Err(KanbanError::BadRequest(
+            "Card must be in review stage to merge".into(),
+        ));
+    }
+
+    ensure_not_queued(pool, &id).await?;
+
+    let codebase_path = get_card_codebase_path(pool, &id).await?;
+    let _merge_lock = MergeLockGuard::acquire(&state, &codebase_path)?;
+    let message = req
+        .message
+        .as_deref()
+        .map(str::trim)
+        .filter(|message| !message.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("feat: {} (partial)", card.title));
+    let (repo, branch_name, worktree_path, title) = (
+        codebase_path.clone(),
+        card.branch_name.clone(),
+        card.worktree_path.clone(),
+        card.title.clone(),
+    );
+    let result = GitWorktreeService::blocking(move || {
+        GitWorktreeService::merge_selected(
+            &repo,
+            &branch_name,
+            &worktree_path,
+            &title,
+            &message,
+            &req.files,
+        )
+    })
+    .await?;
+
+    if result.remaining.files.is_empty() {
+        MergeQueueService::finish_merged_card(pool, &state.sse_tx, &card, &codebase_path).await?;
+    } else {
+        broadcast_event(
+            &state,
+            &WsEvent::MergePartiallyApplied {
+                card_id: id.clone(),
+                commit: result.commit.clone(),
+                remaining_files: result.remaining.files.len(),
+            },
+        );
+    }
+
+    Ok(Json(result))
+}
+
 pub async fn create_card_pr(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
     Json(req): Json<CreatePrRequest>,
 ) -> Result<Json<CreatePrResponse>, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &id).await?;
     let card = CardService::get_card_model(pool, &id).await?;
 
     if card.branch_name.is_empty() {
@@ -1151,22 +1250,28 @@ pub async fn create_card_pr(
         )
     });
 
-    let url = GitWorktreeService::create_github_pr(
-        &codebase_path,
-        &card.branch_name,
-        &card.worktree_path,
-        &title,
-        &body,
-    )?;
+    let (branch_name, worktree_path) = (card.branch_name.clone(), card.worktree_path.clone());
+    let url = GitWorktreeService::blocking(move || {
+        GitWorktreeService::create_github_pr(
+            &codebase_path,
+            &branch_name,
+            &worktree_path,
+            &title,
+            &body,
+        )
+    })
+    .await?;
     Ok(Json(CreatePrResponse { url }))
 }
 
 pub async fn reject_card(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
     Json(req): Json<RejectCardRequest>,
 ) -> Result<Json<CardResponse>, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &id).await?;
     let card = CardService::get_card_model(pool, &id).await?;
 
     if card.stage != "review" {
@@ -1181,20 +1286,37 @@ pub async fn reject_card(
         .execute(pool)
         .await?;
 
-    if let Some(feedback) = &req.feedback {
-        if !feedback.is_empty() {
-            let comment_id = uuid::Uuid::new_v4().to_string();
-            sqlx::query(
-                "INSERT INTO comments (id, card_id, author, content, created_at) VALUES (?, ?, ?, ?, ?)",
-            )
-            .bind(&comment_id)
-            .bind(&id)
-            .bind("Reviewer")
-            .bind(format!("**Review Feedback:** {}", feedback))
-            .bind(chrono::Utc::now().to_rfc3339())
-            .execute(pool)
-            .await?;
-        }
+    // Free-text feedback stays a card comment, where the board shows it, and
+    // also becomes an unanchored thread, so it is resolved and re-dispatched
+    // like line comments.
+    if let Some(feedback) = req.feedback.as_deref().filter(|f| !f.trim().is_empty()) {
+        let comment_id = uuid::Uuid::new_v4().to_string();
+        sqlx::query(
+            "INSERT INTO comments (id, card_id, author, content, created_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&comment_id)
+        .bind(&id)
+        .bind("Reviewer")
+        .bind(
+            Redactor::for_board(pool, card.board_id.as_deref())
+                .await?
+                .redact(&format!("**Review Feedback:** {}", feedback)),
+        )
+        .bind(chrono::Utc::now().to_rfc3339())
+        .execute(pool)
+        .await?;
+        ReviewThreadService::create(
+            pool,
+            &state.sse_tx,
+            &card,
+            CreateReviewThreadRequest {
+                content: feedback.to_string(),
+                author: Some("Reviewer".to_string()),
+                ..Default::default()
+            },
+            None,
+        )
+        .await?;
     }
 
     let event = WsEvent::CardMoved {
@@ -1213,10 +1335,45 @@ pub async fn reject_card(
 pub async fn stop_ai(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
 ) -> Result<Json<CardResponse>, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &id).await?;
     let card = CardService::get_card_model(pool, &id).await?;
 
+    // Competing attempts run in sessions of their own, and a card still in
+    // worktree setup has none yet; its setup task will not dispatch it.
+    let has_attempts = CardAttemptService::has_open(pool, &id).await?;
+    if has_attempts {
+        CardAttemptService::cancel_all(
+            pool,
+            &state.sse_tx,
+            &state.http_client,
+            &state.opencode,
+            &card,
+        )
+        .await?;
+    }
+    if has_attempts || card.ai_status == "setting_up" {
+        sqlx::query("UPDATE cards SET ai_status = 'cancelled', updated_at = ? WHERE id = ?")
+            .bind(chrono::Utc::now().to_rfc3339())
+            .bind(&id)
+            .execute(pool)
+            .await?;
+        broadcast_event(
+            &state,
+            &WsEvent::AiStatusChanged {
+                card_id: id.clone(),
+                board_id: card.board_id.clone(),
+                status: "cancelled".to_string(),
+                progress: json!({}),
+                stage: card.stage.clone(),
+                ai_session_id: None,
+            },
+        );
+        return Ok(Json(CardService::get_card_by_id(pool, &id).await?));
+    }
+
     let session_id = card
         .ai_session_id
         .as_deref()
@@ -1233,7 +1390,7 @@ pub async fn stop_ai(
 
     let dispatch = AiDispatchService::new(
         state.http_client.clone(),
-        state.config.opencode_url.clone(),
+        state.opencode.endpoint_for_card(&card),
     );
     if let Err(e) = dispatch.abort_session(session_id).await {
         tracing::warn!(card_id = id.as_str(), error = %e, "Failed to abort opencode session, marking cancelled anyway");
@@ -1246,14 +1403,18 @@ pub async fn stop_ai(
         .bind(&id)
         .execute(pool)
         .await?;
+    // Before the worktree goes away, so the run keeps its diff.
+    CardRunService::finish_or_warn(pool, &id, "cancelled").await;
 
     if !card.worktree_path.is_empty() {
         if let Ok(codebase_path) = get_card_codebase_path(pool, &id).await {
-            let _ = GitWorktreeService::remove_worktree(
+            let _ = WorktreeHookService::remove_worktree(
+                pool,
+                &state.sse_tx,
+                &card,
                 &codebase_path,
-                &card.worktree_path,
-                &card.branch_name,
-            );
+            )
+            .await;
             if let Err(e) = sqlx::query(
                 "UPDATE cards SET branch_name = '', worktree_path = '', working_directory = '.', updated_at = ? WHERE id = ?",
             )
@@ -1284,8 +1445,10 @@ pub async fn stop_ai(
 pub async fn resume_ai(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
 ) -> Result<Json<CardResponse>, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &id).await?;
     let card = CardService::get_card_model(pool, &id).await?;
 
     let valid_stages = ["plan", "todo", "in_progress"];
@@ -1295,7 +1458,7 @@ pub async fn resume_ai(
         ));
     }
 
-    let active_statuses = ["planning", "dispatched", "working", "queued", "waiting_input"];
+    let active_statuses = ["planning", "setting_up", "dispatched", "working", "queued", "waiting_input"];
     if active_statuses.contains(&card.ai_status.as_str()) {
         return Err(KanbanError::BadRequest(format!(
             "Card AI is already active with status '{}'",
@@ -1304,14 +1467,15 @@ pub async fn resume_ai(
     }
 
     if let Some(session_id) = card.ai_session_id.as_deref().filter(|s| !s.is_empty()) {
+        let opencode_url = state.opencode.endpoint_for_card(&card);
         let _ = state
             .http_client
-            .get(format!("{}/health", state.config.opencode_url))
+            .get(format!("{}/health", opencode_url))
             .timeout(std::time::Duration::from_secs(10))
             .send()
             .await;
 
-        let session_check_url = format!("{}/session/{}", state.config.opencode_url, session_id);
+        let session_check_url = format!("{}/session/{}", opencode_url, session_id);
         let session_exists = match state
             .http_client
             .get(&session_check_url)
@@ -1341,6 +1505,7 @@ pub async fn resume_ai(
         };
 
         if session_exists {
+            let model = ModelSelection::for_card(pool, &card, ModelPurpose::for_stage(&card.stage)).await?;
             let resumed_status = if card.stage == "plan" {
                 "planning"
             } else {
@@ -1353,6 +1518,7 @@ pub async fn resume_ai(
                 .bind(&id)
                 .execute(pool)
                 .await?;
+            CardRunService::start_or_warn(pool, &card, "resume", session_id, None).await;
 
             let event = WsEvent::AiStatusChanged {
                 card_id: id.clone(),
@@ -1373,7 +1539,7 @@ pub async fn resume_ai(
             let http_client = state.http_client.clone();
             let message_url = format!(
                 "{}/session/{}/message",
-                state.config.opencode_url,
+                opencode_url,
                 session_id
             );
             let db_clone = pool.clone();
@@ -1382,7 +1548,7 @@ pub async fn resume_ai(
             tokio::spawn(async move {
                 let result = http_client
                     .post(&message_url)
-                    .json(&json!({"parts": [{"type": "text", "text": prompt}]}))
+                    .json(&message_body(&prompt, model.as_deref()))
                     .send()
                     .await;
 
@@ -1405,6 +1571,7 @@ pub async fn resume_ai(
                         {
                             tracing::warn!(error = %e, card_id = card_id_clone.as_str(), "Failed to update card status after resume non-success response");
                         }
+                        CardRunService::finish_or_warn(&db_clone, &card_id_clone, "failed").await;
                     }
                     Err(err) => {
                         tracing::warn!(card_id = card_id_clone.as_str(), error = %err, "Failed to send resume message");
@@ -1417,6 +1584,7 @@ pub async fn resume_ai(
                         {
                             tracing::warn!(error = %e, card_id = card_id_clone.as_str(), "Failed to update card status after resume message error");
                         }
+                        CardRunService::finish_or_warn(&db_clone, &card_id_clone, "failed").await;
                     }
                 }
             });
@@ -1466,8 +1634,10 @@ pub async fn resume_ai(
 pub async fn conclude_ai(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
 ) -> Result<Json<CardResponse>, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &id).await?;
     let card = CardService::get_card_model(pool, &id).await?;
 
     let Some(session_id) = card.ai_session_id.as_deref().filter(|s| !s.is_empty()) else {
@@ -1475,6 +1645,7 @@ pub async fn conclude_ai(
             "Card has no AI session to conclude".into(),
         ));
     };
+    let model = ModelSelection::for_card(pool, &card, ModelPurpose::for_stage(&card.stage)).await?;
 
     let now = chrono::Utc::now().to_rfc3339();
     sqlx::query("UPDATE cards SET ai_status = ?, updated_at = ? WHERE id = ?")
@@ -1509,7 +1680,8 @@ pub async fn conclude_ai(
 
     let message_url = format!(
         "{}/session/{}/message",
-        state.config.opencode_url, session_id
+        state.opencode.endpoint_for_card(&card),
+        session_id
     );
     let http_client = state.http_client.clone();
     let db_clone = pool.clone();
@@ -1521,7 +1693,7 @@ pub async fn conclude_ai(
     tokio::spawn(async move {
         let result = http_client
             .post(&message_url)
-            .json(&json!({"parts": [{"type": "text", "text": prompt}]}))
+            .json(&message_body(prompt, model.as_deref()))
             .send()
             .await;
 
@@ -1529,6 +1701,8 @@ pub async fn conclude_ai(
             Ok(resp) if resp.status().is_success() => "idle",
             _ => "failed",
         };
+        let outcome = if final_status == "idle" { "completed" } else { "failed" };
+        CardRunService::finish_or_warn(&db_clone, &card_id_clone, outcome).await;
 
         let _ = sqlx::query("UPDATE cards SET ai_status = ?, updated_at = ? WHERE id = ?")
             .bind(final_status)
@@ -1557,8 +1731,10 @@ pub async fn conclude_ai(
 pub async fn retry_ai(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
 ) -> Result<Json<CardResponse>, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &id).await?;
     let card = CardService::get_card_model(pool, &id).await?;
 
     if card.stage != "in_progress" && card.stage != "plan" {
@@ -1572,10 +1748,10 @@ pub async fn retry_ai(
         serde_json::from_str(&card.ai_progress).unwrap_or_else(|_| json!({}));
     progress["retry_count"] =
         json!(progress.get("retry_count").and_then(|v| v.as_u64()).unwrap_or(0) + 1);
-    progress.as_object_mut().map(|m| {
+    if let Some(m) = progress.as_object_mut() {
         m.remove("failure_reason");
         m.remove("failed_at");
-    });
+    }
 
     sqlx::query(
         "UPDATE cards SET ai_status = 'queued', ai_session_id = NULL, ai_progress = ?, stage = 'todo', updated_at = ? WHERE id = ?",
@@ -1614,10 +1790,24 @@ pub async fn retry_ai(
 pub async fn delete_card(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
 ) -> Result<StatusCode, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &id).await?;
 
-    if let Ok(card) = CardService::get_card_model(pool, &id).await {
+    let existing = CardService::get_card_model(pool, &id).await.ok();
+    if let Some(card) = &existing {
+        if let Err(e) = CardAttemptService::cancel_all(
+            pool,
+            &state.sse_tx,
+            &state.http_client,
+            &state.opencode,
+            card,
+        )
+        .await
+        {
+            tracing::warn!(card_id = %id, error = %e, "Failed to clean up attempts before delete");
+        }
         if !card.worktree_path.is_empty() {
             let board_id = sqlx::query_scalar::<_, String>("SELECT board_id FROM cards WHERE id = ?")
                 .bind(&id)
@@ -1633,11 +1823,13 @@ pub async fn delete_card(
                 .fetch_one(pool)
                 .await
                 {
-                    let _ = GitWorktreeService::remove_worktree(
+                    let _ = WorktreeHookService::remove_worktree(
+                        pool,
+                        &state.sse_tx,
+                        card,
                         &codebase_path,
-                        &card.worktree_path,
-                        &card.branch_name,
-                    );
+                    )
+                    .await;
                 }
             }
         }
@@ -1647,6 +1839,7 @@ pub async fn delete_card(
 
     let event = WsEvent::CardDeleted {
         card_id: id.clone(),
+        board_id: existing.and_then(|card| card.board_id),
     };
     if let Ok(payload) = serde_json::to_string(&event) {
         let _ = state.sse_tx.send(payload);
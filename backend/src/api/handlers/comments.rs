@@ -11,7 +11,7 @@ use crate::api::handlers::sse::WsEvent;
 use crate::api::AppState;
 use crate::auth::middleware::AuthUser;
 use crate::domain::{Comment, KanbanError};
-use crate::services::CardService;
+use crate::services::{BoardAccess, CardService, Redactor};
 
 #[derive(Debug, Deserialize)]
 pub struct UpdateCommentRequest {
@@ -21,8 +21,10 @@ pub struct UpdateCommentRequest {
 pub async fn get_comments(
     State(state): State<AppState>,
     Path(card_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
 ) -> Result<Json<Vec<Comment>>, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
     let comments = sqlx::query_as::<_, Comment>(
         "SELECT * FROM comments WHERE card_id = ? ORDER BY created_at ASC"
     )
@@ -39,6 +41,7 @@ pub async fn create_comment(
     Json(mut req): Json<CreateCommentRequest>,
 ) -> Result<(StatusCode, Json<Comment>), KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
     if req.user_id.is_none() {
         req.user_id = Some(auth_user.user_id);
     }
@@ -58,6 +61,7 @@ pub async fn create_comment(
 pub async fn update_comment(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
     Json(req): Json<UpdateCommentRequest>,
 ) -> Result<Json<Comment>, KanbanError> {
     let pool = state.require_db()?;
@@ -65,10 +69,12 @@ pub async fn update_comment(
         .bind(&id)
         .fetch_one(pool)
         .await?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
 
+    let content = Redactor::for_card(pool, &card_id).await?.redact(&req.content);
     let now = Utc::now().to_rfc3339();
     let result = sqlx::query("UPDATE comments SET content = ?, created_at = ? WHERE id = ?")
-        .bind(&req.content)
+        .bind(&content)
         .bind(&now)
         .bind(&id)
         .execute(pool)
@@ -95,12 +101,14 @@ pub async fn update_comment(
 pub async fn delete_comment(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
 ) -> Result<StatusCode, KanbanError> {
     let pool = state.require_db()?;
     let card_id = sqlx::query_scalar::<_, String>("SELECT card_id FROM comments WHERE id = ?")
         .bind(&id)
         .fetch_one(pool)
         .await?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
 
     let result = sqlx::query("DELETE FROM comments WHERE id = ?")
         .bind(&id)
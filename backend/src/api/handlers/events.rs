@@ -0,0 +1,63 @@
+use std::convert::Infallible;
+
+use axum::extract::{Query, State};
+use axum::http::HeaderMap;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::Extension;
+use futures::Stream;
+use serde::Deserialize;
+use tokio::sync::watch;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+
+use crate::api::handlers::ws::check_filter_access;
+use crate::api::state::AppState;
+use crate::auth::middleware::AuthUser;
+use crate::domain::KanbanError;
+use crate::services::event_bus::{seq_of, EventAccess, EventFilter};
+
+#[derive(Debug, Deserialize)]
+pub struct EventsQuery {
+    pub since: Option<i64>,
+    /// Comma-separated filters; see [`EventFilter`].
+    pub board_ids: Option<String>,
+    pub card_ids: Option<String>,
+    pub types: Option<String>,
+}
+
+/// Server-sent events with resume support: the browser's `Last-Event-ID`
+/// header (or `?since=`) replays every event after that sequence number.
+pub async fn stream_events(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    headers: HeaderMap,
+    Query(query): Query<EventsQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, KanbanError> {
+    let db = state.require_db()?;
+
+    let since = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .or(query.since);
+
+    let filter = EventFilter::from_query(
+        query.board_ids.as_deref(),
+        query.card_ids.as_deref(),
+        query.types.as_deref(),
+    );
+    check_filter_access(db, &auth_user, &filter).await?;
+    let access = EventAccess::for_user(db, &auth_user.user_id).await?;
+
+    // SSE has no client → server channel, so the filter never changes.
+    let (_, filter) = watch::channel(filter);
+    let stream = ReceiverStream::new(state.sse_tx.stream_from(since, access, filter)).map(|msg| {
+        let event = match seq_of(&msg) {
+            Some(seq) => Event::default().id(seq.to_string()),
+            None => Event::default(),
+        };
+        Ok(event.data(msg))
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
@@ -1,5 +1,5 @@
 use axum::{
-    extract::{Multipart, Path, State},
+    extract::{Extension, Multipart, Path, State},
     http::{header, StatusCode},
     response::IntoResponse,
     Json,
@@ -10,7 +10,9 @@ use tokio::{fs, io::AsyncWriteExt};
 use uuid::Uuid;
 
 use crate::api::state::AppState;
+use crate::auth::middleware::AuthUser;
 use crate::domain::KanbanError;
+use crate::services::BoardAccess;
 
 #[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
 pub struct CardFile {
@@ -26,9 +28,11 @@ pub struct CardFile {
 pub async fn upload_files(
     State(state): State<AppState>,
     Path(card_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
     mut multipart: Multipart,
 ) -> Result<(StatusCode, Json<Vec<CardFile>>), KanbanError> {
     let db = state.require_db()?;
+    BoardAccess::require_card_access(db, &auth_user.user_id, &card_id).await?;
     let mut uploaded_files = Vec::new();
 
     let card = sqlx::query_scalar::<_, String>("SELECT board_id FROM cards WHERE id = ?")
@@ -106,8 +110,10 @@ pub async fn upload_files(
 pub async fn list_card_files(
     State(state): State<AppState>,
     Path(card_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
 ) -> Result<Json<Vec<CardFile>>, KanbanError> {
     let db = state.require_db()?;
+    BoardAccess::require_card_access(db, &auth_user.user_id, &card_id).await?;
 
     let files = sqlx::query_as::<_, CardFile>(
         r#"
@@ -127,18 +133,20 @@ pub async fn list_card_files(
 pub async fn download_file(
     State(state): State<AppState>,
     Path(file_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
 ) -> Result<impl IntoResponse, KanbanError> {
     let db = state.require_db()?;
 
-    let row: (String, String, String) = sqlx::query_as(
-        "SELECT file_path, original_filename, mime_type FROM card_files WHERE id = ?"
+    let row: (String, String, String, String) = sqlx::query_as(
+        "SELECT card_id, file_path, original_filename, mime_type FROM card_files WHERE id = ?"
     )
     .bind(&file_id)
     .fetch_optional(db)
     .await?
     .ok_or_else(|| KanbanError::NotFound(format!("File {} not found", file_id)))?;
 
-    let (file_path, original_filename, mime_type) = row;
+    let (card_id, file_path, original_filename, mime_type) = row;
+    BoardAccess::require_card_access(db, &auth_user.user_id, &card_id).await?;
 
     let file_data = fs::read(&file_path).await.map_err(|e| {
         KanbanError::Internal(format!("Failed to read file: {}", e))
@@ -160,14 +168,17 @@ pub async fn download_file(
 pub async fn delete_file(
     State(state): State<AppState>,
     Path(file_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
 ) -> Result<StatusCode, KanbanError> {
     let db = state.require_db()?;
 
-    let file_path: String = sqlx::query_scalar("SELECT file_path FROM card_files WHERE id = ?")
-        .bind(&file_id)
-        .fetch_optional(db)
-        .await?
-        .ok_or_else(|| KanbanError::NotFound(format!("File {} not found", file_id)))?;
+    let (card_id, file_path): (String, String) =
+        sqlx::query_as("SELECT card_id, file_path FROM card_files WHERE id = ?")
+            .bind(&file_id)
+            .fetch_optional(db)
+            .await?
+            .ok_or_else(|| KanbanError::NotFound(format!("File {} not found", file_id)))?;
+    BoardAccess::require_card_access(db, &auth_user.user_id, &card_id).await?;
 
     if let Err(e) = fs::remove_file(&file_path).await {
         tracing::warn!("Failed to delete file {}: {}", file_path, e);
@@ -1,13 +1,14 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Extension, Path, State},
     http::StatusCode,
     Json,
 };
 
 use crate::api::handlers::sse::WsEvent;
 use crate::api::AppState;
+use crate::auth::middleware::AuthUser;
 use crate::domain::{KanbanError, Label};
-use crate::services::CardService;
+use crate::services::{BoardAccess, CardService};
 
 pub async fn list_labels(
     State(state): State<AppState>,
@@ -20,8 +21,10 @@ pub async fn list_labels(
 pub async fn add_label(
     State(state): State<AppState>,
     Path((card_id, label_id)): Path<(String, String)>,
+    Extension(auth_user): Extension<AuthUser>,
 ) -> Result<StatusCode, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
     CardService::add_label_to_card(pool, &card_id, &label_id).await?;
 
     let event = WsEvent::LabelAdded {
@@ -38,8 +41,10 @@ pub async fn add_label(
 pub async fn remove_label(
     State(state): State<AppState>,
     Path((card_id, label_id)): Path<(String, String)>,
+    Extension(auth_user): Extension<AuthUser>,
 ) -> Result<StatusCode, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
     CardService::remove_label_from_card(pool, &card_id, &label_id).await?;
 
     let event = WsEvent::LabelRemoved {
@@ -0,0 +1,77 @@
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    Json,
+};
+use sqlx::SqlitePool;
+
+use crate::api::AppState;
+use crate::auth::middleware::AuthUser;
+use crate::domain::KanbanError;
+use crate::services::merge_queue::MergeQueueEntry;
+use crate::services::{BoardAccess, CardService, MergeQueueService};
+
+async fn board_codebase_path(pool: &SqlitePool, board_id: &str) -> Result<String, KanbanError> {
+    let codebase_path: Option<String> =
+        sqlx::query_scalar("SELECT codebase_path FROM board_settings WHERE board_id = ?")
+            .bind(board_id)
+            .fetch_optional(pool)
+            .await?;
+    codebase_path
+        .filter(|path| !path.is_empty())
+        .ok_or_else(|| KanbanError::BadRequest("Board codebase path not configured".into()))
+}
+
+/// Queues an approved card to be updated from the base, verified and merged
+/// in turn.
+pub async fn enqueue_card(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(card_id): Path<String>,
+) -> Result<(StatusCode, Json<MergeQueueEntry>), KanbanError> {
+    let pool = state.require_db()?;
+    let card = CardService::get_card_model(pool, &card_id).await?;
+    let Some(board_id) = card.board_id.as_deref().filter(|id| !id.is_empty()) else {
+        return Err(KanbanError::BadRequest("Card is not assigned to a board".into()));
+    };
+    BoardAccess::require_access(pool, &auth_user.user_id, board_id).await?;
+    let codebase_path = board_codebase_path(pool, board_id).await?;
+    let entry = MergeQueueService::enqueue(
+        pool,
+        &state.sse_tx,
+        &card,
+        &codebase_path,
+        Some(&auth_user.user_id),
+    )
+    .await?;
+    Ok((StatusCode::CREATED, Json(entry)))
+}
+
+/// Removes the card from the queue; only entries still waiting can go.
+pub async fn cancel_card(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(card_id): Path<String>,
+) -> Result<Json<MergeQueueEntry>, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
+    CardService::get_card_model(pool, &card_id).await?;
+    Ok(Json(
+        MergeQueueService::cancel(pool, &state.sse_tx, &card_id).await?,
+    ))
+}
+
+/// The board's entries in its repository's queue in merge order, then its
+/// recent results.
+pub async fn list_board_queue(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(board_id): Path<String>,
+) -> Result<Json<Vec<MergeQueueEntry>>, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_access(pool, &auth_user.user_id, &board_id).await?;
+    let codebase_path = board_codebase_path(pool, &board_id).await?;
+    Ok(Json(
+        MergeQueueService::list_for_board(pool, &codebase_path, &board_id).await?,
+    ))
+}
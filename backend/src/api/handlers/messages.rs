@@ -0,0 +1,261 @@
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::api::handlers::sse::WsEvent;
+use crate::api::AppState;
+use crate::auth::middleware::AuthUser;
+use crate::domain::{AgentLog, KanbanError};
+use crate::services::ai_dispatch::message_body;
+use crate::services::model_selection::ModelPurpose;
+use crate::services::{BoardAccess, CardRunService, CardService, ModelSelection, Redactor};
+
+/// `agent_logs.event_type` of messages a human sent to the card's agent.
+pub const HUMAN_MESSAGE_EVENT: &str = "human.message";
+
+#[derive(Debug, Deserialize)]
+pub struct SendMessageRequest {
+    pub content: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AcknowledgeMessagesRequest {
+    /// ID of the newest message the agent has read.
+    pub last_seen_id: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HumanMessagesResponse {
+    /// Whether a message arrived since the agent last acknowledged.
+    pub pending: bool,
+    pub messages: Vec<AgentLog>,
+}
+
+/// Forwards a human instruction into the card's running session. The message
+/// is logged and flagged before it is sent, so an agent that misses the
+/// prompt still finds it through `kanban_human_messages`.
+pub async fn send_message(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(card_id): Path<String>,
+    Json(req): Json<SendMessageRequest>,
+) -> Result<(StatusCode, Json<AgentLog>), KanbanError> {
+    let pool = state.require_db()?;
+    let card = CardService::get_card_model(pool, &card_id).await?;
+    if let Some(board_id) = card.board_id.as_deref().filter(|id| !id.is_empty()) {
+        BoardAccess::require_access(pool, &auth_user.user_id, board_id).await?;
+    }
+
+    let content = req.content.trim();
+    if content.is_empty() {
+        return Err(KanbanError::BadRequest("Message must not be empty".into()));
+    }
+    let Some(session_id) = card.ai_session_id.clone().filter(|s| !s.is_empty()) else {
+        return Err(KanbanError::BadRequest(
+            "Card has no AI session to message".into(),
+        ));
+    };
+
+    let author: String = sqlx::query_scalar("SELECT username FROM users WHERE id = ?")
+        .bind(&auth_user.user_id)
+        .fetch_optional(pool)
+        .await?
+        .unwrap_or_else(|| "user".to_string());
+    let content = Redactor::for_board(pool, card.board_id.as_deref())
+        .await?
+        .redact(content);
+
+    let model = ModelSelection::for_card(pool, &card, ModelPurpose::for_stage(&card.stage)).await?;
+
+    let now = Utc::now().to_rfc3339();
+    let log = AgentLog {
+        id: Uuid::new_v4().to_string(),
+        card_id: card_id.clone(),
+        session_id: session_id.clone(),
+        event_type: HUMAN_MESSAGE_EVENT.to_string(),
+        agent: Some("human".to_string()),
+        content: content.clone(),
+        metadata: json!({ "author": author, "user_id": auth_user.user_id }).to_string(),
+        created_at: now.clone(),
+    };
+    // Logged and flagged together, so an acknowledgement can never clear
+    // the flag for a message it did not see.
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "INSERT INTO agent_logs (id, card_id, session_id, event_type, agent, content, metadata, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&log.id)
+    .bind(&log.card_id)
+    .bind(&log.session_id)
+    .bind(&log.event_type)
+    .bind(&log.agent)
+    .bind(&log.content)
+    .bind(&log.metadata)
+    .bind(&log.created_at)
+    .execute(&mut *tx)
+    .await?;
+
+    // Only active stages resume; a card in review or done keeps its status.
+    let resumed_status = match card.stage.as_str() {
+        "plan" => Some("planning"),
+        "todo" | "in_progress" => Some("working"),
+        _ => None,
+    };
+    sqlx::query(
+        "UPDATE cards SET ai_status = COALESCE(?, ai_status), human_message_pending = 1, updated_at = ? WHERE id = ?",
+    )
+    .bind(resumed_status)
+    .bind(&now)
+    .bind(&card_id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    let log_event = WsEvent::AgentLogCreated {
+        card_id: card_id.clone(),
+        log: log.clone(),
+    };
+    let _ = state.sse_tx.send(serde_json::to_string(&log_event).unwrap_or_default());
+    if let Some(status) = resumed_status {
+        let status_event = WsEvent::AiStatusChanged {
+            card_id: card_id.clone(),
+            board_id: card.board_id.clone(),
+            status: status.to_string(),
+            progress: serde_json::from_str(&card.ai_progress).unwrap_or_else(|_| json!({})),
+            stage: card.stage.clone(),
+            ai_session_id: Some(session_id.clone()),
+        };
+        let _ = state.sse_tx.send(serde_json::to_string(&status_event).unwrap_or_default());
+    }
+
+    let prompt = format!(
+        "New instructions from {} (a human on this card's board). Take them into account, \
+         then continue your work:\n\n{}",
+        author, content
+    );
+    let message_url = format!(
+        "{}/session/{}/message",
+        state.opencode.endpoint_for_card(&card),
+        session_id
+    );
+    let http_client = state.http_client.clone();
+    let db_clone = pool.clone();
+    let sse_tx_clone = state.sse_tx.clone();
+    let card_stage = card.stage.clone();
+    let board_id = card.board_id.clone();
+
+    tokio::spawn(async move {
+        let result = http_client
+            .post(&message_url)
+            .json(&message_body(&prompt, model.as_deref()))
+            .send()
+            .await;
+
+        let error = match result {
+            Ok(response) if response.status().is_success() => {
+                tracing::info!(card_id = card_id.as_str(), "Human message sent to agent session");
+                return;
+            }
+            Ok(response) => format!("OpenCode returned {}", response.status()),
+            Err(err) => err.to_string(),
+        };
+        tracing::warn!(card_id = card_id.as_str(), error, "Failed to send human message to agent session");
+        if resumed_status.is_none() {
+            return;
+        }
+
+        if let Err(e) = sqlx::query("UPDATE cards SET ai_status = ?, updated_at = ? WHERE id = ?")
+            .bind("failed")
+            .bind(Utc::now().to_rfc3339())
+            .bind(&card_id)
+            .execute(&db_clone)
+            .await
+        {
+            tracing::warn!(error = %e, card_id = card_id.as_str(), "Failed to update card status after human message failure");
+        }
+        CardRunService::finish_or_warn(&db_clone, &card_id, "failed").await;
+        let event = WsEvent::AiStatusChanged {
+            card_id,
+            board_id,
+            status: "failed".to_string(),
+            progress: json!({ "failure_reason": format!("Failed to deliver message: {}", error) }),
+            stage: card_stage,
+            ai_session_id: Some(session_id),
+        };
+        let _ = sse_tx_clone.send(serde_json::to_string(&event).unwrap_or_default());
+    });
+
+    Ok((StatusCode::CREATED, Json(log)))
+}
+
+pub async fn list_messages(
+    State(state): State<AppState>,
+    Path(card_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<HumanMessagesResponse>, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
+    let card = CardService::get_card_model(pool, &card_id).await?;
+    let messages: Vec<AgentLog> = sqlx::query_as(
+        "SELECT id, card_id, session_id, event_type, agent, content, metadata, created_at FROM agent_logs WHERE card_id = ? AND event_type = ? ORDER BY created_at ASC",
+    )
+    .bind(&card_id)
+    .bind(HUMAN_MESSAGE_EVENT)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(Json(HumanMessagesResponse {
+        pending: card.human_message_pending,
+        messages,
+    }))
+}
+
+/// Clears the pending flag once the agent has read the messages, unless one
+/// arrived after the last message it saw.
+pub async fn acknowledge_messages(
+    State(state): State<AppState>,
+    Path(card_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(req): Json<AcknowledgeMessagesRequest>,
+) -> Result<Json<serde_json::Value>, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
+
+    let seen_at: Option<String> = sqlx::query_scalar(
+        "SELECT created_at FROM agent_logs WHERE id = ? AND card_id = ? AND event_type = ?",
+    )
+    .bind(&req.last_seen_id)
+    .bind(&card_id)
+    .bind(HUMAN_MESSAGE_EVENT)
+    .fetch_optional(pool)
+    .await?;
+    let Some(seen_at) = seen_at else {
+        return Err(KanbanError::BadRequest(format!(
+            "Unknown message: {}",
+            req.last_seen_id
+        )));
+    };
+
+    sqlx::query(
+        "UPDATE cards SET human_message_pending = 0 WHERE id = ?
+         AND NOT EXISTS (SELECT 1 FROM agent_logs WHERE card_id = ? AND event_type = ? AND created_at > ?)",
+    )
+    .bind(&card_id)
+    .bind(&card_id)
+    .bind(HUMAN_MESSAGE_EVENT)
+    .bind(&seen_at)
+    .execute(pool)
+    .await?;
+
+    let pending: bool = sqlx::query_scalar("SELECT human_message_pending FROM cards WHERE id = ?")
+        .bind(&card_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(Json(json!({ "pending": pending })))
+}
@@ -1,23 +1,40 @@
+pub mod attempts;
 pub mod board_settings;
 pub mod boards;
 pub mod cards;
 pub mod comments;
+pub mod events;
 pub mod files;
 pub mod labels;
+pub mod merge_queue;
+pub mod messages;
 pub mod notifications;
+pub mod opencode;
 pub mod picker;
+pub mod prompt_templates;
 pub mod questions;
+pub mod reviews;
+pub mod runs;
 pub mod settings;
 pub mod sse;
 pub mod subtasks;
 pub mod ws;
 
-use axum::Json;
+use axum::{extract::State, Json};
 use serde_json::{json, Value};
 
-pub async fn health_check() -> Json<Value> {
+use crate::api::state::AppState;
+
+/// Public liveness summary. Endpoint URLs, errors and process details are
+/// served to signed-in users by `/api/opencode/status`.
+pub async fn health_check(State(state): State<AppState>) -> Json<Value> {
+    let statuses = state.opencode.statuses(None).await;
     Json(json!({
         "status": "ok",
         "timestamp": chrono::Utc::now().to_rfc3339(),
+        "opencode": {
+            "endpoints": statuses.len(),
+            "healthy": statuses.iter().filter(|s| s.healthy).count(),
+        },
     }))
 }
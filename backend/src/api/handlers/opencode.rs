@@ -0,0 +1,96 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::api::state::AppState;
+use crate::domain::KanbanError;
+use crate::services::model_selection::AvailableModel;
+use crate::services::opencode_pool::EndpointStatus;
+use crate::services::ModelSelection;
+
+const DEFAULT_LOG_LINES: usize = 200;
+const MAX_LOG_LINES: usize = 1000;
+
+#[derive(Debug, Deserialize)]
+pub struct OpencodeLogsQuery {
+    pub endpoint: Option<String>,
+    pub lines: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OpencodeModelsQuery {
+    pub endpoint: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OpencodeLogsResponse {
+    pub endpoint: String,
+    pub log_file: String,
+    pub lines: Vec<String>,
+}
+
+/// Health, load, last error and supervised process of every endpoint.
+pub async fn get_opencode_status(State(state): State<AppState>) -> Json<Vec<EndpointStatus>> {
+    Json(state.opencode.statuses(state.db.as_ref()).await)
+}
+
+/// Recent output of an OpenCode server launched by this backend.
+pub async fn get_opencode_logs(
+    State(state): State<AppState>,
+    Query(query): Query<OpencodeLogsQuery>,
+) -> Result<Json<OpencodeLogsResponse>, KanbanError> {
+    let endpoint = query
+        .endpoint
+        .filter(|e| !e.trim().is_empty())
+        .unwrap_or_else(|| state.opencode.primary().to_string());
+
+    let supervisor = state.opencode.supervisor_for(&endpoint).ok_or_else(|| {
+        KanbanError::NotFound(format!(
+            "No OpenCode process is managed by this backend for '{}'",
+            endpoint
+        ))
+    })?;
+
+    let status = supervisor.status().await;
+    let log_file = status.log_file.ok_or_else(|| {
+        KanbanError::NotFound(format!(
+            "OpenCode at '{}' was not started by this backend",
+            status.url
+        ))
+    })?;
+
+    let lines = query
+        .lines
+        .unwrap_or(DEFAULT_LOG_LINES)
+        .min(MAX_LOG_LINES);
+
+    Ok(Json(OpencodeLogsResponse {
+        endpoint: status.url,
+        log_file,
+        lines: supervisor.recent_logs(lines),
+    }))
+}
+
+/// Models the OpenCode server advertises, as accepted by board settings and
+/// card overrides.
+pub async fn list_opencode_models(
+    State(state): State<AppState>,
+    Query(query): Query<OpencodeModelsQuery>,
+) -> Result<Json<Vec<AvailableModel>>, KanbanError> {
+    let endpoint = query
+        .endpoint
+        .filter(|e| !e.trim().is_empty())
+        .unwrap_or_else(|| state.opencode.primary().to_string());
+    if !state.opencode.contains(&endpoint) {
+        return Err(KanbanError::NotFound(format!(
+            "Unknown OpenCode endpoint '{}'",
+            endpoint
+        )));
+    }
+
+    Ok(Json(
+        ModelSelection::available(&state.http_client, endpoint.trim_end_matches('/')).await?,
+    ))
+}
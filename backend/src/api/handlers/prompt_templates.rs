@@ -0,0 +1,165 @@
+use axum::{
+    extract::{Extension, Path, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::api::state::AppState;
+use crate::auth::middleware::AuthUser;
+use crate::domain::{KanbanError, PromptTemplateVersion};
+use crate::services::prompt_templates::{self, EffectivePromptTemplate, KNOWN_VARIABLES};
+use crate::services::{BoardAccess, CardService, PromptTemplateService, Redactor};
+
+#[derive(Debug, Deserialize)]
+pub struct UpdatePromptTemplateRequest {
+    pub content: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PreviewPromptTemplateRequest {
+    pub card_id: String,
+    /// Unsaved template content to preview; defaults to the effective template.
+    #[serde(default)]
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreviewPromptTemplateResponse {
+    pub name: String,
+    pub card_id: String,
+    pub rendered: String,
+    pub variables: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PromptTemplateListResponse {
+    pub templates: Vec<EffectivePromptTemplate>,
+    pub variables: Vec<String>,
+}
+
+pub async fn list_prompt_templates(
+    State(state): State<AppState>,
+    Path(board_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<PromptTemplateListResponse>, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_access(pool, &auth_user.user_id, &board_id).await?;
+    let templates = PromptTemplateService::list_effective(pool, &board_id).await?;
+
+    Ok(Json(PromptTemplateListResponse {
+        templates,
+        variables: KNOWN_VARIABLES.iter().map(|v| v.to_string()).collect(),
+    }))
+}
+
+pub async fn get_prompt_template(
+    State(state): State<AppState>,
+    Path((board_id, name)): Path<(String, String)>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<EffectivePromptTemplate>, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_access(pool, &auth_user.user_id, &board_id).await?;
+    let template = PromptTemplateService::get_effective(pool, Some(&board_id), &name).await?;
+    Ok(Json(template))
+}
+
+pub async fn update_prompt_template(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path((board_id, name)): Path<(String, String)>,
+    Json(req): Json<UpdatePromptTemplateRequest>,
+) -> Result<Json<EffectivePromptTemplate>, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_access(pool, &auth_user.user_id, &board_id).await?;
+    let template = PromptTemplateService::save_override(
+        pool,
+        &board_id,
+        &name,
+        &req.content,
+        &auth_user.user_id,
+    )
+    .await?;
+    Ok(Json(template))
+}
+
+pub async fn reset_prompt_template(
+    State(state): State<AppState>,
+    Path((board_id, name)): Path<(String, String)>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<EffectivePromptTemplate>, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_access(pool, &auth_user.user_id, &board_id).await?;
+    let template = PromptTemplateService::reset_override(pool, &board_id, &name).await?;
+    Ok(Json(template))
+}
+
+pub async fn list_prompt_template_versions(
+    State(state): State<AppState>,
+    Path((board_id, name)): Path<(String, String)>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<Vec<PromptTemplateVersion>>, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_access(pool, &auth_user.user_id, &board_id).await?;
+    let versions = PromptTemplateService::list_versions(pool, &board_id, &name).await?;
+    Ok(Json(versions))
+}
+
+pub async fn restore_prompt_template_version(
+    State(state): State<AppState>,
+    Path((board_id, name, version)): Path<(String, String, i64)>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<EffectivePromptTemplate>, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_access(pool, &auth_user.user_id, &board_id).await?;
+    let template =
+        PromptTemplateService::restore_version(pool, &board_id, &name, version).await?;
+    Ok(Json(template))
+}
+
+pub async fn preview_prompt_template(
+    State(state): State<AppState>,
+    Path((board_id, name)): Path<(String, String)>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(req): Json<PreviewPromptTemplateRequest>,
+) -> Result<Json<PreviewPromptTemplateResponse>, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_access(pool, &auth_user.user_id, &board_id).await?;
+    let card = CardService::get_card_model(pool, &req.card_id).await?;
+
+    if card.board_id.as_deref() != Some(board_id.as_str()) {
+        return Err(KanbanError::BadRequest(format!(
+            "Card {} does not belong to board {}",
+            card.id, board_id
+        )));
+    }
+
+    let content = match req.content {
+        Some(content) => {
+            PromptTemplateService::validate(&content)?;
+            content
+        }
+        None => {
+            PromptTemplateService::get_effective(pool, Some(&board_id), &name)
+                .await?
+                .content
+        }
+    };
+
+    let mut variables = PromptTemplateService::card_variables(pool, &card, &content).await?;
+    if name == prompt_templates::AUTO_DETECT && variables["codebase_path"].is_empty() {
+        variables.insert("codebase_path".into(), card.working_directory.clone());
+    }
+
+    let rendered = Redactor::for_board(pool, card.board_id.as_deref())
+        .await?
+        .redact(&PromptTemplateService::render(&content, &variables)?);
+    let mut variable_names: Vec<String> = variables.into_keys().collect();
+    variable_names.sort();
+
+    Ok(Json(PreviewPromptTemplateResponse {
+        name,
+        card_id: card.id,
+        rendered,
+        variables: variable_names,
+    }))
+}
@@ -1,5 +1,5 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Extension, Path, State},
     http::StatusCode,
     Json,
 };
@@ -9,8 +9,9 @@ use uuid::Uuid;
 
 use crate::api::AppState;
 use crate::api::handlers::sse::WsEvent;
+use crate::auth::middleware::AuthUser;
 use crate::domain::{AiQuestion, KanbanError, NotificationType};
-use crate::services::NotificationService;
+use crate::services::{BoardAccess, NotificationService};
 
 #[derive(Debug, Deserialize)]
 pub struct CreateQuestionRequest {
@@ -137,9 +138,11 @@ pub async fn create_question(
 pub async fn answer_question(
     State(state): State<AppState>,
     Path((card_id, question_id)): Path<(String, String)>,
+    Extension(auth_user): Extension<AuthUser>,
     Json(req): Json<AnswerQuestionRequest>,
 ) -> Result<Json<AiQuestion>, KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
 
     let question: AiQuestion =
         sqlx::query_as("SELECT * FROM ai_questions WHERE id = ? AND card_id = ?")
@@ -0,0 +1,127 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+
+use crate::api::AppState;
+use crate::auth::middleware::AuthUser;
+use crate::domain::KanbanError;
+use crate::services::ai_review::{AiReviewWithFindings, SubmitReviewRequest};
+use crate::services::review_threads::{
+    CreateReviewThreadRequest, ReplyReviewThreadRequest, ReviewThread,
+};
+use crate::services::{AiReviewService, BoardAccess, CardService, ReviewThreadService};
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewThreadsQuery {
+    #[serde(default)]
+    pub unresolved: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateReviewThreadRequest {
+    pub resolved: bool,
+}
+
+/// Every automatic review round on the card with its findings, oldest first.
+pub async fn list_ai_reviews(
+    State(state): State<AppState>,
+    Path(card_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<Vec<AiReviewWithFindings>>, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
+    CardService::get_card_model(pool, &card_id).await?;
+    Ok(Json(AiReviewService::list(pool, &card_id).await?))
+}
+
+/// The reviewer agent's verdict for the card's running review.
+pub async fn submit_ai_review(
+    State(state): State<AppState>,
+    Path(card_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(req): Json<SubmitReviewRequest>,
+) -> Result<Json<AiReviewWithFindings>, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
+    let card = CardService::get_card_model(pool, &card_id).await?;
+    let review =
+        AiReviewService::submit(pool, &state.sse_tx, &state.http_client, &card, &req).await?;
+    Ok(Json(review))
+}
+
+/// The card's review threads, oldest first; `?unresolved=true` keeps only
+/// the ones still open.
+pub async fn list_review_threads(
+    State(state): State<AppState>,
+    Path(card_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<ReviewThreadsQuery>,
+) -> Result<Json<Vec<ReviewThread>>, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
+    CardService::get_card_model(pool, &card_id).await?;
+    Ok(Json(
+        ReviewThreadService::list(pool, &card_id, query.unresolved).await?,
+    ))
+}
+
+pub async fn create_review_thread(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(card_id): Path<String>,
+    Json(req): Json<CreateReviewThreadRequest>,
+) -> Result<(StatusCode, Json<ReviewThread>), KanbanError> {
+    let pool = state.require_db()?;
+    let card = CardService::get_card_model(pool, &card_id).await?;
+    if let Some(board_id) = card.board_id.as_deref().filter(|id| !id.is_empty()) {
+        BoardAccess::require_access(pool, &auth_user.user_id, board_id).await?;
+    }
+    let thread =
+        ReviewThreadService::create(pool, &state.sse_tx, &card, req, Some(&auth_user.user_id))
+            .await?;
+    Ok((StatusCode::CREATED, Json(thread)))
+}
+
+pub async fn reply_review_thread(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(thread_id): Path<String>,
+    Json(req): Json<ReplyReviewThreadRequest>,
+) -> Result<(StatusCode, Json<ReviewThread>), KanbanError> {
+    let pool = state.require_db()?;
+    let card_id = ReviewThreadService::get(pool, &thread_id).await?.thread.card_id;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
+    let thread = ReviewThreadService::reply(
+        pool,
+        &state.sse_tx,
+        &thread_id,
+        req,
+        Some(&auth_user.user_id),
+    )
+    .await?;
+    Ok((StatusCode::CREATED, Json(thread)))
+}
+
+/// Resolves or reopens a thread.
+pub async fn update_review_thread(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(thread_id): Path<String>,
+    Json(req): Json<UpdateReviewThreadRequest>,
+) -> Result<Json<ReviewThread>, KanbanError> {
+    let pool = state.require_db()?;
+    let card_id = ReviewThreadService::get(pool, &thread_id).await?.thread.card_id;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
+    let thread = ReviewThreadService::set_resolved(
+        pool,
+        &state.sse_tx,
+        &thread_id,
+        req.resolved,
+        Some(&auth_user.user_id),
+    )
+    .await?;
+    Ok(Json(thread))
+}
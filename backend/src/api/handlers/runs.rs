@@ -0,0 +1,43 @@
+use axum::{
+    extract::{Extension, Path, State},
+    Json,
+};
+
+use crate::api::AppState;
+use crate::auth::middleware::AuthUser;
+use crate::domain::{AgentLog, KanbanError};
+use crate::services::card_runs::CardRun;
+use crate::services::git_worktree::DiffResult;
+use crate::services::{BoardAccess, CardRunService, CardService};
+
+/// Every AI run on the card, oldest first.
+pub async fn list_runs(
+    State(state): State<AppState>,
+    Path(card_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<Vec<CardRun>>, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
+    CardService::get_card_model(pool, &card_id).await?;
+    Ok(Json(CardRunService::list(pool, &card_id).await?))
+}
+
+pub async fn get_run_diff(
+    State(state): State<AppState>,
+    Path((card_id, run_id)): Path<(String, String)>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<DiffResult>, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
+    Ok(Json(CardRunService::diff(pool, &card_id, &run_id).await?))
+}
+
+pub async fn get_run_logs(
+    State(state): State<AppState>,
+    Path((card_id, run_id)): Path<(String, String)>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<Vec<AgentLog>>, KanbanError> {
+    let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
+    Ok(Json(CardRunService::logs(pool, &card_id, &run_id).await?))
+}
@@ -6,6 +6,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::api::state::AppState;
 use crate::domain::KanbanError;
+use crate::services::redaction::{self, EXTRA_PATTERNS_SETTING};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Setting {
@@ -49,6 +50,14 @@ pub async fn set_setting(
     let db = state.require_db()?;
     let now = chrono::Utc::now().to_rfc3339();
 
+    if key == EXTRA_PATTERNS_SETTING {
+        for pattern in redaction::parse_patterns(&req.value) {
+            regex::Regex::new(&pattern).map_err(|e| {
+                KanbanError::BadRequest(format!("Invalid redaction pattern '{}': {}", pattern, e))
+            })?;
+        }
+    }
+
     sqlx::query(
         "INSERT INTO settings (key, value, updated_at) VALUES (?, ?, ?)
          ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
@@ -58,6 +67,9 @@ pub async fn set_setting(
     .bind(&now)
     .execute(db)
     .await?;
+    if key == EXTRA_PATTERNS_SETTING {
+        state.redactors.clear();
+    }
 
     Ok(Json(Setting {
         key,
@@ -17,6 +17,8 @@ pub enum WsEvent {
     },
     CardDeleted {
         card_id: String,
+        #[serde(default)]
+        board_id: Option<String>,
     },
     SubtaskCreated {
         card_id: String,
@@ -56,6 +58,9 @@ pub enum WsEvent {
     BoardDeleted {
         board_id: String,
     },
+    BoardMembersChanged {
+        board_id: String,
+    },
     LabelAdded {
         card_id: String,
         label_id: String,
@@ -76,6 +81,26 @@ pub enum WsEvent {
         card_id: String,
         log: AgentLog,
     },
+    /// Missing history was recovered from OpenCode; reload the card's logs.
+    AgentLogsBackfilled {
+        card_id: String,
+        inserted: usize,
+    },
+    /// A competing attempt changed status or finished verification.
+    CardAttemptUpdated {
+        card_id: String,
+        attempt: serde_json::Value,
+    },
+    /// A review thread was opened, replied to, resolved or reopened.
+    ReviewThreadUpdated {
+        card_id: String,
+        thread: serde_json::Value,
+    },
+    /// An automatic review started, reported its findings or failed.
+    AiReviewUpdated {
+        card_id: String,
+        review: serde_json::Value,
+    },
     QuestionCreated {
         card_id: String,
         question: serde_json::Value,
@@ -105,7 +130,25 @@ pub enum WsEvent {
     MergeCompleted {
         card_id: String,
     },
+    /// Some of the card's changes landed on the base branch; the rest stay
+    /// on the card branch.
+    MergePartiallyApplied {
+        card_id: String,
+        commit: String,
+        remaining_files: usize,
+    },
     MergeAborted {
         card_id: String,
     },
+    /// A repository's merge queue changed; `entries` is the board's current
+    /// listing, sent once per board on the repository.
+    MergeQueueUpdated {
+        board_id: String,
+        codebase_path: String,
+        entries: serde_json::Value,
+    },
+    /// The requested resume point is no longer retained; reload state.
+    ResyncRequired {
+        latest_seq: i64,
+    },
 }
@@ -1,5 +1,5 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Extension, Path, State},
     http::StatusCode,
     Json,
 };
@@ -8,15 +8,18 @@ use sqlx;
 use crate::api::dto::{CreateSubtaskRequest, UpdateSubtaskRequest};
 use crate::api::handlers::sse::WsEvent;
 use crate::api::AppState;
+use crate::auth::middleware::AuthUser;
 use crate::domain::{KanbanError, Subtask};
-use crate::services::CardService;
+use crate::services::{BoardAccess, CardService};
 
 pub async fn create_subtask(
     State(state): State<AppState>,
     Path(card_id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
     Json(req): Json<CreateSubtaskRequest>,
 ) -> Result<(StatusCode, Json<Subtask>), KanbanError> {
     let pool = state.require_db()?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
     let subtask = CardService::create_subtask(pool, &card_id, req).await?;
 
     let event = WsEvent::SubtaskCreated {
@@ -33,6 +36,7 @@ pub async fn create_subtask(
 pub async fn update_subtask(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
     Json(req): Json<UpdateSubtaskRequest>,
 ) -> Result<Json<Subtask>, KanbanError> {
     let pool = state.require_db()?;
@@ -40,6 +44,7 @@ pub async fn update_subtask(
         .bind(&id)
         .fetch_one(pool)
         .await?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
 
     let subtask = CardService::update_subtask(pool, &id, req).await?;
 
@@ -57,12 +62,14 @@ pub async fn update_subtask(
 pub async fn delete_subtask(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Extension(auth_user): Extension<AuthUser>,
 ) -> Result<StatusCode, KanbanError> {
     let pool = state.require_db()?;
     let card_id = sqlx::query_scalar::<_, String>("SELECT card_id FROM subtasks WHERE id = ?")
         .bind(&id)
         .fetch_one(pool)
         .await?;
+    BoardAccess::require_card_access(pool, &auth_user.user_id, &card_id).await?;
 
     CardService::delete_subtask(pool, &id).await?;
 
@@ -1,39 +1,106 @@
+use std::collections::HashSet;
+
 use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
 use axum::extract::{Path, Query, State};
 use axum::response::IntoResponse;
+use axum::Extension;
+use serde::Deserialize;
+use serde_json::json;
 use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::watch;
 
-use crate::api::handlers::sse::WsEvent;
 use crate::api::AppState;
+use crate::auth::middleware::AuthUser;
 use crate::domain::KanbanError;
+use crate::services::event_bus::{EventAccess, EventFilter};
+use crate::services::BoardAccess;
 
 pub async fn ws_logs_handler(
     ws: WebSocketUpgrade,
     Path(card_id): Path<String>,
     State(state): State<AppState>,
-) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_ws(socket, card_id, state))
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<impl IntoResponse, KanbanError> {
+    let db = state.require_db()?;
+    BoardAccess::require_card_access(db, &auth_user.user_id, &card_id).await?;
+
+    Ok(ws.on_upgrade(move |socket| handle_ws(socket, card_id, state)))
 }
 
-#[derive(serde::Deserialize)]
+#[derive(Deserialize)]
 pub struct WsEventsQuery {
-    pub token: String,
+    pub token: Option<String>,
+    /// Replay every event with a greater `seq` before streaming live events.
+    pub since: Option<i64>,
+    /// Comma-separated initial filters; see [`EventFilter`].
+    pub board_ids: Option<String>,
+    pub card_ids: Option<String>,
+    pub types: Option<String>,
+}
+
+/// Client → server messages on `/ws/events`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+enum ControlMessage {
+    /// Replaces the current filter; omitted fields are unconstrained.
+    Subscribe {
+        #[serde(default)]
+        board_ids: Option<HashSet<String>>,
+        #[serde(default)]
+        card_ids: Option<HashSet<String>>,
+        #[serde(default)]
+        types: Option<HashSet<String>>,
+    },
+    /// Clears the filter: every event the user may see.
+    Reset,
 }
 
 pub async fn ws_events_handler(
     ws: WebSocketUpgrade,
     State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
     Query(params): Query<WsEventsQuery>,
 ) -> Result<impl IntoResponse, KanbanError> {
     let pool = state.require_db()?;
-    let signing_key = crate::auth::jwt::get_or_create_signing_key(pool)
-        .await
-        .map_err(|e| KanbanError::Unauthorized(format!("JWT key error: {e}")))?;
 
-    let _auth_user = crate::auth::jwt::verify_token(&signing_key, &params.token)
-        .map_err(|e| KanbanError::Unauthorized(format!("Invalid token: {e}")))?;
+    // The auth middleware has already accepted the request (cookie or
+    // header); an explicit `?token=` must still be valid.
+    if let Some(token) = &params.token {
+        let signing_key = crate::auth::jwt::get_or_create_signing_key(pool)
+            .await
+            .map_err(|e| KanbanError::Unauthorized(format!("JWT key error: {e}")))?;
+
+        let _auth_user = crate::auth::jwt::verify_token(&signing_key, token)
+            .map_err(|e| KanbanError::Unauthorized(format!("Invalid token: {e}")))?;
+    }
+
+    let filter = EventFilter::from_query(
+        params.board_ids.as_deref(),
+        params.card_ids.as_deref(),
+        params.types.as_deref(),
+    );
+    check_filter_access(pool, &auth_user, &filter).await?;
+    let access = EventAccess::for_user(pool, &auth_user.user_id).await?;
 
-    Ok(ws.on_upgrade(move |socket| handle_ws_events(socket, state)))
+    let since = params.since;
+    Ok(ws.on_upgrade(move |socket| {
+        handle_ws_events(socket, state, auth_user, since, access, filter)
+    }))
+}
+
+/// Rejects filters naming boards the user may not access, so a client can
+/// tell a typo or a revoked board from a quiet one.
+pub async fn check_filter_access(
+    db: &sqlx::SqlitePool,
+    auth_user: &AuthUser,
+    filter: &EventFilter,
+) -> Result<(), KanbanError> {
+    if let Some(board_ids) = &filter.board_ids {
+        for board_id in board_ids {
+            BoardAccess::require_access(db, &auth_user.user_id, board_id).await?;
+        }
+    }
+    Ok(())
 }
 
 async fn handle_ws(mut socket: WebSocket, card_id: String, state: AppState) {
@@ -41,17 +108,17 @@ async fn handle_ws(mut socket: WebSocket, card_id: String, state: AppState) {
 
     loop {
         match rx.recv().await {
-            Ok(msg) => {
-                if let Ok(WsEvent::AgentLogCreated {
-                    card_id: event_card_id,
-                    ..
-                }) = serde_json::from_str::<WsEvent>(&msg)
+            Ok(event) => {
+                let is_log_event = event.event_type == "agentLogCreated"
+                    || event.event_type == "agentLogsBackfilled";
+                if is_log_event
+                    && event.scope.card_id.as_deref() == Some(card_id.as_str())
+                    && socket
+                        .send(Message::Text(event.message.clone().into()))
+                        .await
+                        .is_err()
                 {
-                    if event_card_id == card_id
-                        && socket.send(Message::Text(msg.into())).await.is_err()
-                    {
-                        break;
-                    }
+                    break;
                 }
             }
             Err(RecvError::Lagged(n)) => {
@@ -63,25 +130,77 @@ async fn handle_ws(mut socket: WebSocket, card_id: String, state: AppState) {
     }
 }
 
-async fn handle_ws_events(mut socket: WebSocket, state: AppState) {
-    let mut rx = state.sse_tx.subscribe();
+async fn handle_ws_events(
+    mut socket: WebSocket,
+    state: AppState,
+    auth_user: AuthUser,
+    since: Option<i64>,
+    access: EventAccess,
+    filter: EventFilter,
+) {
+    let (filter_tx, filter_rx) = watch::channel(filter);
+    let mut events = state.sse_tx.stream_from(since, access, filter_rx);
 
     let _ = socket
         .send(Message::Text(r#"{"type":"connected"}"#.into()))
         .await;
 
     loop {
-        match rx.recv().await {
-            Ok(msg) => {
+        tokio::select! {
+            msg = events.recv() => {
+                let Some(msg) = msg else { break };
                 if socket.send(Message::Text(msg.into())).await.is_err() {
                     break;
                 }
             }
-            Err(RecvError::Lagged(n)) => {
-                tracing::debug!(skipped = n, "WS events receiver lagged, continuing");
-                continue;
+            incoming = socket.recv() => {
+                let text = match incoming {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                    Some(Ok(_)) => continue,
+                };
+
+                let reply = apply_control(&state, &auth_user, &filter_tx, &text).await;
+                if socket.send(Message::Text(reply.to_string().into())).await.is_err() {
+                    break;
+                }
             }
-            Err(RecvError::Closed) => break,
         }
     }
 }
+
+async fn apply_control(
+    state: &AppState,
+    auth_user: &AuthUser,
+    filter_tx: &watch::Sender<EventFilter>,
+    text: &str,
+) -> serde_json::Value {
+    let control = match serde_json::from_str::<ControlMessage>(text) {
+        Ok(control) => control,
+        Err(e) => {
+            return json!({ "type": "subscriptionError", "message": format!("Invalid control message: {}", e) });
+        }
+    };
+
+    let filter = match control {
+        ControlMessage::Subscribe {
+            board_ids,
+            card_ids,
+            types,
+        } => EventFilter {
+            board_ids,
+            card_ids,
+            types,
+        },
+        ControlMessage::Reset => EventFilter::default(),
+    };
+
+    if let Ok(db) = state.require_db() {
+        if let Err(e) = check_filter_access(db, auth_user, &filter).await {
+            return json!({ "type": "subscriptionError", "message": e.to_string() });
+        }
+    }
+
+    filter_tx.send_replace(filter.clone());
+    json!({ "type": "subscribed", "filter": filter })
+}
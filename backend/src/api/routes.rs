@@ -1,5 +1,5 @@
 use axum::http::{header, HeaderValue, Method};
-use axum::routing::{get, patch, post};
+use axum::routing::{get, patch, post, put};
 use axum::Router;
 use tower_http::cors::CorsLayer;
 use tower_http::services::ServeDir;
@@ -38,10 +38,51 @@ pub fn create_router(state: AppState, config: &Config) -> Router {
                 .delete(handlers::cards::delete_card),
         )
         .route("/{id}/logs", get(handlers::cards::get_card_logs))
+        .route(
+            "/{id}/messages",
+            get(handlers::messages::list_messages).post(handlers::messages::send_message),
+        )
+        .route(
+            "/{id}/messages/ack",
+            post(handlers::messages::acknowledge_messages),
+        )
+        .route(
+            "/{id}/attempts",
+            get(handlers::attempts::list_attempts).post(handlers::attempts::create_attempts),
+        )
+        .route(
+            "/{id}/attempts/{attempt_id}/diff",
+            get(handlers::attempts::get_attempt_diff),
+        )
+        .route(
+            "/{id}/attempts/{attempt_id}/select",
+            post(handlers::attempts::select_attempt),
+        )
+        .route(
+            "/{id}/ai-reviews",
+            get(handlers::reviews::list_ai_reviews).post(handlers::reviews::submit_ai_review),
+        )
+        .route(
+            "/{id}/review-threads",
+            get(handlers::reviews::list_review_threads)
+                .post(handlers::reviews::create_review_thread),
+        )
+        .route("/{id}/runs", get(handlers::runs::list_runs))
+        .route("/{id}/runs/{run_id}/diff", get(handlers::runs::get_run_diff))
+        .route("/{id}/runs/{run_id}/logs", get(handlers::runs::get_run_logs))
+        .route(
+            "/{id}/logs/backfill",
+            post(handlers::cards::backfill_card_logs),
+        )
+        .route("/{id}/logs/export", get(handlers::cards::export_card_logs))
         .route(
             "/{id}/agent-activity",
             get(handlers::cards::get_agent_activity),
         )
+        .route(
+            "/{id}/transcript",
+            get(handlers::cards::export_card_transcript),
+        )
         .route("/{id}/versions", get(handlers::cards::list_card_versions))
         .route(
             "/{id}/versions/{version_id}/restore",
@@ -50,6 +91,11 @@ pub fn create_router(state: AppState, config: &Config) -> Router {
         .route("/{id}/move", patch(handlers::cards::move_card))
         .route("/{id}/diff", get(handlers::cards::get_card_diff))
         .route("/{id}/merge", post(handlers::cards::merge_card))
+        .route("/{id}/merge-partial", post(handlers::cards::merge_card_partial))
+        .route(
+            "/{id}/merge-queue",
+            post(handlers::merge_queue::enqueue_card).delete(handlers::merge_queue::cancel_card),
+        )
         .route("/{id}/conflicts", get(handlers::cards::get_conflicts))
         .route(
             "/{id}/resolve-conflicts",
@@ -60,6 +106,10 @@ pub fn create_router(state: AppState, config: &Config) -> Router {
             post(handlers::cards::complete_merge),
         )
         .route("/{id}/abort-merge", post(handlers::cards::abort_merge))
+        .route(
+            "/{id}/ai-resolve-conflicts",
+            post(handlers::cards::ai_resolve_conflicts),
+        )
         .route("/{id}/create-pr", post(handlers::cards::create_card_pr))
         .route("/{id}/reject", post(handlers::cards::reject_card))
         .route("/{id}/generate-plan", post(handlers::cards::generate_plan))
@@ -90,6 +140,13 @@ pub fn create_router(state: AppState, config: &Config) -> Router {
         patch(handlers::comments::update_comment).delete(handlers::comments::delete_comment),
     );
 
+    let review_thread_routes = Router::new()
+        .route("/{id}", patch(handlers::reviews::update_review_thread))
+        .route(
+            "/{id}/comments",
+            post(handlers::reviews::reply_review_thread),
+        );
+
     let subtask_routes = Router::new().route(
         "/{id}",
         patch(handlers::subtasks::update_subtask).delete(handlers::subtasks::delete_subtask),
@@ -105,11 +162,26 @@ pub fn create_router(state: AppState, config: &Config) -> Router {
             patch(handlers::boards::update_board).delete(handlers::boards::delete_board),
         )
         .route("/{id}/reorder", patch(handlers::boards::reorder_board))
+        .route(
+            "/{id}/members",
+            get(handlers::boards::list_board_members).put(handlers::boards::set_board_members),
+        )
+        .route("/{id}/logs/export", get(handlers::boards::export_board_logs))
+        .route("/{id}/merge-queue", get(handlers::merge_queue::list_board_queue))
         .route(
             "/{id}/settings",
             get(handlers::board_settings::get_board_settings)
                 .put(handlers::board_settings::update_board_settings),
         )
+        .route(
+            "/{id}/settings/secrets",
+            get(handlers::board_settings::list_secret_variables),
+        )
+        .route(
+            "/{id}/settings/secrets/{name}",
+            put(handlers::board_settings::set_secret_variable)
+                .delete(handlers::board_settings::delete_secret_variable),
+        )
         .route(
             "/{id}/settings/auto-detect",
             post(handlers::board_settings::auto_detect_board_settings),
@@ -125,6 +197,28 @@ pub fn create_router(state: AppState, config: &Config) -> Router {
         .route(
             "/{id}/settings/auto-detect-logs",
             get(handlers::board_settings::get_auto_detect_logs),
+        )
+        .route(
+            "/{id}/prompt-templates",
+            get(handlers::prompt_templates::list_prompt_templates),
+        )
+        .route(
+            "/{id}/prompt-templates/{name}",
+            get(handlers::prompt_templates::get_prompt_template)
+                .put(handlers::prompt_templates::update_prompt_template)
+                .delete(handlers::prompt_templates::reset_prompt_template),
+        )
+        .route(
+            "/{id}/prompt-templates/{name}/preview",
+            post(handlers::prompt_templates::preview_prompt_template),
+        )
+        .route(
+            "/{id}/prompt-templates/{name}/versions",
+            get(handlers::prompt_templates::list_prompt_template_versions),
+        )
+        .route(
+            "/{id}/prompt-templates/{name}/versions/{version}/restore",
+            post(handlers::prompt_templates::restore_prompt_template_version),
         );
 
     let file_routes = Router::new().route(
@@ -179,14 +273,22 @@ pub fn create_router(state: AppState, config: &Config) -> Router {
         )
         .route("/api/auth/me/avatar", post(auth::handlers::upload_avatar))
         .route("/api/auth/logout", post(auth::handlers::logout))
+        .route("/api/events", get(handlers::events::stream_events))
         .route("/ws/events", get(handlers::ws::ws_events_handler))
         .route("/ws/logs/{card_id}", get(handlers::ws::ws_logs_handler))
         .route("/api/board", get(handlers::cards::get_board))
         .route("/api/labels", get(handlers::labels::list_labels))
+        .route("/api/opencode/status", get(handlers::opencode::get_opencode_status))
+        .route("/api/opencode/logs", get(handlers::opencode::get_opencode_logs))
+        .route(
+            "/api/opencode/models",
+            get(handlers::opencode::list_opencode_models),
+        )
         .nest("/api/boards", board_routes)
         .nest("/api/cards", card_routes)
         .nest("/api/subtasks", subtask_routes)
         .nest("/api/comments", comment_routes)
+        .nest("/api/review-threads", review_thread_routes)
         .nest("/api/files", file_routes)
         .nest("/api/notifications", notification_routes)
         .route(
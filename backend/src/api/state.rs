@@ -3,33 +3,39 @@ use std::sync::Arc;
 use std::sync::Mutex;
 
 use sqlx::SqlitePool;
-use tokio::sync::broadcast;
 
 use crate::config::Config;
 use crate::domain::KanbanError;
+use crate::services::merge_queue::MergeLocks;
+use crate::services::{EventBus, OpencodePool, RedactorCache};
 
 #[derive(Clone, Debug)]
 pub struct AppState {
     pub db: Option<SqlitePool>,
-    pub sse_tx: broadcast::Sender<String>,
+    pub sse_tx: EventBus,
     pub http_client: reqwest::Client,
     pub config: Arc<Config>,
-    pub merge_locks: Arc<Mutex<HashSet<String>>>,
+    pub opencode: OpencodePool,
+    pub merge_locks: MergeLocks,
+    pub redactors: RedactorCache,
 }
 
 impl AppState {
     pub fn new(
         db: Option<SqlitePool>,
-        sse_tx: broadcast::Sender<String>,
+        sse_tx: EventBus,
         http_client: reqwest::Client,
         config: Arc<Config>,
     ) -> Self {
+        let opencode = OpencodePool::from_config(&config, http_client.clone());
         Self {
             db,
             sse_tx,
             http_client,
             config,
+            opencode,
             merge_locks: Arc::new(Mutex::new(HashSet::new())),
+            redactors: RedactorCache::default(),
         }
     }
 
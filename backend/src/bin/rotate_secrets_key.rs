@@ -0,0 +1,19 @@
+//! Re-encrypts every secret board variable under a fresh key.
+//!
+//! Stop the server first, then run with the same `DATABASE_URL`:
+//! `cargo run --bin rotate_secrets_key`
+
+use kanban_backend::config::Config;
+use kanban_backend::infrastructure::db;
+use kanban_backend::services::BoardSecretService;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::from_env().unwrap_or_default();
+    let pool = db::init_db(&config.database_url).await?;
+
+    let rotated = BoardSecretService::rotate_key(&pool).await?;
+    println!("Rotated board secrets key; re-encrypted {} secret variable(s)", rotated);
+
+    Ok(())
+}
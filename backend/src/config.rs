@@ -5,6 +5,21 @@ pub struct Config {
     pub port: u16,
     pub database_url: String,
     pub opencode_url: String,
+    /// Additional OpenCode endpoints (`OPENCODE_URLS`, comma separated). When
+    /// empty, `opencode_instances` consecutive ports starting at
+    /// `opencode_url` are used instead.
+    pub opencode_urls: Vec<String>,
+    pub opencode_instances: u16,
+    /// Directory for the captured output of locally managed OpenCode servers.
+    pub opencode_log_dir: String,
+    /// Number of most recent events kept in the `events` table for replay.
+    pub event_retention: i64,
+    /// Window in which per-card AI progress updates are batched (0 disables).
+    pub ai_status_coalesce_ms: u64,
+    /// Directory for compressed agent log archives.
+    pub log_archive_dir: String,
+    /// How often board log retention policies are enforced.
+    pub log_compaction_interval_secs: u64,
     pub frontend_dir: String,
     pub cors_origin: String,
     pub cookie_secure: bool,
@@ -12,6 +27,13 @@ pub struct Config {
 
 impl Config {
     pub fn from_env() -> Result<Self, anyhow::Error> {
+        let opencode_urls: Vec<String> = std::env::var("OPENCODE_URLS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty())
+            .collect();
+
         Ok(Self {
             port: std::env::var("PORT")
                 .ok()
@@ -20,7 +42,30 @@ impl Config {
             database_url: std::env::var("DATABASE_URL")
                 .unwrap_or_else(|_| "sqlite:kanban.db".into()),
             opencode_url: std::env::var("OPENCODE_URL")
-                .unwrap_or_else(|_| "http://localhost:4096".into()),
+                .ok()
+                .or_else(|| opencode_urls.first().cloned())
+                .unwrap_or_else(|| "http://localhost:4096".into()),
+            opencode_urls,
+            opencode_instances: std::env::var("OPENCODE_INSTANCES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1),
+            opencode_log_dir: std::env::var("OPENCODE_LOG_DIR")
+                .unwrap_or_else(|_| "logs".into()),
+            event_retention: std::env::var("EVENT_RETENTION")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(10_000),
+            ai_status_coalesce_ms: std::env::var("AI_STATUS_COALESCE_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1000),
+            log_archive_dir: std::env::var("LOG_ARCHIVE_DIR")
+                .unwrap_or_else(|_| "log-archive".into()),
+            log_compaction_interval_secs: std::env::var("LOG_COMPACTION_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(3600),
             frontend_dir: std::env::var("FRONTEND_DIR")
                 .unwrap_or_else(|_| "../frontend/dist".into()),
             cors_origin: std::env::var("CORS_ORIGIN")
@@ -33,12 +78,41 @@ impl Config {
     }
 }
 
+impl Config {
+    /// Every OpenCode endpoint the backend should use, primary first.
+    pub fn opencode_endpoints(&self) -> Vec<String> {
+        if !self.opencode_urls.is_empty() {
+            return self.opencode_urls.clone();
+        }
+
+        let base = self.opencode_url.trim_end_matches('/');
+        let parsed = base
+            .rsplit_once(':')
+            .and_then(|(host, port)| port.parse::<u16>().ok().map(|port| (host, port)));
+
+        match parsed {
+            Some((host, port)) if self.opencode_instances > 1 => (0..self.opencode_instances)
+                .filter_map(|offset| port.checked_add(offset))
+                .map(|port| format!("{}:{}", host, port))
+                .collect(),
+            _ => vec![base.to_string()],
+        }
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
             port: 21547,
             database_url: "sqlite:kanban.db".into(),
             opencode_url: "http://localhost:4096".into(),
+            opencode_urls: Vec::new(),
+            opencode_instances: 1,
+            opencode_log_dir: "logs".into(),
+            event_retention: 10_000,
+            ai_status_coalesce_ms: 1000,
+            log_archive_dir: "log-archive".into(),
+            log_compaction_interval_secs: 3600,
             frontend_dir: "../frontend/dist".into(),
             cors_origin: "http://localhost:21548,http://127.0.0.1:21548".into(),
             cookie_secure: false,
@@ -22,6 +22,12 @@ pub struct Card {
     pub ai_agent: Option<String>,
     pub branch_name: String,
     pub worktree_path: String,
+    pub opencode_url: String,
+    /// A human message arrived that the agent has not acknowledged yet.
+    pub human_message_pending: bool,
+    /// `provider/model` overrides of the board defaults; `None` inherits.
+    pub planning_model: Option<String>,
+    pub implementation_model: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -32,6 +38,7 @@ pub enum NotificationType {
     AiQuestionPending,
     ReviewRequested,
     AiError,
+    MergeFailed,
 }
 
 impl fmt::Display for NotificationType {
@@ -42,6 +49,7 @@ impl fmt::Display for NotificationType {
             Self::AiQuestionPending => write!(f, "ai_question_pending"),
             Self::ReviewRequested => write!(f, "review_requested"),
             Self::AiError => write!(f, "ai_error"),
+            Self::MergeFailed => write!(f, "merge_failed"),
         }
     }
 }
@@ -133,3 +141,24 @@ pub struct AiQuestion {
     pub answered_at: Option<String>,
     pub created_at: String,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PromptTemplate {
+    pub board_id: String,
+    pub name: String,
+    pub content: String,
+    pub version: i64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PromptTemplateVersion {
+    pub id: String,
+    pub board_id: String,
+    pub name: String,
+    pub version: i64,
+    pub content: String,
+    pub changed_by: String,
+    pub created_at: String,
+}
@@ -13,6 +13,9 @@ pub enum KanbanError {
     #[error("Unauthorized: {0}")]
     Unauthorized(String),
 
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
 
@@ -32,6 +35,7 @@ impl IntoResponse for KanbanError {
             KanbanError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
             KanbanError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
             KanbanError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
+            KanbanError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
             KanbanError::Conflict(msg) => (StatusCode::CONFLICT, msg),
             KanbanError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
             KanbanError::OpenCodeError(msg) => (StatusCode::BAD_GATEWAY, msg),
@@ -4,7 +4,7 @@ pub mod stage;
 
 pub use card::{
     AgentLog, AiQuestion, Card, CardVersion, Comment, Label, Notification, NotificationType,
-    SessionMapping, Subtask,
+    PromptTemplate, PromptTemplateVersion, SessionMapping, Subtask,
 };
 pub use error::KanbanError;
 pub use stage::Stage;
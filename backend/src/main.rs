@@ -1,15 +1,18 @@
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use tokio::net::TcpListener;
-use tokio::sync::broadcast;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use kanban_backend::api::{create_router, AppState};
 use kanban_backend::config::Config;
 use kanban_backend::infrastructure::db;
 use kanban_backend::mcp::KanbanMcp;
-use kanban_backend::services::{GitWorktreeService, OpencodeManager, QueueProcessor, SseRelayService};
+use kanban_backend::services::{
+    EventBus, GitWorktreeService, OpencodeManager, OpencodePool, OpencodeSupervisor, QueueProcessor,
+    LogBackfillService, LogRetentionService, MergeQueueProcessor, SseRelayService, StatusCoalescer,
+};
 use kanban_backend::services::opencode_manager::OpencodeStatus;
 use rmcp::transport::streamable_http_server::{
     session::local::LocalSessionManager, StreamableHttpService,
@@ -74,9 +77,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     tracing::warn!(card_id = %card_id, codebase_path = %codebase_path, error = %error, "Failed to abort stale merge during startup");
                 }
 
-                if let Err(error) = GitWorktreeService::run_git(&codebase_path, &["checkout", "-"]) {
-                    tracing::warn!(card_id = %card_id, codebase_path = %codebase_path, error = %error, "Failed to return branch after stale merge recovery");
-                }
+                GitWorktreeService::return_to_previous_branch(&codebase_path);
             }
 
             Some(pool)
@@ -87,37 +88,93 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     };
 
-    let opencode_mgr = OpencodeManager::new(&config.opencode_url);
-    match opencode_mgr.ensure_running().await {
-        OpencodeStatus::AlreadyRunning => {}
-        OpencodeStatus::Started => {
-            tracing::info!("opencode server started automatically");
+    let sse_tx = EventBus::new(db_pool.clone(), config.event_retention);
+    let http_client = reqwest::Client::new();
+    let config = Arc::new(config);
+
+    let mcp_pool = db_pool.clone();
+    let state = AppState::new(db_pool.clone(), sse_tx.clone(), http_client.clone(), Arc::clone(&config));
+    let opencode = state.opencode.clone();
+    let merge_locks = state.merge_locks.clone();
+    let redactors = state.redactors.clone();
+
+    for url in opencode.urls() {
+        let opencode_mgr = OpencodeManager::new(&url);
+        if !opencode_mgr.is_local() {
+            continue;
         }
-        OpencodeStatus::Failed(e) => {
-            tracing::warn!("opencode auto-start failed: {e} — AI features unavailable until opencode is started manually");
+        let supervisor = OpencodeSupervisor::new(opencode_mgr, &config.opencode_log_dir);
+        let status = supervisor.start().await;
+        opencode.attach_supervisor(supervisor.clone());
+        tokio::spawn(supervisor.supervise());
+
+        match status {
+            OpencodeStatus::AlreadyRunning => {}
+            OpencodeStatus::Started => {
+                tracing::info!(url = url.as_str(), "opencode server started automatically");
+            }
+            OpencodeStatus::Failed(e) => {
+                tracing::warn!(url = url.as_str(), "opencode auto-start failed: {e} — retrying with backoff");
+            }
         }
     }
 
-    let (sse_tx, _rx) = broadcast::channel::<String>(100);
-    let http_client = reqwest::Client::new();
+    tokio::spawn(opencode.clone().start_health_monitor());
+
+    if let Some(pool) = db_pool {
+        let coalescer = StatusCoalescer::new(
+            pool.clone(),
+            sse_tx.clone(),
+            Duration::from_millis(config.ai_status_coalesce_ms),
+        );
+        for url in opencode.urls() {
+            let relay = SseRelayService {
+                opencode_url: url.clone(),
+                db: pool.clone(),
+                sse_tx: sse_tx.clone(),
+                http_client: http_client.clone(),
+                coalescer: coalescer.clone(),
+                redactors: redactors.clone(),
+            };
+
+            tokio::spawn(async move {
+                tracing::info!(url = url.as_str(), "SSE relay started");
+                relay.start().await;
+            });
+        }
 
-    if let Some(pool) = db_pool.clone() {
-        let relay = SseRelayService {
-            opencode_url: config.opencode_url.clone(),
+        {
+            let (db, http_client, sse_tx, opencode) =
+                (pool.clone(), http_client.clone(), sse_tx.clone(), opencode.clone());
+            tokio::spawn(async move {
+                let report =
+                    LogBackfillService::backfill_active(&db, &http_client, &sse_tx, &opencode).await;
+                tracing::info!(
+                    sessions = report.sessions,
+                    inserted = report.inserted,
+                    "Startup agent log backfill finished"
+                );
+            });
+        }
+
+        let retention = LogRetentionService::new(pool.clone(), &config.log_archive_dir);
+        let compaction_interval = Duration::from_secs(config.log_compaction_interval_secs.max(60));
+        tokio::spawn(retention.start(compaction_interval));
+
+        let merge_queue = MergeQueueProcessor {
             db: pool.clone(),
             sse_tx: sse_tx.clone(),
-            http_client: http_client.clone(),
+            merge_locks,
         };
-
         tokio::spawn(async move {
-            tracing::info!("SSE relay started");
-            relay.start().await;
+            tracing::info!("Merge queue processor started");
+            merge_queue.start().await;
         });
 
         let processor = QueueProcessor {
             db: pool,
             http_client: http_client.clone(),
-            opencode_url: config.opencode_url.clone(),
+            opencode: opencode.clone(),
             sse_tx: sse_tx.clone(),
         };
 
@@ -129,11 +186,6 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         tracing::warn!("Background services not started: database unavailable");
     }
 
-    let config = Arc::new(config);
-
-    let mcp_pool = db_pool.clone();
-    let state = AppState::new(db_pool, sse_tx, http_client, Arc::clone(&config));
-
     let mcp_service = StreamableHttpService::new(
         move || {
             Ok(KanbanMcp::new(
@@ -156,14 +208,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing::info!("Health check: http://{}/health", addr);
 
     axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
-        .with_graceful_shutdown(shutdown_signal())
+        .with_graceful_shutdown(shutdown_signal(opencode))
         .await?;
 
     tracing::info!("Kanban Backend shut down gracefully");
     Ok(())
 }
 
-async fn shutdown_signal() {
+async fn shutdown_signal(opencode: OpencodePool) {
     let ctrl_c = async {
         tokio::signal::ctrl_c()
             .await
@@ -189,4 +241,6 @@ async fn shutdown_signal() {
             tracing::info!("Received SIGTERM, shutting down...");
         }
     }
+
+    opencode.shutdown_processes().await;
 }
@@ -179,6 +179,34 @@ fn default_select() -> String {
     "select".to_string()
 }
 
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct ReviewFindingInput {
+    /// File path relative to the repository root
+    file: String,
+    /// Line in the new version of the file; omit for whole-file findings
+    line: Option<i64>,
+    /// Severity: "info", "warning", or "error"
+    severity: String,
+    /// What is wrong and how to fix it
+    message: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct SubmitReviewInput {
+    /// The card being reviewed
+    card_id: String,
+    /// Your reviewer session ID, as given in the review prompt
+    session_id: String,
+    /// Verdict: "approve" or "request_changes"
+    verdict: String,
+    /// Short overall assessment
+    #[serde(default)]
+    summary: String,
+    /// One entry per issue found
+    #[serde(default)]
+    findings: Vec<ReviewFindingInput>,
+}
+
 fn default_empty_array() -> String {
     "[]".to_string()
 }
@@ -379,7 +407,7 @@ impl KanbanMcp {
     }
 
     #[tool(
-        description = "Get full details for one card, including subtasks, comments, and labels. Use this when you need complete context before editing or executing work. Returns a JSON object with card, subtasks, comments, and labels."
+        description = "Get full details for one card, including subtasks, comments, and labels. Use this when you need complete context before editing or executing work. Returns a JSON object with card, subtasks, comments, and labels. If human_message_pending is true, a human sent new instructions: read them with kanban_human_messages."
     )]
     async fn kanban_get_card(
         &self,
@@ -389,6 +417,34 @@ impl KanbanMcp {
         Self::json_result(&data)
     }
 
+    #[tool(
+        description = "Read the messages humans sent to a card's agent from the board, oldest first. Use this when kanban_get_card shows human_message_pending=true; reading acknowledges them and clears the flag. Returns a JSON object with pending (as it was before reading) and messages (each with content, created_at, and metadata.author)."
+    )]
+    async fn kanban_human_messages(
+        &self,
+        Parameters(input): Parameters<GetCardInput>,
+    ) -> Result<CallToolResult, McpError> {
+        let data = self
+            .get(&format!("/api/cards/{}/messages", input.card_id))
+            .await?;
+        let last_seen_id = data
+            .get("messages")
+            .and_then(|v| v.as_array())
+            .and_then(|messages| messages.last())
+            .and_then(|message| message.get("id"))
+            .and_then(|v| v.as_str());
+        if let (Some(true), Some(last_seen_id)) =
+            (data.get("pending").and_then(|v| v.as_bool()), last_seen_id)
+        {
+            self.post(
+                &format!("/api/cards/{}/messages/ack", input.card_id),
+                &json!({ "last_seen_id": last_seen_id }),
+            )
+            .await?;
+        }
+        Self::json_result(&data)
+    }
+
     #[tool(
         description = "Create a new card on a board. Use this when starting a task, bug, or feature. Returns the created card as JSON. Defaults: stage=backlog, priority=medium, board_id=default, working_directory=."
     )]
@@ -657,6 +713,25 @@ impl KanbanMcp {
         }
     }
 
+    #[tool(
+        description = "Submit the automatic code review of a card. Only for reviewer sessions started when a card enters review; call it exactly once. Pass the session_id given in the review prompt. verdict \"request_changes\" sends the card back to its implementer with the findings, \"approve\" leaves it for the human reviewer. Returns the stored review with its findings as JSON."
+    )]
+    async fn kanban_submit_review(
+        &self,
+        Parameters(input): Parameters<SubmitReviewInput>,
+    ) -> Result<CallToolResult, McpError> {
+        let body = json!({
+            "session_id": input.session_id,
+            "verdict": input.verdict,
+            "summary": input.summary,
+            "findings": input.findings,
+        });
+        let data = self
+            .post(&format!("/api/cards/{}/ai-reviews", input.card_id), &body)
+            .await?;
+        Self::json_result(&data)
+    }
+
     #[tool(
         description = "Ask the user a question and wait for their answer. Use this when you need user input before proceeding. For select/multi_select types, provide options as a JSON array of objects with 'label' and 'description' fields. The tool will block until the user responds. Returns the user's answer."
     )]
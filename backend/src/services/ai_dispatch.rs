@@ -5,7 +5,14 @@ use sqlx::SqlitePool;
 
 use crate::domain::{Card, KanbanError, Subtask};
 
+use super::board_variables::BoardVariableService;
+use super::card_attempts::{CardAttempt, CardAttemptService};
+use super::card_runs::CardRunService;
+use super::context_assembler::ContextAssembler;
+use super::model_selection::{split_model, ModelPurpose, ModelSelection};
 use super::plan_generator::PlanGenerator;
+use super::prompt_templates::{self, PromptTemplateService};
+use super::EventBus;
 
 pub struct AiDispatchService {
     http_client: reqwest::Client,
@@ -26,6 +33,11 @@ impl AiDispatchService {
         subtasks: &[Subtask],
         db: &SqlitePool,
     ) -> Result<String, KanbanError> {
+        // Refreshed on every dispatch so edited variables reach the agent.
+        if let Err(e) = BoardVariableService::materialize(db, card).await {
+            tracing::warn!(card_id = card.id, error = %e, "Failed to write board variables to worktree");
+        }
+
         if let Some(existing_session) = card.ai_session_id.as_deref().filter(|s| !s.is_empty()) {
             if self.is_session_alive(existing_session).await {
                 tracing::info!(
@@ -49,16 +61,14 @@ impl AiDispatchService {
 
     async fn is_session_alive(&self, session_id: &str) -> bool {
         let url = format!("{}/session/{}", self.opencode_url, session_id);
-        match self
-            .http_client
-            .get(&url)
-            .timeout(std::time::Duration::from_secs(10))
-            .send()
-            .await
-        {
-            Ok(resp) if resp.status().is_success() => true,
-            _ => false,
-        }
+        matches!(
+            self.http_client
+                .get(&url)
+                .timeout(std::time::Duration::from_secs(10))
+                .send()
+                .await,
+            Ok(resp) if resp.status().is_success()
+        )
     }
 
     async fn dispatch_with_existing_session(
@@ -73,26 +83,32 @@ impl AiDispatchService {
         let plan_path =
             PlanGenerator::write_plan_file(&card.working_directory, &card.title, &plan_content)
                 .map_err(KanbanError::OpenCodeError)?;
+        let context_path = Self::write_context_bundle(db, card, &plan_path).await?;
 
         sqlx::query(
-            "UPDATE cards SET ai_status = ?, plan_path = ?, updated_at = ? WHERE id = ?",
+            "UPDATE cards SET ai_status = ?, plan_path = ?, opencode_url = ?, updated_at = ? WHERE id = ?",
         )
         .bind("dispatched")
         .bind(&plan_path)
+        .bind(&self.opencode_url)
         .bind(chrono::Utc::now().to_rfc3339())
         .bind(&card.id)
         .execute(db)
         .await?;
-
-        let agent_instruction = if let Some(agent) = &card.ai_agent {
-            format!("You are acting as the {} agent. ", agent)
-        } else {
-            String::new()
-        };
-        let prompt = format!(
-            "{}The planning phase is complete. A work plan has been generated at {}. Read it carefully — it includes your earlier plan plus any modifications the human reviewer made. Then execute /start-work to begin. Work through ALL TODOs systematically.",
-            agent_instruction, plan_path
-        );
+        let kind = CardRunService::work_kind(db, &card.id).await;
+        CardRunService::start_or_warn(db, card, kind, session_id, Some(&plan_path)).await;
+
+        let model = ModelSelection::for_card(db, card, ModelPurpose::Implementation).await?;
+        let prompt = PromptTemplateService::render_for_card(
+            db,
+            prompt_templates::WORK_CONTINUATION,
+            card,
+            &[
+                ("plan_path", plan_path.clone()),
+                ("context_path", context_path.clone()),
+            ],
+        )
+        .await?;
 
         let http_client = self.http_client.clone();
         let message_url = format!(
@@ -112,7 +128,7 @@ impl AiDispatchService {
 
             let result = http_client
                 .post(&message_url)
-                .json(&json!({"parts": [{"type": "text", "text": prompt}]}))
+                .json(&message_body(&prompt, model.as_deref()))
                 .send()
                 .await;
 
@@ -177,83 +193,40 @@ impl AiDispatchService {
             .map_err(KanbanError::OpenCodeError)?;
         let plan_path = PlanGenerator::write_plan_file(&card.working_directory, &card.title, &plan_content)
             .map_err(KanbanError::OpenCodeError)?;
+        let context_path = Self::write_context_bundle(db, card, &plan_path).await?;
 
-        // Wake up opencode server (it may be sleeping)
-        let _ = self
-            .http_client
-            .get(format!("{}/health", self.opencode_url))
-            .timeout(std::time::Duration::from_secs(10))
-            .send()
-            .await;
-
-        let session_response = match self
-            .http_client
-            .post(format!("{}/session", self.opencode_url))
-            .json(&json!({}))
-            .send()
-            .await
-        {
-            Ok(response) => response,
-            Err(err) => {
-                tracing::warn!(card_id = card.id, error = %err, "Failed to create OpenCode session");
-                Self::mark_failed_with_plan(db, &card.id, &plan_path).await?;
-                return Ok(String::new());
-            }
-        };
-
-        if !session_response.status().is_success() {
-            tracing::warn!(
-                card_id = card.id,
-                status = %session_response.status(),
-                "OpenCode session creation returned non-success status"
-            );
+        let Some(session_id) = self.create_session(&card.id).await else {
             Self::mark_failed_with_plan(db, &card.id, &plan_path).await?;
             return Ok(String::new());
-        }
-
-        let body = match session_response.json::<Value>().await {
-            Ok(value) => value,
-            Err(err) => {
-                tracing::warn!(card_id = card.id, error = %err, "Failed to decode OpenCode session response");
-                Self::mark_failed_with_plan(db, &card.id, &plan_path).await?;
-                return Ok(String::new());
-            }
-        };
-
-        let session_id = match body
-            .get("id")
-            .and_then(Value::as_str)
-        {
-            Some(id) => id.to_string(),
-            None => {
-                tracing::warn!(card_id = card.id, "OpenCode session response missing id");
-                Self::mark_failed_with_plan(db, &card.id, &plan_path).await?;
-                return Ok(String::new());
-            }
         };
 
         // Save session_id immediately (before sending the message, which blocks)
-        sqlx::query("UPDATE cards SET ai_session_id = ?, ai_status = ?, plan_path = ?, updated_at = ? WHERE id = ?")
+        sqlx::query("UPDATE cards SET ai_session_id = ?, ai_status = ?, plan_path = ?, opencode_url = ?, updated_at = ? WHERE id = ?")
             .bind(&session_id)
             .bind("dispatched")
             .bind(&plan_path)
+            .bind(&self.opencode_url)
             .bind(chrono::Utc::now().to_rfc3339())
             .bind(&card.id)
             .execute(db)
             .await?;
+        let kind = CardRunService::work_kind(db, &card.id).await;
+        CardRunService::start_or_warn(db, card, kind, &session_id, Some(&plan_path)).await;
 
         // Send the work plan message in a background task.
         // The /session/{id}/message endpoint is synchronous (blocks until AI finishes),
         // so we fire-and-forget. The SSE relay will track progress via opencode events.
-        let agent_instruction = if let Some(agent) = &card.ai_agent {
-            format!("You are acting as the {} agent. ", agent)
-        } else {
-            String::new()
-        };
-        let prompt = format!(
-            "{}A work plan has been generated at {}. Read it carefully, then execute /start-work to begin. Work through ALL TODOs systematically.",
-            agent_instruction, plan_path
-        );
+        let prompt = PromptTemplateService::render_for_card(
+            db,
+            prompt_templates::WORK_DISPATCH,
+            card,
+            &[
+                ("plan_path", plan_path.clone()),
+                ("context_path", context_path.clone()),
+            ],
+        )
+        .await?;
+        let model = ModelSelection::for_card(db, card, ModelPurpose::Implementation).await?;
         let http_client = self.http_client.clone();
         let message_url = format!("{}/session/{}/message", self.opencode_url, &session_id);
         let card_id = card.id.clone();
@@ -265,7 +238,7 @@ impl AiDispatchService {
 
             let result = http_client
                 .post(&message_url)
-                .json(&json!({"parts": [{"type": "text", "text": prompt}]}))
+                .json(&message_body(&prompt, model.as_deref()))
                 .send()
                 .await;
 
@@ -291,6 +264,118 @@ impl AiDispatchService {
         Ok(session_id.to_string())
     }
 
+    /// Starts a competing attempt in a fresh session. `card` is the
+    /// attempt's view of the card ([`CardAttempt::card_view`]); the card row
+    /// is left alone. Returns `None` once the attempt has been marked failed.
+    pub async fn dispatch_attempt(
+        &self,
+        card: &Card,
+        attempt: &CardAttempt,
+        subtasks: &[Subtask],
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+    ) -> Result<Option<String>, KanbanError> {
+        if let Err(e) = BoardVariableService::materialize(db, card).await {
+            tracing::warn!(card_id = card.id, error = %e, "Failed to write board variables to attempt worktree");
+        }
+
+        let plan_content = PlanGenerator::generate_plan(card, subtasks)
+            .map_err(KanbanError::OpenCodeError)?;
+        let plan_path = PlanGenerator::write_plan_file(&card.working_directory, &card.title, &plan_content)
+            .map_err(KanbanError::OpenCodeError)?;
+        let context_path = Self::write_context_bundle(db, card, &plan_path).await?;
+
+        let Some(session_id) = self.create_session(&card.id).await else {
+            CardAttemptService::fail(db, sse_tx, &attempt.id, "Failed to create OpenCode session").await?;
+            return Ok(None);
+        };
+        CardAttemptService::record_dispatch(db, sse_tx, attempt, &session_id, &self.opencode_url).await?;
+
+        let prompt = PromptTemplateService::render_for_card(
+            db,
+            prompt_templates::WORK_DISPATCH,
+            card,
+            &[
+                ("plan_path", plan_path.clone()),
+                ("context_path", context_path.clone()),
+            ],
+        )
+        .await?;
+        // The attempt's own model wins over the card and board choice.
+        let model = match attempt.model.clone().filter(|model| !model.is_empty()) {
+            Some(model) => Some(model),
+            None => ModelSelection::for_card(db, card, ModelPurpose::Implementation).await?,
+        };
+        let body = message_body(&prompt, model.as_deref());
+        let http_client = self.http_client.clone();
+        let message_url = format!("{}/session/{}/message", self.opencode_url, &session_id);
+        let attempt_id = attempt.id.clone();
+        let db_clone = db.clone();
+        let sse_tx_clone = sse_tx.clone();
+
+        tokio::spawn(async move {
+            let error = match http_client.post(&message_url).json(&body).send().await {
+                Ok(response) if response.status().is_success() => return,
+                Ok(response) => format!("OpenCode returned {}", response.status()),
+                Err(err) => format!("Failed to send work plan: {}", err),
+            };
+            CardAttemptService::fail_or_warn(&db_clone, &sse_tx_clone, &attempt_id, &error).await;
+        });
+
+        Ok(Some(session_id))
+    }
+
+    /// Creates an OpenCode session, waking the server first. Failures are
+    /// logged and return `None`.
+    pub async fn create_session(&self, card_id: &str) -> Option<String> {
+        // Wake up opencode server (it may be sleeping)
+        let _ = self
+            .http_client
+            .get(format!("{}/health", self.opencode_url))
+            .timeout(std::time::Duration::from_secs(10))
+            .send()
+            .await;
+
+        let session_response = match self
+            .http_client
+            .post(format!("{}/session", self.opencode_url))
+            .json(&json!({}))
+            .send()
+            .await
+        {
+            Ok(response) => response,
+            Err(err) => {
+                tracing::warn!(card_id, error = %err, "Failed to create OpenCode session");
+                return None;
+            }
+        };
+
+        if !session_response.status().is_success() {
+            tracing::warn!(
+                card_id,
+                status = %session_response.status(),
+                "OpenCode session creation returned non-success status"
+            );
+            return None;
+        }
+
+        let body = match session_response.json::<Value>().await {
+            Ok(value) => value,
+            Err(err) => {
+                tracing::warn!(card_id, error = %err, "Failed to decode OpenCode session response");
+                return None;
+            }
+        };
+
+        match body.get("id").and_then(Value::as_str) {
+            Some(id) => Some(id.to_string()),
+            None => {
+                tracing::warn!(card_id, "OpenCode session response missing id");
+                None
+            }
+        }
+    }
+
     pub async fn abort_session(&self, session_id: &str) -> Result<(), KanbanError> {
         let response = self
             .http_client
@@ -309,6 +394,26 @@ impl AiDispatchService {
         Ok(())
     }
 
+    async fn write_context_bundle(
+        db: &SqlitePool,
+        card: &Card,
+        plan_path: &str,
+    ) -> Result<String, KanbanError> {
+        let bundle = ContextAssembler::assemble(db, card).await?;
+        let context_path = ContextAssembler::write_bundle(plan_path, &bundle)
+            .map_err(KanbanError::OpenCodeError)?;
+
+        tracing::info!(
+            card_id = card.id,
+            documents = bundle.documents.len(),
+            used_chars = bundle.used_chars,
+            budget_chars = bundle.budget_chars,
+            "Wrote context bundle"
+        );
+
+        Ok(context_path)
+    }
+
     async fn mark_failed(db: &SqlitePool, card_id: &str) -> Result<(), KanbanError> {
         sqlx::query("UPDATE cards SET ai_status = ?, updated_at = ? WHERE id = ?")
             .bind("failed")
@@ -316,6 +421,7 @@ impl AiDispatchService {
             .bind(card_id)
             .execute(db)
             .await?;
+        CardRunService::finish_or_warn(db, card_id, "failed").await;
 
         Ok(())
     }
@@ -332,7 +438,17 @@ impl AiDispatchService {
             .bind(card_id)
             .execute(db)
             .await?;
+        CardRunService::finish_or_warn(db, card_id, "failed").await;
 
         Ok(())
     }
 }
+
+/// Body for `POST /session/{id}/message`; `model` is `provider/model`.
+pub fn message_body(prompt: &str, model: Option<&str>) -> Value {
+    let mut body = json!({"parts": [{"type": "text", "text": prompt}]});
+    if let Some((provider_id, model_id)) = model.and_then(split_model) {
+        body["model"] = json!({"providerID": provider_id, "modelID": model_id});
+    }
+    body
+}
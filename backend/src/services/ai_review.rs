@@ -0,0 +1,598 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use crate::api::handlers::sse::WsEvent;
+use crate::domain::{Card, KanbanError, NotificationType};
+
+use super::ai_dispatch::message_body;
+use super::git_worktree::DiffResult;
+use super::model_selection::{ModelPurpose, ModelSelection};
+use super::prompt_templates::{self, PromptTemplateService};
+use super::{
+    AiDispatchService, CardRunService, EventBus, GitWorktreeService, NotificationService,
+    Redactor, SessionMappingService,
+};
+
+/// Session mapping agent type of reviewer sessions.
+pub const REVIEWER_AGENT_TYPE: &str = "reviewer";
+pub const SEVERITIES: &[&str] = &["info", "warning", "error"];
+
+/// Diffs beyond this many characters are cut short in the prompt; the
+/// reviewer can still read the worktree.
+const MAX_PROMPT_DIFF_CHARS: usize = 100_000;
+
+const COLUMNS: &str = "id, card_id, round, agent, model, session_id, opencode_url, status, summary, failure_reason, created_at, finished_at";
+const FINDING_COLUMNS: &str = "id, review_id, card_id, file, line, severity, message, position, created_at";
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct AiReview {
+    pub id: String,
+    pub card_id: String,
+    pub round: i64,
+    pub agent: String,
+    pub model: Option<String>,
+    pub session_id: Option<String>,
+    pub opencode_url: String,
+    /// `running`, `approved`, `changes_requested` or `failed`.
+    pub status: String,
+    pub summary: String,
+    pub failure_reason: Option<String>,
+    pub created_at: String,
+    pub finished_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct AiReviewFinding {
+    pub id: String,
+    pub review_id: String,
+    pub card_id: String,
+    pub file: String,
+    /// Line in the new version of the file; `None` for the whole file.
+    pub line: Option<i64>,
+    /// `info`, `warning` or `error`.
+    pub severity: String,
+    pub message: String,
+    pub position: i64,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AiReviewWithFindings {
+    #[serde(flatten)]
+    pub review: AiReview,
+    pub findings: Vec<AiReviewFinding>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReviewFindingInput {
+    pub file: String,
+    pub line: Option<i64>,
+    pub severity: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubmitReviewRequest {
+    /// The reviewer's session; only the running review's own session may
+    /// submit it.
+    pub session_id: String,
+    /// `approve` or `request_changes`.
+    pub verdict: String,
+    #[serde(default)]
+    pub summary: String,
+    #[serde(default)]
+    pub findings: Vec<ReviewFindingInput>,
+}
+
+#[derive(sqlx::FromRow)]
+struct ReviewSettings {
+    codebase_path: String,
+    ai_review_enabled: bool,
+    ai_review_agent: String,
+    ai_review_max_rounds: i64,
+}
+
+/// Automatic review of a card's diff by a separate agent before a human
+/// looks at it. Findings are stored per review round, apart from comments;
+/// a `request_changes` verdict sends the card back to its implementer.
+pub struct AiReviewService;
+
+impl AiReviewService {
+    /// Starts a review round if the card is in review, its board has AI
+    /// review enabled and the reviewer has not used up its rounds. Returns
+    /// `None` when no review was started.
+    pub async fn start(
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+        http_client: &reqwest::Client,
+        opencode_url: &str,
+        card_id: &str,
+    ) -> Result<Option<AiReview>, KanbanError> {
+        let card: Card = sqlx::query_as("SELECT * FROM cards WHERE id = ?")
+            .bind(card_id)
+            .fetch_one(db)
+            .await?;
+        if card.stage != "review" || card.branch_name.is_empty() {
+            return Ok(None);
+        }
+        let Some(board_id) = card.board_id.as_deref().filter(|id| !id.is_empty()) else {
+            return Ok(None);
+        };
+        let settings: Option<ReviewSettings> = sqlx::query_as(
+            "SELECT codebase_path, ai_review_enabled, ai_review_agent, ai_review_max_rounds FROM board_settings WHERE board_id = ?",
+        )
+        .bind(board_id)
+        .fetch_optional(db)
+        .await?;
+        let Some(settings) = settings.filter(|s| s.ai_review_enabled && !s.codebase_path.is_empty())
+        else {
+            return Ok(None);
+        };
+
+        if Self::running(db, card_id).await?.is_some() {
+            return Ok(None);
+        }
+        let bounced: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM ai_reviews WHERE card_id = ? AND status = 'changes_requested'",
+        )
+        .bind(card_id)
+        .fetch_one(db)
+        .await?;
+        if bounced >= settings.ai_review_max_rounds {
+            tracing::info!(card_id, bounced, "AI review rounds used up; leaving card for a human");
+            return Ok(None);
+        }
+
+        let agent = settings.ai_review_agent.trim().to_string();
+        let model = ModelSelection::for_board(db, board_id, ModelPurpose::Review).await?;
+        let now = Utc::now().to_rfc3339();
+        let review: AiReview = sqlx::query_as(&format!(
+            "INSERT INTO ai_reviews (id, card_id, round, agent, model, status, created_at)
+             VALUES (?, ?, (SELECT COALESCE(MAX(round), 0) + 1 FROM ai_reviews WHERE card_id = ?), ?, ?, 'running', ?)
+             RETURNING {}",
+            COLUMNS
+        ))
+        .bind(Uuid::new_v4().to_string())
+        .bind(card_id)
+        .bind(card_id)
+        .bind(&agent)
+        .bind(&model)
+        .bind(&now)
+        .fetch_one(db)
+        .await?;
+        broadcast_review(db, sse_tx, &review).await;
+        tracing::info!(card_id, round = review.round, agent, "Starting AI review");
+
+        // No agent on either side means OpenCode's default for both.
+        if card.ai_agent.as_deref().map(str::trim).unwrap_or_default() == agent {
+            return Self::fail(db, sse_tx, &review.id, "Reviewer agent must differ from the card's agent")
+                .await
+                .map(Some);
+        }
+
+        let (codebase_path, branch_name) = (settings.codebase_path.clone(), card.branch_name.clone());
+        let diff = match GitWorktreeService::cancellable(move || {
+            GitWorktreeService::get_diff(&codebase_path, &branch_name)
+        })
+        .await
+        {
+            Ok(diff) => diff,
+            Err(e) => {
+                return Self::fail(db, sse_tx, &review.id, &format!("Failed to diff card branch: {}", e))
+                    .await
+                    .map(Some);
+            }
+        };
+
+        let dispatcher = AiDispatchService::new(http_client.clone(), opencode_url.to_string());
+        let Some(session_id) = dispatcher.create_session(card_id).await else {
+            return Self::fail(db, sse_tx, &review.id, "Failed to create OpenCode session")
+                .await
+                .map(Some);
+        };
+        SessionMappingService::insert(
+            db,
+            &session_id,
+            card_id,
+            card.ai_session_id.as_deref().unwrap_or_default(),
+            Some(REVIEWER_AGENT_TYPE),
+            &format!("AI review round {}", review.round),
+        )
+        .await
+        .map_err(|e| KanbanError::Internal(format!("Failed to map reviewer session: {}", e)))?;
+
+        let review: AiReview = sqlx::query_as(&format!(
+            "UPDATE ai_reviews SET session_id = ?, opencode_url = ? WHERE id = ? RETURNING {}",
+            COLUMNS
+        ))
+        .bind(&session_id)
+        .bind(opencode_url)
+        .bind(&review.id)
+        .fetch_one(db)
+        .await?;
+        broadcast_review(db, sse_tx, &review).await;
+
+        let prompt = PromptTemplateService::render_for_card(
+            db,
+            prompt_templates::AI_REVIEW,
+            &card,
+            &[
+                ("diff", format_diff(&diff)),
+                ("review.session_id", session_id.clone()),
+            ],
+        )
+        .await?;
+        let mut body = message_body(&prompt, model.as_deref());
+        if !agent.is_empty() {
+            body["agent"] = json!(agent);
+        }
+        let sent = http_client
+            .post(format!("{}/session/{}/message", opencode_url, session_id))
+            .json(&body)
+            .send()
+            .await;
+        let error = match sent {
+            Ok(response) if response.status().is_success() => return Ok(Some(review)),
+            Ok(response) => format!("OpenCode returned {}", response.status()),
+            Err(e) => format!("Failed to send review prompt: {}", e),
+        };
+        Self::fail(db, sse_tx, &review.id, &error).await.map(Some)
+    }
+
+    /// Like [`Self::start`], for the relay where nobody waits on the result.
+    pub async fn start_or_warn(
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+        http_client: &reqwest::Client,
+        opencode_url: &str,
+        card_id: &str,
+    ) {
+        if let Err(e) = Self::start(db, sse_tx, http_client, opencode_url, card_id).await {
+            tracing::warn!(card_id, error = %e, "Failed to start AI review");
+        }
+    }
+
+    /// Records the reviewer's verdict and findings on the card's running
+    /// review. `request_changes` sends a card still in review back to
+    /// `in_progress` with the findings as its next prompt.
+    pub async fn submit(
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+        http_client: &reqwest::Client,
+        card: &Card,
+        req: &SubmitReviewRequest,
+    ) -> Result<AiReviewWithFindings, KanbanError> {
+        let status = match req.verdict.as_str() {
+            "approve" => "approved",
+            "request_changes" => "changes_requested",
+            other => {
+                return Err(KanbanError::BadRequest(format!(
+                    "Invalid verdict '{}': use approve or request_changes",
+                    other
+                )))
+            }
+        };
+        for finding in &req.findings {
+            if finding.file.trim().is_empty() || finding.message.trim().is_empty() {
+                return Err(KanbanError::BadRequest(
+                    "Findings need a file and a message".into(),
+                ));
+            }
+            if !SEVERITIES.contains(&finding.severity.as_str()) {
+                return Err(KanbanError::BadRequest(format!(
+                    "Invalid severity '{}': use {}",
+                    finding.severity,
+                    SEVERITIES.join(", ")
+                )));
+            }
+            if finding.line.is_some_and(|line| line < 1) {
+                return Err(KanbanError::BadRequest("Finding lines start at 1".into()));
+            }
+        }
+
+        let review = Self::running(db, &card.id)
+            .await?
+            .ok_or_else(|| KanbanError::Conflict("No AI review is running for this card".into()))?;
+        if review.session_id.as_deref() != Some(req.session_id.as_str()) {
+            return Err(KanbanError::Forbidden(
+                "Only the running review's reviewer session can submit it".into(),
+            ));
+        }
+
+        let redactor = Redactor::for_board(db, card.board_id.as_deref()).await?;
+        let now = Utc::now().to_rfc3339();
+        let mut tx = db.begin().await?;
+        // Claimed first so a repeated submission cannot add findings twice.
+        let finished: Option<AiReview> = sqlx::query_as(&format!(
+            "UPDATE ai_reviews SET status = ?, summary = ?, finished_at = ? WHERE id = ? AND session_id = ? AND status = 'running' RETURNING {}",
+            COLUMNS
+        ))
+        .bind(status)
+        .bind(redactor.redact(req.summary.trim()))
+        .bind(&now)
+        .bind(&review.id)
+        .bind(&req.session_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(review) = finished else {
+            return Err(KanbanError::Conflict("AI review was already submitted".into()));
+        };
+        for (position, finding) in req.findings.iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO ai_review_findings (id, review_id, card_id, file, line, severity, message, position, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(Uuid::new_v4().to_string())
+            .bind(&review.id)
+            .bind(&card.id)
+            .bind(finding.file.trim())
+            .bind(finding.line)
+            .bind(&finding.severity)
+            .bind(redactor.redact(finding.message.trim()))
+            .bind(position as i64)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        let result = Self::with_findings(db, review).await?;
+        broadcast(
+            sse_tx,
+            &WsEvent::AiReviewUpdated {
+                card_id: card.id.clone(),
+                review: serde_json::to_value(&result).unwrap_or_default(),
+            },
+        );
+        tracing::info!(card_id = card.id, round = result.review.round, status, findings = result.findings.len(), "AI review submitted");
+
+        if status == "changes_requested" {
+            Self::send_back(db, sse_tx, http_client, card, &result).await?;
+        }
+        Ok(result)
+    }
+
+    /// A reviewer session went idle. A review still running at that point
+    /// never reported a verdict.
+    pub async fn session_idle(
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+        session_id: &str,
+    ) -> Result<(), KanbanError> {
+        let review_id: Option<String> = sqlx::query_scalar(
+            "SELECT id FROM ai_reviews WHERE session_id = ? AND status = 'running'",
+        )
+        .bind(session_id)
+        .fetch_optional(db)
+        .await?;
+        if let Some(review_id) = review_id {
+            Self::fail(db, sse_tx, &review_id, "Reviewer finished without submitting a review").await?;
+        }
+        Ok(())
+    }
+
+    /// Every review round on the card with its findings, oldest first.
+    pub async fn list(db: &SqlitePool, card_id: &str) -> Result<Vec<AiReviewWithFindings>, KanbanError> {
+        let reviews: Vec<AiReview> = sqlx::query_as(&format!(
+            "SELECT {} FROM ai_reviews WHERE card_id = ? ORDER BY round ASC",
+            COLUMNS
+        ))
+        .bind(card_id)
+        .fetch_all(db)
+        .await?;
+        let mut result = Vec::with_capacity(reviews.len());
+        for review in reviews {
+            result.push(Self::with_findings(db, review).await?);
+        }
+        Ok(result)
+    }
+
+    async fn running(db: &SqlitePool, card_id: &str) -> Result<Option<AiReview>, KanbanError> {
+        let review = sqlx::query_as(&format!(
+            "SELECT {} FROM ai_reviews WHERE card_id = ? AND status = 'running'",
+            COLUMNS
+        ))
+        .bind(card_id)
+        .fetch_optional(db)
+        .await?;
+        Ok(review)
+    }
+
+    async fn with_findings(db: &SqlitePool, review: AiReview) -> Result<AiReviewWithFindings, KanbanError> {
+        let findings = sqlx::query_as(&format!(
+            "SELECT {} FROM ai_review_findings WHERE review_id = ? ORDER BY position ASC",
+            FINDING_COLUMNS
+        ))
+        .bind(&review.id)
+        .fetch_all(db)
+        .await?;
+        Ok(AiReviewWithFindings { review, findings })
+    }
+
+    async fn fail(
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+        review_id: &str,
+        reason: &str,
+    ) -> Result<AiReview, KanbanError> {
+        let review: AiReview = sqlx::query_as(&format!(
+            "UPDATE ai_reviews SET status = 'failed', failure_reason = ?, finished_at = COALESCE(finished_at, ?) WHERE id = ? RETURNING {}",
+            COLUMNS
+        ))
+        .bind(reason)
+        .bind(Utc::now().to_rfc3339())
+        .bind(review_id)
+        .fetch_one(db)
+        .await?;
+        tracing::warn!(card_id = review.card_id, round = review.round, reason, "AI review failed");
+        broadcast_review(db, sse_tx, &review).await;
+        Ok(review)
+    }
+
+    /// Moves the card back to `in_progress` and prompts its own session with
+    /// the findings. Without a session the card waits, idle, for a human to
+    /// resume it, as after a manual reject.
+    async fn send_back(
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+        http_client: &reqwest::Client,
+        card: &Card,
+        review: &AiReviewWithFindings,
+    ) -> Result<(), KanbanError> {
+        let card: Card = sqlx::query_as("SELECT * FROM cards WHERE id = ?")
+            .bind(&card.id)
+            .fetch_one(db)
+            .await?;
+        if card.stage != "review" {
+            return Ok(());
+        }
+
+        let session_id = card.ai_session_id.clone().filter(|s| !s.is_empty());
+        let model = ModelSelection::for_card(db, &card, ModelPurpose::Implementation).await?;
+        let status = if session_id.is_some() { "dispatched" } else { "idle" };
+        sqlx::query("UPDATE cards SET stage = 'in_progress', ai_status = ?, updated_at = ? WHERE id = ?")
+            .bind(status)
+            .bind(Utc::now().to_rfc3339())
+            .bind(&card.id)
+            .execute(db)
+            .await?;
+
+        broadcast(
+            sse_tx,
+            &WsEvent::CardMoved {
+                card_id: card.id.clone(),
+                from_stage: "review".to_string(),
+                to_stage: "in_progress".to_string(),
+            },
+        );
+        broadcast(
+            sse_tx,
+            &WsEvent::AiStatusChanged {
+                card_id: card.id.clone(),
+                board_id: card.board_id.clone(),
+                status: status.to_string(),
+                progress: serde_json::from_str(&card.ai_progress).unwrap_or_else(|_| json!({})),
+                stage: "in_progress".to_string(),
+                ai_session_id: session_id.clone(),
+            },
+        );
+        let _ = NotificationService::create_notification(
+            db,
+            sse_tx,
+            None,
+            NotificationType::ReviewRequested,
+            &format!("AI review requested changes: {}", card.title),
+            &format!(
+                "The AI reviewer sent card '{}' back with {} finding(s)",
+                card.title,
+                review.findings.len()
+            ),
+            Some(&card.id),
+            card.board_id.as_deref(),
+        )
+        .await;
+
+        let Some(session_id) = session_id else {
+            return Ok(());
+        };
+        let kind = CardRunService::work_kind(db, &card.id).await;
+        CardRunService::start_or_warn(db, &card, kind, &session_id, None).await;
+
+        let body = message_body(&feedback_prompt(review), model.as_deref());
+        let message_url = format!("{}/session/{}/message", review.review.opencode_url, session_id);
+        let http_client = http_client.clone();
+        let db = db.clone();
+        let card_id = card.id.clone();
+        tokio::spawn(async move {
+            let error = match http_client.post(&message_url).json(&body).send().await {
+                Ok(response) if response.status().is_success() => return,
+                Ok(response) => format!("OpenCode returned {}", response.status()),
+                Err(e) => e.to_string(),
+            };
+            tracing::warn!(card_id, error, "Failed to send AI review feedback");
+            if let Err(e) = sqlx::query("UPDATE cards SET ai_status = 'failed', updated_at = ? WHERE id = ?")
+                .bind(Utc::now().to_rfc3339())
+                .bind(&card_id)
+                .execute(&db)
+                .await
+            {
+                tracing::warn!(card_id, error = %e, "Failed to mark card failed");
+            }
+            CardRunService::finish_or_warn(&db, &card_id, "failed").await;
+        });
+        Ok(())
+    }
+}
+
+/// The prompt the implementer gets back when the reviewer requests changes.
+fn feedback_prompt(review: &AiReviewWithFindings) -> String {
+    let mut prompt = format!(
+        "An automated code review (round {}) requested changes before this card can go to a human reviewer.\n\n",
+        review.review.round
+    );
+    if !review.review.summary.is_empty() {
+        prompt.push_str(&format!("## Summary\n{}\n\n", review.review.summary));
+    }
+    if !review.findings.is_empty() {
+        prompt.push_str("## Findings\n");
+        for finding in &review.findings {
+            let location = match finding.line {
+                Some(line) => format!("{}:{}", finding.file, line),
+                None => finding.file.clone(),
+            };
+            prompt.push_str(&format!(
+                "- [{}] {} — {}\n",
+                finding.severity, location, finding.message
+            ));
+        }
+        prompt.push('\n');
+    }
+    prompt.push_str(
+        "Address every finding, re-verify all acceptance criteria, and commit your changes.",
+    );
+    prompt
+}
+
+/// The diff as markdown for the reviewer's prompt.
+fn format_diff(diff: &DiffResult) -> String {
+    if diff.files.is_empty() {
+        return "(no changes)".to_string();
+    }
+    let mut text = format!(
+        "{} file(s) changed, +{} -{}\n",
+        diff.stats.files_changed, diff.stats.additions, diff.stats.deletions
+    );
+    for file in &diff.files {
+        let section = format!(
+            "\n### {} ({}, +{} -{})\n```diff\n{}\n```\n",
+            file.path, file.status, file.additions, file.deletions, file.diff
+        );
+        if text.len() + section.len() > MAX_PROMPT_DIFF_CHARS {
+            text.push_str("\n(diff truncated; read the remaining files in the worktree)\n");
+            break;
+        }
+        text.push_str(&section);
+    }
+    text
+}
+
+async fn broadcast_review(db: &SqlitePool, sse_tx: &EventBus, review: &AiReview) {
+    match AiReviewService::with_findings(db, review.clone()).await {
+        Ok(review) => broadcast(
+            sse_tx,
+            &WsEvent::AiReviewUpdated {
+                card_id: review.review.card_id.clone(),
+                review: serde_json::to_value(&review).unwrap_or_default(),
+            },
+        ),
+        Err(e) => tracing::warn!(review_id = review.id, error = %e, "Failed to broadcast AI review"),
+    }
+}
+
+fn broadcast(sse_tx: &EventBus, event: &WsEvent) {
+    if let Ok(payload) = serde_json::to_string(event) {
+        let _ = sse_tx.send(payload);
+    }
+}
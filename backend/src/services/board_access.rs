@@ -0,0 +1,152 @@
+use std::collections::HashSet;
+
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use crate::domain::KanbanError;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct BoardMember {
+    pub user_id: String,
+    pub username: String,
+    pub nickname: String,
+    pub created_at: String,
+}
+
+/// Board visibility. A board without members is open to every user; once it
+/// has members only they (and the service account) may access it.
+pub struct BoardAccess;
+
+impl BoardAccess {
+    /// Restricted boards the user is not a member of.
+    pub async fn denied_boards(
+        db: &SqlitePool,
+        user_id: &str,
+    ) -> Result<HashSet<String>, KanbanError> {
+        if Self::is_service_account(db, user_id).await? {
+            return Ok(HashSet::new());
+        }
+
+        let rows: Vec<(String,)> = sqlx::query_as(
+            "SELECT DISTINCT board_id FROM board_members
+             WHERE board_id NOT IN (SELECT board_id FROM board_members WHERE user_id = ?)",
+        )
+        .bind(user_id)
+        .fetch_all(db)
+        .await?;
+
+        Ok(rows.into_iter().map(|(board_id,)| board_id).collect())
+    }
+
+    /// Fails with `NotFound` for a missing board and `Forbidden` for one the
+    /// user is not a member of.
+    pub async fn require_access(
+        db: &SqlitePool,
+        user_id: &str,
+        board_id: &str,
+    ) -> Result<(), KanbanError> {
+        let exists: Option<(String,)> = sqlx::query_as("SELECT id FROM boards WHERE id = ?")
+            .bind(board_id)
+            .fetch_optional(db)
+            .await?;
+        if exists.is_none() {
+            return Err(KanbanError::NotFound(format!("Board '{}' not found", board_id)));
+        }
+        if Self::denied_boards(db, user_id).await?.contains(board_id) {
+            return Err(KanbanError::Forbidden(format!(
+                "No access to board '{}'",
+                board_id
+            )));
+        }
+        Ok(())
+    }
+
+    /// `require_access` for the board a card belongs to. Cards without a
+    /// board are open to everyone.
+    pub async fn require_card_access(
+        db: &SqlitePool,
+        user_id: &str,
+        card_id: &str,
+    ) -> Result<(), KanbanError> {
+        let board_id: Option<Option<String>> =
+            sqlx::query_scalar("SELECT board_id FROM cards WHERE id = ?")
+                .bind(card_id)
+                .fetch_optional(db)
+                .await?;
+        match board_id {
+            None => Err(KanbanError::NotFound(format!("Card {} not found", card_id))),
+            Some(Some(board_id)) => Self::require_access(db, user_id, &board_id).await,
+            Some(None) => Ok(()),
+        }
+    }
+
+    pub async fn list_members(
+        db: &SqlitePool,
+        board_id: &str,
+    ) -> Result<Vec<BoardMember>, KanbanError> {
+        let members = sqlx::query_as::<_, BoardMember>(
+            "SELECT bm.user_id, u.username, u.nickname, bm.created_at
+             FROM board_members bm
+             JOIN users u ON u.id = bm.user_id
+             WHERE bm.board_id = ?
+             ORDER BY u.username COLLATE NOCASE ASC",
+        )
+        .bind(board_id)
+        .fetch_all(db)
+        .await?;
+
+        Ok(members)
+    }
+
+    /// Replaces the member list. An empty list opens the board to everyone.
+    pub async fn set_members(
+        db: &SqlitePool,
+        board_id: &str,
+        user_ids: &[String],
+    ) -> Result<Vec<BoardMember>, KanbanError> {
+        let mut unique: Vec<&String> = Vec::new();
+        for user_id in user_ids {
+            if !unique.contains(&user_id) {
+                unique.push(user_id);
+            }
+        }
+
+        for user_id in &unique {
+            let exists: Option<(String,)> = sqlx::query_as("SELECT id FROM users WHERE id = ?")
+                .bind(user_id)
+                .fetch_optional(db)
+                .await?;
+            if exists.is_none() {
+                return Err(KanbanError::BadRequest(format!("Unknown user '{}'", user_id)));
+            }
+        }
+
+        let now = Utc::now().to_rfc3339();
+        let mut tx = db.begin().await?;
+        sqlx::query("DELETE FROM board_members WHERE board_id = ?")
+            .bind(board_id)
+            .execute(&mut *tx)
+            .await?;
+        for user_id in unique {
+            sqlx::query("INSERT INTO board_members (board_id, user_id, created_at) VALUES (?, ?, ?)")
+                .bind(board_id)
+                .bind(user_id)
+                .bind(&now)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        Self::list_members(db, board_id).await
+    }
+
+    async fn is_service_account(db: &SqlitePool, user_id: &str) -> Result<bool, KanbanError> {
+        let service_user: Option<(Vec<u8>,)> =
+            sqlx::query_as("SELECT value FROM app_secrets WHERE key = 'service_user_id'")
+                .fetch_optional(db)
+                .await?;
+
+        Ok(service_user.is_some_and(|(value,)| value == user_id.as_bytes()))
+    }
+}
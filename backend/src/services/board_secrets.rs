@@ -0,0 +1,274 @@
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use chrono::Utc;
+use rand::RngCore;
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use crate::domain::KanbanError;
+
+/// `app_secrets` row holding the AES-256-GCM key for secret variables.
+pub const KEY_NAME: &str = "board_secrets_key";
+
+/// What reads return in place of a secret's value.
+pub const MASK: &str = "********";
+
+const NONCE_LEN: usize = 12;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct SecretVariable {
+    pub name: String,
+    #[sqlx(skip)]
+    pub value: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Encrypted, write-only board variables. Values are only decrypted via
+/// [`BoardSecretService::reveal`] for injection into agent processes and
+/// for redaction; the API only ever sees names and [`MASK`].
+pub struct BoardSecretService;
+
+impl BoardSecretService {
+    /// Loads the encryption key, creating it on first use the same way the
+    /// JWT signing key is bootstrapped.
+    pub async fn get_or_create_key(db: &SqlitePool) -> Result<Vec<u8>, KanbanError> {
+        let existing: Option<Vec<u8>> =
+            sqlx::query_scalar("SELECT value FROM app_secrets WHERE key = ?")
+                .bind(KEY_NAME)
+                .fetch_optional(db)
+                .await?;
+        if let Some(key) = existing {
+            return Ok(key);
+        }
+
+        sqlx::query("INSERT OR IGNORE INTO app_secrets (key, value, created_at) VALUES (?, ?, ?)")
+            .bind(KEY_NAME)
+            .bind(new_key())
+            .bind(Utc::now().to_rfc3339())
+            .execute(db)
+            .await?;
+
+        let stored: Vec<u8> = sqlx::query_scalar("SELECT value FROM app_secrets WHERE key = ?")
+            .bind(KEY_NAME)
+            .fetch_one(db)
+            .await?;
+        Ok(stored)
+    }
+
+    /// Names and timestamps only; `value` is always [`MASK`].
+    pub async fn list(db: &SqlitePool, board_id: &str) -> Result<Vec<SecretVariable>, KanbanError> {
+        let mut secrets: Vec<SecretVariable> = sqlx::query_as(
+            "SELECT name, created_at, updated_at FROM board_secret_variables WHERE board_id = ? ORDER BY name ASC",
+        )
+        .bind(board_id)
+        .fetch_all(db)
+        .await?;
+        for secret in &mut secrets {
+            secret.value = MASK.to_string();
+        }
+        Ok(secrets)
+    }
+
+    pub async fn set(
+        db: &SqlitePool,
+        board_id: &str,
+        name: &str,
+        value: &str,
+    ) -> Result<SecretVariable, KanbanError> {
+        validate_name(name)?;
+        if value.is_empty() {
+            return Err(KanbanError::BadRequest("Secret value must not be empty".into()));
+        }
+
+        let key = Self::get_or_create_key(db).await?;
+        let (nonce, ciphertext) = encrypt(&key, board_id, name, value)?;
+        let now = Utc::now().to_rfc3339();
+
+        let mut secret: SecretVariable = sqlx::query_as(
+            "INSERT INTO board_secret_variables (board_id, name, nonce, ciphertext, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?)
+             ON CONFLICT(board_id, name) DO UPDATE SET
+                nonce = excluded.nonce,
+                ciphertext = excluded.ciphertext,
+                updated_at = excluded.updated_at
+             RETURNING name, created_at, updated_at",
+        )
+        .bind(board_id)
+        .bind(name)
+        .bind(nonce)
+        .bind(ciphertext)
+        .bind(&now)
+        .bind(&now)
+        .fetch_one(db)
+        .await?;
+        secret.value = MASK.to_string();
+        Ok(secret)
+    }
+
+    pub async fn delete(db: &SqlitePool, board_id: &str, name: &str) -> Result<(), KanbanError> {
+        let result =
+            sqlx::query("DELETE FROM board_secret_variables WHERE board_id = ? AND name = ?")
+                .bind(board_id)
+                .bind(name)
+                .execute(db)
+                .await?;
+        if result.rows_affected() == 0 {
+            return Err(KanbanError::NotFound(format!("Secret variable '{}' not found", name)));
+        }
+        Ok(())
+    }
+
+    /// Decrypted `(name, value)` pairs for the board. A secret that no
+    /// longer decrypts (e.g. a corrupted row) is logged and left out rather
+    /// than failing every caller that needs the others.
+    pub async fn reveal(
+        db: &SqlitePool,
+        board_id: &str,
+    ) -> Result<Vec<(String, String)>, KanbanError> {
+        let rows: Vec<(String, Vec<u8>, Vec<u8>)> = sqlx::query_as(
+            "SELECT name, nonce, ciphertext FROM board_secret_variables WHERE board_id = ? ORDER BY name ASC",
+        )
+        .bind(board_id)
+        .fetch_all(db)
+        .await?;
+        if rows.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let key = Self::get_or_create_key(db).await?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(name, nonce, ciphertext)| {
+                match decrypt(&key, board_id, &name, &nonce, &ciphertext) {
+                    Ok(value) => Some((name, value)),
+                    Err(e) => {
+                        tracing::warn!(board_id, name, error = %e, "Skipping secret variable that could not be decrypted");
+                        None
+                    }
+                }
+            })
+            .collect())
+    }
+
+    /// Re-encrypts every secret under a fresh key and replaces the stored
+    /// key, all in one transaction. Run it while the server is stopped.
+    pub async fn rotate_key(db: &SqlitePool) -> Result<usize, KanbanError> {
+        let old_key = Self::get_or_create_key(db).await?;
+        let new_key = new_key();
+
+        let mut tx = db.begin().await?;
+        let rows: Vec<(String, String, Vec<u8>, Vec<u8>)> = sqlx::query_as(
+            "SELECT board_id, name, nonce, ciphertext FROM board_secret_variables",
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for (board_id, name, nonce, ciphertext) in &rows {
+            let value = decrypt(&old_key, board_id, name, nonce, ciphertext)?;
+            let (nonce, ciphertext) = encrypt(&new_key, board_id, name, &value)?;
+            sqlx::query(
+                "UPDATE board_secret_variables SET nonce = ?, ciphertext = ? WHERE board_id = ? AND name = ?",
+            )
+            .bind(nonce)
+            .bind(ciphertext)
+            .bind(board_id)
+            .bind(name)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        sqlx::query("UPDATE app_secrets SET value = ?, created_at = ? WHERE key = ?")
+            .bind(&new_key)
+            .bind(Utc::now().to_rfc3339())
+            .bind(KEY_NAME)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(rows.len())
+    }
+}
+
+/// Secrets become environment variables, so names follow env var rules.
+fn validate_name(name: &str) -> Result<(), KanbanError> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(KanbanError::BadRequest(format!(
+            "Invalid secret name '{}': use letters, digits and underscores",
+            name
+        )))
+    }
+}
+
+fn new_key() -> Vec<u8> {
+    let mut key = [0_u8; 32];
+    rand::thread_rng().fill_bytes(&mut key);
+    key.to_vec()
+}
+
+fn cipher(key: &[u8]) -> Result<Aes256Gcm, KanbanError> {
+    if key.len() != 32 {
+        return Err(KanbanError::Internal("Invalid board secrets key".into()));
+    }
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)))
+}
+
+/// The board and name are bound as associated data, so ciphertexts cannot
+/// be swapped between rows.
+fn associated_data(board_id: &str, name: &str) -> Vec<u8> {
+    format!("{}\0{}", board_id, name).into_bytes()
+}
+
+fn encrypt(
+    key: &[u8],
+    board_id: &str,
+    name: &str,
+    value: &str,
+) -> Result<(Vec<u8>, Vec<u8>), KanbanError> {
+    let mut nonce = [0_u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let aad = associated_data(board_id, name);
+    let ciphertext = cipher(key)?
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: value.as_bytes(),
+                aad: &aad,
+            },
+        )
+        .map_err(|_| KanbanError::Internal("Failed to encrypt secret variable".into()))?;
+    Ok((nonce.to_vec(), ciphertext))
+}
+
+fn decrypt(
+    key: &[u8],
+    board_id: &str,
+    name: &str,
+    nonce: &[u8],
+    ciphertext: &[u8],
+) -> Result<String, KanbanError> {
+    if nonce.len() != NONCE_LEN {
+        return Err(KanbanError::Internal(format!("Corrupt secret variable '{}'", name)));
+    }
+    let aad = associated_data(board_id, name);
+    let plaintext = cipher(key)?
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| {
+            KanbanError::Internal(format!("Failed to decrypt secret variable '{}'", name))
+        })?;
+    String::from_utf8(plaintext)
+        .map_err(|_| KanbanError::Internal(format!("Secret variable '{}' is not UTF-8", name)))
+}
@@ -0,0 +1,182 @@
+use std::path::{Path, PathBuf};
+
+use sqlx::SqlitePool;
+
+use crate::domain::{Card, KanbanError};
+
+use super::redaction::parse_variables;
+use super::{BoardSecretService, GitWorktreeService};
+
+/// File written at the root of each card worktree.
+pub const ENV_FILE: &str = ".env";
+
+#[derive(Debug, Clone)]
+pub struct BoardVariable {
+    pub name: String,
+    pub value: String,
+    pub secret: bool,
+}
+
+/// Materializes a board's plain and secret variables for the processes that
+/// work on its cards: a git-ignored `.env` in the worktree and environment
+/// for commands we run there. Prompts only ever see the names.
+pub struct BoardVariableService;
+
+impl BoardVariableService {
+    /// Plain variables first, then secrets; a secret replaces a plain
+    /// variable of the same name. Names that are not valid environment
+    /// variable names are skipped.
+    pub async fn load(
+        db: &SqlitePool,
+        board_id: Option<&str>,
+    ) -> Result<Vec<BoardVariable>, KanbanError> {
+        let Some(board_id) = board_id.filter(|id| !id.is_empty()) else {
+            return Ok(Vec::new());
+        };
+
+        let raw: Option<String> =
+            sqlx::query_scalar("SELECT variables FROM board_settings WHERE board_id = ?")
+                .bind(board_id)
+                .fetch_optional(db)
+                .await?;
+
+        let mut variables: Vec<BoardVariable> = Vec::new();
+        let plain = parse_variables(raw.as_deref().unwrap_or_default())
+            .into_iter()
+            .map(|(name, value)| (name, value, false));
+        let secrets = BoardSecretService::reveal(db, board_id)
+            .await?
+            .into_iter()
+            .map(|(name, value)| (name, value, true));
+
+        for (name, value, secret) in plain.chain(secrets) {
+            if !is_env_name(&name) {
+                tracing::debug!(board_id, name, "Skipping board variable with invalid name");
+                continue;
+            }
+            variables.retain(|existing| existing.name != name);
+            variables.push(BoardVariable {
+                name,
+                value,
+                secret,
+            });
+        }
+
+        Ok(variables)
+    }
+
+    /// `(name, value)` pairs to pass to `Command::envs` for a card's board.
+    pub async fn env_for_card(
+        db: &SqlitePool,
+        card: &Card,
+    ) -> Result<Vec<(String, String)>, KanbanError> {
+        Ok(Self::load(db, card.board_id.as_deref())
+            .await?
+            .into_iter()
+            .map(|variable| (variable.name, variable.value))
+            .collect())
+    }
+
+    /// Names (never values) for prompts, one bullet per variable.
+    pub fn summary(variables: &[BoardVariable]) -> String {
+        if variables.is_empty() {
+            return "None".to_string();
+        }
+        variables
+            .iter()
+            .map(|variable| {
+                if variable.secret {
+                    format!("- `{}` (secret)", variable.name)
+                } else {
+                    format!("- `{}`", variable.name)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Writes the board's variables to `.env` in the card's worktree and
+    /// keeps it out of git. Cards without a worktree are left alone, as is
+    /// a worktree whose repository tracks its own `.env`.
+    pub async fn materialize(db: &SqlitePool, card: &Card) -> Result<Option<PathBuf>, KanbanError> {
+        if card.worktree_path.is_empty() || !Path::new(&card.worktree_path).exists() {
+            return Ok(None);
+        }
+
+        let variables = Self::load(db, card.board_id.as_deref()).await?;
+        let path = Path::new(&card.worktree_path).join(ENV_FILE);
+        if variables.is_empty() && !path.exists() {
+            return Ok(None);
+        }
+
+        let worktree_path = card.worktree_path.clone();
+        let tracked = GitWorktreeService::blocking(move || {
+            if GitWorktreeService::is_tracked(&worktree_path, ENV_FILE) {
+                return Ok(true);
+            }
+            GitWorktreeService::ensure_excluded(&worktree_path, &format!("/{}", ENV_FILE))?;
+            Ok(false)
+        })
+        .await?;
+        if tracked {
+            tracing::warn!(
+                card_id = card.id,
+                worktree_path = card.worktree_path,
+                "Repository tracks its own .env; not writing board variables to it"
+            );
+            return Ok(None);
+        }
+
+        write_env_file(&path, &variables)
+            .map_err(|e| KanbanError::Internal(format!("Failed to write {}: {}", ENV_FILE, e)))?;
+        Ok(Some(path))
+    }
+}
+
+fn is_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn write_env_file(path: &Path, variables: &[BoardVariable]) -> std::io::Result<()> {
+    let mut content =
+        String::from("# Generated from board variables on every dispatch. Do not edit or commit.\n");
+    for variable in variables {
+        content.push_str(&format!("{}={}\n", variable.name, quote(&variable.value)));
+    }
+    std::fs::write(path, content)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(())
+}
+
+/// Bare when unambiguous, otherwise double-quoted with dotenv escapes.
+fn quote(value: &str) -> String {
+    let bare = value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "_-.,:/@+%".contains(c));
+    if bare {
+        return value.to_string();
+    }
+
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '$' => quoted.push_str("\\$"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
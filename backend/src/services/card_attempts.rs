@@ -0,0 +1,905 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use crate::api::handlers::sse::WsEvent;
+use crate::domain::{Card, KanbanError, NotificationType};
+
+use super::git_worktree::DiffResult;
+use super::model_selection::split_model;
+use super::{
+    AiDispatchService, EventBus, GitWorktreeService, NotificationService, OpencodePool,
+    SessionMappingService, WorktreeHookService,
+};
+
+pub const MIN_ATTEMPTS: usize = 2;
+pub const MAX_ATTEMPTS: usize = 5;
+
+/// Attempts that still compete for the card: not yet selected, discarded or
+/// cancelled.
+const OPEN_STATUSES: &str =
+    "('queued', 'setting_up', 'dispatched', 'working', 'completed', 'failed')";
+
+const COLUMNS: &str = "id, card_id, attempt_number, agent, model, status, session_id, opencode_url, branch_name, worktree_path, base_commit, files_changed, additions, deletions, verification, failure_reason, created_at, updated_at, finished_at";
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct CardAttempt {
+    pub id: String,
+    pub card_id: String,
+    pub attempt_number: i64,
+    pub agent: Option<String>,
+    /// `provider/model`; `None` uses the OpenCode default.
+    pub model: Option<String>,
+    /// `queued`, `setting_up`, `dispatched`, `working`, `completed`,
+    /// `failed`, `cancelled`, `selected` or `discarded`.
+    pub status: String,
+    pub session_id: Option<String>,
+    pub opencode_url: String,
+    pub branch_name: String,
+    pub worktree_path: String,
+    pub base_commit: String,
+    pub files_changed: i64,
+    pub additions: i64,
+    pub deletions: i64,
+    /// [`Verification`](super::worktree_hooks::Verification) JSON; empty
+    /// until the board's verify hooks have run, or when it has none.
+    pub verification: String,
+    pub failure_reason: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub finished_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AttemptSpec {
+    pub agent: Option<String>,
+    pub model: Option<String>,
+}
+
+impl CardAttempt {
+    /// The card as this attempt works on it: its own worktree, branch,
+    /// session, endpoint and agent. The card row itself is never changed.
+    pub fn card_view(&self, card: &Card) -> Card {
+        let mut view = card.clone();
+        view.branch_name = self.branch_name.clone();
+        view.worktree_path = self.worktree_path.clone();
+        if !self.worktree_path.is_empty() {
+            view.working_directory = self.worktree_path.clone();
+        }
+        view.ai_session_id = self.session_id.clone();
+        view.opencode_url = self.opencode_url.clone();
+        if let Some(agent) = self.agent.as_ref().filter(|agent| !agent.is_empty()) {
+            view.ai_agent = Some(agent.clone());
+        }
+        view
+    }
+
+    /// Still waiting for or holding a dispatch slot.
+    pub fn is_active(&self) -> bool {
+        matches!(self.status.as_str(), "queued" | "setting_up" | "dispatched" | "working")
+    }
+
+    /// Session mapping agent type, so attempt logs are told apart.
+    pub fn label(&self) -> String {
+        format!("attempt-{}", self.attempt_number)
+    }
+
+    pub fn branch_suffix(&self) -> String {
+        format!("-attempt-{}", self.attempt_number)
+    }
+}
+
+/// Competing attempts: several sessions work the same card in separate
+/// worktrees, and review keeps one of them.
+pub struct CardAttemptService;
+
+impl CardAttemptService {
+    /// Queues one attempt per spec. The card goes back to `todo`/`queued`;
+    /// the queue processor dispatches the attempts instead of the card.
+    pub async fn create(
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+        card: &Card,
+        specs: &[AttemptSpec],
+    ) -> Result<Vec<CardAttempt>, KanbanError> {
+        if !(MIN_ATTEMPTS..=MAX_ATTEMPTS).contains(&specs.len()) {
+            return Err(KanbanError::BadRequest(format!(
+                "Between {} and {} attempts are allowed",
+                MIN_ATTEMPTS, MAX_ATTEMPTS
+            )));
+        }
+        for spec in specs {
+            if let Some(model) = spec.model.as_deref().filter(|model| !model.is_empty()) {
+                if split_model(model).is_none() {
+                    return Err(KanbanError::BadRequest(format!(
+                        "Invalid model '{}': use provider/model",
+                        model
+                    )));
+                }
+            }
+        }
+        if !matches!(card.stage.as_str(), "backlog" | "plan" | "todo") {
+            return Err(KanbanError::BadRequest(
+                "Attempts can only start from backlog, plan or todo".into(),
+            ));
+        }
+        if matches!(
+            card.ai_status.as_str(),
+            "planning" | "setting_up" | "dispatched" | "working" | "queued" | "waiting"
+        ) {
+            return Err(KanbanError::Conflict(format!(
+                "Card AI status is '{}'; stop it before starting attempts",
+                card.ai_status
+            )));
+        }
+        if !card.worktree_path.is_empty() {
+            return Err(KanbanError::Conflict(
+                "Card already has a worktree; merge or reject it first".into(),
+            ));
+        }
+        if codebase_path(db, card.board_id.as_deref()).await?.is_none() {
+            return Err(KanbanError::BadRequest(
+                "Board codebase path not configured".into(),
+            ));
+        }
+
+        // Claiming the card first takes SQLite's write lock, so a concurrent
+        // request waits and then sees this one's attempts.
+        let now = Utc::now().to_rfc3339();
+        let mut tx = db.begin().await?;
+        let claimed = sqlx::query(
+            "UPDATE cards SET stage = 'todo', ai_status = 'queued', updated_at = ?
+             WHERE id = ? AND worktree_path = ''
+               AND ai_status NOT IN ('planning', 'setting_up', 'dispatched', 'working', 'queued', 'waiting')",
+        )
+        .bind(&now)
+        .bind(&card.id)
+        .execute(&mut *tx)
+        .await?;
+        if claimed.rows_affected() == 0 {
+            return Err(KanbanError::Conflict(
+                "Card is already being worked on".into(),
+            ));
+        }
+        let open: Option<i64> = sqlx::query_scalar(&format!(
+            "SELECT 1 FROM card_attempts WHERE card_id = ? AND status IN {} LIMIT 1",
+            OPEN_STATUSES
+        ))
+        .bind(&card.id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if open.is_some() {
+            return Err(KanbanError::Conflict(
+                "Card already has competing attempts".into(),
+            ));
+        }
+
+        let first_number: i64 = sqlx::query_scalar(
+            "SELECT COALESCE(MAX(attempt_number), 0) + 1 FROM card_attempts WHERE card_id = ?",
+        )
+        .bind(&card.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let mut attempts = Vec::with_capacity(specs.len());
+        for (offset, spec) in specs.iter().enumerate() {
+            let attempt: CardAttempt = sqlx::query_as(&format!(
+                "INSERT INTO card_attempts (id, card_id, attempt_number, agent, model, status, created_at, updated_at)
+                 VALUES (?, ?, ?, ?, ?, 'queued', ?, ?) RETURNING {}",
+                COLUMNS
+            ))
+            .bind(Uuid::new_v4().to_string())
+            .bind(&card.id)
+            .bind(first_number + offset as i64)
+            .bind(spec.agent.as_deref().filter(|agent| !agent.is_empty()))
+            .bind(spec.model.as_deref().filter(|model| !model.is_empty()))
+            .bind(&now)
+            .bind(&now)
+            .fetch_one(&mut *tx)
+            .await?;
+            attempts.push(attempt);
+        }
+        tx.commit().await?;
+
+        if card.stage != "todo" {
+            broadcast(
+                sse_tx,
+                &WsEvent::CardMoved {
+                    card_id: card.id.clone(),
+                    from_stage: card.stage.clone(),
+                    to_stage: "todo".to_string(),
+                },
+            );
+        }
+        broadcast(
+            sse_tx,
+            &WsEvent::AiStatusChanged {
+                card_id: card.id.clone(),
+                board_id: card.board_id.clone(),
+                status: "queued".to_string(),
+                progress: serde_json::from_str(&card.ai_progress).unwrap_or_else(|_| json!({})),
+                stage: "todo".to_string(),
+                ai_session_id: None,
+            },
+        );
+        for attempt in &attempts {
+            broadcast_attempt(sse_tx, attempt);
+        }
+
+        Ok(attempts)
+    }
+
+    pub async fn list(db: &SqlitePool, card_id: &str) -> Result<Vec<CardAttempt>, KanbanError> {
+        let attempts = sqlx::query_as(&format!(
+            "SELECT {} FROM card_attempts WHERE card_id = ? ORDER BY attempt_number ASC",
+            COLUMNS
+        ))
+        .bind(card_id)
+        .fetch_all(db)
+        .await?;
+        Ok(attempts)
+    }
+
+    pub async fn get(
+        db: &SqlitePool,
+        card_id: &str,
+        attempt_id: &str,
+    ) -> Result<CardAttempt, KanbanError> {
+        sqlx::query_as(&format!(
+            "SELECT {} FROM card_attempts WHERE card_id = ? AND id = ?",
+            COLUMNS
+        ))
+        .bind(card_id)
+        .bind(attempt_id)
+        .fetch_optional(db)
+        .await?
+        .ok_or_else(|| KanbanError::NotFound(format!("Attempt not found: {}", attempt_id)))
+    }
+
+    pub async fn has_open(db: &SqlitePool, card_id: &str) -> Result<bool, KanbanError> {
+        let open: Option<i64> = sqlx::query_scalar(&format!(
+            "SELECT 1 FROM card_attempts WHERE card_id = ? AND status IN {} LIMIT 1",
+            OPEN_STATUSES
+        ))
+        .bind(card_id)
+        .fetch_optional(db)
+        .await?;
+        Ok(open.is_some())
+    }
+
+    /// Queued attempts on the board in card priority order, then by number.
+    pub async fn queued_for_board(
+        db: &SqlitePool,
+        board_id: &str,
+        limit: i64,
+    ) -> Result<Vec<CardAttempt>, KanbanError> {
+        let columns = COLUMNS
+            .split(", ")
+            .map(|column| format!("a.{}", column))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let attempts = sqlx::query_as(&format!(
+            "SELECT {} FROM card_attempts a JOIN cards c ON c.id = a.card_id
+             WHERE c.board_id = ? AND a.status = 'queued'
+             ORDER BY CASE c.priority WHEN 'high' THEN 0 WHEN 'medium' THEN 1 WHEN 'low' THEN 2 ELSE 3 END ASC, c.updated_at ASC, a.attempt_number ASC
+             LIMIT ?",
+            columns
+        ))
+        .bind(board_id)
+        .bind(limit)
+        .fetch_all(db)
+        .await?;
+        Ok(attempts)
+    }
+
+    pub async fn record_worktree(
+        db: &SqlitePool,
+        attempt: &CardAttempt,
+        branch_name: &str,
+        worktree_path: &str,
+        base_commit: &str,
+    ) -> Result<CardAttempt, KanbanError> {
+        let updated = sqlx::query_as(&format!(
+            "UPDATE card_attempts SET branch_name = ?, worktree_path = ?, base_commit = ?, updated_at = ? WHERE id = ? RETURNING {}",
+            COLUMNS
+        ))
+        .bind(branch_name)
+        .bind(worktree_path)
+        .bind(base_commit)
+        .bind(Utc::now().to_rfc3339())
+        .bind(&attempt.id)
+        .fetch_one(db)
+        .await?;
+        Ok(updated)
+    }
+
+    /// Claims a queued attempt for its worktree setup hooks; `None` when it
+    /// is no longer queued.
+    pub async fn mark_setting_up(
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+        attempt: &CardAttempt,
+    ) -> Result<Option<CardAttempt>, KanbanError> {
+        let updated: Option<CardAttempt> = sqlx::query_as(&format!(
+            "UPDATE card_attempts SET status = 'setting_up', updated_at = ? WHERE id = ? AND status = 'queued' RETURNING {}",
+            COLUMNS
+        ))
+        .bind(Utc::now().to_rfc3339())
+        .bind(&attempt.id)
+        .fetch_optional(db)
+        .await?;
+        if let Some(attempt) = &updated {
+            broadcast_attempt(sse_tx, attempt);
+        }
+        Ok(updated)
+    }
+
+    pub async fn with_status(db: &SqlitePool, status: &str) -> Result<Vec<CardAttempt>, KanbanError> {
+        let attempts = sqlx::query_as(&format!(
+            "SELECT {} FROM card_attempts WHERE status = ?",
+            COLUMNS
+        ))
+        .bind(status)
+        .fetch_all(db)
+        .await?;
+        Ok(attempts)
+    }
+
+    /// The attempt's session exists. It is mapped to the card before any
+    /// prompt is sent so the relay attributes its events; the card shows as
+    /// in progress from the first dispatched attempt on.
+    pub async fn record_dispatch(
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+        attempt: &CardAttempt,
+        session_id: &str,
+        opencode_url: &str,
+    ) -> Result<CardAttempt, KanbanError> {
+        SessionMappingService::insert(
+            db,
+            session_id,
+            &attempt.card_id,
+            "",
+            Some(&attempt.label()),
+            &format!("Attempt {}", attempt.attempt_number),
+        )
+        .await
+        .map_err(|e| KanbanError::Internal(format!("Failed to map attempt session: {}", e)))?;
+
+        let now = Utc::now().to_rfc3339();
+        let updated: CardAttempt = sqlx::query_as(&format!(
+            "UPDATE card_attempts SET status = 'dispatched', session_id = ?, opencode_url = ?, updated_at = ? WHERE id = ? RETURNING {}",
+            COLUMNS
+        ))
+        .bind(session_id)
+        .bind(opencode_url)
+        .bind(&now)
+        .bind(&attempt.id)
+        .fetch_one(db)
+        .await?;
+        broadcast_attempt(sse_tx, &updated);
+
+        let card: Card = sqlx::query_as("SELECT * FROM cards WHERE id = ?")
+            .bind(&attempt.card_id)
+            .fetch_one(db)
+            .await?;
+        if card.stage == "todo" {
+            sqlx::query("UPDATE cards SET stage = 'in_progress', ai_status = 'working', updated_at = ? WHERE id = ?")
+                .bind(&now)
+                .bind(&card.id)
+                .execute(db)
+                .await?;
+            broadcast(
+                sse_tx,
+                &WsEvent::CardMoved {
+                    card_id: card.id.clone(),
+                    from_stage: "todo".to_string(),
+                    to_stage: "in_progress".to_string(),
+                },
+            );
+            broadcast(
+                sse_tx,
+                &WsEvent::AiStatusChanged {
+                    card_id: card.id.clone(),
+                    board_id: card.board_id.clone(),
+                    status: "working".to_string(),
+                    progress: serde_json::from_str(&card.ai_progress).unwrap_or_else(|_| json!({})),
+                    stage: "in_progress".to_string(),
+                    ai_session_id: None,
+                },
+            );
+        }
+        Ok(updated)
+    }
+
+    pub async fn find_by_session(
+        db: &SqlitePool,
+        session_id: &str,
+    ) -> Result<Option<CardAttempt>, KanbanError> {
+        let attempt = sqlx::query_as(&format!(
+            "SELECT {} FROM card_attempts WHERE session_id = ?",
+            COLUMNS
+        ))
+        .bind(session_id)
+        .fetch_optional(db)
+        .await?;
+        Ok(attempt)
+    }
+
+    /// `session.status` busy for an attempt session.
+    pub async fn mark_working(
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+        session_id: &str,
+    ) -> Result<(), KanbanError> {
+        let updated: Option<CardAttempt> = sqlx::query_as(&format!(
+            "UPDATE card_attempts SET status = 'working', updated_at = ? WHERE session_id = ? AND status = 'dispatched' RETURNING {}",
+            COLUMNS
+        ))
+        .bind(Utc::now().to_rfc3339())
+        .bind(session_id)
+        .fetch_optional(db)
+        .await?;
+        if let Some(attempt) = updated {
+            broadcast_attempt(sse_tx, &attempt);
+        }
+        Ok(())
+    }
+
+    /// `session.idle` for an attempt session: records the attempt's diff
+    /// stats, runs the board's verify hooks in its worktree, and moves the
+    /// card to review once no attempt is still running.
+    pub async fn complete_session(
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+        session_id: &str,
+    ) -> Result<(), KanbanError> {
+        let now = Utc::now().to_rfc3339();
+        // Claimed atomically so a repeated idle event is a no-op.
+        let claimed: Option<CardAttempt> = sqlx::query_as(&format!(
+            "UPDATE card_attempts SET status = 'completed', updated_at = ?, finished_at = ?
+             WHERE session_id = ? AND status IN ('dispatched', 'working') RETURNING {}",
+            COLUMNS
+        ))
+        .bind(&now)
+        .bind(&now)
+        .bind(session_id)
+        .fetch_optional(db)
+        .await?;
+        let Some(attempt) = claimed else {
+            return Ok(());
+        };
+        tracing::info!(card_id = attempt.card_id, attempt = attempt.attempt_number, "Attempt session idle");
+
+        let card: Card = sqlx::query_as("SELECT * FROM cards WHERE id = ?")
+            .bind(&attempt.card_id)
+            .fetch_one(db)
+            .await?;
+        let view = attempt.card_view(&card);
+        let worktree_exists =
+            !attempt.worktree_path.is_empty() && std::path::Path::new(&attempt.worktree_path).exists();
+
+        let (files_changed, additions, deletions) = if worktree_exists && !attempt.base_commit.is_empty() {
+            let (worktree_path, base_commit) = (attempt.worktree_path.clone(), attempt.base_commit.clone());
+            match GitWorktreeService::cancellable(move || {
+                GitWorktreeService::snapshot_diff(&worktree_path, &base_commit)
+            })
+            .await
+            {
+                Ok(diff) => (diff.stats.files_changed, diff.stats.additions, diff.stats.deletions),
+                Err(e) => {
+                    tracing::warn!(attempt_id = attempt.id, error = %e, "Failed to diff attempt");
+                    (0, 0, 0)
+                }
+            }
+        } else {
+            (0, 0, 0)
+        };
+
+        let mut verification = String::new();
+        if worktree_exists {
+            if let Some(codebase) = codebase_path(db, card.board_id.as_deref()).await? {
+                match WorktreeHookService::run_verify(db, sse_tx, &view, &codebase).await {
+                    Ok(Some(result)) => {
+                        verification = serde_json::to_string(&result).unwrap_or_default();
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        tracing::warn!(attempt_id = attempt.id, error = %e, "Failed to verify attempt");
+                    }
+                }
+            }
+        }
+
+        let updated: CardAttempt = sqlx::query_as(&format!(
+            "UPDATE card_attempts SET files_changed = ?, additions = ?, deletions = ?, verification = ?, updated_at = ? WHERE id = ? RETURNING {}",
+            COLUMNS
+        ))
+        .bind(files_changed)
+        .bind(additions)
+        .bind(deletions)
+        .bind(&verification)
+        .bind(Utc::now().to_rfc3339())
+        .bind(&attempt.id)
+        .fetch_one(db)
+        .await?;
+        broadcast_attempt(sse_tx, &updated);
+
+        Self::settle_card(db, sse_tx, &attempt.card_id).await
+    }
+
+    pub async fn fail(
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+        attempt_id: &str,
+        reason: &str,
+    ) -> Result<(), KanbanError> {
+        let now = Utc::now().to_rfc3339();
+        let failed: Option<CardAttempt> = sqlx::query_as(&format!(
+            "UPDATE card_attempts SET status = 'failed', failure_reason = ?, updated_at = ?, finished_at = ?
+             WHERE id = ? AND status IN ('queued', 'setting_up', 'dispatched', 'working') RETURNING {}",
+            COLUMNS
+        ))
+        .bind(reason)
+        .bind(&now)
+        .bind(&now)
+        .bind(attempt_id)
+        .fetch_optional(db)
+        .await?;
+        let Some(attempt) = failed else {
+            return Ok(());
+        };
+        tracing::warn!(card_id = attempt.card_id, attempt = attempt.attempt_number, reason, "Attempt failed");
+        broadcast_attempt(sse_tx, &attempt);
+        Self::settle_card(db, sse_tx, &attempt.card_id).await
+    }
+
+    /// Like [`Self::fail`], for background tasks with nowhere to report to.
+    pub async fn fail_or_warn(db: &SqlitePool, sse_tx: &EventBus, attempt_id: &str, reason: &str) {
+        if let Err(e) = Self::fail(db, sse_tx, attempt_id, reason).await {
+            tracing::warn!(attempt_id, error = %e, "Failed to mark attempt failed");
+        }
+    }
+
+    /// Once every open attempt has finished, the card goes to review if any
+    /// attempt completed and fails otherwise.
+    async fn settle_card(db: &SqlitePool, sse_tx: &EventBus, card_id: &str) -> Result<(), KanbanError> {
+        let attempts: Vec<CardAttempt> = sqlx::query_as(&format!(
+            "SELECT {} FROM card_attempts WHERE card_id = ? AND status IN {}",
+            COLUMNS, OPEN_STATUSES
+        ))
+        .bind(card_id)
+        .fetch_all(db)
+        .await?;
+        if attempts.is_empty() || attempts.iter().any(CardAttempt::is_active) {
+            return Ok(());
+        }
+
+        let card: Card = sqlx::query_as("SELECT * FROM cards WHERE id = ?")
+            .bind(card_id)
+            .fetch_one(db)
+            .await?;
+        if card.stage != "in_progress" && card.stage != "todo" {
+            return Ok(());
+        }
+
+        let now = Utc::now().to_rfc3339();
+        let completed = attempts.iter().filter(|a| a.status == "completed").count();
+        let mut progress: serde_json::Value =
+            serde_json::from_str(&card.ai_progress).unwrap_or_else(|_| json!({}));
+        let (stage, status) = if completed > 0 {
+            ("review", "completed")
+        } else {
+            progress["failure_reason"] = json!(format!("All {} attempts failed", attempts.len()));
+            progress["failed_at"] = json!(now);
+            (card.stage.as_str(), "failed")
+        };
+
+        sqlx::query("UPDATE cards SET stage = ?, ai_status = ?, ai_progress = ?, updated_at = ? WHERE id = ?")
+            .bind(stage)
+            .bind(status)
+            .bind(progress.to_string())
+            .bind(&now)
+            .bind(card_id)
+            .execute(db)
+            .await?;
+
+        if stage != card.stage {
+            broadcast(
+                sse_tx,
+                &WsEvent::CardMoved {
+                    card_id: card_id.to_string(),
+                    from_stage: card.stage.clone(),
+                    to_stage: stage.to_string(),
+                },
+            );
+        }
+        broadcast(
+            sse_tx,
+            &WsEvent::AiStatusChanged {
+                card_id: card_id.to_string(),
+                board_id: card.board_id.clone(),
+                status: status.to_string(),
+                progress,
+                stage: stage.to_string(),
+                ai_session_id: None,
+            },
+        );
+
+        let (notification, title, message) = if completed > 0 {
+            (
+                NotificationType::AiCompleted,
+                format!("AI attempts completed: {}", card.title),
+                format!(
+                    "{} of {} attempts completed for card '{}'; pick one in review",
+                    completed,
+                    attempts.len(),
+                    card.title
+                ),
+            )
+        } else {
+            (
+                NotificationType::AiError,
+                format!("AI attempts failed: {}", card.title),
+                format!("All {} attempts failed for card '{}'", attempts.len(), card.title),
+            )
+        };
+        let _ = NotificationService::create_notification(
+            db,
+            sse_tx,
+            None,
+            notification,
+            &title,
+            &message,
+            Some(card_id),
+            None,
+        )
+        .await;
+        Ok(())
+    }
+
+    /// The attempt's current diff against the commit its branch started from.
+    pub async fn diff(
+        db: &SqlitePool,
+        card_id: &str,
+        attempt_id: &str,
+    ) -> Result<DiffResult, KanbanError> {
+        let attempt = Self::get(db, card_id, attempt_id).await?;
+        if attempt.base_commit.is_empty()
+            || attempt.worktree_path.is_empty()
+            || !std::path::Path::new(&attempt.worktree_path).exists()
+        {
+            return Err(KanbanError::BadRequest(format!(
+                "Attempt {} has no worktree",
+                attempt.attempt_number
+            )));
+        }
+        GitWorktreeService::cancellable(move || {
+            GitWorktreeService::snapshot_diff(&attempt.worktree_path, &attempt.base_commit)
+        })
+        .await
+    }
+
+    /// Makes the winning attempt the card's own branch, worktree and session
+    /// and removes every other open attempt. The card is then reviewed and
+    /// merged like any other.
+    pub async fn select(
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+        http_client: &reqwest::Client,
+        opencode: &OpencodePool,
+        card: &Card,
+        attempt_id: &str,
+    ) -> Result<CardAttempt, KanbanError> {
+        let winner = Self::get(db, &card.id, attempt_id).await?;
+        if winner.status != "completed" {
+            return Err(KanbanError::BadRequest(format!(
+                "Attempt {} is '{}'; only a completed attempt can be selected",
+                winner.attempt_number, winner.status
+            )));
+        }
+        if card.stage == "done" {
+            return Err(KanbanError::BadRequest(
+                "Card is done; its attempts can no longer be selected".into(),
+            ));
+        }
+        let codebase = codebase_path(db, card.board_id.as_deref())
+            .await?
+            .ok_or_else(|| KanbanError::BadRequest("Board codebase path not configured".into()))?;
+
+        // Claim the winner first, so it cannot be discarded by a concurrent
+        // selection of another attempt.
+        let now = Utc::now().to_rfc3339();
+        let selected: CardAttempt = sqlx::query_as(&format!(
+            "UPDATE card_attempts SET status = 'selected', updated_at = ? WHERE id = ? AND status = 'completed' RETURNING {}",
+            COLUMNS
+        ))
+        .bind(&now)
+        .bind(&winner.id)
+        .fetch_optional(db)
+        .await?
+        .ok_or_else(|| {
+            KanbanError::Conflict(format!(
+                "Attempt {} was selected or discarded meanwhile",
+                winner.attempt_number
+            ))
+        })?;
+
+        // Only a card unchanged since it was read moves to review: one merged
+        // or moved meanwhile keeps its stage, and of two concurrent
+        // selections only the first wins. A losing claim is released.
+        let moved = sqlx::query(
+            "UPDATE cards SET branch_name = ?, worktree_path = ?, working_directory = ?, ai_session_id = ?, opencode_url = ?, stage = 'review', ai_status = 'completed', updated_at = ? WHERE id = ? AND stage = ? AND updated_at = ?",
+        )
+        .bind(&winner.branch_name)
+        .bind(&winner.worktree_path)
+        .bind(&winner.worktree_path)
+        .bind(&winner.session_id)
+        .bind(&winner.opencode_url)
+        .bind(&now)
+        .bind(&card.id)
+        .bind(&card.stage)
+        .bind(&card.updated_at)
+        .execute(db)
+        .await?
+        .rows_affected();
+        if moved == 0 {
+            sqlx::query("UPDATE card_attempts SET status = 'completed', updated_at = ? WHERE id = ? AND status = 'selected'")
+                .bind(Utc::now().to_rfc3339())
+                .bind(&winner.id)
+                .execute(db)
+                .await?;
+            return Err(KanbanError::Conflict(
+                "Card changed while the attempt was being selected".into(),
+            ));
+        }
+        broadcast_attempt(sse_tx, &selected);
+
+        let open: Vec<CardAttempt> = sqlx::query_as(&format!(
+            "SELECT {} FROM card_attempts WHERE card_id = ? AND status IN {} AND id != ?",
+            COLUMNS, OPEN_STATUSES
+        ))
+        .bind(&card.id)
+        .bind(&winner.id)
+        .fetch_all(db)
+        .await?;
+        for loser in &open {
+            Self::discard(db, sse_tx, http_client, opencode, card, loser, &codebase, "discarded").await;
+        }
+
+        // The relay matches the card's own session first; drop the attempt
+        // mapping so follow-up work is no longer logged as a sub-session.
+        if let Some(session_id) = winner.session_id.as_deref() {
+            sqlx::query("DELETE FROM session_mappings WHERE child_session_id = ?")
+                .bind(session_id)
+                .execute(db)
+                .await?;
+        }
+
+        if card.stage != "review" {
+            broadcast(
+                sse_tx,
+                &WsEvent::CardMoved {
+                    card_id: card.id.clone(),
+                    from_stage: card.stage.clone(),
+                    to_stage: "review".to_string(),
+                },
+            );
+        }
+        broadcast(
+            sse_tx,
+            &WsEvent::AiStatusChanged {
+                card_id: card.id.clone(),
+                board_id: card.board_id.clone(),
+                status: "completed".to_string(),
+                progress: serde_json::from_str(&card.ai_progress).unwrap_or_else(|_| json!({})),
+                stage: "review".to_string(),
+                ai_session_id: winner.session_id.clone(),
+            },
+        );
+
+        Ok(selected)
+    }
+
+    /// Stops and removes every open attempt; running ones end `cancelled`,
+    /// finished ones `discarded`. Returns how many were open.
+    pub async fn cancel_all(
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+        http_client: &reqwest::Client,
+        opencode: &OpencodePool,
+        card: &Card,
+    ) -> Result<usize, KanbanError> {
+        let open: Vec<CardAttempt> = sqlx::query_as(&format!(
+            "SELECT {} FROM card_attempts WHERE card_id = ? AND status IN {}",
+            COLUMNS, OPEN_STATUSES
+        ))
+        .bind(&card.id)
+        .fetch_all(db)
+        .await?;
+        let codebase = codebase_path(db, card.board_id.as_deref())
+            .await?
+            .unwrap_or_default();
+        for attempt in &open {
+            let status = if attempt.is_active() { "cancelled" } else { "discarded" };
+            Self::discard(db, sse_tx, http_client, opencode, card, attempt, &codebase, status).await;
+        }
+        Ok(open.len())
+    }
+
+    /// Aborts the attempt's session if it is running, removes its worktree
+    /// and branch, and records `status`. Best effort: failures are logged.
+    #[allow(clippy::too_many_arguments)]
+    async fn discard(
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+        http_client: &reqwest::Client,
+        opencode: &OpencodePool,
+        card: &Card,
+        attempt: &CardAttempt,
+        codebase: &str,
+        status: &str,
+    ) {
+        let view = attempt.card_view(card);
+        if matches!(attempt.status.as_str(), "dispatched" | "working") {
+            if let Some(session_id) = attempt.session_id.as_deref() {
+                let dispatcher =
+                    AiDispatchService::new(http_client.clone(), opencode.endpoint_for_card(&view));
+                if let Err(e) = dispatcher.abort_session(session_id).await {
+                    tracing::warn!(attempt_id = attempt.id, error = %e, "Failed to abort attempt session");
+                }
+            }
+        }
+        if !codebase.is_empty() && !attempt.worktree_path.is_empty() {
+            if let Err(e) = WorktreeHookService::remove_worktree(db, sse_tx, &view, codebase).await {
+                tracing::warn!(attempt_id = attempt.id, error = %e, "Failed to remove attempt worktree");
+            }
+        }
+
+        let now = Utc::now().to_rfc3339();
+        let updated: Result<CardAttempt, sqlx::Error> = sqlx::query_as(&format!(
+            "UPDATE card_attempts SET status = ?, worktree_path = '', updated_at = ?, finished_at = COALESCE(finished_at, ?) WHERE id = ? RETURNING {}",
+            COLUMNS
+        ))
+        .bind(status)
+        .bind(&now)
+        .bind(&now)
+        .bind(&attempt.id)
+        .fetch_one(db)
+        .await;
+        match updated {
+            Ok(updated) => broadcast_attempt(sse_tx, &updated),
+            Err(e) => tracing::warn!(attempt_id = attempt.id, error = %e, "Failed to record discarded attempt"),
+        }
+    }
+}
+
+async fn codebase_path(db: &SqlitePool, board_id: Option<&str>) -> Result<Option<String>, KanbanError> {
+    let Some(board_id) = board_id.filter(|id| !id.is_empty()) else {
+        return Ok(None);
+    };
+    let path: Option<String> =
+        sqlx::query_scalar("SELECT codebase_path FROM board_settings WHERE board_id = ?")
+            .bind(board_id)
+            .fetch_optional(db)
+            .await?;
+    Ok(path.filter(|p| !p.is_empty()))
+}
+
+fn broadcast(sse_tx: &EventBus, event: &WsEvent) {
+    if let Ok(payload) = serde_json::to_string(event) {
+        let _ = sse_tx.send(payload);
+    }
+}
+
+fn broadcast_attempt(sse_tx: &EventBus, attempt: &CardAttempt) {
+    broadcast(
+        sse_tx,
+        &WsEvent::CardAttemptUpdated {
+            card_id: attempt.card_id.clone(),
+            attempt: serde_json::to_value(attempt).unwrap_or_default(),
+        },
+    );
+}
@@ -0,0 +1,347 @@
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use crate::domain::{AgentLog, Card, KanbanError};
+
+use super::git_worktree::DiffResult;
+use super::{GitWorktreeService, LogRetentionService};
+
+pub const OUTCOME_RUNNING: &str = "running";
+/// A new run started before this one reported an outcome.
+pub const OUTCOME_SUPERSEDED: &str = "superseded";
+
+const COLUMNS: &str = "id, card_id, run_number, kind, session_id, agent, plan_path, plan_version_id, branch_name, worktree_path, base_commit, head_commit, started_at, ended_at, outcome, files_changed, additions, deletions, input_tokens, output_tokens, reasoning_tokens, cache_read_tokens, cache_write_tokens, cost";
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct CardRun {
+    pub id: String,
+    pub card_id: String,
+    pub run_number: i64,
+    /// `plan`, `work`, `retry` or `resume`.
+    pub kind: String,
+    pub session_id: String,
+    pub agent: Option<String>,
+    pub plan_path: Option<String>,
+    /// Latest `card_versions` row when the run started.
+    pub plan_version_id: Option<String>,
+    pub branch_name: String,
+    pub worktree_path: String,
+    pub base_commit: String,
+    pub head_commit: String,
+    pub started_at: String,
+    pub ended_at: Option<String>,
+    pub outcome: String,
+    pub files_changed: i64,
+    pub additions: i64,
+    pub deletions: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub reasoning_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub cache_write_tokens: i64,
+    pub cost: f64,
+}
+
+/// History of the AI runs on a card. The card row only ever describes the
+/// latest session; each run keeps its own session, branch state, diff and
+/// token usage so attempts can be compared after the fact.
+pub struct CardRunService;
+
+impl CardRunService {
+    /// Records a new run of `session_id` on the card. Any run still open is
+    /// closed as [`OUTCOME_SUPERSEDED`] first.
+    pub async fn start(
+        db: &SqlitePool,
+        card: &Card,
+        kind: &str,
+        session_id: &str,
+        plan_path: Option<&str>,
+    ) -> Result<CardRun, KanbanError> {
+        Self::finish(db, &card.id, OUTCOME_SUPERSEDED).await?;
+
+        let base_commit = match Self::codebase_path(db, card).await? {
+            Some(codebase) if Self::has_worktree(card) => {
+                let worktree_path = card.worktree_path.clone();
+                GitWorktreeService::blocking(move || {
+                    Ok(GitWorktreeService::merge_base(&codebase, &worktree_path).unwrap_or_default())
+                })
+                .await?
+            }
+            _ => String::new(),
+        };
+        let plan_version_id: Option<String> = sqlx::query_scalar(
+            "SELECT id FROM card_versions WHERE card_id = ? ORDER BY created_at DESC LIMIT 1",
+        )
+        .bind(&card.id)
+        .fetch_optional(db)
+        .await?;
+
+        let run: CardRun = sqlx::query_as(&format!(
+            "INSERT INTO card_runs (id, card_id, run_number, kind, session_id, agent, plan_path, plan_version_id, branch_name, worktree_path, base_commit, started_at, outcome)
+             VALUES (?, ?, (SELECT COALESCE(MAX(run_number), 0) + 1 FROM card_runs WHERE card_id = ?), ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+             RETURNING {}",
+            COLUMNS
+        ))
+        .bind(Uuid::new_v4().to_string())
+        .bind(&card.id)
+        .bind(&card.id)
+        .bind(kind)
+        .bind(session_id)
+        .bind(&card.ai_agent)
+        .bind(plan_path.or(card.plan_path.as_deref()))
+        .bind(plan_version_id)
+        .bind(&card.branch_name)
+        .bind(&card.worktree_path)
+        .bind(base_commit)
+        .bind(Utc::now().to_rfc3339())
+        .bind(OUTCOME_RUNNING)
+        .fetch_one(db)
+        .await?;
+
+        Ok(run)
+    }
+
+    /// Closes the card's open run, if any, capturing the worktree's diff
+    /// against the run's base so later runs cannot overwrite it.
+    pub async fn finish(
+        db: &SqlitePool,
+        card_id: &str,
+        outcome: &str,
+    ) -> Result<Option<CardRun>, KanbanError> {
+        let Some(run) = Self::open_run(db, card_id).await? else {
+            return Ok(None);
+        };
+
+        let mut head_commit = String::new();
+        let mut snapshot: Option<DiffResult> = None;
+        if !run.worktree_path.is_empty() && std::path::Path::new(&run.worktree_path).exists() {
+            let (worktree_path, base_commit) = (run.worktree_path.clone(), run.base_commit.clone());
+            let (head, diff) = GitWorktreeService::blocking(move || {
+                let head = GitWorktreeService::head_commit(&worktree_path).unwrap_or_default();
+                let diff = (!base_commit.is_empty())
+                    .then(|| GitWorktreeService::snapshot_diff(&worktree_path, &base_commit));
+                Ok((head, diff))
+            })
+            .await?;
+            head_commit = head;
+            match diff {
+                Some(Ok(diff)) => snapshot = Some(diff),
+                Some(Err(e)) => {
+                    tracing::warn!(card_id, run_id = run.id, error = %e, "Failed to snapshot run diff")
+                }
+                None => {}
+            }
+        }
+        let (files_changed, additions, deletions) = snapshot
+            .as_ref()
+            .map(|diff| (diff.stats.files_changed, diff.stats.additions, diff.stats.deletions))
+            .unwrap_or_default();
+        let diff_json = snapshot
+            .as_ref()
+            .and_then(|diff| serde_json::to_string(diff).ok());
+
+        let finished: CardRun = sqlx::query_as(&format!(
+            "UPDATE card_runs SET ended_at = ?, outcome = ?, head_commit = ?, files_changed = ?, additions = ?, deletions = ?, diff = ?
+             WHERE id = ? RETURNING {}",
+            COLUMNS
+        ))
+        .bind(Utc::now().to_rfc3339())
+        .bind(outcome)
+        .bind(head_commit)
+        .bind(files_changed)
+        .bind(additions)
+        .bind(deletions)
+        .bind(diff_json)
+        .bind(&run.id)
+        .fetch_one(db)
+        .await?;
+
+        Ok(Some(finished))
+    }
+
+    /// Like [`Self::start`] and [`Self::finish`], for call sites where run
+    /// bookkeeping must never fail the AI operation itself.
+    pub async fn start_or_warn(
+        db: &SqlitePool,
+        card: &Card,
+        kind: &str,
+        session_id: &str,
+        plan_path: Option<&str>,
+    ) {
+        if let Err(e) = Self::start(db, card, kind, session_id, plan_path).await {
+            tracing::warn!(card_id = card.id, kind, error = %e, "Failed to record card run");
+        }
+    }
+
+    pub async fn finish_or_warn(db: &SqlitePool, card_id: &str, outcome: &str) {
+        if let Err(e) = Self::finish(db, card_id, outcome).await {
+            tracing::warn!(card_id, outcome, error = %e, "Failed to close card run");
+        }
+    }
+
+    /// Adds a finished OpenCode message's `tokens` and `cost` to the card's
+    /// latest run. Each message is counted once, however often it is seen.
+    pub async fn record_usage(db: &SqlitePool, card_id: &str, info: &Value) -> Result<(), KanbanError> {
+        let Some(tokens) = info.get("tokens") else {
+            return Ok(());
+        };
+        let Some(message_id) = info.get("id").and_then(Value::as_str) else {
+            return Ok(());
+        };
+        let count = |value: Option<&Value>| value.and_then(Value::as_i64).unwrap_or(0);
+        let cache = tokens.get("cache");
+
+        let mut tx = db.begin().await?;
+        let run_id: Option<String> = sqlx::query_scalar(
+            "SELECT id FROM card_runs WHERE card_id = ? ORDER BY run_number DESC LIMIT 1",
+        )
+        .bind(card_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(run_id) = run_id else {
+            return Ok(());
+        };
+        let claimed = sqlx::query(
+            "INSERT OR IGNORE INTO card_run_messages (message_id, run_id, created_at) VALUES (?, ?, ?)",
+        )
+        .bind(message_id)
+        .bind(&run_id)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&mut *tx)
+        .await?;
+        if claimed.rows_affected() == 0 {
+            return Ok(());
+        }
+
+        sqlx::query(
+            "UPDATE card_runs SET
+                input_tokens = input_tokens + ?,
+                output_tokens = output_tokens + ?,
+                reasoning_tokens = reasoning_tokens + ?,
+                cache_read_tokens = cache_read_tokens + ?,
+                cache_write_tokens = cache_write_tokens + ?,
+                cost = cost + ?
+             WHERE id = ?",
+        )
+        .bind(count(tokens.get("input")))
+        .bind(count(tokens.get("output")))
+        .bind(count(tokens.get("reasoning")))
+        .bind(count(cache.and_then(|c| c.get("read"))))
+        .bind(count(cache.and_then(|c| c.get("write"))))
+        .bind(info.get("cost").and_then(Value::as_f64).unwrap_or(0.0))
+        .bind(&run_id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// `retry` once the card has had a work run, otherwise `work`.
+    pub async fn work_kind(db: &SqlitePool, card_id: &str) -> &'static str {
+        let worked: Option<i64> = sqlx::query_scalar(
+            "SELECT 1 FROM card_runs WHERE card_id = ? AND kind IN ('work', 'retry') LIMIT 1",
+        )
+        .bind(card_id)
+        .fetch_optional(db)
+        .await
+        .unwrap_or(None);
+        if worked.is_some() {
+            "retry"
+        } else {
+            "work"
+        }
+    }
+
+    pub async fn list(db: &SqlitePool, card_id: &str) -> Result<Vec<CardRun>, KanbanError> {
+        let runs = sqlx::query_as(&format!(
+            "SELECT {} FROM card_runs WHERE card_id = ? ORDER BY run_number ASC",
+            COLUMNS
+        ))
+        .bind(card_id)
+        .fetch_all(db)
+        .await?;
+        Ok(runs)
+    }
+
+    pub async fn get(db: &SqlitePool, card_id: &str, run_id: &str) -> Result<CardRun, KanbanError> {
+        sqlx::query_as(&format!(
+            "SELECT {} FROM card_runs WHERE card_id = ? AND id = ?",
+            COLUMNS
+        ))
+        .bind(card_id)
+        .bind(run_id)
+        .fetch_optional(db)
+        .await?
+        .ok_or_else(|| KanbanError::NotFound(format!("Run not found: {}", run_id)))
+    }
+
+    /// The diff captured when the run ended; an open run is diffed live.
+    pub async fn diff(db: &SqlitePool, card_id: &str, run_id: &str) -> Result<DiffResult, KanbanError> {
+        let run = Self::get(db, card_id, run_id).await?;
+        let stored: Option<String> = sqlx::query_scalar("SELECT diff FROM card_runs WHERE id = ?")
+            .bind(&run.id)
+            .fetch_one(db)
+            .await?;
+        if let Some(stored) = stored {
+            return serde_json::from_str(&stored)
+                .map_err(|e| KanbanError::Internal(format!("Corrupt run diff: {}", e)));
+        }
+
+        let live = run.ended_at.is_none()
+            && !run.base_commit.is_empty()
+            && std::path::Path::new(&run.worktree_path).exists();
+        if !live {
+            return Err(KanbanError::NotFound(format!(
+                "No diff was recorded for run {}",
+                run.run_number
+            )));
+        }
+        GitWorktreeService::cancellable(move || {
+            GitWorktreeService::snapshot_diff(&run.worktree_path, &run.base_commit)
+        })
+        .await
+    }
+
+    /// Agent log rows written while the run was active, archived rows included.
+    pub async fn logs(db: &SqlitePool, card_id: &str, run_id: &str) -> Result<Vec<AgentLog>, KanbanError> {
+        let run = Self::get(db, card_id, run_id).await?;
+        let logs = LogRetentionService::load_logs(db, card_id).await?;
+        Ok(logs
+            .into_iter()
+            .filter(|log| {
+                log.created_at >= run.started_at
+                    && run.ended_at.as_ref().is_none_or(|end| log.created_at <= *end)
+            })
+            .collect())
+    }
+
+    async fn open_run(db: &SqlitePool, card_id: &str) -> Result<Option<CardRun>, KanbanError> {
+        let run = sqlx::query_as(&format!(
+            "SELECT {} FROM card_runs WHERE card_id = ? AND ended_at IS NULL ORDER BY run_number DESC LIMIT 1",
+            COLUMNS
+        ))
+        .bind(card_id)
+        .fetch_optional(db)
+        .await?;
+        Ok(run)
+    }
+
+    async fn codebase_path(db: &SqlitePool, card: &Card) -> Result<Option<String>, KanbanError> {
+        let Some(board_id) = card.board_id.as_deref() else {
+            return Ok(None);
+        };
+        let path: Option<String> =
+            sqlx::query_scalar("SELECT codebase_path FROM board_settings WHERE board_id = ?")
+                .bind(board_id)
+                .fetch_optional(db)
+                .await?;
+        Ok(path.filter(|p| !p.is_empty()))
+    }
+
+    fn has_worktree(card: &Card) -> bool {
+        !card.worktree_path.is_empty() && std::path::Path::new(&card.worktree_path).exists()
+    }
+}
@@ -9,6 +9,8 @@ use crate::api::dto::{
 };
 use crate::domain::{Card, Comment, KanbanError, Label, Stage, Subtask};
 
+use super::Redactor;
+
 pub struct CardService;
 
 impl CardService {
@@ -248,6 +250,16 @@ impl CardService {
             Some(s) => Some(s.clone()),
             None => existing.ai_agent,
         };
+        let planning_model = match &req.planning_model {
+            Some(s) if s.trim().is_empty() => None,
+            Some(s) => Some(s.trim().to_string()),
+            None => existing.planning_model,
+        };
+        let implementation_model = match &req.implementation_model {
+            Some(s) if s.trim().is_empty() => None,
+            Some(s) => Some(s.trim().to_string()),
+            None => existing.implementation_model,
+        };
 
         stage
             .parse::<Stage>()
@@ -256,7 +268,7 @@ impl CardService {
         Self::save_card_version_snapshot(pool, &existing_for_snapshot, "user").await?;
 
         sqlx::query(
-            "UPDATE cards SET title = ?, description = ?, stage = ?, position = ?, priority = ?, working_directory = ?, linked_documents = ?, ai_agent = ?, updated_at = ? WHERE id = ?",
+            "UPDATE cards SET title = ?, description = ?, stage = ?, position = ?, priority = ?, working_directory = ?, linked_documents = ?, ai_agent = ?, planning_model = ?, implementation_model = ?, updated_at = ? WHERE id = ?",
         )
         .bind(&title)
         .bind(&description)
@@ -266,6 +278,8 @@ impl CardService {
         .bind(&working_directory)
         .bind(&linked_documents)
         .bind(&ai_agent)
+        .bind(&planning_model)
+        .bind(&implementation_model)
         .bind(&now)
         .bind(id)
         .execute(pool)
@@ -460,8 +474,7 @@ impl CardService {
         card_id: &str,
         req: CreateCommentRequest,
     ) -> Result<Comment, KanbanError> {
-        // Verify card exists
-        let _: Card = sqlx::query_as("SELECT * FROM cards WHERE id = ?")
+        let card: Card = sqlx::query_as("SELECT * FROM cards WHERE id = ?")
             .bind(card_id)
             .fetch_optional(pool)
             .await?
@@ -470,6 +483,9 @@ impl CardService {
         let id = Uuid::new_v4().to_string();
         let now = Utc::now().to_rfc3339();
         let author = req.author.unwrap_or_else(|| "user".into());
+        let content = Redactor::for_board(pool, card.board_id.as_deref())
+            .await?
+            .redact(&req.content);
 
         sqlx::query(
             "INSERT INTO comments (id, card_id, author, content, user_id, created_at) VALUES (?, ?, ?, ?, ?, ?)",
@@ -477,7 +493,7 @@ impl CardService {
         .bind(&id)
         .bind(card_id)
         .bind(&author)
-        .bind(&req.content)
+        .bind(&content)
         .bind(&req.user_id)
         .bind(&now)
         .execute(pool)
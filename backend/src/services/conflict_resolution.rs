@@ -0,0 +1,163 @@
+use chrono::Utc;
+use serde_json::json;
+use sqlx::SqlitePool;
+
+use crate::api::handlers::sse::WsEvent;
+use crate::domain::{Card, KanbanError};
+
+use super::ai_dispatch::message_body;
+use super::git_worktree::{ConflictDetail, MergeResult};
+use super::model_selection::{ModelPurpose, ModelSelection};
+use super::prompt_templates::{self, PromptTemplateService};
+use super::{CardRunService, EventBus, GitWorktreeService};
+
+/// Conflicted file contents beyond this many characters are cut short in
+/// the prompt; the agent still has the files in its worktree.
+const MAX_PROMPT_CONFLICT_CHARS: usize = 100_000;
+
+/// Hands merge conflicts to the card's own agent. The base branch is merged
+/// into the card branch inside the card's worktree, and the agent resolves
+/// and commits the merge there; when its session goes idle the card returns
+/// to review and its diff is the resolved result.
+pub struct ConflictResolutionService;
+
+impl ConflictResolutionService {
+    /// Starts the merge in the card's worktree. A clean merge just updates
+    /// the card branch; conflicts move the card to `in_progress` and prompt
+    /// its session with the [`ConflictDetail`].
+    pub async fn start(
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+        http_client: &reqwest::Client,
+        opencode_url: &str,
+        card: &Card,
+        codebase_path: &str,
+    ) -> Result<MergeResult, KanbanError> {
+        let Some(session_id) = card.ai_session_id.clone().filter(|s| !s.is_empty()) else {
+            return Err(KanbanError::BadRequest(
+                "Card has no AI session to resolve conflicts".into(),
+            ));
+        };
+
+        let (repo, worktree_path, title) =
+            (codebase_path.to_string(), card.worktree_path.clone(), card.title.clone());
+        let (result, base_branch) = GitWorktreeService::blocking(move || {
+            let result = GitWorktreeService::merge_base_into_worktree(&repo, &worktree_path, &title)?;
+            Ok((result, GitWorktreeService::default_branch(&repo)))
+        })
+        .await?;
+        let Some(detail) = result.conflict_detail.as_ref() else {
+            return Ok(result);
+        };
+
+        let prompt = PromptTemplateService::render_for_card(
+            db,
+            prompt_templates::CONFLICT_RESOLUTION,
+            card,
+            &[
+                ("base_branch", base_branch),
+                ("conflicts", format_conflicts(detail)),
+            ],
+        )
+        .await?;
+        let model = ModelSelection::for_card(db, card, ModelPurpose::Implementation).await?;
+
+        sqlx::query("UPDATE cards SET stage = 'in_progress', ai_status = 'dispatched', updated_at = ? WHERE id = ?")
+            .bind(Utc::now().to_rfc3339())
+            .bind(&card.id)
+            .execute(db)
+            .await?;
+
+        broadcast(
+            sse_tx,
+            &WsEvent::MergeConflictDetected {
+                card_id: card.id.clone(),
+                conflict_count: result.conflicts.len(),
+            },
+        );
+        broadcast(
+            sse_tx,
+            &WsEvent::CardMoved {
+                card_id: card.id.clone(),
+                from_stage: "review".to_string(),
+                to_stage: "in_progress".to_string(),
+            },
+        );
+        broadcast(
+            sse_tx,
+            &WsEvent::AiStatusChanged {
+                card_id: card.id.clone(),
+                board_id: card.board_id.clone(),
+                status: "dispatched".to_string(),
+                progress: serde_json::from_str(&card.ai_progress).unwrap_or_else(|_| json!({})),
+                stage: "in_progress".to_string(),
+                ai_session_id: Some(session_id.clone()),
+            },
+        );
+
+        let kind = CardRunService::work_kind(db, &card.id).await;
+        CardRunService::start_or_warn(db, card, kind, &session_id, None).await;
+
+        let body = message_body(&prompt, model.as_deref());
+        let message_url = format!("{}/session/{}/message", opencode_url, session_id);
+        let http_client = http_client.clone();
+        let db = db.clone();
+        let card_id = card.id.clone();
+        tokio::spawn(async move {
+            let error = match http_client.post(&message_url).json(&body).send().await {
+                Ok(response) if response.status().is_success() => return,
+                Ok(response) => format!("OpenCode returned {}", response.status()),
+                Err(e) => e.to_string(),
+            };
+            tracing::warn!(card_id, error, "Failed to send conflict resolution prompt");
+            if let Err(e) = sqlx::query("UPDATE cards SET ai_status = 'failed', updated_at = ? WHERE id = ?")
+                .bind(Utc::now().to_rfc3339())
+                .bind(&card_id)
+                .execute(&db)
+                .await
+            {
+                tracing::warn!(card_id, error = %e, "Failed to mark card failed");
+            }
+            CardRunService::finish_or_warn(&db, &card_id, "failed").await;
+        });
+
+        Ok(result)
+    }
+}
+
+/// The conflicted files with each side's content, for the prompt.
+fn format_conflicts(detail: &ConflictDetail) -> String {
+    let mut text = String::new();
+    for file in &detail.files {
+        let mut section = format!("### {} ({})\n", file.path, file.conflict_type);
+        if file.is_binary {
+            section.push_str("Binary file: pick one side with `git checkout --ours` or `--theirs`.\n");
+        } else {
+            for (label, content) in [
+                ("ours", &file.ours_content),
+                ("theirs", &file.theirs_content),
+                ("base", &file.base_content),
+            ] {
+                match content {
+                    Some(content) => {
+                        section.push_str(&format!("{}:\n```\n{}\n```\n", label, content.trim_end()))
+                    }
+                    None => section.push_str(&format!("{}: (deleted)\n", label)),
+                }
+            }
+        }
+        section.push('\n');
+        if text.len() + section.len() > MAX_PROMPT_CONFLICT_CHARS {
+            text.push_str("(conflicts truncated; read the remaining files in the worktree)\n");
+            break;
+        }
+        text.push_str(&section);
+    }
+    text
+}
+
+fn broadcast(sse_tx: &EventBus, event: &WsEvent) {
+    if let Ok(payload) = serde_json::to_string(event) {
+        let _ = sse_tx.send(payload);
+    }
+}
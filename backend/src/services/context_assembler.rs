@@ -0,0 +1,383 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use crate::domain::{Card, KanbanError};
+
+pub const DEFAULT_CONTEXT_BUDGET_CHARS: i64 = 48_000;
+
+/// Files larger than this are never read in full; only the head is considered.
+const MAX_READ_BYTES: u64 = 1024 * 1024;
+
+const TEXT_MIME_TYPES: &[&str] = &[
+    "application/json",
+    "application/xml",
+    "application/yaml",
+    "application/x-yaml",
+    "application/toml",
+    "application/javascript",
+    "application/typescript",
+    "application/sql",
+    "application/x-sh",
+];
+
+const TEXT_EXTENSIONS: &[&str] = &[
+    "md", "markdown", "txt", "rst", "adoc", "json", "yaml", "yml", "toml", "xml", "csv", "tsv",
+    "html", "css", "js", "jsx", "ts", "tsx", "rs", "py", "go", "java", "kt", "rb", "sh", "sql",
+    "ini", "cfg", "conf", "env", "log", "proto", "graphql",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextSourceKind {
+    LinkedDocument,
+    BoardDocument,
+    Attachment,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ContextDocument {
+    pub kind: ContextSourceKind,
+    pub label: String,
+    pub path: Option<String>,
+    #[serde(skip_serializing)]
+    pub content: Option<String>,
+    pub original_chars: usize,
+    pub included_chars: usize,
+    pub truncated: bool,
+    /// Why the document was not inlined (URL, missing, binary, over budget).
+    pub skipped: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ContextBundle {
+    pub budget_chars: usize,
+    pub used_chars: usize,
+    pub documents: Vec<ContextDocument>,
+}
+
+impl ContextBundle {
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    pub fn to_markdown(&self) -> String {
+        if self.documents.is_empty() {
+            return "None".to_string();
+        }
+
+        let mut output = String::new();
+        output.push_str(&format!(
+            "_Context budget: {} of {} characters used._\n\n",
+            self.used_chars, self.budget_chars
+        ));
+
+        for doc in &self.documents {
+            let kind = match doc.kind {
+                ContextSourceKind::LinkedDocument => "Linked document",
+                ContextSourceKind::BoardDocument => "Board reference",
+                ContextSourceKind::Attachment => "Attachment",
+            };
+            output.push_str(&format!("### {}: {}\n", kind, doc.label));
+            if let Some(path) = &doc.path {
+                output.push_str(&format!("Path: `{}`\n", path));
+            }
+
+            match (&doc.content, &doc.skipped) {
+                (Some(content), _) => {
+                    let fence = if content.contains("```") { "````" } else { "```" };
+                    output.push_str(&format!("\n{}\n{}", fence, content));
+                    if !content.ends_with('\n') {
+                        output.push('\n');
+                    }
+                    output.push_str(fence);
+                    output.push('\n');
+                    if doc.truncated {
+                        output.push_str(&format!(
+                            "[... truncated: showing first {} of {} characters ...]\n",
+                            doc.included_chars, doc.original_chars
+                        ));
+                    }
+                }
+                (None, Some(reason)) => {
+                    output.push_str(&format!("[not inlined: {}]\n", reason));
+                }
+                (None, None) => {}
+            }
+            output.push('\n');
+        }
+
+        output
+    }
+}
+
+/// Collects linked documents, board reference documents and text attachments
+/// for a card and fits them into the board's character budget.
+pub struct ContextAssembler;
+
+impl ContextAssembler {
+    pub async fn assemble(db: &SqlitePool, card: &Card) -> Result<ContextBundle, KanbanError> {
+        let board_id = card.board_id.clone().unwrap_or_default();
+        let board: Option<(String, String, i64)> = sqlx::query_as(
+            "SELECT codebase_path, document_links, context_budget_chars FROM board_settings WHERE board_id = ?",
+        )
+        .bind(&board_id)
+        .fetch_optional(db)
+        .await?;
+        let (codebase_path, document_links, budget) = board.unwrap_or_else(|| {
+            (String::new(), String::new(), DEFAULT_CONTEXT_BUDGET_CHARS)
+        });
+
+        let base_dirs: Vec<&str> = [card.working_directory.as_str(), codebase_path.as_str()]
+            .into_iter()
+            .filter(|dir| !dir.trim().is_empty())
+            .collect();
+
+        let mut documents = Vec::new();
+
+        for link in Self::parse_linked_documents(&card.linked_documents) {
+            documents.push(
+                Self::load_reference(ContextSourceKind::LinkedDocument, &link, &base_dirs).await,
+            );
+        }
+
+        for link in Self::parse_document_links(&document_links) {
+            documents.push(
+                Self::load_reference(ContextSourceKind::BoardDocument, &link, &base_dirs).await,
+            );
+        }
+
+        let attachments: Vec<(String, String, String)> = sqlx::query_as(
+            "SELECT original_filename, file_path, mime_type FROM card_files WHERE card_id = ? ORDER BY uploaded_at ASC",
+        )
+        .bind(&card.id)
+        .fetch_all(db)
+        .await?;
+
+        for (name, path, mime) in attachments {
+            let mut doc = if Self::is_text_attachment(&name, &mime) {
+                Self::load_file(ContextSourceKind::Attachment, &name, Path::new(&path)).await
+            } else {
+                Self::skipped(
+                    ContextSourceKind::Attachment,
+                    &name,
+                    Some(path.clone()),
+                    format!("binary attachment ({})", mime),
+                )
+            };
+            doc.path = Some(path);
+            documents.push(doc);
+        }
+
+        Ok(Self::apply_budget(documents, budget.max(0) as usize))
+    }
+
+    /// Writes the bundle next to `plan_path` as `<plan>.context.md` and
+    /// returns the written path.
+    pub fn write_bundle(plan_path: &str, bundle: &ContextBundle) -> Result<String, String> {
+        let plan = Path::new(plan_path);
+        let stem = plan
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "plan".to_string());
+        let bundle_path = plan.with_file_name(format!("{}.context.md", stem));
+
+        let mut content = String::from("# Context Bundle\n\n");
+        content.push_str(&bundle.to_markdown());
+
+        std::fs::write(&bundle_path, content).map_err(|e| {
+            format!(
+                "Failed to write context bundle '{}': {}",
+                bundle_path.display(),
+                e
+            )
+        })?;
+
+        Ok(bundle_path.to_string_lossy().to_string())
+    }
+
+    /// Gives every inlinable document an equal share of the budget, handing
+    /// whatever small documents leave unused to the larger ones.
+    pub fn apply_budget(mut documents: Vec<ContextDocument>, budget: usize) -> ContextBundle {
+        let mut order: Vec<usize> = documents
+            .iter()
+            .enumerate()
+            .filter(|(_, doc)| doc.content.is_some())
+            .map(|(index, _)| index)
+            .collect();
+        order.sort_by_key(|&index| documents[index].original_chars);
+
+        let mut remaining = budget;
+        let mut pending = order.len();
+        let mut used = 0;
+
+        for index in order {
+            let share = remaining / pending.max(1);
+            pending -= 1;
+
+            let doc = &mut documents[index];
+            if share == 0 {
+                doc.content = None;
+                doc.included_chars = 0;
+                doc.skipped = Some("context budget exhausted".to_string());
+                continue;
+            }
+
+            if doc.original_chars > share {
+                let content = doc.content.take().unwrap_or_default();
+                doc.content = Some(content.chars().take(share).collect());
+                doc.included_chars = share;
+                doc.truncated = true;
+            } else {
+                doc.included_chars = doc.original_chars;
+            }
+
+            remaining -= doc.included_chars;
+            used += doc.included_chars;
+        }
+
+        ContextBundle {
+            budget_chars: budget,
+            used_chars: used,
+            documents,
+        }
+    }
+
+    fn parse_linked_documents(raw: &str) -> Vec<String> {
+        serde_json::from_str::<Vec<String>>(raw)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|doc| doc.trim().to_string())
+            .filter(|doc| !doc.is_empty())
+            .collect()
+    }
+
+    /// Board reference documents are one path or URL per line; older rows
+    /// store a JSON array instead.
+    fn parse_document_links(raw: &str) -> Vec<String> {
+        if let Ok(links) = serde_json::from_str::<Vec<String>>(raw) {
+            return links
+                .into_iter()
+                .map(|link| link.trim().to_string())
+                .filter(|link| !link.is_empty())
+                .collect();
+        }
+
+        raw.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    async fn load_reference(
+        kind: ContextSourceKind,
+        reference: &str,
+        base_dirs: &[&str],
+    ) -> ContextDocument {
+        if reference.starts_with("http://") || reference.starts_with("https://") {
+            return Self::skipped(kind, reference, None, "URL, not fetched".to_string());
+        }
+
+        match Self::resolve_path(reference, base_dirs).await {
+            Ok(path) => Self::load_file(kind, reference, &path).await,
+            Err(reason) => Self::skipped(kind, reference, None, reason.to_string()),
+        }
+    }
+
+    /// Finds `reference` under one of `base_dirs`. The real path, with `..`
+    /// and symlinks resolved, must stay inside that directory, so a card
+    /// cannot pull arbitrary server files into its prompt.
+    async fn resolve_path(reference: &str, base_dirs: &[&str]) -> Result<PathBuf, &'static str> {
+        let mut outside = false;
+        for dir in base_dirs {
+            let Ok(root) = tokio::fs::canonicalize(dir).await else {
+                continue;
+            };
+            let Ok(candidate) = tokio::fs::canonicalize(root.join(reference)).await else {
+                continue;
+            };
+            if !candidate.starts_with(&root) {
+                outside = true;
+                continue;
+            }
+            if tokio::fs::metadata(&candidate).await.is_ok_and(|meta| meta.is_file()) {
+                return Ok(candidate);
+            }
+        }
+
+        Err(if outside {
+            "outside the card's working directory and board codebase"
+        } else {
+            "file not found"
+        })
+    }
+
+    async fn load_file(kind: ContextSourceKind, label: &str, path: &Path) -> ContextDocument {
+        let display = path.to_string_lossy().to_string();
+
+        let bytes = match Self::read_head(path).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                return Self::skipped(kind, label, Some(display), format!("unreadable: {}", e))
+            }
+        };
+
+        if bytes.contains(&0) {
+            return Self::skipped(kind, label, Some(display), "binary file".to_string());
+        }
+
+        let content = String::from_utf8_lossy(&bytes).to_string();
+        let original_chars = content.chars().count();
+
+        ContextDocument {
+            kind,
+            label: label.to_string(),
+            path: Some(display),
+            content: Some(content),
+            original_chars,
+            included_chars: 0,
+            truncated: false,
+            skipped: None,
+        }
+    }
+
+    async fn read_head(path: &Path) -> std::io::Result<Vec<u8>> {
+        use tokio::io::AsyncReadExt;
+
+        let file = tokio::fs::File::open(path).await?;
+        let mut bytes = Vec::new();
+        file.take(MAX_READ_BYTES).read_to_end(&mut bytes).await?;
+        Ok(bytes)
+    }
+
+    fn is_text_attachment(name: &str, mime: &str) -> bool {
+        if mime.starts_with("text/") || TEXT_MIME_TYPES.contains(&mime) {
+            return true;
+        }
+
+        Path::new(name)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .is_some_and(|ext| TEXT_EXTENSIONS.contains(&ext.as_str()))
+    }
+
+    fn skipped(
+        kind: ContextSourceKind,
+        label: &str,
+        path: Option<String>,
+        reason: String,
+    ) -> ContextDocument {
+        ContextDocument {
+            kind,
+            label: label.to_string(),
+            path,
+            content: None,
+            original_chars: 0,
+            included_chars: 0,
+            truncated: false,
+            skipped: Some(reason),
+        }
+    }
+}
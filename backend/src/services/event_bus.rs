@@ -0,0 +1,574 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::SqlitePool;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc, watch};
+
+use crate::api::handlers::sse::WsEvent;
+use crate::domain::KanbanError;
+
+use super::BoardAccess;
+
+pub const DEFAULT_EVENT_RETENTION: i64 = 10_000;
+
+const CHANNEL_CAPACITY: usize = 1024;
+const MAX_BATCH: usize = 256;
+const REPLAY_PAGE: i64 = 500;
+/// Retention is enforced after roughly this many new events.
+const PRUNE_EVERY: usize = 500;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct StoredEvent {
+    pub seq: i64,
+    pub event_type: String,
+    pub payload: String,
+    pub created_at: String,
+    pub board_id: Option<String>,
+    pub card_id: Option<String>,
+    pub user_id: Option<String>,
+}
+
+impl StoredEvent {
+    /// The event as it is delivered to clients, with its `seq`.
+    pub fn message(&self) -> String {
+        with_seq(&self.payload, self.seq)
+    }
+
+    fn into_published(self) -> PublishedEvent {
+        PublishedEvent {
+            seq: Some(self.seq),
+            message: with_seq(&self.payload, self.seq),
+            event_type: self.event_type,
+            scope: EventScope {
+                board_id: self.board_id,
+                card_id: self.card_id,
+                user_id: self.user_id,
+            },
+        }
+    }
+}
+
+/// What an event is about, used for filtering and access checks.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventScope {
+    pub board_id: Option<String>,
+    pub card_id: Option<String>,
+    /// Set for events addressed to a single user (notifications).
+    pub user_id: Option<String>,
+}
+
+/// An event as broadcast to subscribers.
+#[derive(Debug, Clone)]
+pub struct PublishedEvent {
+    pub seq: Option<i64>,
+    pub event_type: String,
+    pub scope: EventScope,
+    /// The serialized event, with `seq` when it was persisted.
+    pub message: String,
+}
+
+/// A client's subscription. Every set field must match; an event that lacks
+/// the constrained attribute (e.g. no card for a `card_ids` filter) does not.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventFilter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub board_ids: Option<HashSet<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub card_ids: Option<HashSet<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub types: Option<HashSet<String>>,
+}
+
+impl EventFilter {
+    /// Builds a filter from comma-separated query parameters.
+    pub fn from_query(
+        board_ids: Option<&str>,
+        card_ids: Option<&str>,
+        types: Option<&str>,
+    ) -> Self {
+        fn split(raw: Option<&str>) -> Option<HashSet<String>> {
+            let values: HashSet<String> = raw?
+                .split(',')
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+                .collect();
+            (!values.is_empty()).then_some(values)
+        }
+
+        Self {
+            board_ids: split(board_ids),
+            card_ids: split(card_ids),
+            types: split(types),
+        }
+    }
+
+    pub fn matches(&self, event: &PublishedEvent) -> bool {
+        fn allows(set: &Option<HashSet<String>>, value: Option<&String>) -> bool {
+            match set {
+                None => true,
+                Some(set) => value.is_some_and(|v| set.contains(v)),
+            }
+        }
+
+        allows(&self.board_ids, event.scope.board_id.as_ref())
+            && allows(&self.card_ids, event.scope.card_id.as_ref())
+            && allows(&self.types, Some(&event.event_type))
+    }
+}
+
+/// Event types that belong to no board. Without a card they are shown to
+/// everyone, restricted boards or not.
+const GLOBAL_EVENT_TYPES: &[&str] = &["notificationCreated", "resyncRequired"];
+
+/// What a connected user may see: nothing from restricted boards they are
+/// not a member of, and only their own user-addressed events.
+#[derive(Debug, Clone)]
+pub struct EventAccess {
+    pub user_id: String,
+    pub denied_boards: HashSet<String>,
+}
+
+impl EventAccess {
+    pub async fn for_user(db: &SqlitePool, user_id: &str) -> Result<Self, KanbanError> {
+        Ok(Self {
+            user_id: user_id.to_string(),
+            denied_boards: BoardAccess::denied_boards(db, user_id).await?,
+        })
+    }
+
+    pub fn permits(&self, event: &PublishedEvent) -> bool {
+        let board_visible = match &event.scope.board_id {
+            Some(board_id) => !self.denied_boards.contains(board_id),
+            // An event whose board is unknown may come from a board the user
+            // cannot see; only users who can see every board get it, unless
+            // it is addressed to them or is global by type.
+            None => {
+                self.denied_boards.is_empty()
+                    || event.scope.user_id.as_deref() == Some(self.user_id.as_str())
+                    || (event.scope.card_id.is_none()
+                        && GLOBAL_EVENT_TYPES.contains(&event.event_type.as_str()))
+            }
+        };
+        if !board_visible {
+            return false;
+        }
+        match &event.scope.user_id {
+            Some(user_id) => *user_id == self.user_id,
+            None => true,
+        }
+    }
+}
+
+/// Fan-out for `WsEvent`s. Every event is written to the `events` table and
+/// gets a monotonically increasing `seq` before it is broadcast, so a client
+/// that lagged or reconnected can replay exactly what it missed.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    tx: broadcast::Sender<Arc<PublishedEvent>>,
+    writer: mpsc::UnboundedSender<String>,
+    db: Option<SqlitePool>,
+}
+
+impl EventBus {
+    /// Must be called from within a Tokio runtime; spawns the writer task.
+    pub fn new(db: Option<SqlitePool>, retention: i64) -> Self {
+        let (tx, _) = broadcast::channel(CHANNEL_CAPACITY);
+        let (writer, rx) = mpsc::unbounded_channel();
+
+        tokio::spawn(run_writer(db.clone(), tx.clone(), rx, retention));
+
+        Self { tx, writer, db }
+    }
+
+    /// Queues a serialized `WsEvent` for persistence and broadcast.
+    pub fn send(&self, payload: String) -> bool {
+        self.writer.send(payload).is_ok()
+    }
+
+    /// Live events only; see [`EventBus::stream_from`] for replay.
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<PublishedEvent>> {
+        self.tx.subscribe()
+    }
+
+    pub async fn latest_seq(&self) -> Result<i64, KanbanError> {
+        let Some(db) = &self.db else {
+            return Ok(0);
+        };
+        let (latest,): (Option<i64>,) = sqlx::query_as("SELECT MAX(seq) FROM events")
+            .fetch_one(db)
+            .await?;
+        Ok(latest.unwrap_or(0))
+    }
+
+    pub async fn replay(&self, after: i64, limit: i64) -> Result<Vec<StoredEvent>, KanbanError> {
+        let Some(db) = &self.db else {
+            return Ok(Vec::new());
+        };
+        let events = sqlx::query_as::<_, StoredEvent>(
+            "SELECT * FROM events WHERE seq > ? ORDER BY seq ASC LIMIT ?",
+        )
+        .bind(after)
+        .bind(limit)
+        .fetch_all(db)
+        .await?;
+        Ok(events)
+    }
+
+    /// Streams every event after `since` (or from now when `None`), then live
+    /// events, limited to what `access` permits and `filter` currently
+    /// selects. Lagging is recovered from the table instead of skipping. If
+    /// `since` is older than the retained history, a `resyncRequired` event
+    /// tells the client to reload its state.
+    pub fn stream_from(
+        &self,
+        since: Option<i64>,
+        access: EventAccess,
+        filter: watch::Receiver<EventFilter>,
+    ) -> mpsc::Receiver<String> {
+        let (out, rx) = mpsc::channel(64);
+        let bus = self.clone();
+        tokio::spawn(async move {
+            let mut subscriber = Subscriber {
+                bus: &bus,
+                access,
+                filter,
+                out: &out,
+            };
+            tokio::select! {
+                _ = subscriber.pump(since) => {}
+                _ = out.closed() => {}
+            }
+        });
+        rx
+    }
+}
+
+struct Subscriber<'a> {
+    bus: &'a EventBus,
+    access: EventAccess,
+    filter: watch::Receiver<EventFilter>,
+    out: &'a mpsc::Sender<String>,
+}
+
+impl Subscriber<'_> {
+    /// Sends the event if the subscriber may and wants to see it. Returns
+    /// false once the client is gone.
+    async fn deliver(&mut self, event: &PublishedEvent) -> bool {
+        if event.event_type == "boardMembersChanged" {
+            if let Some(db) = &self.bus.db {
+                match EventAccess::for_user(db, &self.access.user_id).await {
+                    Ok(access) => self.access = access,
+                    Err(e) => tracing::warn!(error = %e, "Failed to refresh board access"),
+                }
+            }
+        }
+
+        if !self.access.permits(event) || !self.filter.borrow().matches(event) {
+            return true;
+        }
+        self.out.send(event.message.clone()).await.is_ok()
+    }
+
+    async fn pump(&mut self, since: Option<i64>) {
+        if self.bus.db.is_none() {
+            let mut rx = self.bus.subscribe();
+            loop {
+                match rx.recv().await {
+                    Ok(event) => {
+                        if !self.deliver(&event).await {
+                            return;
+                        }
+                    }
+                    Err(RecvError::Lagged(n)) => {
+                        tracing::debug!(skipped = n, "Event subscriber lagged without an event log");
+                    }
+                    Err(RecvError::Closed) => return,
+                }
+            }
+        }
+
+        // Fix the starting point before subscribing; anything newer is then
+        // either replayed from the table or received live, never both.
+        let mut last = match since {
+            Some(since) => since,
+            None => self.bus.latest_seq().await.unwrap_or(0),
+        };
+        let mut rx = self.bus.subscribe();
+
+        if !self.catch_up(&mut last).await {
+            return;
+        }
+
+        loop {
+            match rx.recv().await {
+                Ok(event) => {
+                    if event.seq.is_some_and(|seq| seq <= last) {
+                        continue;
+                    }
+                    if !self.deliver(&event).await {
+                        return;
+                    }
+                    if let Some(seq) = event.seq {
+                        last = seq;
+                    }
+                }
+                Err(RecvError::Lagged(n)) => {
+                    tracing::debug!(skipped = n, last_seq = last, "Event subscriber lagged, replaying");
+                    if !self.catch_up(&mut last).await {
+                        return;
+                    }
+                }
+                Err(RecvError::Closed) => return,
+            }
+        }
+    }
+
+    /// Sends stored events after `last`. Returns false once the client is gone.
+    async fn catch_up(&mut self, last: &mut i64) -> bool {
+        let Some(db) = self.bus.db.clone() else {
+            return true;
+        };
+
+        let bounds: Result<(Option<i64>, Option<i64>), sqlx::Error> =
+            sqlx::query_as("SELECT MIN(seq), MAX(seq) FROM events")
+                .fetch_one(&db)
+                .await;
+        let (oldest, latest) = match bounds {
+            Ok((oldest, latest)) => (oldest.unwrap_or(0), latest.unwrap_or(0)),
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to read event log bounds");
+                return true;
+            }
+        };
+
+        let missing_history = oldest > 0 && *last + 1 < oldest;
+        if missing_history || *last > latest {
+            let event = WsEvent::ResyncRequired { latest_seq: latest };
+            let payload = serde_json::to_string(&event).unwrap_or_default();
+            if self.out.send(with_seq(&payload, latest)).await.is_err() {
+                return false;
+            }
+            *last = latest;
+        }
+
+        loop {
+            let events = match self.bus.replay(*last, REPLAY_PAGE).await {
+                Ok(events) => events,
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to replay events");
+                    return true;
+                }
+            };
+            let page_len = events.len() as i64;
+
+            for event in events {
+                let seq = event.seq;
+                if !self.deliver(&event.into_published()).await {
+                    return false;
+                }
+                *last = seq;
+            }
+
+            if page_len < REPLAY_PAGE {
+                return true;
+            }
+        }
+    }
+}
+
+/// Extracts the `seq` that [`with_seq`] put at the front of a message.
+pub fn seq_of(message: &str) -> Option<i64> {
+    let rest = message.strip_prefix("{\"seq\":")?;
+    let end = rest.find(|c: char| !c.is_ascii_digit())?;
+    rest[..end].parse().ok()
+}
+
+fn with_seq(payload: &str, seq: i64) -> String {
+    match payload.strip_prefix('{') {
+        Some(rest) if rest.trim_start().starts_with('}') => format!("{{\"seq\":{}}}", seq),
+        Some(rest) => format!("{{\"seq\":{},{}", seq, rest),
+        None => payload.to_string(),
+    }
+}
+
+async fn run_writer(
+    db: Option<SqlitePool>,
+    tx: broadcast::Sender<Arc<PublishedEvent>>,
+    mut rx: mpsc::UnboundedReceiver<String>,
+    retention: i64,
+) {
+    if let Some(db) = &db {
+        prune(db, retention).await;
+    }
+
+    let mut card_boards = CardBoards::default();
+    let mut since_prune = 0;
+    while let Some(first) = rx.recv().await {
+        let mut batch = vec![first];
+        while batch.len() < MAX_BATCH {
+            match rx.try_recv() {
+                Ok(payload) => batch.push(payload),
+                Err(_) => break,
+            }
+        }
+
+        let mut events = Vec::with_capacity(batch.len());
+        for payload in batch {
+            let (event_type, scope) = describe(&payload, db.as_ref(), &mut card_boards).await;
+            events.push(PublishedEvent {
+                seq: None,
+                event_type,
+                scope,
+                message: payload,
+            });
+        }
+
+        if let Some(db) = &db {
+            match persist(db, &events).await {
+                Ok(seqs) => {
+                    for (event, seq) in events.iter_mut().zip(seqs) {
+                        event.message = with_seq(&event.message, seq);
+                        event.seq = Some(seq);
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, count = events.len(), "Failed to persist events; broadcasting without seq");
+                }
+            }
+        }
+
+        since_prune += events.len();
+        for event in events {
+            let _ = tx.send(Arc::new(event));
+        }
+
+        if since_prune >= PRUNE_EVERY {
+            if let Some(db) = &db {
+                prune(db, retention).await;
+            }
+            since_prune = 0;
+        }
+    }
+}
+
+/// Remembers which board each card is on so card-only events (subtasks,
+/// comments, logs) can be scoped without a query per event.
+#[derive(Default)]
+struct CardBoards {
+    boards: HashMap<String, String>,
+}
+
+impl CardBoards {
+    const MAX_ENTRIES: usize = 10_000;
+
+    fn remember(&mut self, card_id: &str, board_id: &str) {
+        if self.boards.len() >= Self::MAX_ENTRIES {
+            self.boards.clear();
+        }
+        self.boards.insert(card_id.to_string(), board_id.to_string());
+    }
+
+    async fn lookup(&mut self, db: Option<&SqlitePool>, card_id: &str) -> Option<String> {
+        if let Some(board_id) = self.boards.get(card_id) {
+            return Some(board_id.clone());
+        }
+        let board_id: Option<String> =
+            sqlx::query_scalar("SELECT board_id FROM cards WHERE id = ?")
+                .bind(card_id)
+                .fetch_optional(db?)
+                .await
+                .ok()
+                .flatten();
+        if let Some(board_id) = &board_id {
+            self.remember(card_id, board_id);
+        }
+        board_id
+    }
+}
+
+async fn describe(
+    payload: &str,
+    db: Option<&SqlitePool>,
+    card_boards: &mut CardBoards,
+) -> (String, EventScope) {
+    let Ok(value) = serde_json::from_str::<Value>(payload) else {
+        return ("unknown".to_string(), EventScope::default());
+    };
+    let text = |v: &Value, key: &str| v.get(key).and_then(Value::as_str).map(str::to_string);
+
+    let event_type = text(&value, "type").unwrap_or_else(|| "unknown".to_string());
+    let card = value.get("card");
+    let board = value.get("board");
+    let notification = value.get("notification");
+
+    let card_id = text(&value, "card_id")
+        .or_else(|| card.and_then(|c| text(c, "id")))
+        .or_else(|| notification.and_then(|n| text(n, "card_id")));
+    let mut board_id = text(&value, "board_id")
+        .or_else(|| card.and_then(|c| text(c, "board_id")))
+        .or_else(|| board.and_then(|b| text(b, "id")))
+        .or_else(|| notification.and_then(|n| text(n, "board_id")));
+    let user_id = notification.and_then(|n| text(n, "user_id"));
+
+    match (&card_id, &board_id) {
+        (Some(card_id), Some(board_id)) => card_boards.remember(card_id, board_id),
+        (Some(card_id), None) => board_id = card_boards.lookup(db, card_id).await,
+        _ => {}
+    }
+
+    (
+        event_type,
+        EventScope {
+            board_id,
+            card_id,
+            user_id,
+        },
+    )
+}
+
+async fn persist(db: &SqlitePool, events: &[PublishedEvent]) -> Result<Vec<i64>, sqlx::Error> {
+    let created_at = Utc::now().to_rfc3339();
+    let mut tx = db.begin().await?;
+    let mut seqs = Vec::with_capacity(events.len());
+
+    for event in events {
+        let result = sqlx::query(
+            "INSERT INTO events (event_type, payload, created_at, board_id, card_id, user_id) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&event.event_type)
+        .bind(&event.message)
+        .bind(&created_at)
+        .bind(&event.scope.board_id)
+        .bind(&event.scope.card_id)
+        .bind(&event.scope.user_id)
+        .execute(&mut *tx)
+        .await?;
+        seqs.push(result.last_insert_rowid());
+    }
+
+    tx.commit().await?;
+    Ok(seqs)
+}
+
+/// Keeps the newest `retention` events.
+async fn prune(db: &SqlitePool, retention: i64) {
+    let result = sqlx::query(
+        "DELETE FROM events WHERE seq <= (SELECT COALESCE(MAX(seq), 0) FROM events) - ?",
+    )
+    .bind(retention.max(1))
+    .execute(db)
+    .await;
+
+    match result {
+        Ok(done) if done.rows_affected() > 0 => {
+            tracing::debug!(pruned = done.rows_affected(), "Pruned event log");
+        }
+        Ok(_) => {}
+        Err(e) => tracing::warn!(error = %e, "Failed to prune event log"),
+    }
+}
@@ -0,0 +1,367 @@
+//! Runs git and parses its machine-readable (`-z`, `--porcelain=v2`) output
+//! into typed entries, so nothing downstream splits human-readable text.
+
+use std::cell::RefCell;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::domain::KanbanError;
+
+/// How often a cancellable git process is checked for cancellation.
+const CANCEL_POLL: Duration = Duration::from_millis(20);
+
+/// Stops the git commands of one operation: the running process is killed
+/// and any later command fails straight away.
+#[derive(Debug, Clone, Default)]
+pub struct GitCancel(Arc<AtomicBool>);
+
+impl GitCancel {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Runs `work` with every git command on this thread tied to `self`.
+    /// The previous token is restored even if `work` panics, so a pooled
+    /// thread never keeps a finished operation's token.
+    pub fn scope<T>(&self, work: impl FnOnce() -> T) -> T {
+        let previous = CURRENT_CANCEL.with(|current| current.replace(Some(self.clone())));
+        let _restore = RestoreCancel(previous);
+        work()
+    }
+}
+
+/// Puts the thread's previous cancel token back when dropped.
+struct RestoreCancel(Option<GitCancel>);
+
+impl Drop for RestoreCancel {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CURRENT_CANCEL.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+thread_local! {
+    static CURRENT_CANCEL: RefCell<Option<GitCancel>> = const { RefCell::new(None) };
+}
+
+/// Runs `git args` in `dir` and returns its stdout. `index` replaces the
+/// repository's index via `GIT_INDEX_FILE`.
+pub fn run(dir: &str, args: &[&str], index: Option<&Path>) -> Result<Vec<u8>, KanbanError> {
+    let mut command = Command::new("git");
+    command.args(args).current_dir(dir).stdin(Stdio::null());
+    if let Some(index) = index {
+        command.env("GIT_INDEX_FILE", index);
+    }
+
+    let cancel = CURRENT_CANCEL.with(|current| current.borrow().clone());
+    let (success, stdout, stderr) = match cancel {
+        None => {
+            let output = command
+                .output()
+                .map_err(|e| KanbanError::Internal(format!("Failed to run git: {}", e)))?;
+            (output.status.success(), output.stdout, output.stderr)
+        }
+        Some(cancel) => run_cancellable(&mut command, &cancel)?,
+    };
+
+    if !success {
+        let stderr = String::from_utf8_lossy(&stderr);
+        return Err(KanbanError::Internal(format!(
+            "Git command failed: {}",
+            stderr.trim()
+        )));
+    }
+    Ok(stdout)
+}
+
+fn run_cancellable(
+    command: &mut Command,
+    cancel: &GitCancel,
+) -> Result<(bool, Vec<u8>, Vec<u8>), KanbanError> {
+    let cancelled = || KanbanError::Internal("Git command cancelled".into());
+    if cancel.is_cancelled() {
+        return Err(cancelled());
+    }
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| KanbanError::Internal(format!("Failed to run git: {}", e)))?;
+
+    // The pipes are drained on their own threads so a large diff cannot
+    // fill them and stall git. On cancel they are left behind: a process git
+    // started may still hold the pipes open after git itself is killed.
+    let stdout = child.stdout.take().map(|pipe| std::thread::spawn(move || drain(pipe)));
+    let stderr = child.stderr.take().map(|pipe| std::thread::spawn(move || drain(pipe)));
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if cancel.is_cancelled() => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(cancelled());
+            }
+            Ok(None) => std::thread::sleep(CANCEL_POLL),
+            Err(e) => return Err(KanbanError::Internal(format!("Failed to wait for git: {}", e))),
+        }
+    };
+    let collect = |reader: Option<std::thread::JoinHandle<Vec<u8>>>| {
+        reader.and_then(|reader| reader.join().ok()).unwrap_or_default()
+    };
+    Ok((status.success(), collect(stdout), collect(stderr)))
+}
+
+fn drain(mut pipe: impl Read) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    let _ = pipe.read_to_end(&mut buffer);
+    buffer
+}
+
+/// NUL-separated fields of `-z` output, without the empty tail.
+fn fields(output: &[u8]) -> impl Iterator<Item = String> + '_ {
+    output
+        .split(|&byte| byte == 0)
+        .filter(|field| !field.is_empty())
+        .map(|field| String::from_utf8_lossy(field).into_owned())
+}
+
+/// One path from `git status --porcelain=v2 -z`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatusEntry {
+    /// Tracked and changed; `staged` and `unstaged` are the `XY` codes
+    /// (`.` for unchanged).
+    Changed { staged: char, unstaged: char, path: String },
+    /// Renamed or copied; `from` is the original path.
+    Renamed { staged: char, unstaged: char, path: String, from: String },
+    Unmerged(UnmergedEntry),
+    Untracked { path: String },
+    Ignored { path: String },
+}
+
+impl StatusEntry {
+    pub fn path(&self) -> &str {
+        match self {
+            Self::Changed { path, .. }
+            | Self::Renamed { path, .. }
+            | Self::Untracked { path }
+            | Self::Ignored { path } => path,
+            Self::Unmerged(entry) => &entry.path,
+        }
+    }
+}
+
+/// A conflicted path and the blob of each index stage it has.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnmergedEntry {
+    pub path: String,
+    /// Stage 1, the common ancestor.
+    pub base: Option<String>,
+    /// Stage 2, the branch being merged into.
+    pub ours: Option<String>,
+    /// Stage 3, the branch being merged.
+    pub theirs: Option<String>,
+}
+
+impl UnmergedEntry {
+    pub fn conflict_type(&self) -> &'static str {
+        match (self.base.is_some(), self.ours.is_some(), self.theirs.is_some()) {
+            (false, true, true) => "added-by-both",
+            (true, false, true) => "deleted-by-us",
+            (true, true, false) => "deleted-by-them",
+            _ => "both-modified",
+        }
+    }
+}
+
+pub fn parse_status(output: &[u8]) -> Vec<StatusEntry> {
+    let mut entries = Vec::new();
+    let mut records = fields(output);
+    while let Some(record) = records.next() {
+        let (kind, rest) = record.split_at(record.len().min(2));
+        let xy = |rest: &str| {
+            let mut codes = rest.chars();
+            (codes.next().unwrap_or('.'), codes.next().unwrap_or('.'))
+        };
+        match kind {
+            "1 " => {
+                let parts: Vec<&str> = rest.splitn(8, ' ').collect();
+                if let [codes, .., path] = parts.as_slice() {
+                    let (staged, unstaged) = xy(codes);
+                    entries.push(StatusEntry::Changed { staged, unstaged, path: path.to_string() });
+                }
+            }
+            "2 " => {
+                let parts: Vec<&str> = rest.splitn(9, ' ').collect();
+                let from = records.next().unwrap_or_default();
+                if let [codes, .., path] = parts.as_slice() {
+                    let (staged, unstaged) = xy(codes);
+                    entries.push(StatusEntry::Renamed { staged, unstaged, path: path.to_string(), from });
+                }
+            }
+            "u " => {
+                let parts: Vec<&str> = rest.splitn(10, ' ').collect();
+                if let [_, _, m1, m2, m3, _, h1, h2, h3, path] = parts.as_slice() {
+                    let stage = |mode: &str, hash: &str| (mode != "000000").then(|| hash.to_string());
+                    entries.push(StatusEntry::Unmerged(UnmergedEntry {
+                        path: path.to_string(),
+                        base: stage(m1, h1),
+                        ours: stage(m2, h2),
+                        theirs: stage(m3, h3),
+                    }));
+                }
+            }
+            "? " => entries.push(StatusEntry::Untracked { path: rest.to_string() }),
+            "! " => entries.push(StatusEntry::Ignored { path: rest.to_string() }),
+            _ => {}
+        }
+    }
+    entries
+}
+
+/// How a path changed in a diff.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffStatus {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+    Copied,
+    TypeChanged,
+    Unmerged,
+}
+
+impl DiffStatus {
+    fn from_code(code: &str) -> Self {
+        match code.chars().next() {
+            Some('A') => Self::Added,
+            Some('D') => Self::Deleted,
+            Some('R') => Self::Renamed,
+            Some('C') => Self::Copied,
+            Some('T') => Self::TypeChanged,
+            Some('U') => Self::Unmerged,
+            _ => Self::Modified,
+        }
+    }
+
+    /// The `FileDiff::status` label; copies and type changes show as
+    /// modified.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Added => "added",
+            Self::Deleted => "deleted",
+            Self::Renamed => "renamed",
+            _ => "modified",
+        }
+    }
+
+    fn has_source(self) -> bool {
+        matches!(self, Self::Renamed | Self::Copied)
+    }
+}
+
+/// One path from `git diff --name-status -z`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameStatusEntry {
+    pub status: DiffStatus,
+    pub path: String,
+    /// The source path of a rename or copy.
+    pub from: Option<String>,
+}
+
+pub fn parse_name_status(output: &[u8]) -> Vec<NameStatusEntry> {
+    let mut entries = Vec::new();
+    let mut records = fields(output);
+    while let Some(code) = records.next() {
+        let status = DiffStatus::from_code(&code);
+        let first = records.next().unwrap_or_default();
+        let (path, from) = if status.has_source() {
+            (records.next().unwrap_or_default(), Some(first))
+        } else {
+            (first, None)
+        };
+        entries.push(NameStatusEntry { status, path, from });
+    }
+    entries
+}
+
+/// One path from `git diff --numstat -z`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumstatEntry {
+    pub path: String,
+    pub from: Option<String>,
+    /// `None` for binary files.
+    pub additions: Option<i64>,
+    pub deletions: Option<i64>,
+}
+
+impl NumstatEntry {
+    pub fn is_binary(&self) -> bool {
+        self.additions.is_none() && self.deletions.is_none()
+    }
+}
+
+pub fn parse_numstat(output: &[u8]) -> Vec<NumstatEntry> {
+    let mut entries = Vec::new();
+    let mut records = fields(output);
+    while let Some(record) = records.next() {
+        let mut parts = record.splitn(3, '\t');
+        let additions = parts.next().and_then(|n| n.parse().ok());
+        let deletions = parts.next().and_then(|n| n.parse().ok());
+        // Renames leave the path empty and follow with source and target.
+        let (path, from) = match parts.next().filter(|path| !path.is_empty()) {
+            Some(path) => (path.to_string(), None),
+            None => {
+                let from = records.next().unwrap_or_default();
+                (records.next().unwrap_or_default(), Some(from))
+            }
+        };
+        entries.push(NumstatEntry { path, from, additions, deletions });
+    }
+    entries
+}
+
+/// The lines one `@@ -a,b +c,d @@` header of a unified diff covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hunk {
+    pub old_start: i64,
+    pub old_lines: i64,
+    pub new_start: i64,
+    pub new_lines: i64,
+}
+
+impl Hunk {
+    /// Whether `line` falls in the hunk on the `old` or `new` side.
+    pub fn covers(&self, side: &str, line: i64) -> bool {
+        let (start, lines) = match side {
+            "old" => (self.old_start, self.old_lines),
+            _ => (self.new_start, self.new_lines),
+        };
+        line >= start && line < start + lines
+    }
+}
+
+pub fn parse_hunks(diff: &str) -> Vec<Hunk> {
+    // A range without a count covers one line.
+    fn range(spec: &str) -> Option<(i64, i64)> {
+        match spec.split_once(',') {
+            Some((start, lines)) => Some((start.parse().ok()?, lines.parse().ok()?)),
+            None => Some((spec.parse().ok()?, 1)),
+        }
+    }
+    diff.lines()
+        .filter_map(|line| {
+            let mut parts = line.strip_prefix("@@ ")?.split(' ');
+            let (old_start, old_lines) = range(parts.next()?.strip_prefix('-')?)?;
+            let (new_start, new_lines) = range(parts.next()?.strip_prefix('+')?)?;
+            Some(Hunk { old_start, old_lines, new_start, new_lines })
+        })
+        .collect()
+}
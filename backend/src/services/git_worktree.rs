@@ -1,12 +1,14 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 use serde::{Deserialize, Serialize};
 
 use crate::domain::KanbanError;
 
+use super::git::{self, GitCancel, StatusEntry, UnmergedEntry};
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FileDiff {
     pub path: String,
@@ -65,13 +67,79 @@ pub struct ResolveRequest {
     pub resolutions: Vec<FileResolution>,
 }
 
+/// A file to take from the card's diff in a partial merge.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FileSelection {
+    pub path: String,
+    /// 0-based indices of the `@@` hunks in the file's diff; the whole file
+    /// when omitted.
+    #[serde(default)]
+    pub hunks: Option<Vec<usize>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PartialMergeResult {
+    /// The squashed commit added to the base branch.
+    pub commit: String,
+    pub accepted_files: Vec<String>,
+    /// What is left on the card branch after the accepted changes.
+    pub remaining: DiffResult,
+}
+
+/// Git operations on the main checkout and card worktrees. Every method
+/// blocks on git; async callers go through [`Self::blocking`] or
+/// [`Self::cancellable`] so Tokio worker threads stay free.
 pub struct GitWorktreeService;
 
 impl GitWorktreeService {
+    /// Runs `work` on the blocking pool and waits for it. The work runs to
+    /// the end even if the caller is dropped, for operations that must not
+    /// stop halfway such as merges, rebases and worktree changes.
+    pub async fn blocking<T, F>(work: F) -> Result<T, KanbanError>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> Result<T, KanbanError> + Send + 'static,
+    {
+        tokio::task::spawn_blocking(work)
+            .await
+            .map_err(|e| KanbanError::Internal(format!("Git task failed: {}", e)))?
+    }
+
+    /// Like [`Self::blocking`] for read-only work: dropping the returned
+    /// future, e.g. when the client disconnects, kills the running git
+    /// process and fails the rest of the work.
+    pub async fn cancellable<T, F>(work: F) -> Result<T, KanbanError>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> Result<T, KanbanError> + Send + 'static,
+    {
+        struct CancelOnDrop(GitCancel);
+        impl Drop for CancelOnDrop {
+            fn drop(&mut self) {
+                self.0.cancel();
+            }
+        }
+
+        let cancel = GitCancel::default();
+        let _guard = CancelOnDrop(cancel.clone());
+        Self::blocking(move || cancel.scope(work)).await
+    }
+
     pub fn create_worktree(
         repo_path: &str,
         card_id: &str,
         card_title: &str,
+    ) -> Result<(String, String), KanbanError> {
+        Self::create_worktree_with_suffix(repo_path, card_id, card_title, "")
+    }
+
+    /// Like [`Self::create_worktree`], with `suffix` appended to both the
+    /// branch and the worktree directory so one card can have several.
+    pub fn create_worktree_with_suffix(
+        repo_path: &str,
+        card_id: &str,
+        card_title: &str,
+        suffix: &str,
     ) -> Result<(String, String), KanbanError> {
         let repo_root = Path::new(repo_path);
         if !repo_root.join(".git").exists() {
@@ -84,13 +152,13 @@ impl GitWorktreeService {
         let slug = Self::slugify_title(card_title);
         let id_prefix: String = card_id.chars().take(8).collect();
         let branch_name = if slug.is_empty() {
-            format!("ai/{}", id_prefix)
+            format!("ai/{}{}", id_prefix, suffix)
         } else {
-            format!("ai/{}-{}", id_prefix, slug)
+            format!("ai/{}-{}{}", id_prefix, slug, suffix)
         };
 
         let worktree_root = repo_root.join(".lightup-workspaces");
-        let worktree_path = worktree_root.join(card_id);
+        let worktree_path = worktree_root.join(format!("{}{}", card_id, suffix));
 
         Self::ensure_gitignore_entry(repo_path)?;
 
@@ -144,62 +212,94 @@ impl GitWorktreeService {
     pub fn get_diff(repo_path: &str, branch_name: &str) -> Result<DiffResult, KanbanError> {
         let default_branch = Self::detect_default_branch(repo_path);
         let range = format!("{}...{}", default_branch, branch_name);
+        Self::collect_diff(&["diff", range.as_str()], |args| Self::run_git(repo_path, args))
+    }
 
-        let name_status = Self::run_git(repo_path, &["diff", range.as_str(), "--name-status"])?;
-        let numstat = Self::run_git(repo_path, &["diff", range.as_str(), "--numstat"])?;
+    /// The worktree's full state against `base`, including uncommitted and
+    /// untracked (but not ignored) files. A throwaway index is used so the
+    /// agent's own index is never touched.
+    pub fn snapshot_diff(worktree_path: &str, base: &str) -> Result<DiffResult, KanbanError> {
+        let index = std::env::temp_dir().join(format!("kanban-snapshot-{}.index", uuid::Uuid::new_v4()));
+        let run = |args: &[&str]| Self::run_git_with_index(worktree_path, &index, args);
+
+        let result = run(&["read-tree", "HEAD"])
+            .and_then(|_| run(&["add", "-A"]))
+            .and_then(|_| Self::collect_diff(&["diff", "--cached", base], run));
+        let _ = fs::remove_file(&index);
+        result
+    }
 
-        let mut stat_map: HashMap<String, (i64, i64)> = HashMap::new();
-        for line in numstat.lines().filter(|line| !line.trim().is_empty()) {
-            let parts: Vec<&str> = line.split('\t').collect();
-            if parts.len() < 3 {
-                continue;
-            }
+    /// Where the worktree's branch forked from the main checkout's branch.
+    pub fn merge_base(repo_path: &str, worktree_path: &str) -> Option<String> {
+        let default_branch = Self::detect_default_branch(repo_path);
+        Self::run_git(worktree_path, &["merge-base", default_branch.as_str(), "HEAD"])
+            .ok()
+            .map(|sha| sha.trim().to_string())
+            .filter(|sha| !sha.is_empty())
+    }
+
+    pub fn head_commit(worktree_path: &str) -> Option<String> {
+        Self::run_git(worktree_path, &["rev-parse", "HEAD"])
+            .ok()
+            .map(|sha| sha.trim().to_string())
+    }
+
+    /// The commit `branch_name` points at in the main checkout.
+    pub fn branch_head(repo_path: &str, branch_name: &str) -> Option<String> {
+        Self::run_git(
+            repo_path,
+            &["rev-parse", "--verify", "--quiet", branch_name],
+        )
+        .ok()
+        .map(|sha| sha.trim().to_string())
+        .filter(|sha| !sha.is_empty())
+    }
+
+    /// Whether `commit` exists and is reachable from `branch_name`.
+    pub fn is_ancestor(repo_path: &str, commit: &str, branch_name: &str) -> bool {
+        let commit = format!("{}^{{commit}}", commit);
+        Self::run_git(repo_path, &["rev-parse", "--verify", "--quiet", commit.as_str()]).is_ok()
+            && Self::run_git(
+                repo_path,
+                &["merge-base", "--is-ancestor", commit.as_str(), branch_name],
+            )
+            .is_ok()
+    }
 
-            let additions = parts[0].parse::<i64>().unwrap_or(0);
-            let deletions = parts[1].parse::<i64>().unwrap_or(0);
-            let path = parts.last().unwrap_or(&"").to_string();
-            stat_map.insert(path, (additions, deletions));
+    /// Builds a [`DiffResult`] from `diff_args` (e.g. `["diff", "a...b"]`),
+    /// running git through `run`.
+    fn collect_diff(
+        diff_args: &[&str],
+        run: impl Fn(&[&str]) -> Result<String, KanbanError>,
+    ) -> Result<DiffResult, KanbanError> {
+        fn with<'a>(diff_args: &[&'a str], extra: &[&'a str]) -> Vec<&'a str> {
+            diff_args.iter().chain(extra).copied().collect()
         }
+        let name_status = run(&with(diff_args, &["--name-status", "-z"]))?;
+        let numstat = run(&with(diff_args, &["--numstat", "-z"]))?;
+
+        let stat_map: HashMap<String, (i64, i64)> = git::parse_numstat(numstat.as_bytes())
+            .into_iter()
+            .map(|entry| {
+                let stats = (entry.additions.unwrap_or(0), entry.deletions.unwrap_or(0));
+                (entry.path, stats)
+            })
+            .collect();
 
         let mut files = Vec::new();
         let mut total_additions = 0_i64;
         let mut total_deletions = 0_i64;
 
-        for line in name_status.lines().filter(|line| !line.trim().is_empty()) {
-            let parts: Vec<&str> = line.split('\t').collect();
-            if parts.len() < 2 {
-                continue;
-            }
-
-            let raw_status = parts[0];
-            let status = if raw_status.starts_with('A') {
-                "added"
-            } else if raw_status.starts_with('M') {
-                "modified"
-            } else if raw_status.starts_with('D') {
-                "deleted"
-            } else if raw_status.starts_with('R') {
-                "renamed"
-            } else {
-                "modified"
-            }
-            .to_string();
-
-            let path = if raw_status.starts_with('R') && parts.len() >= 3 {
-                parts[2].to_string()
-            } else {
-                parts[1].to_string()
-            };
-
-            let (additions, deletions) = stat_map.get(&path).copied().unwrap_or((0, 0));
-            let diff = Self::run_git(repo_path, &["diff", range.as_str(), "--", path.as_str()])?;
+        for entry in git::parse_name_status(name_status.as_bytes()) {
+            let (additions, deletions) = stat_map.get(&entry.path).copied().unwrap_or((0, 0));
+            let diff = run(&with(diff_args, &["--", entry.path.as_str()]))?;
 
             total_additions += additions;
             total_deletions += deletions;
 
             files.push(FileDiff {
-                path,
-                status,
+                path: entry.path,
+                status: entry.status.label().to_string(),
                 additions,
                 deletions,
                 diff,
@@ -229,8 +329,7 @@ impl GitWorktreeService {
 
         let default_branch = Self::detect_default_branch(repo_path);
 
-        let _previous_branch =
-            Self::run_git(repo_path, &["rev-parse", "--abbrev-ref", "HEAD"]).ok();
+        let previous_branch = Self::current_branch(repo_path)?;
 
         let stashed = Self::run_git(
             repo_path,
@@ -261,17 +360,11 @@ impl GitWorktreeService {
                 conflict_detail: None,
             },
             Err(error) => {
-                let conflicts =
-                    Self::run_git(repo_path, &["diff", "--name-only", "--diff-filter=U"])
-                        .ok()
-                        .map(|output| {
-                            output
-                                .lines()
-                                .filter(|line| !line.trim().is_empty())
-                                .map(str::to_owned)
-                                .collect::<Vec<_>>()
-                        })
-                        .unwrap_or_default();
+                let conflicts = Self::unmerged(repo_path)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|entry| entry.path)
+                    .collect();
 
                 let conflict_detail = if keep_conflicts {
                     Some(Self::get_conflict_details(repo_path)?)
@@ -289,11 +382,14 @@ impl GitWorktreeService {
             }
         };
 
-        let should_checkout_previous = !(keep_conflicts && !merge_result.success);
-        if should_checkout_previous {
-            if let Err(error) = Self::run_git(repo_path, &["checkout", "-"]) {
+        if merge_result.success || !keep_conflicts {
+            if let Err(error) = Self::run_git(repo_path, &["checkout", previous_branch.as_str()]) {
                 tracing::warn!(error = %error, "Failed to return to previous branch after merge");
             }
+        } else {
+            // The conflicts stay checked out until the merge is completed or
+            // aborted, possibly after a restart; remember where to go back to.
+            Self::record_previous_branch(repo_path, &previous_branch)?;
         }
 
         if stashed {
@@ -305,95 +401,238 @@ impl GitWorktreeService {
         Ok(merge_result)
     }
 
-    pub fn get_conflict_details(repo_path: &str) -> Result<ConflictDetail, KanbanError> {
-        if !Self::is_merge_in_progress(repo_path) {
-            return Ok(ConflictDetail {
-                files: Vec::new(),
-                merge_in_progress: false,
-            });
+    /// Commits only the selected files or hunks of the card branch onto the
+    /// base branch as one squashed commit, then merges the base back into
+    /// the card branch so the rejected remainder is all its diff still shows.
+    pub fn merge_selected(
+        repo_path: &str,
+        branch_name: &str,
+        worktree_path: &str,
+        card_title: &str,
+        message: &str,
+        selections: &[FileSelection],
+    ) -> Result<PartialMergeResult, KanbanError> {
+        if selections.is_empty() {
+            return Err(KanbanError::BadRequest("Select at least one file to merge".into()));
+        }
+        if Self::is_merge_in_progress(repo_path) {
+            return Err(KanbanError::Conflict(
+                "Finish or abort the merge in progress first".into(),
+            ));
+        }
+        let has_worktree = !worktree_path.is_empty() && Path::new(worktree_path).exists();
+        if has_worktree {
+            Self::commit_worktree_changes(worktree_path, card_title)?;
         }
 
-        let unmerged_output = Self::run_git(repo_path, &["ls-files", "--unmerged"])?;
-        let mut path_stages: HashMap<String, (bool, bool, bool)> = HashMap::new();
+        let default_branch = Self::detect_default_branch(repo_path);
+        let range = format!("{}...{}", default_branch, branch_name);
+        let mut patch = String::new();
+        let mut accepted_files = Vec::new();
+        for selection in selections {
+            if accepted_files.contains(&selection.path) {
+                return Err(KanbanError::BadRequest(format!(
+                    "File '{}' is selected twice",
+                    selection.path
+                )));
+            }
+            let file_patch = Self::run_git(
+                repo_path,
+                &["diff", "--binary", "--no-renames", range.as_str(), "--", selection.path.as_str()],
+            )?;
+            if file_patch.trim().is_empty() {
+                return Err(KanbanError::BadRequest(format!(
+                    "File '{}' is not part of the card's diff",
+                    selection.path
+                )));
+            }
+            match &selection.hunks {
+                Some(hunks) => patch.push_str(&Self::select_hunks(&file_patch, &selection.path, hunks)?),
+                None => patch.push_str(&file_patch),
+            }
+            accepted_files.push(selection.path.clone());
+        }
 
-        for line in unmerged_output
-            .lines()
-            .filter(|line| !line.trim().is_empty())
-        {
-            let Some((meta, path)) = line.split_once('\t') else {
-                continue;
-            };
-
-            let stage = meta
-                .split_whitespace()
-                .nth(2)
-                .and_then(|value| value.parse::<u8>().ok());
-
-            let entry = path_stages
-                .entry(path.to_string())
-                .or_insert((false, false, false));
-
-            match stage {
-                Some(1) => entry.0 = true,
-                Some(2) => entry.1 = true,
-                Some(3) => entry.2 = true,
-                _ => {}
+        let patch_path =
+            std::env::temp_dir().join(format!("kanban-partial-{}.patch", uuid::Uuid::new_v4()));
+        fs::write(&patch_path, &patch)
+            .map_err(|e| KanbanError::Internal(format!("Failed to write patch: {}", e)))?;
+
+        let stashed = Self::run_git(
+            repo_path,
+            &["stash", "push", "-u", "-m", "kanban-merge-temp"],
+        )
+        .is_ok()
+            && Self::run_git(repo_path, &["stash", "list"])
+                .map(|list| list.contains("kanban-merge-temp"))
+                .unwrap_or(false);
+
+        let commit = Self::run_git(repo_path, &["checkout", default_branch.as_str()]).and_then(|_| {
+            let patch_arg = patch_path.to_string_lossy();
+            // `apply --index` is all-or-nothing, so a failed apply leaves the
+            // base branch untouched.
+            let committed = Self::run_git(repo_path, &["apply", "--index", "--whitespace=nowarn", patch_arg.as_ref()])
+                .map_err(|e| {
+                    KanbanError::Conflict(format!(
+                        "Selected changes do not apply cleanly onto {}: {}",
+                        default_branch, e
+                    ))
+                })
+                .and_then(|_| {
+                    Self::run_git(repo_path, &["commit", "-m", message]).inspect_err(|_| {
+                        // Drop the staged patch so the base branch is left as it was.
+                        if let Err(error) = Self::run_git(repo_path, &["reset", "--hard", "HEAD"]) {
+                            tracing::warn!(error = %error, "Failed to reset base branch after failed partial merge commit");
+                        }
+                    })?;
+                    Self::run_git(repo_path, &["rev-parse", "HEAD"]).map(|sha| sha.trim().to_string())
+                });
+
+            // Only reached after checking out the base branch, so `-` is the
+            // branch we started on.
+            if let Err(error) = Self::run_git(repo_path, &["checkout", "-"]) {
+                tracing::warn!(error = %error, "Failed to return to previous branch after partial merge");
+            }
+            committed
+        });
+        let _ = fs::remove_file(&patch_path);
+
+        if stashed {
+            if let Err(error) = Self::run_git(repo_path, &["stash", "pop"]) {
+                tracing::warn!(error = %error, "Failed to restore stash after partial merge");
             }
         }
+        let commit = commit?;
 
-        let mut binary_paths = std::collections::HashSet::new();
-        if let Ok(binary_output) =
-            Self::run_git(repo_path, &["diff", "--numstat", "HEAD", "MERGE_HEAD"])
-        {
-            for line in binary_output.lines().filter(|line| !line.trim().is_empty()) {
-                let parts: Vec<&str> = line.split('\t').collect();
-                if parts.len() < 3 {
-                    continue;
-                }
+        // The accepted changes are identical on both sides, so this merge is
+        // clean and leaves the card branch's diff at the remainder.
+        let sync_message = format!("Merge {} after partial accept", default_branch);
+        let synced = if has_worktree {
+            Self::run_git(
+                worktree_path,
+                &["merge", "--no-ff", "-m", sync_message.as_str(), default_branch.as_str()],
+            )
+            .map(|_| ())
+            .inspect_err(|_| {
+                let _ = Self::run_git(worktree_path, &["merge", "--abort"]);
+            })
+        } else {
+            Self::merge_without_checkout(repo_path, branch_name, &default_branch, &sync_message)
+        };
+        if let Err(error) = synced {
+            tracing::warn!(
+                branch_name,
+                error = %error,
+                "Failed to merge base back into card branch after partial merge"
+            );
+        }
 
-                if parts[0] == "-" && parts[1] == "-" {
-                    binary_paths.insert(parts[2].to_string());
-                }
+        Ok(PartialMergeResult {
+            commit,
+            accepted_files,
+            remaining: Self::get_diff(repo_path, branch_name)?,
+        })
+    }
+
+    /// Keeps the file header and the chosen `@@` hunks of a single-file patch.
+    fn select_hunks(patch: &str, path: &str, hunks: &[usize]) -> Result<String, KanbanError> {
+        let mut header = String::new();
+        let mut parts: Vec<String> = Vec::new();
+        for line in patch.split_inclusive('\n') {
+            if line.starts_with("@@") {
+                parts.push(String::new());
+            }
+            match parts.last_mut() {
+                Some(hunk) => hunk.push_str(line),
+                None => header.push_str(line),
             }
         }
 
-        let mut files: Vec<ConflictFile> = path_stages
-            .iter()
-            .map(|(path, (has_base, has_ours, has_theirs))| {
-                let conflict_type = match (*has_base, *has_ours, *has_theirs) {
-                    (true, true, true) => "both-modified",
-                    (false, true, true) => "added-by-both",
-                    (true, false, true) => "deleted-by-us",
-                    (true, true, false) => "deleted-by-them",
-                    _ => "both-modified",
-                }
-                .to_string();
+        if parts.is_empty() {
+            return Err(KanbanError::BadRequest(format!(
+                "File '{}' has no hunks to pick from; accept the whole file",
+                path
+            )));
+        }
+        if hunks.is_empty() {
+            return Err(KanbanError::BadRequest(format!(
+                "Pick at least one hunk of '{}' or leave out `hunks`",
+                path
+            )));
+        }
+        if let Some(index) = hunks.iter().find(|&&index| index >= parts.len()) {
+            return Err(KanbanError::BadRequest(format!(
+                "File '{}' has {} hunks; there is no hunk {}",
+                path,
+                parts.len(),
+                index
+            )));
+        }
 
-                let is_binary = binary_paths.contains(path);
-                let base_content = if *has_base {
-                    Self::run_git(repo_path, &["show", format!(":1:{}", path).as_str()]).ok()
-                } else {
-                    None
-                };
-                let ours_content = if *has_ours {
-                    Self::run_git(repo_path, &["show", format!(":2:{}", path).as_str()]).ok()
-                } else {
-                    None
-                };
-                let theirs_content = if *has_theirs {
-                    Self::run_git(repo_path, &["show", format!(":3:{}", path).as_str()]).ok()
-                } else {
-                    None
-                };
+        let mut selected = header;
+        for (index, hunk) in parts.iter().enumerate() {
+            if hunks.contains(&index) {
+                selected.push_str(hunk);
+            }
+        }
+        Ok(selected)
+    }
 
-                ConflictFile {
-                    path: path.clone(),
-                    ours_content,
-                    theirs_content,
-                    base_content,
-                    conflict_type,
-                    is_binary,
-                }
+    /// Merges `base` into `branch_name` without a checkout, for branches
+    /// whose worktree is gone.
+    fn merge_without_checkout(
+        repo_path: &str,
+        branch_name: &str,
+        base: &str,
+        message: &str,
+    ) -> Result<(), KanbanError> {
+        let tree = Self::run_git(repo_path, &["merge-tree", "--write-tree", base, branch_name])?;
+        let tree = tree.lines().next().unwrap_or_default().trim().to_string();
+        let head = Self::run_git(repo_path, &["rev-parse", branch_name])?;
+        let commit = Self::run_git(
+            repo_path,
+            &["commit-tree", tree.as_str(), "-p", head.trim(), "-p", base, "-m", message],
+        )?;
+        let reference = format!("refs/heads/{}", branch_name);
+        Self::run_git(
+            repo_path,
+            &["update-ref", reference.as_str(), commit.trim(), head.trim()],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_conflict_details(repo_path: &str) -> Result<ConflictDetail, KanbanError> {
+        if !Self::is_merge_in_progress(repo_path) {
+            return Ok(ConflictDetail {
+                files: Vec::new(),
+                merge_in_progress: false,
+            });
+        }
+
+        let binary_paths: HashSet<String> =
+            Self::run_git(repo_path, &["diff", "--numstat", "-z", "HEAD", "MERGE_HEAD"])
+                .map(|output| {
+                    git::parse_numstat(output.as_bytes())
+                        .into_iter()
+                        .filter(|entry| entry.is_binary())
+                        .map(|entry| entry.path)
+                        .collect()
+                })
+                .unwrap_or_default();
+        let blob = |oid: &Option<String>| {
+            oid.as_deref()
+                .and_then(|oid| Self::run_git(repo_path, &["cat-file", "blob", oid]).ok())
+        };
+
+        let mut files: Vec<ConflictFile> = Self::unmerged(repo_path)?
+            .into_iter()
+            .map(|entry| ConflictFile {
+                ours_content: blob(&entry.ours),
+                theirs_content: blob(&entry.theirs),
+                base_content: blob(&entry.base),
+                conflict_type: entry.conflict_type().to_string(),
+                is_binary: binary_paths.contains(&entry.path),
+                path: entry.path,
             })
             .collect();
 
@@ -405,8 +644,210 @@ impl GitWorktreeService {
         })
     }
 
+    /// Applies ours/theirs/manual choices to conflicted files of the merge
+    /// in progress and returns the conflicts left.
+    pub fn resolve_files(
+        repo_path: &str,
+        resolutions: &[FileResolution],
+    ) -> Result<ConflictDetail, KanbanError> {
+        for resolution in resolutions {
+            let file = resolution.file_path.as_str();
+            match resolution.choice.as_str() {
+                "ours" | "theirs" => {
+                    let side = format!("--{}", resolution.choice);
+                    Self::run_git(repo_path, &["checkout", side.as_str(), "--", file])?;
+                }
+                "manual" => {
+                    let manual_content = resolution.manual_content.as_ref().ok_or_else(|| {
+                        KanbanError::BadRequest(format!(
+                            "manual_content is required for manual resolution: {}",
+                            file
+                        ))
+                    })?;
+                    fs::write(Path::new(repo_path).join(file), manual_content).map_err(|e| {
+                        KanbanError::Internal(format!(
+                            "Failed to write manual resolution for {}: {}",
+                            file, e
+                        ))
+                    })?;
+                }
+                _ => {
+                    return Err(KanbanError::BadRequest(format!(
+                        "Invalid resolution choice '{}' for file {}",
+                        resolution.choice, file
+                    )));
+                }
+            }
+            Self::run_git(repo_path, &["add", "--", file])?;
+        }
+        Self::get_conflict_details(repo_path)
+    }
+
+    /// Commits the merge in progress once no conflicts remain and returns to
+    /// the branch checked out before it.
+    pub fn complete_merge(repo_path: &str) -> Result<(), KanbanError> {
+        if !Self::unmerged(repo_path)?.is_empty() {
+            return Err(KanbanError::BadRequest(
+                "Cannot complete merge while conflicts remain".into(),
+            ));
+        }
+        Self::run_git(repo_path, &["commit", "--no-edit"])?;
+        Self::return_to_previous_branch(repo_path);
+        Ok(())
+    }
+
+    /// Abandons the merge in progress, falling back to a hard reset, and
+    /// returns to the branch checked out before it.
+    pub fn abort_merge(repo_path: &str) -> Result<(), KanbanError> {
+        if let Err(abort_error) = Self::run_git(repo_path, &["merge", "--abort"]) {
+            tracing::warn!(error = %abort_error, "git merge --abort failed, using fallback reset");
+            Self::run_git(repo_path, &["reset", "--hard", "HEAD"])?;
+        }
+        Self::return_to_previous_branch(repo_path);
+        Ok(())
+    }
+
+    /// Checks out the branch recorded when a conflicted merge was kept. The
+    /// checkout stays where it is when nothing was recorded.
+    pub fn return_to_previous_branch(repo_path: &str) {
+        let Ok(record) = Self::previous_branch_path(repo_path) else {
+            return;
+        };
+        let Ok(previous_branch) = fs::read_to_string(&record) else {
+            tracing::warn!(repo_path, "No branch recorded before the merge; staying on the current branch");
+            return;
+        };
+        match Self::run_git(repo_path, &["checkout", previous_branch.trim()]) {
+            Ok(_) => {
+                let _ = fs::remove_file(&record);
+            }
+            Err(error) => {
+                tracing::warn!(error = %error, branch = previous_branch.trim(), "Failed to return to previous branch after merge");
+            }
+        }
+    }
+
+    /// The checked-out branch, or the commit when the HEAD is detached.
+    fn current_branch(repo_path: &str) -> Result<String, KanbanError> {
+        let branch = Self::run_git(repo_path, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+        if branch.trim() != "HEAD" {
+            return Ok(branch.trim().to_string());
+        }
+        Ok(Self::run_git(repo_path, &["rev-parse", "HEAD"])?.trim().to_string())
+    }
+
+    fn record_previous_branch(repo_path: &str, branch: &str) -> Result<(), KanbanError> {
+        fs::write(Self::previous_branch_path(repo_path)?, branch).map_err(|e| {
+            KanbanError::Internal(format!("Failed to record the branch before the merge: {}", e))
+        })
+    }
+
+    fn previous_branch_path(repo_path: &str) -> Result<PathBuf, KanbanError> {
+        let path = Self::run_git(repo_path, &["rev-parse", "--git-path", "KANBAN_PREVIOUS_BRANCH"])?;
+        Ok(Path::new(repo_path).join(path.trim()))
+    }
+
+    /// Every changed, untracked or conflicted path in the checkout.
+    pub fn status(path: &str) -> Result<Vec<StatusEntry>, KanbanError> {
+        let output = git::run(path, &["status", "--porcelain=v2", "-z"], None)?;
+        Ok(git::parse_status(&output))
+    }
+
+    /// Conflicted paths of the merge or rebase in progress, by path.
+    pub fn unmerged(path: &str) -> Result<Vec<UnmergedEntry>, KanbanError> {
+        Ok(Self::status(path)?
+            .into_iter()
+            .filter_map(|entry| match entry {
+                StatusEntry::Unmerged(entry) => Some(entry),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Also true inside a linked worktree, whose `.git` is a file.
     pub fn is_merge_in_progress(repo_path: &str) -> bool {
         Path::new(repo_path).join(".git/MERGE_HEAD").exists()
+            || Self::run_git(repo_path, &["rev-parse", "-q", "--verify", "MERGE_HEAD"]).is_ok()
+    }
+
+    /// Merges the base branch into the card branch inside the card's
+    /// worktree, leaving any conflicts there for the agent to resolve. The
+    /// main checkout is not touched.
+    pub fn merge_base_into_worktree(
+        repo_path: &str,
+        worktree_path: &str,
+        card_title: &str,
+    ) -> Result<MergeResult, KanbanError> {
+        if Self::is_merge_in_progress(worktree_path) {
+            return Err(KanbanError::Conflict(
+                "A merge is already in progress in the card's worktree".into(),
+            ));
+        }
+        Self::commit_worktree_changes(worktree_path, card_title)?;
+
+        let default_branch = Self::detect_default_branch(repo_path);
+        let merge_message = format!("Merge {} into card branch", default_branch);
+        match Self::run_git(
+            worktree_path,
+            &["merge", "--no-ff", "-m", merge_message.as_str(), default_branch.as_str()],
+        ) {
+            Ok(_) => Ok(MergeResult {
+                success: true,
+                message: format!("Merged {} into the card branch without conflicts", default_branch),
+                conflicts: Vec::new(),
+                conflict_detail: None,
+            }),
+            Err(error) => {
+                let detail = Self::get_conflict_details(worktree_path)?;
+                if detail.files.is_empty() {
+                    let _ = Self::run_git(worktree_path, &["merge", "--abort"]);
+                    return Err(error);
+                }
+                Ok(MergeResult {
+                    success: false,
+                    message: format!("Merging {} into the card branch conflicts", default_branch),
+                    conflicts: detail.files.iter().map(|file| file.path.clone()).collect(),
+                    conflict_detail: Some(detail),
+                })
+            }
+        }
+    }
+
+    /// Merges the current base into the card branch inside its worktree. A
+    /// merge, unlike a rebase, keeps the branch's own merge commits, so
+    /// conflicts resolved earlier are not replayed. Conflicts abort the
+    /// merge and leave the branch as it was.
+    pub fn update_from_base(
+        repo_path: &str,
+        worktree_path: &str,
+        card_title: &str,
+    ) -> Result<(), KanbanError> {
+        Self::commit_worktree_changes(worktree_path, card_title)?;
+        let default_branch = Self::detect_default_branch(repo_path);
+        let merge_message = format!("Merge {} into card branch", default_branch);
+        if let Err(error) = Self::run_git(
+            worktree_path,
+            &["merge", "--no-edit", "-m", merge_message.as_str(), default_branch.as_str()],
+        ) {
+            let conflicts = Self::unmerged(worktree_path)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|entry| entry.path)
+                .collect::<Vec<_>>()
+                .join(", ");
+            let _ = Self::run_git(worktree_path, &["merge", "--abort"]);
+            return Err(KanbanError::Conflict(if conflicts.is_empty() {
+                format!("Merging {} into the card branch failed: {}", default_branch, error)
+            } else {
+                format!("Merging {} into the card branch conflicts in {}", default_branch, conflicts)
+            }));
+        }
+        Ok(())
+    }
+
+    /// The branch cards fork from and merge into.
+    pub fn default_branch(repo_path: &str) -> String {
+        Self::detect_default_branch(repo_path)
     }
 
     pub fn create_github_pr(
@@ -469,8 +910,12 @@ impl GitWorktreeService {
         worktree_path: &str,
         card_title: &str,
     ) -> Result<(), KanbanError> {
-        let status = Self::run_git(worktree_path, &["status", "--porcelain"])?;
-        if status.trim().is_empty() {
+        if Self::is_merge_in_progress(worktree_path) {
+            return Err(KanbanError::Conflict(
+                "Merge conflicts in the card's worktree are still being resolved".into(),
+            ));
+        }
+        if Self::status(worktree_path)?.is_empty() {
             return Ok(());
         }
 
@@ -526,6 +971,39 @@ impl GitWorktreeService {
         Ok(())
     }
 
+    pub fn is_tracked(repo_path: &str, file: &str) -> bool {
+        Self::run_git(repo_path, &["ls-files", "--error-unmatch", "--", file]).is_ok()
+    }
+
+    /// Adds `pattern` to the repository's `info/exclude`, which every
+    /// worktree shares. Unlike `.gitignore` this never shows up in a diff,
+    /// so the auto-commit's `git add -A` cannot pick the file up.
+    pub fn ensure_excluded(repo_path: &str, pattern: &str) -> Result<(), KanbanError> {
+        let exclude = Self::run_git(repo_path, &["rev-parse", "--git-path", "info/exclude"])?;
+        let exclude_path = Path::new(repo_path).join(exclude.trim());
+
+        let existing = fs::read_to_string(&exclude_path).unwrap_or_default();
+        if existing.lines().map(str::trim).any(|line| line == pattern) {
+            return Ok(());
+        }
+
+        if let Some(parent) = exclude_path.parent() {
+            fs::create_dir_all(parent).map_err(|e| {
+                KanbanError::Internal(format!("Failed to create git info directory: {}", e))
+            })?;
+        }
+        let mut updated = existing;
+        if !updated.is_empty() && !updated.ends_with('\n') {
+            updated.push('\n');
+        }
+        updated.push_str(pattern);
+        updated.push('\n');
+
+        fs::write(&exclude_path, updated)
+            .map_err(|e| KanbanError::Internal(format!("Failed to update git exclude: {}", e)))?;
+        Ok(())
+    }
+
     fn slugify_title(title: &str) -> String {
         let mut slug = String::new();
         let mut last_dash = false;
@@ -595,21 +1073,11 @@ impl GitWorktreeService {
         "gh".to_string()
     }
 
-    pub fn run_git(repo_path: &str, args: &[&str]) -> Result<String, KanbanError> {
-        let output = Command::new("git")
-            .args(args)
-            .current_dir(repo_path)
-            .output()
-            .map_err(|e| KanbanError::Internal(format!("Failed to run git: {}", e)))?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(KanbanError::Internal(format!(
-                "Git command failed: {}",
-                stderr.trim()
-            )));
-        }
+    fn run_git_with_index(repo_path: &str, index: &Path, args: &[&str]) -> Result<String, KanbanError> {
+        git::run(repo_path, args, Some(index)).map(|stdout| String::from_utf8_lossy(&stdout).into_owned())
+    }
 
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    pub fn run_git(repo_path: &str, args: &[&str]) -> Result<String, KanbanError> {
+        git::run(repo_path, args, None).map(|stdout| String::from_utf8_lossy(&stdout).into_owned())
     }
 }
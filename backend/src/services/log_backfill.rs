@@ -0,0 +1,303 @@
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use crate::api::handlers::sse::WsEvent;
+use crate::domain::{Card, KanbanError};
+
+use super::{
+    CardAttemptService, CardRunService, EventBus, LogRetentionService, OpencodePool, Redactor,
+    SseRelayService,
+};
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct BackfillReport {
+    pub sessions: usize,
+    pub inserted: usize,
+}
+
+/// A log row reconstructed from OpenCode's message history.
+struct BackfillEntry {
+    upstream_id: String,
+    event_type: &'static str,
+    agent: Option<String>,
+    content: String,
+    metadata: Value,
+    created_at: String,
+}
+
+/// Fills holes in `agent_logs` left while the relay was disconnected by
+/// replaying each session's message history from OpenCode. Rows are keyed by
+/// upstream message IDs, so running it repeatedly is harmless.
+pub struct LogBackfillService;
+
+impl LogBackfillService {
+    /// Backfills every card with a live session, each against its own endpoint.
+    pub async fn backfill_active(
+        db: &SqlitePool,
+        http_client: &reqwest::Client,
+        sse_tx: &EventBus,
+        opencode: &OpencodePool,
+    ) -> BackfillReport {
+        let cards = match Self::active_cards(db).await {
+            Ok(cards) => cards,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to list cards for log backfill");
+                return BackfillReport::default();
+            }
+        };
+
+        let mut total = BackfillReport::default();
+        for card in cards {
+            let url = opencode.endpoint_for_card(&card);
+            total.add(Self::backfill_logged(db, http_client, sse_tx, &url, &card).await);
+        }
+        total
+    }
+
+    /// Backfills the cards whose sessions live on `opencode_url`; used by the
+    /// relay after it reconnects. Cards without endpoint affinity are tried
+    /// too, as they predate the pool.
+    pub async fn backfill_endpoint(
+        db: &SqlitePool,
+        http_client: &reqwest::Client,
+        sse_tx: &EventBus,
+        opencode_url: &str,
+    ) -> BackfillReport {
+        let url = opencode_url.trim_end_matches('/');
+        let cards = match Self::active_cards(db).await {
+            Ok(cards) => cards,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to list cards for log backfill");
+                return BackfillReport::default();
+            }
+        };
+
+        let mut total = BackfillReport::default();
+        for card in cards {
+            let affinity = card.opencode_url.trim_end_matches('/');
+            if affinity.is_empty() || affinity == url {
+                total.add(Self::backfill_logged(db, http_client, sse_tx, url, &card).await);
+            }
+        }
+        total
+    }
+
+    /// Backfills the card's parent session and every mapped child session.
+    pub async fn backfill_card(
+        db: &SqlitePool,
+        http_client: &reqwest::Client,
+        sse_tx: &EventBus,
+        opencode_url: &str,
+        card: &Card,
+    ) -> Result<BackfillReport, KanbanError> {
+        let mut report = BackfillReport::default();
+        let Some(parent) = card.ai_session_id.as_deref().filter(|s| !s.is_empty()) else {
+            return Ok(report);
+        };
+
+        let children: Vec<(String, Option<String>)> = sqlx::query_as(
+            "SELECT child_session_id, agent_type FROM session_mappings WHERE card_id = ? ORDER BY created_at ASC",
+        )
+        .bind(&card.id)
+        .fetch_all(db)
+        .await?;
+
+        let floor = LogRetentionService::backfill_floor(db, &card.id).await?;
+        let redactor = Redactor::for_board(db, card.board_id.as_deref()).await?;
+        let sessions = std::iter::once((parent.to_string(), None, false))
+            .chain(children.into_iter().map(|(id, agent_type)| (id, agent_type, true)));
+
+        for (session_id, agent_type, is_subagent) in sessions {
+            let Some(messages) =
+                Self::fetch_messages(http_client, opencode_url, &session_id).await?
+            else {
+                continue;
+            };
+            report.sessions += 1;
+            // Competing attempts keep their own usage, as they do in the relay.
+            let counts_usage = !is_subagent
+                || CardAttemptService::find_by_session(db, &session_id).await?.is_none();
+
+            for entry in Self::entries(&messages, agent_type.as_deref(), is_subagent) {
+                let expired = floor.is_some_and(|floor| {
+                    DateTime::parse_from_rfc3339(&entry.created_at).is_ok_and(|at| at <= floor)
+                });
+                if expired {
+                    continue;
+                }
+                let result = sqlx::query(
+                    "INSERT OR IGNORE INTO agent_logs (id, card_id, session_id, event_type, agent, content, metadata, created_at, upstream_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                )
+                .bind(Uuid::new_v4().to_string())
+                .bind(&card.id)
+                .bind(&session_id)
+                .bind(entry.event_type)
+                .bind(&entry.agent)
+                .bind(redactor.redact(&entry.content))
+                .bind(redactor.redact(&entry.metadata.to_string()))
+                .bind(&entry.created_at)
+                .bind(&entry.upstream_id)
+                .execute(db)
+                .await?;
+                report.inserted += result.rows_affected() as usize;
+                if counts_usage {
+                    if let Some(info) = entry.metadata.get("info") {
+                        CardRunService::record_usage(db, &card.id, info).await?;
+                    }
+                }
+            }
+        }
+
+        if report.inserted > 0 {
+            let event = WsEvent::AgentLogsBackfilled {
+                card_id: card.id.clone(),
+                inserted: report.inserted,
+            };
+            if let Ok(payload) = serde_json::to_string(&event) {
+                let _ = sse_tx.send(payload);
+            }
+        }
+
+        Ok(report)
+    }
+
+    async fn backfill_logged(
+        db: &SqlitePool,
+        http_client: &reqwest::Client,
+        sse_tx: &EventBus,
+        opencode_url: &str,
+        card: &Card,
+    ) -> BackfillReport {
+        match Self::backfill_card(db, http_client, sse_tx, opencode_url, card).await {
+            Ok(report) => {
+                if report.inserted > 0 {
+                    tracing::info!(
+                        card_id = card.id,
+                        inserted = report.inserted,
+                        "Backfilled agent logs from OpenCode history"
+                    );
+                }
+                report
+            }
+            Err(e) => {
+                tracing::warn!(card_id = card.id, error = %e, "Agent log backfill failed");
+                BackfillReport::default()
+            }
+        }
+    }
+
+    async fn active_cards(db: &SqlitePool) -> Result<Vec<Card>, KanbanError> {
+        let cards = sqlx::query_as(
+            "SELECT * FROM cards WHERE ai_session_id IS NOT NULL AND ai_session_id != '' AND stage != 'done'",
+        )
+        .fetch_all(db)
+        .await?;
+        Ok(cards)
+    }
+
+    /// `None` when OpenCode no longer knows the session.
+    async fn fetch_messages(
+        http_client: &reqwest::Client,
+        opencode_url: &str,
+        session_id: &str,
+    ) -> Result<Option<Vec<Value>>, KanbanError> {
+        let url = format!(
+            "{}/session/{}/message",
+            opencode_url.trim_end_matches('/'),
+            session_id
+        );
+        let response = http_client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| KanbanError::OpenCodeError(format!("Failed to fetch session history: {}", e)))?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(KanbanError::OpenCodeError(format!(
+                "Session history request failed with status {}",
+                response.status()
+            )));
+        }
+
+        let messages = response
+            .json::<Vec<Value>>()
+            .await
+            .map_err(|e| KanbanError::OpenCodeError(format!("Invalid session history: {}", e)))?;
+        Ok(Some(messages))
+    }
+
+    /// Finished assistant messages become the same `message.updated` rows,
+    /// with the same content and metadata, that the relay writes. Streamed
+    /// parts and prompts are not logged live, so they are not backfilled.
+    fn entries(messages: &[Value], agent_type: Option<&str>, is_subagent: bool) -> Vec<BackfillEntry> {
+        let mut entries = Vec::new();
+
+        for message in messages {
+            let Some(info) = message.get("info") else {
+                continue;
+            };
+            if info.get("role").and_then(Value::as_str) != Some("assistant")
+                || info.get("finish").and_then(Value::as_str).is_none()
+            {
+                continue;
+            }
+            let Some(message_id) = info.get("id").and_then(Value::as_str) else {
+                continue;
+            };
+            let agent = agent_type.map(str::to_owned).or_else(|| {
+                info.get("agent").and_then(Value::as_str).map(str::to_owned)
+            });
+
+            let properties = json!({ "info": info });
+            entries.push(BackfillEntry {
+                upstream_id: format!("message:{}", message_id),
+                event_type: "message.updated",
+                agent: agent.clone(),
+                content: SseRelayService::build_log_content(
+                    "message.updated",
+                    &properties,
+                    agent.as_deref(),
+                    is_subagent,
+                ),
+                metadata: metadata(properties, agent_type, is_subagent),
+                created_at: timestamp(info.pointer("/time/completed"))
+                    .or_else(|| timestamp(info.pointer("/time/created")))
+                    .unwrap_or_else(|| Utc::now().to_rfc3339()),
+            });
+        }
+
+        entries
+    }
+}
+
+impl BackfillReport {
+    fn add(&mut self, other: BackfillReport) {
+        self.sessions += other.sessions;
+        self.inserted += other.inserted;
+    }
+}
+
+/// The relay's metadata for the same event: the event properties, tagged
+/// when they come from a sub-agent.
+fn metadata(mut value: Value, agent_type: Option<&str>, is_subagent: bool) -> Value {
+    if is_subagent {
+        value["_subagent"] = json!(true);
+        if let Some(agent_type) = agent_type {
+            value["_agent_type"] = json!(agent_type);
+        }
+    }
+    value
+}
+
+/// OpenCode reports times as epoch milliseconds.
+fn timestamp(value: Option<&Value>) -> Option<String> {
+    let millis = value?.as_i64()?;
+    DateTime::from_timestamp_millis(millis).map(|t| t.to_rfc3339())
+}
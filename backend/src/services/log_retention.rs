@@ -0,0 +1,357 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use crate::domain::{AgentLog, KanbanError};
+
+/// Event type of the row that replaces a Done card's compacted log.
+pub const SUMMARY_EVENT: &str = "log.summary";
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct CompactionReport {
+    pub archived: usize,
+    pub deleted: usize,
+    pub summarized_cards: usize,
+}
+
+#[derive(sqlx::FromRow)]
+struct RetentionPolicy {
+    board_id: String,
+    log_retention_days: i64,
+    log_done_summary_only: bool,
+    log_archive: bool,
+}
+
+/// Enforces board log retention policies. Rows are only ever removed after
+/// they have been written to a gzip JSONL archive (when the board archives),
+/// and [`LogRetentionService::load_logs`] merges archives back into reads.
+#[derive(Clone)]
+pub struct LogRetentionService {
+    db: SqlitePool,
+    archive_dir: PathBuf,
+}
+
+impl LogRetentionService {
+    pub fn new(db: SqlitePool, archive_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            db,
+            archive_dir: archive_dir.into(),
+        }
+    }
+
+    pub async fn start(self, interval: Duration) {
+        loop {
+            match self.compact_all().await {
+                Ok(report) if report.archived + report.deleted > 0 => {
+                    tracing::info!(
+                        archived = report.archived,
+                        deleted = report.deleted,
+                        summarized_cards = report.summarized_cards,
+                        "Agent log compaction finished"
+                    );
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!(error = %e, "Agent log compaction failed"),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    pub async fn compact_all(&self) -> Result<CompactionReport, KanbanError> {
+        let policies: Vec<RetentionPolicy> = sqlx::query_as(
+            "SELECT board_id, log_retention_days, log_done_summary_only, log_archive FROM board_settings
+             WHERE log_retention_days > 0 OR log_done_summary_only = 1",
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let mut report = CompactionReport::default();
+        for policy in policies {
+            if policy.log_done_summary_only {
+                self.summarize_done_cards(&policy, &mut report).await?;
+            }
+            if policy.log_retention_days > 0 {
+                self.expire_old_logs(&policy, &mut report).await?;
+            }
+        }
+        Ok(report)
+    }
+
+    /// Replaces the detailed log of each Done card with one summary row.
+    async fn summarize_done_cards(
+        &self,
+        policy: &RetentionPolicy,
+        report: &mut CompactionReport,
+    ) -> Result<(), KanbanError> {
+        let cards: Vec<(String, Option<String>)> = sqlx::query_as(
+            "SELECT c.id, c.ai_session_id FROM cards c
+             WHERE c.board_id = ? AND c.stage = 'done'
+               AND EXISTS (SELECT 1 FROM agent_logs l WHERE l.card_id = c.id AND l.event_type != ?)",
+        )
+        .bind(&policy.board_id)
+        .bind(SUMMARY_EVENT)
+        .fetch_all(&self.db)
+        .await?;
+
+        for (card_id, session_id) in cards {
+            let logs: Vec<AgentLog> = sqlx::query_as(
+                "SELECT * FROM agent_logs WHERE card_id = ? AND event_type != ? ORDER BY created_at ASC",
+            )
+            .bind(&card_id)
+            .bind(SUMMARY_EVENT)
+            .fetch_all(&self.db)
+            .await?;
+
+            let summary = summarize(&logs);
+            let session_id = session_id.unwrap_or_default();
+            self.remove(&card_id, &logs, policy.log_archive, Some((&session_id, summary)), report)
+                .await?;
+            report.summarized_cards += 1;
+        }
+        Ok(())
+    }
+
+    async fn expire_old_logs(
+        &self,
+        policy: &RetentionPolicy,
+        report: &mut CompactionReport,
+    ) -> Result<(), KanbanError> {
+        let cutoff = (Utc::now() - chrono::Duration::days(policy.log_retention_days)).to_rfc3339();
+        let logs: Vec<AgentLog> = sqlx::query_as(
+            "SELECT l.* FROM agent_logs l JOIN cards c ON c.id = l.card_id
+             WHERE c.board_id = ? AND l.created_at < ? AND l.event_type != ?
+             ORDER BY l.card_id, l.created_at ASC",
+        )
+        .bind(&policy.board_id)
+        .bind(&cutoff)
+        .bind(SUMMARY_EVENT)
+        .fetch_all(&self.db)
+        .await?;
+
+        let mut by_card: BTreeMap<String, Vec<AgentLog>> = BTreeMap::new();
+        for log in logs {
+            by_card.entry(log.card_id.clone()).or_default().push(log);
+        }
+        for (card_id, logs) in by_card {
+            self.remove(&card_id, &logs, policy.log_archive, None, report).await?;
+        }
+        Ok(())
+    }
+
+    /// Archives (if requested) and then deletes the given rows. A summary,
+    /// given as `(session_id, content)`, is written in the same transaction
+    /// as the deletion so a failure can never leave a card with neither.
+    async fn remove(
+        &self,
+        card_id: &str,
+        logs: &[AgentLog],
+        archive: bool,
+        summary: Option<(&str, String)>,
+        report: &mut CompactionReport,
+    ) -> Result<(), KanbanError> {
+        if logs.is_empty() {
+            return Ok(());
+        }
+
+        let mut archived = None;
+        if archive {
+            let file_name = format!(
+                "{}-{}.jsonl.gz",
+                Utc::now().format("%Y%m%dT%H%M%S"),
+                &Uuid::new_v4().to_string()[..8]
+            );
+            let path = self.archive_dir.join(card_id).join(file_name);
+            let rows = logs.to_vec();
+            let target = path.clone();
+            tokio::task::spawn_blocking(move || write_archive(&target, &rows))
+                .await
+                .map_err(|e| KanbanError::Internal(format!("Archive task failed: {}", e)))?
+                .map_err(|e| KanbanError::Internal(format!("Failed to write log archive: {}", e)))?;
+            archived = Some(path);
+        }
+
+        let mut tx = self.db.begin().await?;
+        if let Some(path) = &archived {
+            sqlx::query(
+                "INSERT INTO agent_log_archives (id, card_id, path, entry_count, first_at, last_at, created_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(Uuid::new_v4().to_string())
+            .bind(card_id)
+            .bind(path.to_string_lossy().to_string())
+            .bind(logs.len() as i64)
+            .bind(&logs[0].created_at)
+            .bind(&logs[logs.len() - 1].created_at)
+            .bind(Utc::now().to_rfc3339())
+            .execute(&mut *tx)
+            .await?;
+        }
+        for log in logs {
+            sqlx::query("DELETE FROM agent_logs WHERE id = ?")
+                .bind(&log.id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        if let Some((session_id, content)) = summary {
+            sqlx::query(
+                "INSERT INTO agent_logs (id, card_id, session_id, event_type, agent, content, metadata, created_at) VALUES (?, ?, ?, ?, NULL, ?, '{}', ?)",
+            )
+            .bind(Uuid::new_v4().to_string())
+            .bind(card_id)
+            .bind(session_id)
+            .bind(SUMMARY_EVENT)
+            .bind(content)
+            .bind(&logs[logs.len() - 1].created_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        if archived.is_some() {
+            report.archived += logs.len();
+        }
+        report.deleted += logs.len();
+        Ok(())
+    }
+
+    /// Every log row for the card, live and archived, oldest first.
+    pub async fn load_logs(db: &SqlitePool, card_id: &str) -> Result<Vec<AgentLog>, KanbanError> {
+        let mut logs: Vec<AgentLog> =
+            sqlx::query_as("SELECT * FROM agent_logs WHERE card_id = ? ORDER BY created_at ASC")
+                .bind(card_id)
+                .fetch_all(db)
+                .await?;
+
+        let archives: Vec<(String,)> = sqlx::query_as(
+            "SELECT path FROM agent_log_archives WHERE card_id = ? ORDER BY first_at ASC",
+        )
+        .bind(card_id)
+        .fetch_all(db)
+        .await?;
+        if archives.is_empty() {
+            return Ok(logs);
+        }
+
+        let paths: Vec<String> = archives.into_iter().map(|(path,)| path).collect();
+        let archived = tokio::task::spawn_blocking(move || {
+            let mut rows = Vec::new();
+            for path in paths {
+                match read_archive(Path::new(&path)) {
+                    Ok(mut entries) => rows.append(&mut entries),
+                    Err(e) => tracing::warn!(path, error = %e, "Failed to read agent log archive"),
+                }
+            }
+            rows
+        })
+        .await
+        .map_err(|e| KanbanError::Internal(format!("Archive task failed: {}", e)))?;
+
+        logs.extend(archived);
+        logs.sort_by_key(|log| {
+            DateTime::parse_from_rfc3339(&log.created_at)
+                .map(|t| t.timestamp_nanos_opt().unwrap_or_default())
+                .unwrap_or_default()
+        });
+        Ok(logs)
+    }
+
+    /// History older than this was deliberately expired or archived for the
+    /// card, so backfills must not restore it.
+    pub async fn backfill_floor(
+        db: &SqlitePool,
+        card_id: &str,
+    ) -> Result<Option<DateTime<Utc>>, KanbanError> {
+        let archived: Option<String> =
+            sqlx::query_scalar("SELECT MAX(last_at) FROM agent_log_archives WHERE card_id = ?")
+                .bind(card_id)
+                .fetch_one(db)
+                .await?;
+        let retention_days: Option<i64> = sqlx::query_scalar(
+            "SELECT s.log_retention_days FROM board_settings s JOIN cards c ON c.board_id = s.board_id WHERE c.id = ?",
+        )
+        .bind(card_id)
+        .fetch_optional(db)
+        .await?;
+
+        let archived = archived
+            .and_then(|at| DateTime::parse_from_rfc3339(&at).ok())
+            .map(|at| at.with_timezone(&Utc));
+        let expired = retention_days
+            .filter(|days| *days > 0)
+            .map(|days| Utc::now() - chrono::Duration::days(days));
+        Ok(archived.max(expired))
+    }
+
+    pub fn to_jsonl(logs: &[AgentLog]) -> String {
+        let mut out = String::new();
+        for log in logs {
+            if let Ok(line) = serde_json::to_string(log) {
+                out.push_str(&line);
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
+
+fn write_archive(path: &Path, logs: &[AgentLog]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = std::fs::File::create(path)?;
+    let mut encoder = GzEncoder::new(file, Compression::default());
+    for log in logs {
+        serde_json::to_writer(&mut encoder, log)?;
+        encoder.write_all(b"\n")?;
+    }
+    encoder.finish()?.sync_all()
+}
+
+fn read_archive(path: &Path) -> std::io::Result<Vec<AgentLog>> {
+    let file = std::fs::File::open(path)?;
+    let mut logs = Vec::new();
+    for line in BufReader::new(GzDecoder::new(file)).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        logs.push(serde_json::from_str(&line)?);
+    }
+    Ok(logs)
+}
+
+fn summarize(logs: &[AgentLog]) -> String {
+    let mut agents: BTreeMap<&str, usize> = BTreeMap::new();
+    for log in logs {
+        if let Some(agent) = log.agent.as_deref() {
+            *agents.entry(agent).or_default() += 1;
+        }
+    }
+
+    let mut summary = format!("Compacted {} log entries", logs.len());
+    if let (Some(first), Some(last)) = (logs.first(), logs.last()) {
+        summary.push_str(&format!(" ({} – {})", first.created_at, last.created_at));
+    }
+    if !agents.is_empty() {
+        let agents: Vec<String> = agents
+            .into_iter()
+            .map(|(agent, count)| format!("{} ×{}", agent, count))
+            .collect();
+        summary.push_str(&format!("; agents: {}", agents.join(", ")));
+    }
+    if let Some(todos) = logs.iter().rev().find(|l| l.event_type == "todo.updated") {
+        summary.push_str(&format!("; last todos: {}", todos.content));
+    }
+    if let Some(finish) = logs.iter().rev().find(|l| l.event_type == "message.updated") {
+        summary.push_str(&format!("; last message: {}", finish.content));
+    }
+    summary
+}
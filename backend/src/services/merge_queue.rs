@@ -0,0 +1,483 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use crate::api::handlers::sse::WsEvent;
+use crate::domain::{Card, KanbanError, NotificationType};
+
+use super::{EventBus, GitWorktreeService, NotificationService, WorktreeHookService};
+
+/// Codebase paths with a merge underway, whether from the queue or from a
+/// manual merge left waiting on conflict resolution.
+pub type MergeLocks = Arc<Mutex<HashSet<String>>>;
+
+/// Finished entries kept in listings after the active ones.
+const RECENT_LIMIT: i64 = 20;
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+const COLUMNS: &str = "id, card_id, board_id, codebase_path, status, failure_reason, verification, merge_commit, enqueued_by, created_at, started_at, finished_at";
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct MergeQueueEntry {
+    pub id: String,
+    pub card_id: String,
+    pub board_id: Option<String>,
+    pub codebase_path: String,
+    /// `queued`, `rebasing` (merging the base into the card branch),
+    /// `verifying`, `merging`, `merged`, `failed` or `cancelled`.
+    pub status: String,
+    pub failure_reason: Option<String>,
+    /// JSON from the board's `verify` hooks, when it has any.
+    pub verification: Option<String>,
+    pub merge_commit: Option<String>,
+    pub enqueued_by: Option<String>,
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    /// 1-based place among the repository's active entries; the entry being
+    /// merged is 1. `None` once finished.
+    #[sqlx(skip)]
+    pub position: Option<i64>,
+}
+
+/// Approved cards waiting to land, one queue per repository.
+pub struct MergeQueueService;
+
+impl MergeQueueService {
+    pub async fn enqueue(
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+        card: &Card,
+        codebase_path: &str,
+        user_id: Option<&str>,
+    ) -> Result<MergeQueueEntry, KanbanError> {
+        if card.stage != "review" {
+            return Err(KanbanError::BadRequest(
+                "Card must be in review stage to queue a merge".into(),
+            ));
+        }
+        if card.branch_name.is_empty() {
+            return Err(KanbanError::BadRequest("Card has no git branch".into()));
+        }
+        if card.worktree_path.is_empty() || !Path::new(&card.worktree_path).exists() {
+            return Err(KanbanError::BadRequest(
+                "Card has no worktree to update and verify in".into(),
+            ));
+        }
+        if Self::active_for_card(db, &card.id).await?.is_some() {
+            return Err(KanbanError::Conflict("Card is already in the merge queue".into()));
+        }
+
+        let id = Uuid::new_v4().to_string();
+        sqlx::query(
+            "INSERT INTO merge_queue (id, card_id, board_id, codebase_path, enqueued_by, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&id)
+        .bind(&card.id)
+        .bind(&card.board_id)
+        .bind(codebase_path)
+        .bind(user_id)
+        .bind(Utc::now().to_rfc3339())
+        .execute(db)
+        .await?;
+        tracing::info!(card_id = %card.id, codebase_path, "Card queued for merge");
+
+        broadcast_repo(db, sse_tx, codebase_path).await;
+        Self::get(db, &id).await
+    }
+
+    /// Takes a card out of the queue before its merge has started.
+    pub async fn cancel(
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+        card_id: &str,
+    ) -> Result<MergeQueueEntry, KanbanError> {
+        let cancelled: Option<MergeQueueEntry> = sqlx::query_as(&format!(
+            "UPDATE merge_queue SET status = 'cancelled', finished_at = ? WHERE card_id = ? AND status = 'queued' RETURNING {}",
+            COLUMNS
+        ))
+        .bind(Utc::now().to_rfc3339())
+        .bind(card_id)
+        .fetch_optional(db)
+        .await?;
+        let Some(entry) = cancelled else {
+            return Err(match Self::active_for_card(db, card_id).await? {
+                Some(_) => KanbanError::Conflict("Card is already being merged".into()),
+                None => KanbanError::NotFound("Card is not in the merge queue".into()),
+            });
+        };
+
+        broadcast_repo(db, sse_tx, &entry.codebase_path).await;
+        Ok(entry)
+    }
+
+    pub async fn active_for_card(
+        db: &SqlitePool,
+        card_id: &str,
+    ) -> Result<Option<MergeQueueEntry>, KanbanError> {
+        let entry = sqlx::query_as(&format!(
+            "SELECT {} FROM merge_queue WHERE card_id = ? AND status IN ('queued', 'rebasing', 'verifying', 'merging')",
+            COLUMNS
+        ))
+        .bind(card_id)
+        .fetch_optional(db)
+        .await?;
+        Ok(entry)
+    }
+
+    /// Active entries in merge order, then the most recently finished.
+    /// The board's entries in the repository's queue. Positions count every
+    /// board sharing the repository, since they merge in one order.
+    pub async fn list_for_board(
+        db: &SqlitePool,
+        codebase_path: &str,
+        board_id: &str,
+    ) -> Result<Vec<MergeQueueEntry>, KanbanError> {
+        let active: Vec<MergeQueueEntry> = sqlx::query_as(&format!(
+            "SELECT {} FROM merge_queue WHERE codebase_path = ? AND status IN ('queued', 'rebasing', 'verifying', 'merging') ORDER BY status = 'queued', created_at ASC",
+            COLUMNS
+        ))
+        .bind(codebase_path)
+        .fetch_all(db)
+        .await?;
+        let mut entries: Vec<MergeQueueEntry> = active
+            .into_iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.board_id.as_deref() == Some(board_id))
+            .map(|(index, mut entry)| {
+                entry.position = Some(index as i64 + 1);
+                entry
+            })
+            .collect();
+        let recent: Vec<MergeQueueEntry> = sqlx::query_as(&format!(
+            "SELECT {} FROM merge_queue WHERE codebase_path = ? AND board_id = ? AND status NOT IN ('queued', 'rebasing', 'verifying', 'merging') ORDER BY finished_at DESC LIMIT ?",
+            COLUMNS
+        ))
+        .bind(codebase_path)
+        .bind(board_id)
+        .bind(RECENT_LIMIT)
+        .fetch_all(db)
+        .await?;
+        entries.extend(recent);
+        Ok(entries)
+    }
+
+    async fn get(db: &SqlitePool, id: &str) -> Result<MergeQueueEntry, KanbanError> {
+        let entry: MergeQueueEntry = sqlx::query_as(&format!(
+            "SELECT {} FROM merge_queue WHERE id = ?",
+            COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(db)
+        .await?
+        .ok_or_else(|| KanbanError::NotFound(format!("Merge queue entry not found: {}", id)))?;
+        let Some(board_id) = entry.board_id.clone() else {
+            return Ok(entry);
+        };
+        let listed = Self::list_for_board(db, &entry.codebase_path, &board_id).await?;
+        Ok(listed.into_iter().find(|e| e.id == entry.id).unwrap_or(entry))
+    }
+
+    /// Removes the merged card's worktree and moves it to done.
+    pub async fn finish_merged_card(
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+        card: &Card,
+        codebase_path: &str,
+    ) -> Result<(), KanbanError> {
+        let _ = WorktreeHookService::remove_worktree(db, sse_tx, card, codebase_path).await;
+
+        sqlx::query("UPDATE cards SET stage = 'done', branch_name = '', worktree_path = '', working_directory = '.', updated_at = ? WHERE id = ?")
+            .bind(Utc::now().to_rfc3339())
+            .bind(&card.id)
+            .execute(db)
+            .await?;
+
+        broadcast(sse_tx, &WsEvent::MergeCompleted { card_id: card.id.clone() });
+        broadcast(
+            sse_tx,
+            &WsEvent::CardMoved {
+                card_id: card.id.clone(),
+                from_stage: "review".to_string(),
+                to_stage: "done".to_string(),
+            },
+        );
+        Ok(())
+    }
+}
+
+/// Drains each repository's queue in order: merge the current base into the
+/// card branch, run the board's verify hooks, merge, clean up, next card.
+/// Repositories drain in parallel; within one, the merge lock keeps manual
+/// merges and the queue from overlapping.
+pub struct MergeQueueProcessor {
+    pub db: SqlitePool,
+    pub sse_tx: EventBus,
+    pub merge_locks: MergeLocks,
+}
+
+impl MergeQueueProcessor {
+    pub async fn start(self) {
+        // Nothing holds a lock across restarts; entries caught mid-merge
+        // start over.
+        if let Err(e) = sqlx::query(
+            "UPDATE merge_queue SET status = 'queued', started_at = NULL WHERE status IN ('rebasing', 'verifying', 'merging')",
+        )
+        .execute(&self.db)
+        .await
+        {
+            tracing::warn!("Failed to requeue interrupted merges: {}", e);
+        }
+
+        loop {
+            if let Err(e) = self.process_repos().await {
+                tracing::warn!("Merge queue error: {}", e);
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    async fn process_repos(&self) -> Result<(), KanbanError> {
+        let repos: Vec<String> = sqlx::query_scalar(
+            "SELECT DISTINCT codebase_path FROM merge_queue WHERE status = 'queued'",
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        for repo in repos {
+            let Some(lock) = RepoLock::try_acquire(&self.merge_locks, &repo) else {
+                continue;
+            };
+            let (db, sse_tx) = (self.db.clone(), self.sse_tx.clone());
+            tokio::spawn(async move {
+                let _lock = lock;
+                if let Err(e) = drain(&db, &sse_tx, &repo).await {
+                    tracing::warn!(codebase_path = %repo, "Merge queue drain failed: {}", e);
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+async fn drain(db: &SqlitePool, sse_tx: &EventBus, codebase_path: &str) -> Result<(), KanbanError> {
+    loop {
+        let next: Option<MergeQueueEntry> = sqlx::query_as(&format!(
+            "SELECT {} FROM merge_queue WHERE codebase_path = ? AND status = 'queued' ORDER BY created_at ASC LIMIT 1",
+            COLUMNS
+        ))
+        .bind(codebase_path)
+        .fetch_optional(db)
+        .await?;
+        let Some(entry) = next else {
+            return Ok(());
+        };
+        if let Err(e) = land(db, sse_tx, &entry).await {
+            finish(db, sse_tx, &entry, "failed", Some(&e.to_string()), None).await?;
+            notify_failure(db, sse_tx, &entry, &e.to_string()).await;
+        }
+    }
+}
+
+/// Update from the base, verify, merge and clean up one card. Errors fail the entry and
+/// leave the card in review.
+async fn land(db: &SqlitePool, sse_tx: &EventBus, entry: &MergeQueueEntry) -> Result<(), KanbanError> {
+    let card: Option<Card> = sqlx::query_as("SELECT * FROM cards WHERE id = ?")
+        .bind(&entry.card_id)
+        .fetch_optional(db)
+        .await?;
+    let Some(card) = card.filter(|c| c.stage == "review" && !c.branch_name.is_empty()) else {
+        return finish(db, sse_tx, entry, "cancelled", Some("Card left review before it was merged"), None)
+            .await;
+    };
+    let repo = entry.codebase_path.as_str();
+    // Updating and verifying happen in the worktree; landing the branch
+    // without them would skip the queue's checks.
+    if card.worktree_path.is_empty() || !Path::new(&card.worktree_path).exists() {
+        return Err(KanbanError::BadRequest(
+            "Card has no worktree to update and verify in".into(),
+        ));
+    }
+
+    set_status(db, sse_tx, entry, "rebasing").await?;
+    let (repo_path, worktree_path, title) =
+        (repo.to_string(), card.worktree_path.clone(), card.title.clone());
+    GitWorktreeService::blocking(move || {
+        GitWorktreeService::update_from_base(&repo_path, &worktree_path, &title)
+    })
+    .await?;
+
+    set_status(db, sse_tx, entry, "verifying").await?;
+    if let Some(verification) = WorktreeHookService::run_verify(db, sse_tx, &card, repo).await? {
+        sqlx::query("UPDATE merge_queue SET verification = ? WHERE id = ?")
+            .bind(serde_json::to_string(&verification).unwrap_or_default())
+            .bind(&entry.id)
+            .execute(db)
+            .await?;
+        if !verification.passed {
+            let failed: Vec<&str> = verification
+                .steps
+                .iter()
+                .filter(|step| !step.passed)
+                .map(|step| step.step.as_str())
+                .collect();
+            return Err(KanbanError::BadRequest(format!(
+                "Verification failed: {}",
+                failed.join(", ")
+            )));
+        }
+    }
+
+    set_status(db, sse_tx, entry, "merging").await?;
+    let (repo_path, branch_name, worktree_path, title) = (
+        repo.to_string(),
+        card.branch_name.clone(),
+        card.worktree_path.clone(),
+        card.title.clone(),
+    );
+    let (result, commit) = GitWorktreeService::blocking(move || {
+        let result =
+            GitWorktreeService::merge_branch(&repo_path, &branch_name, false, &worktree_path, &title)?;
+        let base = GitWorktreeService::default_branch(&repo_path);
+        Ok((result, GitWorktreeService::branch_head(&repo_path, &base)))
+    })
+    .await?;
+    if !result.success {
+        return Err(KanbanError::Conflict(result.message));
+    }
+
+    MergeQueueService::finish_merged_card(db, sse_tx, &card, repo).await?;
+    finish(db, sse_tx, entry, "merged", None, commit.as_deref()).await
+}
+
+async fn set_status(
+    db: &SqlitePool,
+    sse_tx: &EventBus,
+    entry: &MergeQueueEntry,
+    status: &str,
+) -> Result<(), KanbanError> {
+    sqlx::query("UPDATE merge_queue SET status = ?, started_at = COALESCE(started_at, ?) WHERE id = ?")
+        .bind(status)
+        .bind(Utc::now().to_rfc3339())
+        .bind(&entry.id)
+        .execute(db)
+        .await?;
+    broadcast_repo(db, sse_tx, &entry.codebase_path).await;
+    Ok(())
+}
+
+async fn finish(
+    db: &SqlitePool,
+    sse_tx: &EventBus,
+    entry: &MergeQueueEntry,
+    status: &str,
+    failure_reason: Option<&str>,
+    merge_commit: Option<&str>,
+) -> Result<(), KanbanError> {
+    sqlx::query(
+        "UPDATE merge_queue SET status = ?, failure_reason = ?, merge_commit = ?, finished_at = ? WHERE id = ?",
+    )
+    .bind(status)
+    .bind(failure_reason)
+    .bind(merge_commit)
+    .bind(Utc::now().to_rfc3339())
+    .bind(&entry.id)
+    .execute(db)
+    .await?;
+    tracing::info!(card_id = %entry.card_id, status, failure_reason, "Merge queue entry finished");
+    broadcast_repo(db, sse_tx, &entry.codebase_path).await;
+    Ok(())
+}
+
+async fn notify_failure(db: &SqlitePool, sse_tx: &EventBus, entry: &MergeQueueEntry, reason: &str) {
+    let title: String = sqlx::query_scalar("SELECT title FROM cards WHERE id = ?")
+        .bind(&entry.card_id)
+        .fetch_optional(db)
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    let _ = NotificationService::create_notification(
+        db,
+        sse_tx,
+        None,
+        NotificationType::MergeFailed,
+        &format!("Merge failed: {}", title),
+        &format!("Card '{}' was taken out of the merge queue: {}", title, reason),
+        Some(&entry.card_id),
+        entry.board_id.as_deref(),
+    )
+    .await;
+}
+
+/// Holds a repository's merge lock until dropped.
+struct RepoLock {
+    locks: MergeLocks,
+    codebase_path: String,
+}
+
+impl RepoLock {
+    fn try_acquire(locks: &MergeLocks, codebase_path: &str) -> Option<Self> {
+        let mut held = locks.lock().ok()?;
+        if !held.insert(codebase_path.to_string()) {
+            return None;
+        }
+        Some(Self {
+            locks: Arc::clone(locks),
+            codebase_path: codebase_path.to_string(),
+        })
+    }
+}
+
+impl Drop for RepoLock {
+    fn drop(&mut self) {
+        if let Ok(mut held) = self.locks.lock() {
+            held.remove(&self.codebase_path);
+        }
+    }
+}
+
+/// One event per board on the repository, each with only that board's
+/// entries, so members of one board never see another's queue.
+async fn broadcast_repo(db: &SqlitePool, sse_tx: &EventBus, codebase_path: &str) {
+    let boards: Vec<String> = match sqlx::query_scalar(
+        "SELECT board_id FROM board_settings WHERE codebase_path = ?
+         UNION SELECT board_id FROM merge_queue WHERE codebase_path = ? AND board_id IS NOT NULL",
+    )
+    .bind(codebase_path)
+    .bind(codebase_path)
+    .fetch_all(db)
+    .await
+    {
+        Ok(boards) => boards,
+        Err(e) => {
+            tracing::warn!(codebase_path, "Failed to load merge queue boards for broadcast: {}", e);
+            return;
+        }
+    };
+    for board_id in boards {
+        match MergeQueueService::list_for_board(db, codebase_path, &board_id).await {
+            Ok(entries) => broadcast(
+                sse_tx,
+                &WsEvent::MergeQueueUpdated {
+                    board_id,
+                    codebase_path: codebase_path.to_string(),
+                    entries: serde_json::to_value(entries).unwrap_or_default(),
+                },
+            ),
+            Err(e) => tracing::warn!(codebase_path, board_id, "Failed to load merge queue for broadcast: {}", e),
+        }
+    }
+}
+
+fn broadcast(sse_tx: &EventBus, event: &WsEvent) {
+    if let Ok(payload) = serde_json::to_string(event) {
+        let _ = sse_tx.send(payload);
+    }
+}
@@ -5,8 +5,30 @@ pub mod queue_processor;
 pub mod sse_relay;
 pub mod git_worktree;
 pub mod opencode_manager;
+pub mod opencode_pool;
+pub mod opencode_supervisor;
 pub mod notification_service;
 pub mod session_mapping;
+pub mod prompt_templates;
+pub mod context_assembler;
+pub mod event_bus;
+pub mod board_access;
+pub mod status_coalescer;
+pub mod transcript;
+pub mod log_backfill;
+pub mod log_retention;
+pub mod redaction;
+pub mod review_threads;
+pub mod board_secrets;
+pub mod board_variables;
+pub mod worktree_hooks;
+pub mod card_runs;
+pub mod card_attempts;
+pub mod model_selection;
+pub mod ai_review;
+pub mod conflict_resolution;
+pub mod merge_queue;
+pub mod git;
 
 pub use card_service::CardService;
 pub use plan_generator::PlanGenerator;
@@ -15,5 +37,26 @@ pub use queue_processor::QueueProcessor;
 pub use sse_relay::SseRelayService;
 pub use git_worktree::GitWorktreeService;
 pub use opencode_manager::OpencodeManager;
+pub use opencode_pool::OpencodePool;
+pub use opencode_supervisor::OpencodeSupervisor;
 pub use notification_service::NotificationService;
 pub use session_mapping::SessionMappingService;
+pub use prompt_templates::PromptTemplateService;
+pub use context_assembler::ContextAssembler;
+pub use event_bus::EventBus;
+pub use board_access::BoardAccess;
+pub use status_coalescer::StatusCoalescer;
+pub use transcript::TranscriptService;
+pub use log_backfill::LogBackfillService;
+pub use log_retention::LogRetentionService;
+pub use redaction::{Redactor, RedactorCache};
+pub use review_threads::ReviewThreadService;
+pub use board_secrets::BoardSecretService;
+pub use board_variables::BoardVariableService;
+pub use worktree_hooks::WorktreeHookService;
+pub use card_runs::CardRunService;
+pub use card_attempts::CardAttemptService;
+pub use model_selection::ModelSelection;
+pub use ai_review::AiReviewService;
+pub use conflict_resolution::ConflictResolutionService;
+pub use merge_queue::{MergeQueueProcessor, MergeQueueService};
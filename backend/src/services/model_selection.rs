@@ -0,0 +1,212 @@
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::SqlitePool;
+
+use crate::domain::{Card, KanbanError};
+
+/// What a prompt is for; boards keep a default model for each.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelPurpose {
+    Planning,
+    Implementation,
+    AutoDetect,
+    Review,
+}
+
+impl ModelPurpose {
+    /// Follow-up prompts on a card's session: planning while the card is
+    /// still in `plan`, implementation afterwards.
+    pub fn for_stage(stage: &str) -> Self {
+        if stage == "plan" {
+            Self::Planning
+        } else {
+            Self::Implementation
+        }
+    }
+
+    fn board_column(self) -> &'static str {
+        match self {
+            Self::Planning => "planning_model",
+            Self::Implementation => "implementation_model",
+            Self::AutoDetect => "auto_detect_model",
+            Self::Review => "review_model",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AvailableModel {
+    /// `provider/model`, the form stored in settings and on cards.
+    pub id: String,
+    pub provider_id: String,
+    pub provider_name: String,
+    pub model_id: String,
+    pub name: String,
+    /// OpenCode's default model for this provider.
+    pub is_default: bool,
+}
+
+/// Resolves which model a prompt is sent with and checks model ids against
+/// what OpenCode advertises. `None` leaves the choice to OpenCode.
+pub struct ModelSelection;
+
+impl ModelSelection {
+    /// The card's override for `purpose`, falling back to its board default.
+    pub async fn for_card(
+        db: &SqlitePool,
+        card: &Card,
+        purpose: ModelPurpose,
+    ) -> Result<Option<String>, KanbanError> {
+        let card_override = match purpose {
+            ModelPurpose::Planning => card.planning_model.as_deref(),
+            ModelPurpose::Implementation => card.implementation_model.as_deref(),
+            ModelPurpose::AutoDetect | ModelPurpose::Review => None,
+        };
+        if let Some(model) = card_override.map(str::trim).filter(|m| !m.is_empty()) {
+            return Ok(Some(model.to_string()));
+        }
+
+        match card.board_id.as_deref().filter(|id| !id.is_empty()) {
+            Some(board_id) => Self::for_board(db, board_id, purpose).await,
+            None => Ok(None),
+        }
+    }
+
+    pub async fn for_board(
+        db: &SqlitePool,
+        board_id: &str,
+        purpose: ModelPurpose,
+    ) -> Result<Option<String>, KanbanError> {
+        let model: Option<String> = sqlx::query_scalar(&format!(
+            "SELECT {} FROM board_settings WHERE board_id = ?",
+            purpose.board_column()
+        ))
+        .bind(board_id)
+        .fetch_optional(db)
+        .await?;
+
+        Ok(model
+            .map(|m| m.trim().to_string())
+            .filter(|m| !m.is_empty()))
+    }
+
+    /// Models from `GET /config/providers` on the given endpoint.
+    pub async fn available(
+        http_client: &reqwest::Client,
+        opencode_url: &str,
+    ) -> Result<Vec<AvailableModel>, KanbanError> {
+        let response = http_client
+            .get(format!("{}/config/providers", opencode_url))
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .map_err(|e| KanbanError::OpenCodeError(format!("Failed to list OpenCode models: {}", e)))?;
+
+        if !response.status().is_success() {
+            return Err(KanbanError::OpenCodeError(format!(
+                "OpenCode model listing failed with status {}",
+                response.status()
+            )));
+        }
+
+        let body = response.json::<Value>().await.map_err(|e| {
+            KanbanError::OpenCodeError(format!("Failed to decode OpenCode providers: {}", e))
+        })?;
+
+        Ok(parse_providers(&body))
+    }
+
+    /// Rejects ids that are not `provider/model` or that the endpoint does
+    /// not advertise. Empty ids clear a choice and always pass; the endpoint
+    /// is only asked when there is something to check.
+    pub async fn validate(
+        http_client: &reqwest::Client,
+        opencode_url: &str,
+        models: &[&str],
+    ) -> Result<(), KanbanError> {
+        let models: Vec<&str> = models
+            .iter()
+            .map(|m| m.trim())
+            .filter(|m| !m.is_empty())
+            .collect();
+        if models.is_empty() {
+            return Ok(());
+        }
+
+        for model in &models {
+            if split_model(model).is_none() {
+                return Err(KanbanError::BadRequest(format!(
+                    "Invalid model '{}': use provider/model",
+                    model
+                )));
+            }
+        }
+
+        let available = Self::available(http_client, opencode_url).await?;
+        for model in models {
+            if !available.iter().any(|m| m.id == model) {
+                return Err(KanbanError::BadRequest(format!(
+                    "Model '{}' is not offered by OpenCode",
+                    model
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// `provider/model` split into OpenCode's `(providerID, modelID)`.
+pub fn split_model(model: &str) -> Option<(&str, &str)> {
+    let (provider, model) = model.trim().split_once('/')?;
+    (!provider.is_empty() && !model.is_empty()).then_some((provider, model))
+}
+
+/// `{"providers": [{"id", "name", "models": {id: {"name"}}}], "default": {provider: model}}`
+fn parse_providers(body: &Value) -> Vec<AvailableModel> {
+    let defaults = body.get("default");
+    let mut models = Vec::new();
+
+    for provider in body
+        .get("providers")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let Some(provider_id) = provider.get("id").and_then(Value::as_str) else {
+            continue;
+        };
+        let provider_name = provider
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or(provider_id);
+        let default_model = defaults
+            .and_then(|d| d.get(provider_id))
+            .and_then(Value::as_str);
+
+        for (model_id, model) in provider
+            .get("models")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+        {
+            models.push(AvailableModel {
+                id: format!("{}/{}", provider_id, model_id),
+                provider_id: provider_id.to_string(),
+                provider_name: provider_name.to_string(),
+                model_id: model_id.clone(),
+                name: model
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .unwrap_or(model_id)
+                    .to_string(),
+                is_default: default_model == Some(model_id.as_str()),
+            });
+        }
+    }
+
+    models.sort_by(|a, b| a.id.cmp(&b.id));
+    models
+}
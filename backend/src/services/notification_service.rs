@@ -1,17 +1,18 @@
 use chrono::Utc;
 use sqlx::SqlitePool;
-use tokio::sync::broadcast;
 use uuid::Uuid;
 
 use crate::api::handlers::sse::WsEvent;
 use crate::domain::{KanbanError, Notification, NotificationType};
+use crate::services::EventBus;
 
 pub struct NotificationService;
 
 impl NotificationService {
+    #[allow(clippy::too_many_arguments)]
     pub async fn create_notification(
         pool: &SqlitePool,
-        sse_tx: &broadcast::Sender<String>,
+        sse_tx: &EventBus,
         user_id: Option<&str>,
         notification_type: NotificationType,
         title: &str,
@@ -1,8 +1,9 @@
 use std::process::Stdio;
 use std::time::Duration;
 
-use tokio::process::Command;
+use tokio::process::{Child, Command};
 
+#[derive(Debug, Clone)]
 pub struct OpencodeManager {
     url: String,
     port: u16,
@@ -33,40 +34,44 @@ impl OpencodeManager {
         }
     }
 
-    pub async fn ensure_running(&self) -> OpencodeStatus {
-        if self.is_healthy().await {
-            tracing::info!(url = self.url.as_str(), "opencode server already running");
-            return OpencodeStatus::AlreadyRunning;
-        }
+    /// Only endpoints on this machine can be auto-started.
+    pub fn is_local(&self) -> bool {
+        let host = self
+            .url
+            .split("://")
+            .nth(1)
+            .unwrap_or(&self.url)
+            .rsplit_once(':')
+            .map(|(host, _)| host)
+            .unwrap_or_default();
+        matches!(host, "localhost" | "127.0.0.1" | "0.0.0.0" | "[::1]")
+    }
 
-        tracing::info!("opencode not reachable, attempting auto-start...");
+    pub fn url(&self) -> &str {
+        &self.url
+    }
 
-        let binary_path = match self.find_binary().await {
-            Some(path) => {
-                tracing::info!(path = path.as_str(), "Found opencode binary");
-                path
-            }
-            None => {
-                tracing::info!("opencode not installed, attempting installation...");
-                match self.install().await {
-                    Ok(path) => {
-                        tracing::info!(path = path.as_str(), "opencode installed successfully");
-                        path
-                    }
-                    Err(e) => {
-                        return OpencodeStatus::Failed(format!("Failed to install opencode: {e}"));
-                    }
-                }
-            }
-        };
+    pub fn port(&self) -> u16 {
+        self.port
+    }
 
-        match self.start_server(&binary_path).await {
-            Ok(()) => OpencodeStatus::Started,
-            Err(e) => OpencodeStatus::Failed(format!("Failed to start opencode serve: {e}")),
+    /// Finds the `opencode` binary, installing it when it is missing.
+    pub async fn locate_binary(&self) -> Result<String, String> {
+        if let Some(path) = self.find_binary().await {
+            tracing::info!(path = path.as_str(), "Found opencode binary");
+            return Ok(path);
         }
+
+        tracing::info!("opencode not installed, attempting installation...");
+        let path = self
+            .install()
+            .await
+            .map_err(|e| format!("Failed to install opencode: {e}"))?;
+        tracing::info!(path = path.as_str(), "opencode installed successfully");
+        Ok(path)
     }
 
-    async fn is_healthy(&self) -> bool {
+    pub async fn is_healthy(&self) -> bool {
         self.http_client
             .get(format!("{}/health", self.url))
             .send()
@@ -130,7 +135,9 @@ impl OpencodeManager {
             .ok_or_else(|| "opencode binary not found after installation".into())
     }
 
-    async fn start_server(&self, binary_path: &str) -> Result<(), String> {
+    /// Spawns `opencode serve` with stdout and stderr piped so the caller
+    /// can capture them. The child is killed if its handle is dropped.
+    pub fn spawn_server(&self, binary_path: &str) -> Result<Child, String> {
         tracing::info!(
             port = self.port,
             binary = binary_path,
@@ -143,20 +150,24 @@ impl OpencodeManager {
             .arg(self.port.to_string())
             .arg("--hostname")
             .arg("127.0.0.1")
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
             .stdin(Stdio::null())
-            .kill_on_drop(false)
+            .kill_on_drop(true)
             .spawn()
             .map_err(|e| format!("Failed to spawn opencode serve: {e}"))?;
 
-        let pid = child.id().unwrap_or(0);
-        tracing::info!(pid = pid, port = self.port, "opencode serve process spawned");
+        tracing::info!(
+            pid = child.id().unwrap_or(0),
+            port = self.port,
+            "opencode serve process spawned"
+        );
 
-        // Detach — we don't await the child; it runs as a background daemon
-        std::mem::forget(child);
+        Ok(child)
+    }
 
-        // Poll for health with exponential backoff
+    /// Polls `/health` with exponential backoff until the server answers.
+    pub async fn wait_until_healthy(&self) -> bool {
         let mut delay_ms = 200;
         for attempt in 1..=15 {
             tokio::time::sleep(Duration::from_millis(delay_ms)).await;
@@ -167,7 +178,7 @@ impl OpencodeManager {
                     url = self.url.as_str(),
                     "opencode serve is healthy"
                 );
-                return Ok(());
+                return true;
             }
 
             tracing::debug!(
@@ -178,8 +189,6 @@ impl OpencodeManager {
             delay_ms = (delay_ms * 2).min(5000);
         }
 
-        Err(format!(
-            "opencode serve started (pid {pid}) but health check failed after 15 attempts"
-        ))
+        false
     }
 }
@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use crate::config::Config;
+use crate::domain::{Card, KanbanError};
+
+use super::opencode_supervisor::{OpencodeSupervisor, ProcessStatus};
+
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// AI statuses that hold a session open on an endpoint.
+const ACTIVE_STATUSES: &str = "'planning', 'dispatched', 'working', 'waiting', 'waiting_input'";
+
+#[derive(Debug)]
+struct Endpoint {
+    url: String,
+    healthy: AtomicBool,
+    last_error: Mutex<Option<String>>,
+    supervisor: OnceLock<OpencodeSupervisor>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointStatus {
+    pub url: String,
+    pub healthy: bool,
+    pub active_cards: i64,
+    pub last_error: Option<String>,
+    /// Present when the backend launched and supervises this server.
+    pub process: Option<ProcessStatus>,
+}
+
+/// The set of OpenCode servers the backend can dispatch to. New sessions go to
+/// the healthy endpoint with the fewest active cards; a card keeps talking to
+/// the endpoint that owns its session (`cards.opencode_url`).
+#[derive(Debug, Clone)]
+pub struct OpencodePool {
+    endpoints: Arc<Vec<Endpoint>>,
+    http_client: reqwest::Client,
+}
+
+impl OpencodePool {
+    pub fn new(urls: Vec<String>, http_client: reqwest::Client) -> Self {
+        let mut endpoints: Vec<Endpoint> = Vec::new();
+        for url in urls {
+            let url = url.trim().trim_end_matches('/').to_string();
+            if url.is_empty() || endpoints.iter().any(|e| e.url == url) {
+                continue;
+            }
+            endpoints.push(Endpoint {
+                url,
+                healthy: AtomicBool::new(true),
+                last_error: Mutex::new(None),
+                supervisor: OnceLock::new(),
+            });
+        }
+
+        if endpoints.is_empty() {
+            endpoints.push(Endpoint {
+                url: Config::default().opencode_url,
+                healthy: AtomicBool::new(true),
+                last_error: Mutex::new(None),
+                supervisor: OnceLock::new(),
+            });
+        }
+
+        Self {
+            endpoints: Arc::new(endpoints),
+            http_client,
+        }
+    }
+
+    pub fn from_config(config: &Config, http_client: reqwest::Client) -> Self {
+        Self::new(config.opencode_endpoints(), http_client)
+    }
+
+    pub fn urls(&self) -> Vec<String> {
+        self.endpoints.iter().map(|e| e.url.clone()).collect()
+    }
+
+    pub fn primary(&self) -> &str {
+        &self.endpoints[0].url
+    }
+
+    pub fn contains(&self, url: &str) -> bool {
+        let url = url.trim_end_matches('/');
+        self.endpoints.iter().any(|e| e.url == url)
+    }
+
+    /// The endpoint owning the card's session. Cards dispatched before the
+    /// pool existed have no affinity and were always on the primary endpoint.
+    pub fn endpoint_for_card(&self, card: &Card) -> String {
+        let affinity = card.opencode_url.trim_end_matches('/');
+        if !affinity.is_empty() && self.contains(affinity) {
+            affinity.to_string()
+        } else {
+            self.primary().to_string()
+        }
+    }
+
+    /// Endpoint for a work dispatch: reuse the planning session's endpoint when
+    /// the card still has one, otherwise pick the least-loaded endpoint.
+    pub async fn endpoint_for_dispatch(
+        &self,
+        db: &SqlitePool,
+        card: &Card,
+    ) -> Result<String, KanbanError> {
+        if card.ai_session_id.as_deref().is_some_and(|s| !s.is_empty()) {
+            return Ok(self.endpoint_for_card(card));
+        }
+        self.select_least_loaded(db).await
+    }
+
+    pub async fn select_least_loaded(&self, db: &SqlitePool) -> Result<String, KanbanError> {
+        if self.endpoints.len() == 1 {
+            return Ok(self.primary().to_string());
+        }
+
+        let loads = self.active_counts(db).await?;
+        let healthy: Vec<&Endpoint> = self
+            .endpoints
+            .iter()
+            .filter(|e| e.healthy.load(Ordering::Relaxed))
+            .collect();
+        let candidates: Vec<&Endpoint> = if healthy.is_empty() {
+            self.endpoints.iter().collect()
+        } else {
+            healthy
+        };
+
+        let selected = candidates
+            .into_iter()
+            .min_by_key(|e| loads.get(&e.url).copied().unwrap_or(0))
+            .map(|e| e.url.clone())
+            .unwrap_or_else(|| self.primary().to_string());
+
+        Ok(selected)
+    }
+
+    pub async fn statuses(&self, db: Option<&SqlitePool>) -> Vec<EndpointStatus> {
+        let loads = match db {
+            Some(db) => self.active_counts(db).await.unwrap_or_default(),
+            None => HashMap::new(),
+        };
+
+        let mut statuses = Vec::with_capacity(self.endpoints.len());
+        for e in self.endpoints.iter() {
+            let process = match e.supervisor.get() {
+                Some(supervisor) => Some(supervisor.status().await),
+                None => None,
+            };
+            statuses.push(EndpointStatus {
+                url: e.url.clone(),
+                healthy: e.healthy.load(Ordering::Relaxed),
+                active_cards: loads.get(&e.url).copied().unwrap_or(0),
+                last_error: e.last_error.lock().ok().and_then(|err| err.clone()),
+                process,
+            });
+        }
+        statuses
+    }
+
+    /// Registers the supervisor managing the local server behind `url`.
+    pub fn attach_supervisor(&self, supervisor: OpencodeSupervisor) {
+        let url = supervisor.url().trim_end_matches('/');
+        if let Some(endpoint) = self.endpoints.iter().find(|e| e.url == url) {
+            let _ = endpoint.supervisor.set(supervisor);
+        }
+    }
+
+    pub fn supervisor_for(&self, url: &str) -> Option<&OpencodeSupervisor> {
+        let url = url.trim_end_matches('/');
+        self.endpoints
+            .iter()
+            .find(|e| e.url == url)
+            .and_then(|e| e.supervisor.get())
+    }
+
+    /// Stops every server this backend launched.
+    pub async fn shutdown_processes(&self) {
+        for endpoint in self.endpoints.iter() {
+            if let Some(supervisor) = endpoint.supervisor.get() {
+                supervisor.shutdown().await;
+            }
+        }
+    }
+
+    pub async fn refresh_health(&self) {
+        for endpoint in self.endpoints.iter() {
+            let result = self
+                .http_client
+                .get(format!("{}/health", endpoint.url))
+                .timeout(Duration::from_secs(5))
+                .send()
+                .await;
+
+            let error = match result {
+                Ok(resp) if resp.status().is_success() => None,
+                Ok(resp) => Some(format!("health check returned {}", resp.status())),
+                Err(e) => Some(e.to_string()),
+            };
+
+            let was_healthy = endpoint.healthy.swap(error.is_none(), Ordering::Relaxed);
+            match (&error, was_healthy) {
+                (Some(err), true) => {
+                    tracing::warn!(url = endpoint.url.as_str(), error = err.as_str(), "OpenCode endpoint became unhealthy");
+                }
+                (None, false) => {
+                    tracing::info!(url = endpoint.url.as_str(), "OpenCode endpoint recovered");
+                }
+                _ => {}
+            }
+
+            if let Ok(mut last_error) = endpoint.last_error.lock() {
+                *last_error = error;
+            }
+        }
+    }
+
+    pub async fn start_health_monitor(self) {
+        loop {
+            self.refresh_health().await;
+            tokio::time::sleep(HEALTH_CHECK_INTERVAL).await;
+        }
+    }
+
+    async fn active_counts(&self, db: &SqlitePool) -> Result<HashMap<String, i64>, KanbanError> {
+        let rows: Vec<(String, i64)> = sqlx::query_as(&format!(
+            "SELECT opencode_url, COUNT(*) FROM cards WHERE ai_status IN ({}) GROUP BY opencode_url",
+            ACTIVE_STATUSES
+        ))
+        .fetch_all(db)
+        .await?;
+
+        let mut loads = HashMap::new();
+        for (url, count) in rows {
+            let url = url.trim_end_matches('/');
+            let url = if url.is_empty() { self.primary() } else { url };
+            *loads.entry(url.to_string()).or_insert(0) += count;
+        }
+
+        Ok(loads)
+    }
+}
@@ -0,0 +1,428 @@
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Child;
+
+use super::opencode_manager::{OpencodeManager, OpencodeStatus};
+
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+/// Consecutive failed health checks before a managed process is restarted.
+const UNHEALTHY_THRESHOLD: u32 = 3;
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(60);
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+const LOG_MAX_BYTES: u64 = 5 * 1024 * 1024;
+const LOG_KEEP_FILES: usize = 3;
+const LOG_BUFFER_LINES: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessState {
+    Starting,
+    Running,
+    Restarting,
+    Stopped,
+    Failed,
+    /// Something else already serves the endpoint; we only observe it.
+    External,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessStatus {
+    pub url: String,
+    pub state: ProcessState,
+    pub pid: Option<u32>,
+    pub started_at: Option<String>,
+    pub uptime_seconds: Option<i64>,
+    pub restarts: u32,
+    pub last_error: Option<String>,
+    pub log_file: Option<String>,
+}
+
+/// Size-capped log file that keeps `keep` rotated generations
+/// (`name.log.1` is the most recent).
+pub struct RotatingLogFile {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    file: Option<File>,
+    size: u64,
+}
+
+impl RotatingLogFile {
+    pub fn new(path: impl Into<PathBuf>, max_bytes: u64, keep: usize) -> Self {
+        Self {
+            path: path.into(),
+            max_bytes,
+            keep,
+            file: None,
+            size: 0,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.file.is_none() {
+            self.open()?;
+        }
+        if self.size > 0 && self.size + len > self.max_bytes {
+            self.rotate()?;
+        }
+
+        if let Some(file) = self.file.as_mut() {
+            writeln!(file, "{}", line)?;
+            self.size += len;
+        }
+        Ok(())
+    }
+
+    fn open(&mut self) -> std::io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = file.metadata()?.len();
+        self.file = Some(file);
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file = None;
+
+        if self.keep == 0 {
+            let _ = fs::remove_file(&self.path);
+        } else {
+            let generation = |n: usize| PathBuf::from(format!("{}.{}", self.path.display(), n));
+            let _ = fs::remove_file(generation(self.keep));
+            for n in (1..self.keep).rev() {
+                let from = generation(n);
+                if from.exists() {
+                    fs::rename(&from, generation(n + 1))?;
+                }
+            }
+            fs::rename(&self.path, generation(1))?;
+        }
+
+        self.open()
+    }
+}
+
+struct ProcessRecord {
+    child: Option<Child>,
+    state: ProcessState,
+    pid: Option<u32>,
+    started_at: Option<DateTime<Utc>>,
+    restarts: u32,
+    last_error: Option<String>,
+    shutting_down: bool,
+}
+
+struct SupervisorInner {
+    manager: OpencodeManager,
+    record: tokio::sync::Mutex<ProcessRecord>,
+    log_file: Arc<Mutex<RotatingLogFile>>,
+    log_buffer: Arc<Mutex<VecDeque<String>>>,
+}
+
+/// Owns a local `opencode serve` child: captures its output, restarts it with
+/// backoff when it dies or stops answering health checks, and terminates it on
+/// shutdown.
+#[derive(Clone)]
+pub struct OpencodeSupervisor {
+    inner: Arc<SupervisorInner>,
+}
+
+impl std::fmt::Debug for OpencodeSupervisor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OpencodeSupervisor")
+            .field("url", &self.url())
+            .finish_non_exhaustive()
+    }
+}
+
+impl OpencodeSupervisor {
+    pub fn new(manager: OpencodeManager, log_dir: &str) -> Self {
+        let log_path = Path::new(log_dir).join(format!("opencode-{}.log", manager.port()));
+        Self {
+            inner: Arc::new(SupervisorInner {
+                manager,
+                record: tokio::sync::Mutex::new(ProcessRecord {
+                    child: None,
+                    state: ProcessState::Stopped,
+                    pid: None,
+                    started_at: None,
+                    restarts: 0,
+                    last_error: None,
+                    shutting_down: false,
+                }),
+                log_file: Arc::new(Mutex::new(RotatingLogFile::new(
+                    log_path,
+                    LOG_MAX_BYTES,
+                    LOG_KEEP_FILES,
+                ))),
+                log_buffer: Arc::new(Mutex::new(VecDeque::with_capacity(LOG_BUFFER_LINES))),
+            }),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        self.inner.manager.url()
+    }
+
+    /// Adopts an already running server or starts a managed one.
+    pub async fn start(&self) -> OpencodeStatus {
+        if self.inner.manager.is_healthy().await {
+            tracing::info!(url = self.url(), "opencode server already running");
+            self.inner.record.lock().await.state = ProcessState::External;
+            return OpencodeStatus::AlreadyRunning;
+        }
+
+        tracing::info!(url = self.url(), "opencode not reachable, attempting auto-start...");
+        match self.spawn_and_wait().await {
+            Ok(()) => OpencodeStatus::Started,
+            Err(e) => OpencodeStatus::Failed(e),
+        }
+    }
+
+    /// Health-checks the process forever, restarting managed children that
+    /// exit or stay unhealthy.
+    pub async fn supervise(self) {
+        let mut failures = 0;
+        let mut backoff = Duration::from_secs(1);
+
+        loop {
+            tokio::time::sleep(HEALTH_CHECK_INTERVAL).await;
+
+            let (state, exited) = {
+                let mut record = self.inner.record.lock().await;
+                if record.shutting_down {
+                    return;
+                }
+                // Adopted servers are not ours to restart. A failed first
+                // start is retried below like a crash.
+                if record.state == ProcessState::External {
+                    continue;
+                }
+                let exited = match record.child.as_mut().map(Child::try_wait) {
+                    Some(Ok(Some(status))) => Some(status.to_string()),
+                    Some(Err(e)) => Some(e.to_string()),
+                    _ => None,
+                };
+                (record.state, exited)
+            };
+
+            let reason = if let Some(status) = exited {
+                Some(format!("opencode serve exited ({})", status))
+            } else if self.inner.manager.is_healthy().await {
+                failures = 0;
+                backoff = Duration::from_secs(1);
+                let mut record = self.inner.record.lock().await;
+                if record.child.is_none() {
+                    // Someone started a server by hand after ours failed.
+                    record.state = ProcessState::External;
+                } else if record.state != ProcessState::Running {
+                    record.state = ProcessState::Running;
+                }
+                None
+            } else {
+                failures += 1;
+                (failures >= UNHEALTHY_THRESHOLD || state == ProcessState::Failed)
+                    .then(|| format!("{} consecutive health checks failed", failures))
+            };
+
+            let Some(reason) = reason else {
+                continue;
+            };
+
+            tracing::warn!(
+                url = self.url(),
+                reason = reason.as_str(),
+                backoff_secs = backoff.as_secs(),
+                "Restarting opencode serve"
+            );
+            {
+                let mut record = self.inner.record.lock().await;
+                record.state = ProcessState::Restarting;
+                record.last_error = Some(reason);
+                record.restarts += 1;
+                if let Some(mut child) = record.child.take() {
+                    let _ = child.kill().await;
+                }
+                record.pid = None;
+            }
+
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_RESTART_BACKOFF);
+            failures = 0;
+
+            if let Err(e) = self.spawn_and_wait().await {
+                tracing::warn!(url = self.url(), error = e.as_str(), "opencode restart failed");
+            }
+        }
+    }
+
+    /// Terminates a managed child: SIGTERM first, SIGKILL after a grace period.
+    pub async fn shutdown(&self) {
+        let mut record = self.inner.record.lock().await;
+        record.shutting_down = true;
+
+        let Some(mut child) = record.child.take() else {
+            return;
+        };
+
+        tracing::info!(url = self.url(), pid = record.pid.unwrap_or(0), "Stopping opencode serve");
+
+        #[cfg(unix)]
+        if let Some(pid) = child.id() {
+            let _ = tokio::process::Command::new("kill")
+                .arg("-TERM")
+                .arg(pid.to_string())
+                .status()
+                .await;
+        }
+
+        if tokio::time::timeout(SHUTDOWN_GRACE, child.wait()).await.is_err() {
+            let _ = child.kill().await;
+        }
+
+        record.state = ProcessState::Stopped;
+        record.pid = None;
+        record.started_at = None;
+    }
+
+    pub async fn status(&self) -> ProcessStatus {
+        let record = self.inner.record.lock().await;
+        let managed = record.state != ProcessState::External;
+        ProcessStatus {
+            url: self.url().to_string(),
+            state: record.state,
+            pid: record.pid,
+            started_at: record.started_at.map(|t| t.to_rfc3339()),
+            uptime_seconds: record
+                .started_at
+                .map(|t| (Utc::now() - t).num_seconds()),
+            restarts: record.restarts,
+            last_error: record.last_error.clone(),
+            log_file: managed.then(|| self.log_path()),
+        }
+    }
+
+    pub fn recent_logs(&self, lines: usize) -> Vec<String> {
+        let buffer = match self.inner.log_buffer.lock() {
+            Ok(buffer) => buffer,
+            Err(_) => return Vec::new(),
+        };
+        let skip = buffer.len().saturating_sub(lines);
+        buffer.iter().skip(skip).cloned().collect()
+    }
+
+    pub fn log_path(&self) -> String {
+        self.inner
+            .log_file
+            .lock()
+            .map(|log| log.path().to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    async fn spawn_and_wait(&self) -> Result<(), String> {
+        self.inner.record.lock().await.state = ProcessState::Starting;
+
+        let result = async {
+            let binary = self.inner.manager.locate_binary().await?;
+            let pid = {
+                // Spawn under the lock so a shutdown that began while a
+                // restart was backing off cannot be raced.
+                let mut record = self.inner.record.lock().await;
+                if record.shutting_down {
+                    return Err("shutting down".to_string());
+                }
+                let mut child = self
+                    .inner
+                    .manager
+                    .spawn_server(&binary)
+                    .map_err(|e| format!("Failed to start opencode serve: {e}"))?;
+
+                if let Some(stdout) = child.stdout.take() {
+                    self.pump_logs(stdout, "stdout");
+                }
+                if let Some(stderr) = child.stderr.take() {
+                    self.pump_logs(stderr, "stderr");
+                }
+
+                let pid = child.id();
+                record.pid = pid;
+                record.started_at = Some(Utc::now());
+                record.child = Some(child);
+                pid
+            };
+
+            if self.inner.manager.wait_until_healthy().await {
+                Ok(())
+            } else {
+                Err(format!(
+                    "opencode serve started (pid {}) but health check failed after 15 attempts",
+                    pid.unwrap_or(0)
+                ))
+            }
+        }
+        .await;
+
+        let mut record = self.inner.record.lock().await;
+        if record.shutting_down {
+            record.state = ProcessState::Stopped;
+            return result;
+        }
+        match &result {
+            Ok(()) => {
+                record.state = ProcessState::Running;
+            }
+            Err(e) => {
+                record.state = ProcessState::Failed;
+                record.last_error = Some(e.clone());
+            }
+        }
+
+        result
+    }
+
+    fn pump_logs<R>(&self, stream: R, source: &'static str)
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+    {
+        let log_file = Arc::clone(&self.inner.log_file);
+        let log_buffer = Arc::clone(&self.inner.log_buffer);
+
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stream).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let entry = format!("{} [{}] {}", Utc::now().to_rfc3339(), source, line);
+
+                if let Ok(mut file) = log_file.lock() {
+                    if let Err(e) = file.write_line(&entry) {
+                        tracing::debug!(error = %e, "Failed to write opencode log line");
+                    }
+                }
+
+                if let Ok(mut buffer) = log_buffer.lock() {
+                    if buffer.len() >= LOG_BUFFER_LINES {
+                        buffer.pop_front();
+                    }
+                    buffer.push_back(entry);
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,642 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+use uuid::Uuid;
+
+use crate::domain::{Card, KanbanError, PromptTemplate, PromptTemplateVersion, Subtask};
+
+use super::{BoardVariableService, CardService, ContextAssembler, Redactor};
+
+pub const PLANNING: &str = "planning";
+pub const AUTO_DETECT: &str = "auto_detect";
+pub const WORK_DISPATCH: &str = "work_dispatch";
+pub const WORK_CONTINUATION: &str = "work_continuation";
+pub const AI_REVIEW: &str = "ai_review";
+pub const CONFLICT_RESOLUTION: &str = "conflict_resolution";
+
+pub const TEMPLATE_NAMES: &[&str] = &[
+    PLANNING,
+    AUTO_DETECT,
+    WORK_DISPATCH,
+    WORK_CONTINUATION,
+    AI_REVIEW,
+    CONFLICT_RESOLUTION,
+];
+
+/// Every variable a template may reference. Variables that are not relevant
+/// for a given render (e.g. `plan_path` while planning) render as empty text.
+pub const KNOWN_VARIABLES: &[&str] = &[
+    "card.id",
+    "card.title",
+    "card.description",
+    "card.priority",
+    "card.stage",
+    "card.working_directory",
+    "agent_instruction",
+    "subtasks",
+    "board.id",
+    "board_settings",
+    "board_variables",
+    "codebase_path",
+    "attached_files",
+    "linked_docs",
+    "linked_context",
+    "plan_path",
+    "context_path",
+    "diff",
+    "base_branch",
+    "conflicts",
+    "review.session_id",
+];
+
+const DEFAULT_PLANNING: &str = "IMPORTANT: You are working on card_id = \"{{card.id}}\". ALL subtasks must be created on THIS card. Do NOT create new cards.
+
+## SAFETY RULES — MANDATORY
+- ONLY use the provided kanban MCP tools: kanban_create_subtask, kanban_update_card, kanban_add_comment, kanban_get_card
+- Do NOT search the filesystem for database files
+- Do NOT create, open, or modify any .db or .sqlite files
+- Do NOT use Python, sqlite3, shell commands, or any tool to access databases directly
+- Do NOT attempt to fix MCP tool errors by accessing underlying infrastructure
+- If a kanban MCP tool returns an error, STOP and report the error. Do NOT work around it.
+
+You are a project planning assistant. Analyze this card and create a detailed implementation plan.
+
+## Board Context (apply to ALL work on this board)
+{{board_settings}}
+
+## Available Environment Variables
+Set in the worktree's `.env` and in the environment of setup and verification commands. Reference them by name; never print secret values.
+{{board_variables}}
+
+## Card Details
+- Card ID: {{card.id}}
+- Title: {{card.title}}
+- Description: {{card.description}}
+- Priority: {{card.priority}}
+- Working Directory: {{card.working_directory}}
+- Linked Documents:
+{{linked_docs}}
+- Attached Files:
+{{attached_files}}
+- Current Subtasks:
+{{subtasks}}
+
+## Reference Material
+{{linked_context}}
+
+## Instructions
+1. Analyze the card requirements
+2. Break down the work into concrete, actionable subtasks organized by phases
+3. Use the `kanban_create_subtask` MCP tool to add each subtask to card_id \"{{card.id}}\"
+4. Set appropriate phase names (e.g., \"Design\", \"Implementation\", \"Testing\") and phase_order for grouping
+5. If you create any plan documents or markdown files, update the card's linked_documents using `kanban_update_card`
+6. Add a summary comment using `kanban_add_comment`
+
+CRITICAL: The card_id for ALL tool calls is: {{card.id}}";
+
+const DEFAULT_AUTO_DETECT: &str = r#"You are a codebase analysis assistant. Analyze the codebase at "{{codebase_path}}" and fill in the board settings.
+
+Board ID: {{board.id}}
+
+## Instructions
+1. Explore the codebase directory structure, config files, package manifests, and source code
+2. Identify: tech stack, frameworks, languages, versions
+3. Identify: communication patterns (REST, gRPC, WebSocket, message queues)
+4. Identify: environments (dev, staging, production) from config files
+5. Identify: code conventions (linter configs, formatting, naming patterns)
+6. Identify: testing frameworks and requirements
+7. Identify: API conventions (REST style, auth patterns, error formats)
+8. Identify: infrastructure (Docker, K8s, CI/CD, cloud provider)
+9. Use the `kanban_update_board_settings` MCP tool to save ALL findings to board_id "{{board.id}}"
+10. Set each field with a clear, structured summary (use bullet points, be specific with version numbers)
+
+## SAFETY RULES
+- ONLY use kanban MCP tools to save results
+- Do NOT modify any files in the codebase
+- Do NOT run any build or test commands
+- Do NOT access databases directly
+- READ ONLY -- analyze files, do not change them"#;
+
+const DEFAULT_WORK_DISPATCH: &str = "{{agent_instruction}}A work plan has been generated at {{plan_path}}. Read it carefully, along with the reference material bundled at {{context_path}}, then execute /start-work to begin. Work through ALL TODOs systematically.";
+
+const DEFAULT_WORK_CONTINUATION: &str = "{{agent_instruction}}The planning phase is complete. A work plan has been generated at {{plan_path}}. Read it carefully — it includes your earlier plan plus any modifications the human reviewer made. Reference material is bundled at {{context_path}}. Then execute /start-work to begin. Work through ALL TODOs systematically.";
+
+const DEFAULT_AI_REVIEW: &str = "You are reviewing the work done on card_id = \"{{card.id}}\" before a human reviewer sees it. You did not write this change; judge it critically.
+
+## SAFETY RULES — MANDATORY
+- READ ONLY: do not create, modify, or delete files, and do not commit
+- ONLY use the kanban MCP tools kanban_get_card and kanban_submit_review
+- Do NOT access databases directly
+
+## Acceptance Criteria
+- Title: {{card.title}}
+- Description: {{card.description}}
+- Subtasks:
+{{subtasks}}
+
+## Board Context
+{{board_settings}}
+
+## Changes
+The card's branch against the default branch. The worktree is at {{card.working_directory}}.
+
+{{diff}}
+
+## Instructions
+1. Check the change against the description and every subtask
+2. Look for bugs, missing tests, security problems, and violations of the board conventions
+3. Call `kanban_submit_review` exactly once for card_id \"{{card.id}}\" with:
+   - session_id \"{{review.session_id}}\"
+   - verdict \"approve\" when a human can take it from here, or \"request_changes\" when it must be reworked first
+   - a short summary
+   - one finding per issue: file, line (in the new version of the file; omit for whole-file issues), severity (info, warning, error) and message";
+
+const DEFAULT_CONFLICT_RESOLUTION: &str = "Your branch for card_id = \"{{card.id}}\" no longer merges cleanly into {{base_branch}}. A merge of {{base_branch}} into your branch was started in your worktree at {{card.working_directory}} and stopped on the conflicts below.
+
+## Conflicts
+\"ours\" is your branch, \"theirs\" is {{base_branch}}.
+
+{{conflicts}}
+
+## Instructions
+1. Resolve every conflicted file so that both your change and the work that landed on {{base_branch}} are kept
+2. Remove all conflict markers, then make sure the project still builds and its tests pass
+3. `git add` the resolved files and conclude the merge with `git commit --no-edit`
+4. Do NOT abort the merge, rebase, or reset the branch";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectivePromptTemplate {
+    pub name: String,
+    pub board_id: String,
+    pub content: String,
+    pub version: i64,
+    pub is_override: bool,
+    pub default_content: String,
+    pub updated_at: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct BoardContextRow {
+    codebase_path: String,
+    context_markdown: String,
+    tech_stack: String,
+    communication_patterns: String,
+    environments: String,
+    code_conventions: String,
+    testing_requirements: String,
+    api_conventions: String,
+    infrastructure: String,
+    github_repo: String,
+}
+
+pub type PromptVariables = HashMap<String, String>;
+
+pub struct PromptTemplateService;
+
+impl PromptTemplateService {
+    pub fn default_template(name: &str) -> Option<&'static str> {
+        match name {
+            PLANNING => Some(DEFAULT_PLANNING),
+            AUTO_DETECT => Some(DEFAULT_AUTO_DETECT),
+            WORK_DISPATCH => Some(DEFAULT_WORK_DISPATCH),
+            WORK_CONTINUATION => Some(DEFAULT_WORK_CONTINUATION),
+            AI_REVIEW => Some(DEFAULT_AI_REVIEW),
+            CONFLICT_RESOLUTION => Some(DEFAULT_CONFLICT_RESOLUTION),
+            _ => None,
+        }
+    }
+
+    fn require_known_name(name: &str) -> Result<&'static str, KanbanError> {
+        Self::default_template(name).ok_or_else(|| {
+            KanbanError::NotFound(format!(
+                "Unknown prompt template '{}'. Valid: {}",
+                name,
+                TEMPLATE_NAMES.join(", ")
+            ))
+        })
+    }
+
+    /// Substitutes `{{variable}}` placeholders. Unknown variable names are an
+    /// error so typos surface when a template is saved rather than at dispatch.
+    pub fn render(template: &str, variables: &PromptVariables) -> Result<String, KanbanError> {
+        let mut output = String::with_capacity(template.len());
+        let mut rest = template;
+
+        while let Some(start) = rest.find("{{") {
+            output.push_str(&rest[..start]);
+            let after_open = &rest[start + 2..];
+            let Some(end) = after_open.find("}}") else {
+                return Err(KanbanError::BadRequest(
+                    "Unterminated '{{' placeholder in prompt template".into(),
+                ));
+            };
+
+            let name = after_open[..end].trim();
+            if !KNOWN_VARIABLES.contains(&name) {
+                return Err(KanbanError::BadRequest(format!(
+                    "Unknown prompt template variable '{}'",
+                    name
+                )));
+            }
+
+            if let Some(value) = variables.get(name) {
+                output.push_str(value);
+            }
+            rest = &after_open[end + 2..];
+        }
+
+        output.push_str(rest);
+        Ok(output)
+    }
+
+    /// Whether `template` has a `{{name}}` placeholder.
+    pub fn uses_variable(template: &str, name: &str) -> bool {
+        template
+            .split("{{")
+            .skip(1)
+            .filter_map(|after_open| after_open.split_once("}}"))
+            .any(|(placeholder, _)| placeholder.trim() == name)
+    }
+
+    pub fn validate(template: &str) -> Result<(), KanbanError> {
+        if template.trim().is_empty() {
+            return Err(KanbanError::BadRequest(
+                "Prompt template content must not be empty".into(),
+            ));
+        }
+        Self::render(template, &PromptVariables::new()).map(|_| ())
+    }
+
+    pub async fn get_effective(
+        db: &SqlitePool,
+        board_id: Option<&str>,
+        name: &str,
+    ) -> Result<EffectivePromptTemplate, KanbanError> {
+        let default_content = Self::require_known_name(name)?;
+        let board_id = board_id.unwrap_or_default();
+
+        let stored: Option<PromptTemplate> = if board_id.is_empty() {
+            None
+        } else {
+            sqlx::query_as("SELECT * FROM prompt_templates WHERE board_id = ? AND name = ?")
+                .bind(board_id)
+                .bind(name)
+                .fetch_optional(db)
+                .await?
+        };
+
+        Ok(match stored {
+            Some(template) => EffectivePromptTemplate {
+                name: template.name,
+                board_id: template.board_id,
+                content: template.content,
+                version: template.version,
+                is_override: true,
+                default_content: default_content.to_string(),
+                updated_at: Some(template.updated_at),
+            },
+            None => EffectivePromptTemplate {
+                name: name.to_string(),
+                board_id: board_id.to_string(),
+                content: default_content.to_string(),
+                version: 0,
+                is_override: false,
+                default_content: default_content.to_string(),
+                updated_at: None,
+            },
+        })
+    }
+
+    pub async fn list_effective(
+        db: &SqlitePool,
+        board_id: &str,
+    ) -> Result<Vec<EffectivePromptTemplate>, KanbanError> {
+        let mut templates = Vec::with_capacity(TEMPLATE_NAMES.len());
+        for name in TEMPLATE_NAMES {
+            templates.push(Self::get_effective(db, Some(board_id), name).await?);
+        }
+        Ok(templates)
+    }
+
+    pub async fn save_override(
+        db: &SqlitePool,
+        board_id: &str,
+        name: &str,
+        content: &str,
+        changed_by: &str,
+    ) -> Result<EffectivePromptTemplate, KanbanError> {
+        Self::require_known_name(name)?;
+        Self::validate(content)?;
+
+        let now = Utc::now().to_rfc3339();
+        let mut tx = db.begin().await?;
+        // The version is allocated by the insert itself, so two concurrent
+        // saves cannot both read the same MAX(version).
+        let version: i64 = sqlx::query_scalar(
+            "INSERT INTO prompt_template_versions (id, board_id, name, version, content, changed_by, created_at)
+             SELECT ?, ?, ?, COALESCE(MAX(version), 0) + 1, ?, ?, ?
+             FROM prompt_template_versions WHERE board_id = ? AND name = ?
+             RETURNING version",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(board_id)
+        .bind(name)
+        .bind(content)
+        .bind(changed_by)
+        .bind(&now)
+        .bind(board_id)
+        .bind(name)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "INSERT INTO prompt_templates (board_id, name, content, version, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?)
+             ON CONFLICT(board_id, name) DO UPDATE SET
+                 content = excluded.content,
+                 version = excluded.version,
+                 updated_at = excluded.updated_at",
+        )
+        .bind(board_id)
+        .bind(name)
+        .bind(content)
+        .bind(version)
+        .bind(&now)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Self::get_effective(db, Some(board_id), name).await
+    }
+
+    /// Drops the board override so the built-in default applies again.
+    /// Version history is kept so the override can be restored later.
+    pub async fn reset_override(
+        db: &SqlitePool,
+        board_id: &str,
+        name: &str,
+    ) -> Result<EffectivePromptTemplate, KanbanError> {
+        Self::require_known_name(name)?;
+
+        sqlx::query("DELETE FROM prompt_templates WHERE board_id = ? AND name = ?")
+            .bind(board_id)
+            .bind(name)
+            .execute(db)
+            .await?;
+
+        Self::get_effective(db, Some(board_id), name).await
+    }
+
+    pub async fn list_versions(
+        db: &SqlitePool,
+        board_id: &str,
+        name: &str,
+    ) -> Result<Vec<PromptTemplateVersion>, KanbanError> {
+        Self::require_known_name(name)?;
+
+        let versions = sqlx::query_as(
+            "SELECT * FROM prompt_template_versions WHERE board_id = ? AND name = ? ORDER BY version DESC",
+        )
+        .bind(board_id)
+        .bind(name)
+        .fetch_all(db)
+        .await?;
+
+        Ok(versions)
+    }
+
+    pub async fn restore_version(
+        db: &SqlitePool,
+        board_id: &str,
+        name: &str,
+        version: i64,
+    ) -> Result<EffectivePromptTemplate, KanbanError> {
+        let stored: PromptTemplateVersion = sqlx::query_as(
+            "SELECT * FROM prompt_template_versions WHERE board_id = ? AND name = ? AND version = ?",
+        )
+        .bind(board_id)
+        .bind(name)
+        .bind(version)
+        .fetch_optional(db)
+        .await?
+        .ok_or_else(|| {
+            KanbanError::NotFound(format!(
+                "Prompt template version {} not found for '{}'",
+                version, name
+            ))
+        })?;
+
+        Self::save_override(db, board_id, name, &stored.content, "restore").await
+    }
+
+    /// Renders the board's effective template for `name` against a card.
+    /// `extra` supplies call-site values such as `plan_path`. Secrets are
+    /// redacted from the result before it reaches the agent.
+    pub async fn render_for_card(
+        db: &SqlitePool,
+        name: &str,
+        card: &Card,
+        extra: &[(&str, String)],
+    ) -> Result<String, KanbanError> {
+        let template = Self::get_effective(db, card.board_id.as_deref(), name).await?;
+        let mut variables = Self::card_variables(db, card, &template.content).await?;
+        for (key, value) in extra {
+            variables.insert((*key).to_string(), value.clone());
+        }
+        let rendered = Self::render(&template.content, &variables)?;
+        Ok(Redactor::for_board(db, card.board_id.as_deref())
+            .await?
+            .redact(&rendered))
+    }
+
+    pub async fn render_for_board(
+        db: &SqlitePool,
+        name: &str,
+        board_id: &str,
+        codebase_path: &str,
+    ) -> Result<String, KanbanError> {
+        let template = Self::get_effective(db, Some(board_id), name).await?;
+        let mut variables = PromptVariables::new();
+        variables.insert("board.id".into(), board_id.to_string());
+        variables.insert("codebase_path".into(), codebase_path.to_string());
+        variables.insert(
+            "board_settings".into(),
+            Self::board_context_markdown(db, board_id).await?,
+        );
+        let rendered = Self::render(&template.content, &variables)?;
+        Ok(Redactor::for_board(db, Some(board_id))
+            .await?
+            .redact(&rendered))
+    }
+
+    /// Variables for rendering `template` against `card`. Linked context is
+    /// only assembled when the template uses it; otherwise it is empty.
+    pub async fn card_variables(
+        db: &SqlitePool,
+        card: &Card,
+        template: &str,
+    ) -> Result<PromptVariables, KanbanError> {
+        let subtasks = CardService::get_subtasks(db, &card.id).await?;
+
+        let board_id = card.board_id.clone().unwrap_or_default();
+        let codebase_path = if board_id.is_empty() {
+            String::new()
+        } else {
+            sqlx::query_scalar::<_, String>(
+                "SELECT codebase_path FROM board_settings WHERE board_id = ?",
+            )
+            .bind(&board_id)
+            .fetch_optional(db)
+            .await?
+            .unwrap_or_default()
+        };
+
+        let agent_instruction = card
+            .ai_agent
+            .as_deref()
+            .filter(|agent| !agent.is_empty())
+            .map(|agent| format!("You are acting as the {} agent. ", agent))
+            .unwrap_or_default();
+
+        let mut variables = PromptVariables::new();
+        variables.insert("card.id".into(), card.id.clone());
+        variables.insert("card.title".into(), card.title.clone());
+        variables.insert("card.description".into(), card.description.clone());
+        variables.insert("card.priority".into(), card.priority.clone());
+        variables.insert("card.stage".into(), card.stage.clone());
+        variables.insert(
+            "card.working_directory".into(),
+            card.working_directory.clone(),
+        );
+        variables.insert("agent_instruction".into(), agent_instruction);
+        variables.insert("subtasks".into(), Self::format_subtasks(&subtasks));
+        variables.insert("board.id".into(), board_id.clone());
+        variables.insert(
+            "board_settings".into(),
+            Self::board_context_markdown(db, &board_id).await?,
+        );
+        variables.insert(
+            "board_variables".into(),
+            BoardVariableService::summary(
+                &BoardVariableService::load(db, Some(&board_id)).await?,
+            ),
+        );
+        variables.insert("codebase_path".into(), codebase_path);
+        variables.insert(
+            "attached_files".into(),
+            Self::format_attached_files(db, &card.id).await,
+        );
+        variables.insert(
+            "linked_docs".into(),
+            Self::format_linked_docs(&card.linked_documents),
+        );
+        let linked_context = if Self::uses_variable(template, "linked_context") {
+            ContextAssembler::assemble(db, card).await?.to_markdown()
+        } else {
+            String::new()
+        };
+        variables.insert("linked_context".into(), linked_context);
+        variables.insert(
+            "plan_path".into(),
+            card.plan_path.clone().unwrap_or_default(),
+        );
+
+        Ok(variables)
+    }
+
+    pub async fn board_context_markdown(
+        db: &SqlitePool,
+        board_id: &str,
+    ) -> Result<String, KanbanError> {
+        if board_id.is_empty() {
+            return Ok(String::new());
+        }
+
+        let settings: Option<BoardContextRow> = sqlx::query_as(
+            "SELECT codebase_path, context_markdown, tech_stack, communication_patterns, environments, code_conventions, testing_requirements, api_conventions, infrastructure, github_repo FROM board_settings WHERE board_id = ?",
+        )
+        .bind(board_id)
+        .fetch_optional(db)
+        .await?;
+
+        let Some(ctx) = settings else {
+            return Ok(String::new());
+        };
+
+        let mut context_sections = Vec::new();
+
+        if !ctx.context_markdown.is_empty() {
+            context_sections.push(format!("{}\n\n", ctx.context_markdown));
+        }
+
+        let sections = [
+            ("Codebase Path", &ctx.codebase_path),
+            ("Tech Stack", &ctx.tech_stack),
+            ("Communication Patterns", &ctx.communication_patterns),
+            ("Environments", &ctx.environments),
+            ("Code Conventions", &ctx.code_conventions),
+            ("Testing Requirements", &ctx.testing_requirements),
+            ("API Conventions", &ctx.api_conventions),
+            ("Infrastructure", &ctx.infrastructure),
+            ("GitHub Repository", &ctx.github_repo),
+        ];
+        for (heading, value) in sections {
+            if !value.trim().is_empty() {
+                context_sections.push(format!("### {}\n{}\n\n", heading, value));
+            }
+        }
+
+        Ok(context_sections.concat())
+    }
+
+    fn format_subtasks(subtasks: &[Subtask]) -> String {
+        if subtasks.is_empty() {
+            return "None".to_string();
+        }
+
+        subtasks
+            .iter()
+            .map(|s| format!("- {}", s.title))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn format_linked_docs(linked_documents: &str) -> String {
+        serde_json::from_str::<Vec<String>>(linked_documents)
+            .ok()
+            .filter(|docs| !docs.is_empty())
+            .map(|docs| {
+                docs.iter()
+                    .map(|doc| format!("- {}", doc))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .unwrap_or_else(|| "None".to_string())
+    }
+
+    async fn format_attached_files(db: &SqlitePool, card_id: &str) -> String {
+        match sqlx::query(
+            "SELECT original_filename, file_size, mime_type FROM card_files WHERE card_id = ? ORDER BY uploaded_at ASC",
+        )
+        .bind(card_id)
+        .fetch_all(db)
+        .await
+        {
+            Ok(rows) if rows.is_empty() => "None".to_string(),
+            Ok(rows) => rows
+                .iter()
+                .map(|row| {
+                    let name: String = row.get("original_filename");
+                    let size: i64 = row.get("file_size");
+                    let mime: String = row.get("mime_type");
+                    format!("- {} ({} bytes, {})", name, size, mime)
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Err(_) => "None".to_string(),
+        }
+    }
+}
@@ -3,22 +3,31 @@ use std::time::Duration;
 use chrono::Utc;
 use serde_json::json;
 use sqlx::SqlitePool;
-use tokio::sync::broadcast;
 
 use crate::api::handlers::sse::WsEvent;
 use crate::domain::{Card, KanbanError};
 
-use super::{AiDispatchService, CardService, GitWorktreeService};
+use super::card_attempts::CardAttempt;
+use super::{
+    AiDispatchService, CardAttemptService, CardRunService, CardService, EventBus,
+    GitWorktreeService, OpencodePool, WorktreeHookService,
+};
 
+const SETUP_INTERRUPTED: &str = "Worktree setup was interrupted by a restart";
+
+#[derive(Clone)]
 pub struct QueueProcessor {
     pub db: SqlitePool,
     pub http_client: reqwest::Client,
-    pub opencode_url: String,
-    pub sse_tx: broadcast::Sender<String>,
+    pub opencode: OpencodePool,
+    pub sse_tx: EventBus,
 }
 
 impl QueueProcessor {
     pub async fn start(self) {
+        if let Err(e) = self.recover_interrupted_setups().await {
+            tracing::warn!("Failed to recover interrupted worktree setups: {}", e);
+        }
         loop {
             if let Err(e) = self.process_queue().await {
                 tracing::warn!("Queue processor error: {}", e);
@@ -40,13 +49,28 @@ impl QueueProcessor {
                 continue;
             }
 
-            let slots = concurrency_limit.saturating_sub(active_count);
+            let mut slots = concurrency_limit.saturating_sub(active_count);
+            let queued_attempts = CardAttemptService::queued_for_board(
+                &self.db,
+                &board_id,
+                std::cmp::min(slots, i64::MAX as usize) as i64,
+            )
+            .await?;
+            slots = slots.saturating_sub(queued_attempts.len());
+            for attempt in queued_attempts {
+                self.dispatch_attempt(&board_id, attempt).await;
+            }
+            if slots == 0 {
+                continue;
+            }
+
             let queued_cards = self
                 .get_queued_cards(&board_id, std::cmp::min(slots, i64::MAX as usize) as i64)
                 .await?;
 
             for card in queued_cards {
                 let mut dispatch_card = card;
+                let mut new_worktree_codebase: Option<String> = None;
 
                 if dispatch_card.worktree_path.is_empty() {
                     let codebase_path: Option<String> = sqlx::query_scalar(
@@ -60,11 +84,16 @@ impl QueueProcessor {
 
                     if let Some(codebase) = codebase_path {
                         if !codebase.is_empty() {
-                            match GitWorktreeService::create_worktree(
-                                &codebase,
-                                &dispatch_card.id,
-                                &dispatch_card.title,
-                            ) {
+                            let (repo, card_id, title) = (
+                                codebase.clone(),
+                                dispatch_card.id.clone(),
+                                dispatch_card.title.clone(),
+                            );
+                            match GitWorktreeService::blocking(move || {
+                                GitWorktreeService::create_worktree(&repo, &card_id, &title)
+                            })
+                            .await
+                            {
                                 Ok((branch_name, worktree_path)) => {
                                     if let Err(error) = sqlx::query(
                                         "UPDATE cards SET branch_name = ?, worktree_path = ?, working_directory = ?, updated_at = ? WHERE id = ?",
@@ -90,6 +119,7 @@ impl QueueProcessor {
                                         {
                                             Ok(updated_card) => {
                                                 dispatch_card = updated_card;
+                                                new_worktree_codebase = Some(codebase.clone());
                                             }
                                             Err(error) => {
                                                 tracing::warn!(
@@ -113,54 +143,65 @@ impl QueueProcessor {
                     }
                 }
 
-                let subtasks = CardService::get_subtasks(&self.db, &dispatch_card.id).await?;
-                let dispatcher =
-                    AiDispatchService::new(self.http_client.clone(), self.opencode_url.clone());
-
-                match dispatcher.dispatch_card(&dispatch_card, &subtasks, &self.db).await {
-                    Ok(session_id) if !session_id.is_empty() => {
-                        if let Err(e) = sqlx::query(
-                            "UPDATE cards SET stage = ?, updated_at = ? WHERE id = ?",
-                        )
-                        .bind("in_progress")
-                        .bind(chrono::Utc::now().to_rfc3339())
-                        .bind(&dispatch_card.id)
-                        .execute(&self.db)
-                        .await
-                        {
-                            tracing::warn!(
-                                card_id = dispatch_card.id,
-                                error = %e,
-                                "Failed to move card to in_progress after dispatch"
-                            );
-                        }
+                if let Some(codebase) = new_worktree_codebase {
+                    if WorktreeHookService::has_setup(&self.db, dispatch_card.board_id.as_deref()).await {
+                        self.start_card_setup(&dispatch_card, codebase).await;
+                        continue;
+                    }
+                }
 
-                        let move_event = WsEvent::CardMoved {
-                            card_id: dispatch_card.id.clone(),
-                            from_stage: "todo".to_string(),
-                            to_stage: "in_progress".to_string(),
-                        };
-                        if let Ok(payload) = serde_json::to_string(&move_event) {
-                            let _ = self.sse_tx.send(payload);
-                        }
+                self.dispatch_card(&dispatch_card).await?;
+            }
+        }
 
-                        let event = WsEvent::AiStatusChanged {
-                            card_id: dispatch_card.id.clone(),
-                            board_id: dispatch_card.board_id.clone(),
-                            status: "dispatched".to_string(),
-                            progress: json!({}),
-                            stage: "in_progress".to_string(),
-                            ai_session_id: Some(session_id),
-                        };
-                        if let Ok(payload) = serde_json::to_string(&event) {
-                            let _ = self.sse_tx.send(payload);
-                        }
-                    }
-                    Ok(_) => {}
-                    Err(e) => {
-                        tracing::warn!(card_id = dispatch_card.id, "Queue dispatch failed: {}", e);
-                    }
+        Ok(())
+    }
+
+    async fn dispatch_card(&self, card: &Card) -> Result<(), KanbanError> {
+        let subtasks = CardService::get_subtasks(&self.db, &card.id).await?;
+        let endpoint = self.opencode.endpoint_for_dispatch(&self.db, card).await?;
+        let dispatcher = AiDispatchService::new(self.http_client.clone(), endpoint);
+
+        match dispatcher.dispatch_card(card, &subtasks, &self.db).await {
+            Ok(session_id) if !session_id.is_empty() => {
+                if let Err(e) = sqlx::query("UPDATE cards SET stage = ?, updated_at = ? WHERE id = ?")
+                    .bind("in_progress")
+                    .bind(chrono::Utc::now().to_rfc3339())
+                    .bind(&card.id)
+                    .execute(&self.db)
+                    .await
+                {
+                    tracing::warn!(
+                        card_id = card.id,
+                        error = %e,
+                        "Failed to move card to in_progress after dispatch"
+                    );
                 }
+
+                let move_event = WsEvent::CardMoved {
+                    card_id: card.id.clone(),
+                    from_stage: "todo".to_string(),
+                    to_stage: "in_progress".to_string(),
+                };
+                if let Ok(payload) = serde_json::to_string(&move_event) {
+                    let _ = self.sse_tx.send(payload);
+                }
+
+                let event = WsEvent::AiStatusChanged {
+                    card_id: card.id.clone(),
+                    board_id: card.board_id.clone(),
+                    status: "dispatched".to_string(),
+                    progress: json!({}),
+                    stage: "in_progress".to_string(),
+                    ai_session_id: Some(session_id),
+                };
+                if let Ok(payload) = serde_json::to_string(&event) {
+                    let _ = self.sse_tx.send(payload);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => {
+                tracing::warn!(card_id = card.id, "Queue dispatch failed: {}", e);
             }
         }
 
@@ -180,8 +221,10 @@ impl QueueProcessor {
         .max(1);
 
         let cutoff = Utc::now() - chrono::Duration::minutes(timeout_minutes);
+        // Cards running competing attempts have no session of their own.
         let cards = sqlx::query_as::<_, Card>(
-            "SELECT * FROM cards WHERE ai_status IN ('dispatched', 'working', 'waiting')",
+            "SELECT * FROM cards WHERE ai_status IN ('dispatched', 'working', 'waiting')
+             AND NOT EXISTS (SELECT 1 FROM card_attempts a WHERE a.card_id = cards.id AND a.status IN ('queued', 'setting_up', 'dispatched', 'working'))",
         )
         .fetch_all(&self.db)
         .await?;
@@ -225,7 +268,8 @@ impl QueueProcessor {
                 continue;
             };
 
-            let session_url = format!("{}/session/{}", self.opencode_url, session_id);
+            let opencode_url = self.opencode.endpoint_for_card(&card);
+            let session_url = format!("{}/session/{}", opencode_url, session_id);
             let (is_stuck, reason) = match self.http_client.get(&session_url).send().await {
                 Ok(response) if response.status() == reqwest::StatusCode::NOT_FOUND => {
                     (true, "OpenCode session not found".to_string())
@@ -260,7 +304,10 @@ impl QueueProcessor {
                 continue;
             }
 
-            if let Some(tool_state) = self.check_session_has_running_tool(session_id).await {
+            if let Some(tool_state) = self
+                .check_session_has_running_tool(&opencode_url, session_id)
+                .await
+            {
                 tracing::info!(
                     card_id = card.id,
                     session_id,
@@ -332,11 +379,12 @@ impl QueueProcessor {
         Ok(())
     }
 
-    async fn check_session_has_running_tool(&self, session_id: &str) -> Option<String> {
-        let messages_url = format!(
-            "{}/session/{}/message",
-            self.opencode_url, session_id
-        );
+    async fn check_session_has_running_tool(
+        &self,
+        opencode_url: &str,
+        session_id: &str,
+    ) -> Option<String> {
+        let messages_url = format!("{}/session/{}/message", opencode_url, session_id);
         let response = self.http_client.get(&messages_url).send().await.ok()?;
         let msgs: Vec<serde_json::Value> = response.json().await.ok()?;
         let last = msgs.last()?;
@@ -360,10 +408,15 @@ impl QueueProcessor {
         let Some(session_id) = card.ai_session_id.as_deref() else {
             return;
         };
-        if self.check_session_has_running_tool(session_id).await.is_some() {
+        let opencode_url = self.opencode.endpoint_for_card(card);
+        if self
+            .check_session_has_running_tool(&opencode_url, session_id)
+            .await
+            .is_some()
+        {
             return;
         }
-        let session_url = format!("{}/session/{}", self.opencode_url, session_id);
+        let session_url = format!("{}/session/{}", opencode_url, session_id);
         let is_busy = match self.http_client.get(&session_url).send().await {
             Ok(resp) => match resp.json::<serde_json::Value>().await {
                 Ok(body) => {
@@ -400,6 +453,239 @@ impl QueueProcessor {
         }
     }
 
+    /// Creates the attempt's own worktree and branch, runs the board's setup
+    /// hooks in it and starts its session. Failures only fail the attempt.
+    async fn dispatch_attempt(&self, board_id: &str, attempt: CardAttempt) {
+        let card = match CardService::get_card_model(&self.db, &attempt.card_id).await {
+            Ok(card) => card,
+            Err(e) => {
+                tracing::warn!(attempt_id = attempt.id, error = %e, "Failed to load card for attempt");
+                return;
+            }
+        };
+        let codebase: String = sqlx::query_scalar(
+            "SELECT codebase_path FROM board_settings WHERE board_id = ?",
+        )
+        .bind(board_id)
+        .fetch_optional(&self.db)
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+        if codebase.is_empty() {
+            CardAttemptService::fail_or_warn(&self.db, &self.sse_tx, &attempt.id, "Board codebase path not configured").await;
+            return;
+        }
+
+        let (repo, card_id, title, suffix) =
+            (codebase.clone(), card.id.clone(), card.title.clone(), attempt.branch_suffix());
+        let created = GitWorktreeService::blocking(move || {
+            let (branch_name, worktree_path) =
+                GitWorktreeService::create_worktree_with_suffix(&repo, &card_id, &title, &suffix)?;
+            let base_commit = GitWorktreeService::merge_base(&repo, &worktree_path).unwrap_or_default();
+            Ok((branch_name, worktree_path, base_commit))
+        })
+        .await;
+        let attempt = match created {
+            Ok((branch_name, worktree_path, base_commit)) => {
+                match CardAttemptService::record_worktree(
+                    &self.db,
+                    &attempt,
+                    &branch_name,
+                    &worktree_path,
+                    &base_commit,
+                )
+                .await
+                {
+                    Ok(updated) => updated,
+                    Err(e) => {
+                        remove_worktree(&codebase, &worktree_path, &branch_name).await;
+                        CardAttemptService::fail_or_warn(&self.db, &self.sse_tx, &attempt.id, &e.to_string()).await;
+                        return;
+                    }
+                }
+            }
+            Err(e) => {
+                let reason = format!("Failed to create worktree: {}", e);
+                CardAttemptService::fail_or_warn(&self.db, &self.sse_tx, &attempt.id, &reason).await;
+                return;
+            }
+        };
+
+        if !WorktreeHookService::has_setup(&self.db, card.board_id.as_deref()).await {
+            self.send_attempt(&card, &attempt).await;
+            return;
+        }
+        let attempt = match CardAttemptService::mark_setting_up(&self.db, &self.sse_tx, &attempt).await {
+            Ok(Some(attempt)) => attempt,
+            Ok(None) => return,
+            Err(e) => {
+                CardAttemptService::fail_or_warn(&self.db, &self.sse_tx, &attempt.id, &e.to_string()).await;
+                return;
+            }
+        };
+
+        let processor = self.clone();
+        tokio::spawn(async move {
+            let view = attempt.card_view(&card);
+            if let Err(error) =
+                WorktreeHookService::run_setup(&processor.db, &processor.sse_tx, &view, &codebase).await
+            {
+                let reason = match error {
+                    KanbanError::Internal(reason) => reason,
+                    other => other.to_string(),
+                };
+                remove_worktree(&codebase, &attempt.worktree_path, &attempt.branch_name).await;
+                CardAttemptService::fail_or_warn(&processor.db, &processor.sse_tx, &attempt.id, &reason)
+                    .await;
+                return;
+            }
+
+            // Cancelled or discarded while the hooks ran.
+            match CardAttemptService::get(&processor.db, &card.id, &attempt.id).await {
+                Ok(current) if current.status == "setting_up" => {
+                    processor.send_attempt(&card, &current).await;
+                }
+                Ok(current) => {
+                    tracing::info!(attempt_id = current.id, status = current.status, "Attempt left setup; not dispatching");
+                }
+                Err(e) => {
+                    tracing::warn!(attempt_id = attempt.id, error = %e, "Failed to reload attempt after setup");
+                }
+            }
+        });
+    }
+
+    async fn send_attempt(&self, card: &Card, attempt: &CardAttempt) {
+        let view = attempt.card_view(card);
+        let result = async {
+            let subtasks = CardService::get_subtasks(&self.db, &card.id).await?;
+            let endpoint = self.opencode.select_least_loaded(&self.db).await?;
+            let dispatcher = AiDispatchService::new(self.http_client.clone(), endpoint);
+            dispatcher
+                .dispatch_attempt(&view, attempt, &subtasks, &self.db, &self.sse_tx)
+                .await?;
+            Ok::<(), KanbanError>(())
+        }
+        .await;
+        if let Err(e) = result {
+            CardAttemptService::fail_or_warn(&self.db, &self.sse_tx, &attempt.id, &e.to_string()).await;
+        }
+    }
+
+    /// Setup hooks can run for minutes, so they run in a task of their own
+    /// while the card shows `setting_up` and keeps its slot. The card is
+    /// dispatched once they pass, unless it was stopped meanwhile.
+    async fn start_card_setup(&self, card: &Card, codebase: String) {
+        let claimed = sqlx::query(
+            "UPDATE cards SET ai_status = 'setting_up', updated_at = ? WHERE id = ? AND ai_status = 'queued'",
+        )
+        .bind(Utc::now().to_rfc3339())
+        .bind(&card.id)
+        .execute(&self.db)
+        .await;
+        match claimed {
+            Ok(result) if result.rows_affected() == 1 => {}
+            Ok(_) => return,
+            Err(e) => {
+                tracing::warn!(card_id = card.id, error = %e, "Failed to mark card as setting up");
+                return;
+            }
+        }
+        let mut card = card.clone();
+        card.ai_status = "setting_up".to_string();
+        let event = WsEvent::AiStatusChanged {
+            card_id: card.id.clone(),
+            board_id: card.board_id.clone(),
+            status: card.ai_status.clone(),
+            progress: serde_json::from_str(&card.ai_progress).unwrap_or_else(|_| json!({})),
+            stage: card.stage.clone(),
+            ai_session_id: card.ai_session_id.clone(),
+        };
+        if let Ok(payload) = serde_json::to_string(&event) {
+            let _ = self.sse_tx.send(payload);
+        }
+
+        let processor = self.clone();
+        tokio::spawn(async move {
+            if let Err(error) =
+                WorktreeHookService::run_setup(&processor.db, &processor.sse_tx, &card, &codebase).await
+            {
+                processor.fail_worktree_setup(&card, &codebase, error).await;
+                return;
+            }
+
+            match CardService::get_card_model(&processor.db, &card.id).await {
+                Ok(current) if current.ai_status == "setting_up" => {
+                    if let Err(e) = processor.dispatch_card(&current).await {
+                        tracing::warn!(card_id = card.id, "Queue dispatch failed: {}", e);
+                    }
+                    // A failed dispatch goes back to the queue like any
+                    // other; the worktree is ready, so setup does not rerun.
+                    let _ = sqlx::query(
+                        "UPDATE cards SET ai_status = 'queued' WHERE id = ? AND ai_status = 'setting_up'",
+                    )
+                    .bind(&card.id)
+                    .execute(&processor.db)
+                    .await;
+                }
+                Ok(current) => {
+                    tracing::info!(card_id = card.id, ai_status = current.ai_status, "Card left setup; not dispatching");
+                }
+                Err(e) => {
+                    tracing::warn!(card_id = card.id, error = %e, "Failed to reload card after setup");
+                }
+            }
+        });
+    }
+
+    /// Setup tasks do not survive a restart. Their worktrees are dropped and
+    /// the cards and attempts failed, so a retry starts from a fresh one.
+    async fn recover_interrupted_setups(&self) -> Result<(), KanbanError> {
+        let cards = sqlx::query_as::<_, Card>("SELECT * FROM cards WHERE ai_status = 'setting_up'")
+            .fetch_all(&self.db)
+            .await?;
+        for card in cards {
+            let codebase = self.codebase_path(card.board_id.as_deref()).await;
+            let reason = KanbanError::Internal(SETUP_INTERRUPTED.to_string());
+            self.fail_worktree_setup(&card, &codebase, reason).await;
+        }
+
+        for attempt in CardAttemptService::with_status(&self.db, "setting_up").await? {
+            let card = CardService::get_card_model(&self.db, &attempt.card_id).await?;
+            let codebase = self.codebase_path(card.board_id.as_deref()).await;
+            remove_worktree(&codebase, &attempt.worktree_path, &attempt.branch_name).await;
+            CardAttemptService::fail_or_warn(&self.db, &self.sse_tx, &attempt.id, SETUP_INTERRUPTED).await;
+        }
+        Ok(())
+    }
+
+    /// Drops the half-prepared worktree so a retry starts from a fresh one,
+    /// then fails the card with the setup error as its reason.
+    async fn fail_worktree_setup(&self, card: &Card, codebase: &str, error: KanbanError) {
+        let reason = match error {
+            KanbanError::Internal(reason) => reason,
+            other => other.to_string(),
+        };
+        tracing::warn!(card_id = card.id, reason, "Worktree setup failed; not dispatching");
+
+        remove_worktree(codebase, &card.worktree_path, &card.branch_name).await;
+        if let Err(e) = sqlx::query(
+            "UPDATE cards SET branch_name = '', worktree_path = '', working_directory = '.', updated_at = ? WHERE id = ?",
+        )
+        .bind(Utc::now().to_rfc3339())
+        .bind(&card.id)
+        .execute(&self.db)
+        .await
+        {
+            tracing::warn!(card_id = card.id, error = %e, "Failed to clear worktree after setup failure");
+        }
+
+        if let Err(e) = self.mark_card_status_and_emit(card, "failed", &reason).await {
+            tracing::warn!(card_id = card.id, error = %e, "Failed to mark card failed after setup failure");
+        }
+    }
+
     async fn mark_card_status_and_emit(
         &self,
         card: &Card,
@@ -423,6 +709,7 @@ impl QueueProcessor {
         .bind(&card.id)
         .execute(&self.db)
         .await?;
+        CardRunService::finish_or_warn(&self.db, &card.id, status).await;
 
         let event = WsEvent::AiStatusChanged {
             card_id: card.id.to_string(),
@@ -439,6 +726,16 @@ impl QueueProcessor {
         Ok(())
     }
 
+    async fn codebase_path(&self, board_id: Option<&str>) -> String {
+        sqlx::query_scalar("SELECT codebase_path FROM board_settings WHERE board_id = ?")
+            .bind(board_id)
+            .fetch_optional(&self.db)
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    }
+
     async fn get_board_concurrency_limit(&self, board_id: &str) -> usize {
         let concurrency = sqlx::query_scalar::<_, i64>(
             "SELECT ai_concurrency FROM board_settings WHERE board_id = ?",
@@ -459,7 +756,9 @@ impl QueueProcessor {
 
     async fn get_queued_board_ids(&self) -> Result<Vec<String>, KanbanError> {
         let board_ids = sqlx::query_scalar::<_, String>(
-            "SELECT DISTINCT board_id FROM cards WHERE stage = 'todo' AND ai_status = 'queued'",
+            "SELECT DISTINCT board_id FROM cards WHERE stage = 'todo' AND ai_status = 'queued'
+             UNION
+             SELECT DISTINCT c.board_id FROM card_attempts a JOIN cards c ON c.id = a.card_id WHERE a.status = 'queued'",
         )
         .fetch_all(&self.db)
         .await?;
@@ -467,11 +766,17 @@ impl QueueProcessor {
         Ok(board_ids)
     }
 
+    /// Each running attempt takes a slot of its own; a card running
+    /// attempts does not count itself.
     async fn count_active_cards(&self, board_id: &str) -> Result<usize, KanbanError> {
         let count: (i64,) = sqlx::query_as(
-            "SELECT COUNT(*) FROM cards WHERE board_id = ? AND stage IN ('todo', 'in_progress') AND ai_status IN ('dispatched', 'working')",
+            "SELECT
+                (SELECT COUNT(*) FROM cards c WHERE c.board_id = ? AND c.stage IN ('todo', 'in_progress') AND c.ai_status IN ('setting_up', 'dispatched', 'working')
+                    AND NOT EXISTS (SELECT 1 FROM card_attempts a WHERE a.card_id = c.id AND a.status IN ('queued', 'setting_up', 'dispatched', 'working')))
+              + (SELECT COUNT(*) FROM card_attempts a JOIN cards c ON c.id = a.card_id WHERE c.board_id = ? AND a.status IN ('setting_up', 'dispatched', 'working'))",
         )
         .bind(board_id)
+        .bind(board_id)
         .fetch_one(&self.db)
         .await?;
 
@@ -480,7 +785,9 @@ impl QueueProcessor {
 
     async fn get_queued_cards(&self, board_id: &str, limit: i64) -> Result<Vec<Card>, KanbanError> {
         let cards = sqlx::query_as::<_, Card>(
-            "SELECT * FROM cards WHERE board_id = ? AND stage = 'todo' AND ai_status = 'queued' ORDER BY CASE priority WHEN 'high' THEN 0 WHEN 'medium' THEN 1 WHEN 'low' THEN 2 ELSE 3 END ASC, updated_at ASC LIMIT ?",
+            "SELECT * FROM cards WHERE board_id = ? AND stage = 'todo' AND ai_status = 'queued'
+             AND NOT EXISTS (SELECT 1 FROM card_attempts a WHERE a.card_id = cards.id AND a.status = 'queued')
+             ORDER BY CASE priority WHEN 'high' THEN 0 WHEN 'medium' THEN 1 WHEN 'low' THEN 2 ELSE 3 END ASC, updated_at ASC LIMIT ?",
         )
         .bind(board_id)
         .bind(limit)
@@ -490,3 +797,13 @@ impl QueueProcessor {
         Ok(cards)
     }
 }
+
+/// Best-effort removal of a worktree the processor just created.
+async fn remove_worktree(codebase: &str, worktree_path: &str, branch_name: &str) {
+    let (repo, worktree_path, branch_name) =
+        (codebase.to_string(), worktree_path.to_string(), branch_name.to_string());
+    let _ = GitWorktreeService::blocking(move || {
+        GitWorktreeService::remove_worktree(&repo, &worktree_path, &branch_name)
+    })
+    .await;
+}
@@ -0,0 +1,278 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+
+use regex::Regex;
+use sqlx::SqlitePool;
+
+use crate::domain::KanbanError;
+
+use super::BoardSecretService;
+
+/// Global setting (`/api/settings/{key}`) holding admin-defined patterns,
+/// either a JSON array of regexes or one regex per line.
+pub const EXTRA_PATTERNS_SETTING: &str = "redaction_patterns";
+
+/// Known values shorter than this are left alone; they match too much.
+const MIN_SECRET_LEN: usize = 6;
+
+/// Variable names that mark a board variable's value as a secret.
+const SECRET_NAME_HINTS: &[&str] = &[
+    "TOKEN",
+    "SECRET",
+    "PASSWORD",
+    "PASSWD",
+    "API_KEY",
+    "APIKEY",
+    "PRIVATE_KEY",
+    "ACCESS_KEY",
+    "CREDENTIAL",
+    "AUTH",
+    "KEY",
+    "PAT",
+];
+
+/// Well-known token shapes. The replacement keeps any captured prefix.
+static BUILTIN_PATTERNS: LazyLock<Vec<(Regex, &'static str)>> = LazyLock::new(|| {
+    [
+        (
+            r"-----BEGIN [A-Z ]*PRIVATE KEY-----[\s\S]*?-----END [A-Z ]*PRIVATE KEY-----",
+            "[REDACTED:private_key]",
+        ),
+        (
+            r"\b(?:gh[pousr]_[A-Za-z0-9]{36,255}|github_pat_[A-Za-z0-9_]{22,255})\b",
+            "[REDACTED:github_token]",
+        ),
+        (r"\bglpat-[A-Za-z0-9_-]{20,}", "[REDACTED:gitlab_token]"),
+        (r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b", "[REDACTED:aws_access_key]"),
+        (r"\bxox[abposr]-[A-Za-z0-9-]{10,}", "[REDACTED:slack_token]"),
+        (r"\bsk-(?:ant-)?[A-Za-z0-9_-]{20,}", "[REDACTED:api_key]"),
+        (
+            r"\beyJ[A-Za-z0-9_-]{8,}\.eyJ[A-Za-z0-9_-]{8,}\.[A-Za-z0-9_-]{8,}",
+            "[REDACTED:jwt]",
+        ),
+        (
+            r"(?i)\b(bearer\s+)[A-Za-z0-9._~+/=-]{16,}",
+            "${1}[REDACTED:bearer_token]",
+        ),
+        (
+            r#"\b([a-zA-Z][a-zA-Z0-9+.-]*://)[^/\s:@"'\\]+(?::[^/\s@"'\\]*)?@"#,
+            "${1}[REDACTED:credentials]@",
+        ),
+    ]
+    .into_iter()
+    .map(|(pattern, replacement)| {
+        (
+            Regex::new(pattern).expect("built-in redaction pattern"),
+            replacement,
+        )
+    })
+    .collect()
+});
+
+/// Replaces secrets in text before it is stored, broadcast or sent to an
+/// agent: known values (secret variables, secret-looking plain board
+/// variables, `app_secrets`),
+/// common token formats, and admin-configured patterns.
+#[derive(Debug, Default, Clone)]
+pub struct Redactor {
+    /// (value, label), longest value first so overlapping secrets redact fully.
+    secrets: Vec<(String, String)>,
+    extra: Vec<Regex>,
+}
+
+impl Redactor {
+    pub fn new(secrets: impl IntoIterator<Item = (String, String)>, extra: Vec<Regex>) -> Self {
+        let mut secrets: Vec<(String, String)> = secrets
+            .into_iter()
+            .filter(|(value, _)| value.len() >= MIN_SECRET_LEN)
+            .collect();
+        secrets.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+        secrets.dedup_by(|a, b| a.0 == b.0);
+        Self { secrets, extra }
+    }
+
+    /// Builds the redactor for a board; `None` covers board-less cards.
+    pub async fn for_board(db: &SqlitePool, board_id: Option<&str>) -> Result<Self, KanbanError> {
+        let mut secrets = Vec::new();
+
+        if let Some(board_id) = board_id.filter(|id| !id.is_empty()) {
+            let variables: Option<String> =
+                sqlx::query_scalar("SELECT variables FROM board_settings WHERE board_id = ?")
+                    .bind(board_id)
+                    .fetch_optional(db)
+                    .await?;
+            for (name, value) in parse_variables(variables.as_deref().unwrap_or_default()) {
+                if is_secret_name(&name) {
+                    secrets.push((value, name));
+                }
+            }
+            for (name, value) in BoardSecretService::reveal(db, board_id).await? {
+                secrets.push((value, name));
+            }
+        }
+
+        let app_secrets: Vec<(String, Vec<u8>)> =
+            sqlx::query_as("SELECT key, value FROM app_secrets")
+                .fetch_all(db)
+                .await?;
+        for (key, value) in app_secrets {
+            if let Ok(value) = String::from_utf8(value) {
+                secrets.push((value, key));
+            }
+        }
+
+        let configured: Option<String> =
+            sqlx::query_scalar("SELECT value FROM settings WHERE key = ?")
+                .bind(EXTRA_PATTERNS_SETTING)
+                .fetch_optional(db)
+                .await?;
+        let mut extra = Vec::new();
+        for pattern in parse_patterns(configured.as_deref().unwrap_or_default()) {
+            match Regex::new(&pattern) {
+                Ok(regex) => extra.push(regex),
+                Err(e) => tracing::warn!(pattern, error = %e, "Ignoring invalid redaction pattern"),
+            }
+        }
+
+        Ok(Self::new(secrets, extra))
+    }
+
+    pub async fn for_card(db: &SqlitePool, card_id: &str) -> Result<Self, KanbanError> {
+        let board_id: Option<Option<String>> =
+            sqlx::query_scalar("SELECT board_id FROM cards WHERE id = ?")
+                .bind(card_id)
+                .fetch_optional(db)
+                .await?;
+        Self::for_board(db, board_id.flatten().as_deref()).await
+    }
+
+    /// Adds a secret that is only known to the caller, e.g. a one-off PAT.
+    pub fn with_secret(mut self, value: &str, label: &str) -> Self {
+        let secrets = std::mem::take(&mut self.secrets)
+            .into_iter()
+            .chain(std::iter::once((value.to_string(), label.to_string())));
+        Self::new(secrets, self.extra)
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        let mut out = text.to_string();
+
+        for (value, label) in &self.secrets {
+            if out.contains(value.as_str()) {
+                out = out.replace(value.as_str(), &format!("[REDACTED:{}]", label));
+            }
+        }
+        for (regex, replacement) in BUILTIN_PATTERNS.iter() {
+            if let Cow::Owned(replaced) = regex.replace_all(&out, *replacement) {
+                out = replaced;
+            }
+        }
+        for regex in &self.extra {
+            if let Cow::Owned(replaced) = regex.replace_all(&out, "[REDACTED]") {
+                out = replaced;
+            }
+        }
+
+        out
+    }
+}
+
+/// Built redactors by board, for hot paths such as the SSE relay that would
+/// otherwise re-read and decrypt every secret per event. Whatever changes a
+/// board's variables or secrets, or the global patterns, must invalidate it.
+#[derive(Debug, Default, Clone)]
+pub struct RedactorCache {
+    inner: Arc<Mutex<CacheEntries>>,
+}
+
+#[derive(Debug, Default)]
+struct CacheEntries {
+    /// Bumped on every invalidation so a build that raced one is not stored.
+    generation: u64,
+    boards: HashMap<String, Arc<Redactor>>,
+}
+
+impl RedactorCache {
+    pub async fn for_board(
+        &self,
+        db: &SqlitePool,
+        board_id: Option<&str>,
+    ) -> Result<Arc<Redactor>, KanbanError> {
+        let key = board_id.unwrap_or_default().to_string();
+        let generation = {
+            let entries = self.inner.lock().unwrap();
+            if let Some(redactor) = entries.boards.get(&key) {
+                return Ok(Arc::clone(redactor));
+            }
+            entries.generation
+        };
+
+        let redactor = Arc::new(Redactor::for_board(db, board_id).await?);
+        let mut entries = self.inner.lock().unwrap();
+        if entries.generation == generation {
+            entries.boards.insert(key, Arc::clone(&redactor));
+        }
+        Ok(redactor)
+    }
+
+    pub fn invalidate(&self, board_id: &str) {
+        let mut entries = self.inner.lock().unwrap();
+        entries.generation += 1;
+        entries.boards.remove(board_id);
+    }
+
+    pub fn clear(&self) {
+        let mut entries = self.inner.lock().unwrap();
+        entries.generation += 1;
+        entries.boards.clear();
+    }
+}
+
+/// Board variables are free-form: a JSON object or `KEY=VALUE` lines.
+pub fn parse_variables(raw: &str) -> Vec<(String, String)> {
+    if let Ok(serde_json::Value::Object(map)) = serde_json::from_str(raw) {
+        return map
+            .into_iter()
+            .filter_map(|(name, value)| match value {
+                serde_json::Value::String(value) => Some((name, value)),
+                serde_json::Value::Null => None,
+                other => Some((name, other.to_string())),
+            })
+            .collect();
+    }
+
+    raw.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (name, value) = line.split_once('=')?;
+            let value = value.trim().trim_matches('"').trim_matches('\'');
+            Some((name.trim().to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Accepts a JSON array of patterns or one pattern per line.
+pub fn parse_patterns(raw: &str) -> Vec<String> {
+    if let Ok(patterns) = serde_json::from_str::<Vec<String>>(raw) {
+        return patterns;
+    }
+    raw.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+fn is_secret_name(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    SECRET_NAME_HINTS.iter().any(|hint| {
+        upper
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .any(|word| word == *hint)
+            || (hint.contains('_') && upper.contains(hint))
+            || (hint.len() > 4 && upper.contains(hint))
+    })
+}
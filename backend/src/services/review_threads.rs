@@ -0,0 +1,389 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use crate::api::handlers::sse::WsEvent;
+use crate::domain::{Card, KanbanError};
+
+use super::git;
+use super::{EventBus, GitWorktreeService, Redactor};
+
+pub const SIDES: &[&str] = &["new", "old"];
+
+const COLUMNS: &str = "id, card_id, file, line, side, commit_sha, resolved, resolved_by, resolved_at, created_at, updated_at";
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct ReviewThreadRow {
+    pub id: String,
+    pub card_id: String,
+    /// `None` for feedback on the change as a whole.
+    pub file: Option<String>,
+    /// `None` for feedback on the whole file.
+    pub line: Option<i64>,
+    /// `new` or `old`: the side of the diff `line` counts in.
+    pub side: String,
+    /// Branch head the thread was written against; threads whose commit is
+    /// no longer the head may point at moved lines.
+    pub commit_sha: String,
+    pub resolved: bool,
+    pub resolved_by: Option<String>,
+    pub resolved_at: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct ReviewThreadComment {
+    pub id: String,
+    pub thread_id: String,
+    pub author: String,
+    pub user_id: Option<String>,
+    pub content: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReviewThread {
+    #[serde(flatten)]
+    pub thread: ReviewThreadRow,
+    /// The opening comment first, then replies.
+    pub comments: Vec<ReviewThreadComment>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CreateReviewThreadRequest {
+    pub file: Option<String>,
+    pub line: Option<i64>,
+    pub side: Option<String>,
+    /// Defaults to the head of the card's branch.
+    pub commit: Option<String>,
+    pub content: String,
+    pub author: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReplyReviewThreadRequest {
+    pub content: String,
+    pub author: Option<String>,
+}
+
+/// Reviewer comments anchored to a file, line and commit of a card's diff,
+/// with resolved state. Unanchored threads hold feedback on the whole
+/// change, such as the text given when a card is rejected.
+pub struct ReviewThreadService;
+
+impl ReviewThreadService {
+    /// Opens a thread. Anchored threads must name a file in the card's
+    /// current diff and a line inside one of its hunks on `side`; the commit
+    /// must be on the card's branch and defaults to its head.
+    pub async fn create(
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+        card: &Card,
+        req: CreateReviewThreadRequest,
+        user_id: Option<&str>,
+    ) -> Result<ReviewThread, KanbanError> {
+        if req.content.trim().is_empty() {
+            return Err(KanbanError::BadRequest(
+                "Review comment must not be empty".into(),
+            ));
+        }
+        let file = req
+            .file
+            .as_deref()
+            .map(str::trim)
+            .filter(|file| !file.is_empty())
+            .map(str::to_string);
+        let side = req.side.as_deref().unwrap_or("new");
+        if !SIDES.contains(&side) {
+            return Err(KanbanError::BadRequest(format!(
+                "Invalid side '{}': use new or old",
+                side
+            )));
+        }
+        match (file.is_some(), req.line) {
+            (_, Some(line)) if line < 1 => {
+                return Err(KanbanError::BadRequest("Lines start at 1".into()));
+            }
+            (false, Some(_)) => {
+                return Err(KanbanError::BadRequest("A line needs a file".into()));
+            }
+            _ => {}
+        }
+
+        let commit = req
+            .commit
+            .as_deref()
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .map(str::to_string);
+        let codebase = codebase_path(db, card).await?;
+        let git = codebase.filter(|_| !card.branch_name.is_empty());
+        if git.is_none() && (file.is_some() || commit.is_some()) {
+            return Err(KanbanError::BadRequest("Card has no git branch".into()));
+        }
+        if let (Some(commit), Some(codebase)) = (&commit, &git) {
+            let (codebase, sha, branch_name) =
+                (codebase.clone(), commit.clone(), card.branch_name.clone());
+            let on_branch = GitWorktreeService::cancellable(move || {
+                Ok(GitWorktreeService::is_ancestor(&codebase, &sha, &branch_name))
+            })
+            .await?;
+            if !on_branch {
+                return Err(KanbanError::BadRequest(format!(
+                    "Commit '{}' is not on the card's branch",
+                    commit
+                )));
+            }
+        }
+        if let (Some(file), Some(codebase)) = (&file, &git) {
+            let (codebase, branch_name) = (codebase.clone(), card.branch_name.clone());
+            let diff = GitWorktreeService::cancellable(move || {
+                GitWorktreeService::get_diff(&codebase, &branch_name)
+            })
+            .await?;
+            let Some(file_diff) = diff.files.iter().find(|f| &f.path == file) else {
+                return Err(KanbanError::BadRequest(format!(
+                    "File '{}' is not part of the card's diff",
+                    file
+                )));
+            };
+            if let Some(line) = req.line {
+                if !git::parse_hunks(&file_diff.diff)
+                    .iter()
+                    .any(|hunk| hunk.covers(side, line))
+                {
+                    return Err(KanbanError::BadRequest(format!(
+                        "Line {} of '{}' is outside the diff on the {} side",
+                        line, file, side
+                    )));
+                }
+            }
+        }
+        let commit = match (commit, git) {
+            (Some(commit), _) => commit,
+            (None, Some(codebase)) => {
+                let branch_name = card.branch_name.clone();
+                GitWorktreeService::cancellable(move || {
+                    Ok(GitWorktreeService::branch_head(&codebase, &branch_name).unwrap_or_default())
+                })
+                .await?
+            }
+            (None, None) => String::new(),
+        };
+
+        let now = Utc::now().to_rfc3339();
+        let thread_id = Uuid::new_v4().to_string();
+        let mut tx = db.begin().await?;
+        sqlx::query(
+            "INSERT INTO review_threads (id, card_id, file, line, side, commit_sha, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&thread_id)
+        .bind(&card.id)
+        .bind(&file)
+        .bind(req.line)
+        .bind(side)
+        .bind(&commit)
+        .bind(&now)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            "INSERT INTO review_thread_comments (id, thread_id, author, user_id, content, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(&thread_id)
+        .bind(req.author.as_deref().unwrap_or("user"))
+        .bind(user_id)
+        .bind(
+            Redactor::for_board(db, card.board_id.as_deref())
+                .await?
+                .redact(req.content.trim()),
+        )
+        .bind(&now)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        let thread = Self::get(db, &thread_id).await?;
+        broadcast(sse_tx, &thread);
+        Ok(thread)
+    }
+
+    pub async fn reply(
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+        thread_id: &str,
+        req: ReplyReviewThreadRequest,
+        user_id: Option<&str>,
+    ) -> Result<ReviewThread, KanbanError> {
+        if req.content.trim().is_empty() {
+            return Err(KanbanError::BadRequest(
+                "Review comment must not be empty".into(),
+            ));
+        }
+        let thread = Self::get(db, thread_id).await?;
+        let now = Utc::now().to_rfc3339();
+        sqlx::query(
+            "INSERT INTO review_thread_comments (id, thread_id, author, user_id, content, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(thread_id)
+        .bind(req.author.as_deref().unwrap_or("user"))
+        .bind(user_id)
+        .bind(
+            Redactor::for_card(db, &thread.thread.card_id)
+                .await?
+                .redact(req.content.trim()),
+        )
+        .bind(&now)
+        .execute(db)
+        .await?;
+        sqlx::query("UPDATE review_threads SET updated_at = ? WHERE id = ?")
+            .bind(&now)
+            .bind(thread_id)
+            .execute(db)
+            .await?;
+
+        let thread = Self::get(db, thread_id).await?;
+        broadcast(sse_tx, &thread);
+        Ok(thread)
+    }
+
+    pub async fn set_resolved(
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+        thread_id: &str,
+        resolved: bool,
+        resolved_by: Option<&str>,
+    ) -> Result<ReviewThread, KanbanError> {
+        let now = Utc::now().to_rfc3339();
+        let result = sqlx::query(
+            "UPDATE review_threads SET resolved = ?, resolved_by = ?, resolved_at = ?, updated_at = ? WHERE id = ?",
+        )
+        .bind(resolved)
+        .bind(resolved_by.filter(|_| resolved))
+        .bind(resolved.then_some(&now))
+        .bind(&now)
+        .bind(thread_id)
+        .execute(db)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(KanbanError::NotFound(format!(
+                "Review thread not found: {}",
+                thread_id
+            )));
+        }
+
+        let thread = Self::get(db, thread_id).await?;
+        broadcast(sse_tx, &thread);
+        Ok(thread)
+    }
+
+    /// The card's threads, oldest first.
+    pub async fn list(
+        db: &SqlitePool,
+        card_id: &str,
+        unresolved_only: bool,
+    ) -> Result<Vec<ReviewThread>, KanbanError> {
+        let rows: Vec<ReviewThreadRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM review_threads WHERE card_id = ? AND (? = 0 OR resolved = 0) ORDER BY created_at ASC",
+            COLUMNS
+        ))
+        .bind(card_id)
+        .bind(unresolved_only)
+        .fetch_all(db)
+        .await?;
+        let mut threads = Vec::with_capacity(rows.len());
+        for row in rows {
+            threads.push(Self::with_comments(db, row).await?);
+        }
+        Ok(threads)
+    }
+
+    pub async fn get(db: &SqlitePool, thread_id: &str) -> Result<ReviewThread, KanbanError> {
+        let row: ReviewThreadRow = sqlx::query_as(&format!(
+            "SELECT {} FROM review_threads WHERE id = ?",
+            COLUMNS
+        ))
+        .bind(thread_id)
+        .fetch_optional(db)
+        .await?
+        .ok_or_else(|| KanbanError::NotFound(format!("Review thread not found: {}", thread_id)))?;
+        Self::with_comments(db, row).await
+    }
+
+    async fn with_comments(
+        db: &SqlitePool,
+        thread: ReviewThreadRow,
+    ) -> Result<ReviewThread, KanbanError> {
+        let comments = sqlx::query_as(
+            "SELECT id, thread_id, author, user_id, content, created_at FROM review_thread_comments WHERE thread_id = ? ORDER BY created_at ASC",
+        )
+        .bind(&thread.id)
+        .fetch_all(db)
+        .await?;
+        Ok(ReviewThread { thread, comments })
+    }
+
+    /// Unresolved threads as the plan section a re-dispatched agent works
+    /// through: one numbered item per thread with its location and
+    /// conversation. Empty when there is nothing to address.
+    pub fn feedback_markdown(threads: &[ReviewThread]) -> String {
+        if threads.is_empty() {
+            return String::new();
+        }
+        let mut text = String::from(
+            "## Review Feedback\n\nThe reviewer left these unresolved threads. Address every one:\n",
+        );
+        for (index, thread) in threads.iter().enumerate() {
+            let location = match (&thread.thread.file, thread.thread.line) {
+                (Some(file), Some(line)) if thread.thread.side == "old" => {
+                    format!("`{}` removed line {}", file, line)
+                }
+                (Some(file), Some(line)) => format!("`{}` line {}", file, line),
+                (Some(file), None) => format!("`{}`", file),
+                (None, _) => "General".to_string(),
+            };
+            let commit = if thread.thread.commit_sha.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    " (at commit {})",
+                    &thread.thread.commit_sha[..thread.thread.commit_sha.len().min(12)]
+                )
+            };
+            text.push_str(&format!("\n### {}. {}{}\n", index + 1, location, commit));
+            for comment in &thread.comments {
+                text.push_str(&format!("- **{}**: {}\n", comment.author, comment.content));
+            }
+        }
+        text.push_str(
+            "\n**Action Required**: Address each thread above and re-verify all acceptance criteria.\n",
+        );
+        text
+    }
+}
+
+async fn codebase_path(db: &SqlitePool, card: &Card) -> Result<Option<String>, KanbanError> {
+    let Some(board_id) = card.board_id.as_deref().filter(|id| !id.is_empty()) else {
+        return Ok(None);
+    };
+    let path: Option<String> =
+        sqlx::query_scalar("SELECT codebase_path FROM board_settings WHERE board_id = ?")
+            .bind(board_id)
+            .fetch_optional(db)
+            .await?;
+    Ok(path.filter(|p| !p.is_empty()))
+}
+
+fn broadcast(sse_tx: &EventBus, thread: &ReviewThread) {
+    let event = WsEvent::ReviewThreadUpdated {
+        card_id: thread.thread.card_id.clone(),
+        thread: serde_json::to_value(thread).unwrap_or_default(),
+    };
+    if let Ok(payload) = serde_json::to_string(&event) {
+        let _ = sse_tx.send(payload);
+    }
+}
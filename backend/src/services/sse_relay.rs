@@ -4,29 +4,39 @@ use anyhow::{anyhow, Result};
 use chrono::Utc;
 use futures::StreamExt;
 use reqwest_eventsource::{Event, EventSource};
-use serde_json::{json, Value};
+use serde_json::{json, Map, Value};
 use sqlx::SqlitePool;
-use tokio::sync::broadcast;
 use uuid::Uuid;
 
 use crate::api::handlers::sse::WsEvent;
 use crate::domain::{AgentLog, Card, NotificationType};
 
-use super::{CardService, NotificationService};
+use super::ai_review::REVIEWER_AGENT_TYPE;
+use super::{
+    AiReviewService, CardAttemptService, CardRunService, CardService, EventBus, LogBackfillService, NotificationService, RedactorCache,
+    StatusCoalescer,
+};
 
 pub struct SseRelayService {
     pub opencode_url: String,
     pub db: SqlitePool,
-    pub sse_tx: broadcast::Sender<String>,
+    pub sse_tx: EventBus,
     pub http_client: reqwest::Client,
+    /// Shared by every relay so a card's updates batch in one place.
+    pub coalescer: StatusCoalescer,
+    /// The app's cache, so settings and secret changes invalidate it.
+    pub redactors: RedactorCache,
 }
 
 impl SseRelayService {
     pub async fn start(self) {
         let mut backoff_seconds = 1;
+        let mut reconnect = false;
 
         loop {
-            match self.connect_and_relay().await {
+            let result = self.connect_and_relay(reconnect).await;
+            reconnect = true;
+            match result {
                 Ok(()) => {
                     tracing::info!("SSE relay disconnected, reconnecting...");
                     backoff_seconds = 1;
@@ -44,7 +54,7 @@ impl SseRelayService {
         }
     }
 
-    async fn connect_and_relay(&self) -> Result<()> {
+    async fn connect_and_relay(&self, reconnect: bool) -> Result<()> {
         let endpoint = format!("{}/event", self.opencode_url.trim_end_matches('/'));
         tracing::info!(url = endpoint.as_str(), "Connecting OpenCode SSE relay");
 
@@ -55,6 +65,9 @@ impl SseRelayService {
             match next_event {
                 Ok(Event::Open) => {
                     tracing::info!("OpenCode SSE relay connected");
+                    if reconnect {
+                        self.spawn_backfill();
+                    }
                 }
                 Ok(Event::Message(message)) => {
                     let Some((event_type, payload)) =
@@ -99,6 +112,50 @@ impl SseRelayService {
         Ok(())
     }
 
+    /// Events missed while disconnected are recovered from session history.
+    fn spawn_backfill(&self) {
+        let db = self.db.clone();
+        let http_client = self.http_client.clone();
+        let sse_tx = self.sse_tx.clone();
+        let url = self.opencode_url.clone();
+        tokio::spawn(async move {
+            LogBackfillService::backfill_endpoint(&db, &http_client, &sse_tx, &url).await;
+        });
+    }
+
+    async fn handle_attempt_event(
+        &self,
+        session_id: &str,
+        event_type: &str,
+        properties: &Value,
+    ) -> Result<()> {
+        match event_type {
+            "session.status" => {
+                let busy = properties
+                    .get("status")
+                    .and_then(|s| s.get("type"))
+                    .and_then(Value::as_str)
+                    == Some("busy");
+                if busy {
+                    CardAttemptService::mark_working(&self.db, &self.sse_tx, session_id).await?;
+                }
+            }
+            "session.idle" => {
+                // Verification commands can take minutes; keep relaying.
+                let db = self.db.clone();
+                let sse_tx = self.sse_tx.clone();
+                let session_id = session_id.to_string();
+                tokio::spawn(async move {
+                    if let Err(e) = CardAttemptService::complete_session(&db, &sse_tx, &session_id).await {
+                        tracing::warn!(session_id, error = %e, "Failed to complete attempt");
+                    }
+                });
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
     /// Extract session_id from opencode event properties.
     /// opencode events nest session ID in different locations:
     /// - `properties.sessionID` (session.status, session.idle, session.diff)
@@ -208,6 +265,13 @@ impl SseRelayService {
         } else {
             None
         };
+        // Competing attempts are mapped like sub-agents but have a lifecycle
+        // of their own.
+        let attempt = if is_subagent {
+            CardAttemptService::find_by_session(&self.db, session_id).await?
+        } else {
+            None
+        };
 
         let should_log = match event_type {
             "message.part.updated" | "session.diff" | "server.connected" | "server.heartbeat" => {
@@ -222,7 +286,7 @@ impl SseRelayService {
         };
 
         if should_log {
-            let log = self
+            let created = self
                 .create_agent_log(
                     &card,
                     session_id,
@@ -232,16 +296,36 @@ impl SseRelayService {
                     subagent_agent_type.as_deref(),
                 )
                 .await?;
-            let log_event = WsEvent::AgentLogCreated {
-                card_id: card.id.clone(),
-                log,
-            };
-            if let Ok(payload) = serde_json::to_string(&log_event) {
-                let _ = self.sse_tx.send(payload);
+            // Usage is counted once per message, so it does not matter
+            // whether this event or a history backfill stored the row.
+            if event_type == "message.updated" && attempt.is_none() {
+                if let Some(info) = properties.get("info") {
+                    if let Err(e) = CardRunService::record_usage(&self.db, &card.id, info).await {
+                        tracing::warn!(card_id = card.id, error = %e, "Failed to record run usage");
+                    }
+                }
+            }
+            // `None` when a history backfill already recorded this message.
+            if let Some(log) = created {
+                let log_event = WsEvent::AgentLogCreated {
+                    card_id: card.id.clone(),
+                    log,
+                };
+                if let Ok(payload) = serde_json::to_string(&log_event) {
+                    let _ = self.sse_tx.send(payload);
+                }
             }
         }
 
         if is_subagent {
+            if attempt.is_some() {
+                self.handle_attempt_event(session_id, event_type, properties)
+                    .await?;
+            } else if event_type == "session.idle"
+                && subagent_agent_type.as_deref() == Some(REVIEWER_AGENT_TYPE)
+            {
+                AiReviewService::session_idle(&self.db, &self.sse_tx, session_id).await?;
+            }
             return Ok(());
         }
 
@@ -267,6 +351,7 @@ impl SseRelayService {
                                 session_id,
                                 "AI session busy → moving card to in_progress"
                             );
+                            self.coalescer.flush_now(&card.id).await?;
                             sqlx::query(
                                 "UPDATE cards SET ai_status = ?, stage = ?, updated_at = ? WHERE id = ?",
                             )
@@ -276,9 +361,10 @@ impl SseRelayService {
                             .bind(&card.id)
                             .execute(&self.db)
                             .await?;
-                        } else if card.stage == "in_progress"
-                            && card.ai_status != "working"
+                        } else if (card.stage == "in_progress" && card.ai_status != "working")
+                            || (card.stage == "plan" && card.ai_status == "planning")
                         {
+                            self.coalescer.flush_now(&card.id).await?;
                             sqlx::query(
                                 "UPDATE cards SET ai_status = ?, updated_at = ? WHERE id = ?",
                             )
@@ -287,15 +373,11 @@ impl SseRelayService {
                             .bind(&card.id)
                             .execute(&self.db)
                             .await?;
-                        } else if card.stage == "plan" && card.ai_status == "planning" {
-                            sqlx::query(
-                                "UPDATE cards SET ai_status = ?, updated_at = ? WHERE id = ?",
-                            )
-                            .bind("working")
-                            .bind(&now)
-                            .bind(&card.id)
-                            .execute(&self.db)
-                            .await?;
+                        } else {
+                            // Nothing changed: repeated busy pings collapse
+                            // into at most one broadcast per window.
+                            self.coalescer.queue_status(&card.id).await;
+                            return Ok(());
                         }
                     }
                     _ => {
@@ -308,12 +390,21 @@ impl SseRelayService {
                 if card.stage == "review" || card.stage == "done" {
                     return Ok(());
                 }
+                if card.stage != "in_progress" && card.stage != "plan" {
+                    self.coalescer.queue_status(&card.id).await;
+                    return Ok(());
+                }
+                // Terminal transitions carry the latest progress with them.
+                self.coalescer.flush_now(&card.id).await?;
                 if card.stage == "in_progress" {
                     tracing::info!(
                         card_id = card.id,
                         session_id,
                         "AI session idle → moving card to review"
                     );
+                    // Close the run first so whoever sees the card in review
+                    // also sees its finished run and diff snapshot.
+                    CardRunService::finish_or_warn(&self.db, &card.id, "completed").await;
                     sqlx::query(
                         "UPDATE cards SET ai_status = ?, stage = ?, updated_at = ? WHERE id = ?",
                     )
@@ -335,6 +426,17 @@ impl SseRelayService {
                         None,
                     )
                     .await;
+
+                    // Boards with AI review get a first look before a human.
+                    let db = self.db.clone();
+                    let sse_tx = self.sse_tx.clone();
+                    let http_client = self.http_client.clone();
+                    let opencode_url = self.opencode_url.clone();
+                    let card_id = card.id.clone();
+                    tokio::spawn(async move {
+                        AiReviewService::start_or_warn(&db, &sse_tx, &http_client, &opencode_url, &card_id)
+                            .await;
+                    });
                 } else if card.stage == "plan" {
                     sqlx::query("UPDATE cards SET ai_status = ?, updated_at = ? WHERE id = ?")
                         .bind("idle")
@@ -342,6 +444,7 @@ impl SseRelayService {
                         .bind(&card.id)
                         .execute(&self.db)
                         .await?;
+                    CardRunService::finish_or_warn(&self.db, &card.id, "completed").await;
                 }
             }
 
@@ -357,25 +460,18 @@ impl SseRelayService {
                     .and_then(|info| info.get("finish"))
                     .and_then(Value::as_str);
 
-                let mut progress: Value =
-                    serde_json::from_str(&card.ai_progress).unwrap_or_else(|_| json!({}));
-                progress["current_agent"] = json!(agent);
-
+                let mut patch = Map::new();
+                patch.insert("current_agent".into(), json!(agent));
                 if let Some(finish_reason) = finish {
-                    progress["last_finish_reason"] = json!(finish_reason);
+                    patch.insert("last_finish_reason".into(), json!(finish_reason));
                 }
 
-                sqlx::query("UPDATE cards SET ai_progress = ?, updated_at = ? WHERE id = ?")
-                    .bind(progress.to_string())
-                    .bind(&now)
-                    .bind(&card.id)
-                    .execute(&self.db)
-                    .await?;
+                self.coalescer.queue_progress(&card.id, patch).await;
+                return Ok(());
             }
 
             "todo.updated" => {
-                let mut progress: Value =
-                    serde_json::from_str(&card.ai_progress).unwrap_or_else(|_| json!({}));
+                let mut patch = Map::new();
 
                 if let Some(todos) = properties.get("todos").and_then(Value::as_array) {
                     let total = todos.len();
@@ -388,8 +484,8 @@ impl SseRelayService {
                                 == Some("completed")
                         })
                         .count();
-                    progress["total_todos"] = json!(total);
-                    progress["completed_todos"] = json!(completed);
+                    patch.insert("total_todos".into(), json!(total));
+                    patch.insert("completed_todos".into(), json!(completed));
 
                     if let Some(current) = todos.iter().find(|t| {
                         t.get("status")
@@ -402,17 +498,13 @@ impl SseRelayService {
                             .or_else(|| current.get("text"))
                             .and_then(Value::as_str)
                         {
-                            progress["current_task"] = json!(content);
+                            patch.insert("current_task".into(), json!(content));
                         }
                     }
                 }
 
-                sqlx::query("UPDATE cards SET ai_progress = ?, updated_at = ? WHERE id = ?")
-                    .bind(progress.to_string())
-                    .bind(&now)
-                    .bind(&card.id)
-                    .execute(&self.db)
-                    .await?;
+                self.coalescer.queue_progress(&card.id, patch).await;
+                return Ok(());
             }
 
             _ => {
@@ -622,7 +714,7 @@ impl SseRelayService {
         properties: &Value,
         is_subagent: bool,
         subagent_type: Option<&str>,
-    ) -> Result<AgentLog> {
+    ) -> Result<Option<AgentLog>> {
         let id = Uuid::new_v4().to_string();
         let created_at = Utc::now().to_rfc3339();
         let agent = subagent_type
@@ -634,8 +726,16 @@ impl SseRelayService {
                     .and_then(Value::as_str)
                     .map(str::to_owned)
             });
-        let content =
-            Self::build_log_content(event_type, properties, agent.as_deref(), is_subagent);
+        let redactor = self
+            .redactors
+            .for_board(&self.db, card.board_id.as_deref())
+            .await?;
+        let content = redactor.redact(&Self::build_log_content(
+            event_type,
+            properties,
+            agent.as_deref(),
+            is_subagent,
+        ));
         let metadata = if is_subagent {
             let mut meta = properties.clone();
             meta["_subagent"] = json!(true);
@@ -646,9 +746,22 @@ impl SseRelayService {
         } else {
             properties.to_string()
         };
+        let metadata = redactor.redact(&metadata);
 
-        sqlx::query(
-            "INSERT INTO agent_logs (id, card_id, session_id, event_type, agent, content, metadata, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        // Finished messages are keyed like history backfills so neither
+        // path duplicates the other.
+        let upstream_id = match event_type {
+            "message.updated" => properties
+                .get("info")
+                .filter(|info| info.get("finish").is_some())
+                .and_then(|info| info.get("id"))
+                .and_then(Value::as_str)
+                .map(|id| format!("message:{}", id)),
+            _ => None,
+        };
+
+        let result = sqlx::query(
+            "INSERT OR IGNORE INTO agent_logs (id, card_id, session_id, event_type, agent, content, metadata, created_at, upstream_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
         )
         .bind(&id)
         .bind(&card.id)
@@ -658,10 +771,14 @@ impl SseRelayService {
         .bind(&content)
         .bind(&metadata)
         .bind(&created_at)
+        .bind(&upstream_id)
         .execute(&self.db)
         .await?;
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
 
-        Ok(AgentLog {
+        Ok(Some(AgentLog {
             id,
             card_id: card.id.clone(),
             session_id: session_id.to_string(),
@@ -670,10 +787,10 @@ impl SseRelayService {
             content,
             metadata,
             created_at,
-        })
+        }))
     }
 
-    fn build_log_content(
+    pub(crate) fn build_log_content(
         event_type: &str,
         properties: &Value,
         agent: Option<&str>,
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::Utc;
+use serde_json::{json, Map, Value};
+use sqlx::SqlitePool;
+
+use crate::api::handlers::sse::WsEvent;
+use crate::domain::KanbanError;
+
+use super::{CardService, EventBus};
+
+/// Batches `ai_progress` writes and `AiStatusChanged` broadcasts per card.
+///
+/// Progress updates (agent, todos) are merged and written once per window;
+/// repeated status events that change nothing collapse into one broadcast.
+/// Callers flush explicitly before stage changes and terminal states so those
+/// are never delayed. A zero window disables coalescing.
+#[derive(Clone)]
+pub struct StatusCoalescer {
+    db: SqlitePool,
+    sse_tx: EventBus,
+    window: Duration,
+    pending: Arc<Mutex<HashMap<String, Map<String, Value>>>>,
+}
+
+impl StatusCoalescer {
+    pub fn new(db: SqlitePool, sse_tx: EventBus, window: Duration) -> Self {
+        Self {
+            db,
+            sse_tx,
+            window,
+            pending: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Merges `patch` into the card's pending progress. The first update in
+    /// a window schedules the flush; later ones only overwrite keys.
+    pub async fn queue_progress(&self, card_id: &str, patch: Map<String, Value>) {
+        if self.window.is_zero() {
+            self.pending_insert(card_id, patch);
+            if let Err(e) = self.flush(card_id, true).await {
+                tracing::warn!(card_id, error = %e, "Failed to write AI progress");
+            }
+            return;
+        }
+
+        if self.pending_insert(card_id, patch) {
+            let coalescer = self.clone();
+            let card_id = card_id.to_string();
+            tokio::spawn(async move {
+                tokio::time::sleep(coalescer.window).await;
+                if let Err(e) = coalescer.flush(&card_id, true).await {
+                    tracing::warn!(card_id, error = %e, "Failed to flush coalesced AI progress");
+                }
+            });
+        }
+    }
+
+    /// Schedules a status broadcast without changing progress.
+    pub async fn queue_status(&self, card_id: &str) {
+        self.queue_progress(card_id, Map::new()).await;
+    }
+
+    /// Writes any pending progress now without broadcasting; the caller is
+    /// about to broadcast the card's new state itself.
+    pub async fn flush_now(&self, card_id: &str) -> Result<(), KanbanError> {
+        self.flush(card_id, false).await
+    }
+
+    /// Returns true when this starts a new pending entry for the card.
+    fn pending_insert(&self, card_id: &str, patch: Map<String, Value>) -> bool {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        match pending.get_mut(card_id) {
+            Some(existing) => {
+                existing.extend(patch);
+                false
+            }
+            None => {
+                pending.insert(card_id.to_string(), patch);
+                true
+            }
+        }
+    }
+
+    async fn flush(&self, card_id: &str, broadcast: bool) -> Result<(), KanbanError> {
+        let patch = {
+            let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+            pending.remove(card_id)
+        };
+        let Some(patch) = patch else {
+            return Ok(());
+        };
+
+        // Merged by SQLite in the write itself, so progress written by
+        // anyone else since this update was queued is kept.
+        if !patch.is_empty() {
+            sqlx::query(
+                "UPDATE cards SET ai_progress = json_patch(CASE WHEN json_valid(ai_progress) AND json_type(ai_progress) = 'object' THEN ai_progress ELSE '{}' END, ?), updated_at = ? WHERE id = ?",
+            )
+            .bind(Value::Object(patch).to_string())
+            .bind(Utc::now().to_rfc3339())
+            .bind(card_id)
+            .execute(&self.db)
+            .await?;
+        }
+
+        let card = match CardService::get_card_model(&self.db, card_id).await {
+            Ok(card) => card,
+            Err(KanbanError::NotFound(_)) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let progress: Value = serde_json::from_str(&card.ai_progress).unwrap_or_else(|_| json!({}));
+
+        if broadcast {
+            let event = WsEvent::AiStatusChanged {
+                card_id: card.id.clone(),
+                board_id: card.board_id.clone(),
+                status: card.ai_status.clone(),
+                progress,
+                stage: card.stage.clone(),
+                ai_session_id: card.ai_session_id.clone(),
+            };
+            if let Ok(payload) = serde_json::to_string(&event) {
+                let _ = self.sse_tx.send(payload);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+
+use chrono::DateTime;
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use crate::domain::{AgentLog, AiQuestion, Card, Comment, KanbanError, SessionMapping};
+
+use super::LogRetentionService;
+
+/// One line of a card's AI run, in the order it happened.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TranscriptEntry {
+    Agent {
+        at: String,
+        session_id: String,
+        event_type: String,
+        agent: Option<String>,
+        content: String,
+    },
+    Subagent {
+        at: String,
+        session_id: String,
+        parent_session_id: String,
+        agent_type: Option<String>,
+        event_type: String,
+        content: String,
+    },
+    Question {
+        at: String,
+        session_id: String,
+        question: String,
+        options: serde_json::Value,
+    },
+    Answer {
+        at: String,
+        session_id: String,
+        question: String,
+        answer: String,
+    },
+    Comment {
+        at: String,
+        author: String,
+        content: String,
+    },
+    StageChange {
+        at: String,
+        from_stage: String,
+        to_stage: String,
+        ai_status: String,
+    },
+}
+
+impl TranscriptEntry {
+    pub fn at(&self) -> &str {
+        match self {
+            Self::Agent { at, .. }
+            | Self::Subagent { at, .. }
+            | Self::Question { at, .. }
+            | Self::Answer { at, .. }
+            | Self::Comment { at, .. }
+            | Self::StageChange { at, .. } => at,
+        }
+    }
+}
+
+/// A review thread comment with the location of its thread.
+#[derive(sqlx::FromRow)]
+struct ReviewComment {
+    created_at: String,
+    author: String,
+    content: String,
+    file: Option<String>,
+    line: Option<i64>,
+}
+
+pub struct TranscriptService;
+
+impl TranscriptService {
+    /// Interleaves agent logs (parent and sub-agent sessions), questions and
+    /// their answers, comments (review threads included) and stage changes
+    /// for a card.
+    pub async fn entries(
+        db: &SqlitePool,
+        card_id: &str,
+    ) -> Result<Vec<TranscriptEntry>, KanbanError> {
+        let logs: Vec<AgentLog> = LogRetentionService::load_logs(db, card_id)
+            .await?
+            .into_iter()
+            .filter(|log| log.event_type != "message.part.delta")
+            .collect();
+
+        let mappings: Vec<SessionMapping> =
+            sqlx::query_as("SELECT * FROM session_mappings WHERE card_id = ?")
+                .bind(card_id)
+                .fetch_all(db)
+                .await?;
+        let mappings: HashMap<String, SessionMapping> = mappings
+            .into_iter()
+            .map(|m| (m.child_session_id.clone(), m))
+            .collect();
+
+        let questions: Vec<AiQuestion> = sqlx::query_as(
+            "SELECT * FROM ai_questions WHERE card_id = ? ORDER BY created_at ASC",
+        )
+        .bind(card_id)
+        .fetch_all(db)
+        .await?;
+
+        let comments: Vec<Comment> = sqlx::query_as(
+            "SELECT * FROM comments WHERE card_id = ? ORDER BY created_at ASC",
+        )
+        .bind(card_id)
+        .fetch_all(db)
+        .await?;
+
+        let review_comments: Vec<ReviewComment> = sqlx::query_as(
+            "SELECT c.created_at, c.author, c.content, t.file, t.line FROM review_thread_comments c JOIN review_threads t ON t.id = c.thread_id WHERE t.card_id = ? ORDER BY c.created_at ASC",
+        )
+        .bind(card_id)
+        .fetch_all(db)
+        .await?;
+
+        let stages: Vec<(String, String, String, String)> = sqlx::query_as(
+            "SELECT from_stage, to_stage, ai_status, created_at FROM card_stage_history WHERE card_id = ? ORDER BY id ASC",
+        )
+        .bind(card_id)
+        .fetch_all(db)
+        .await?;
+
+        let mut entries = Vec::new();
+
+        for log in logs {
+            let entry = match mappings.get(&log.session_id) {
+                Some(mapping) => TranscriptEntry::Subagent {
+                    at: log.created_at,
+                    session_id: log.session_id,
+                    parent_session_id: mapping.parent_session_id.clone(),
+                    agent_type: mapping.agent_type.clone().or(log.agent),
+                    event_type: log.event_type,
+                    content: log.content,
+                },
+                None => TranscriptEntry::Agent {
+                    at: log.created_at,
+                    session_id: log.session_id,
+                    event_type: log.event_type,
+                    agent: log.agent,
+                    content: log.content,
+                },
+            };
+            entries.push(entry);
+        }
+
+        for question in questions {
+            let options = serde_json::from_str(&question.options)
+                .unwrap_or_else(|_| serde_json::Value::Array(Vec::new()));
+            let answered = match (&question.answer, &question.answered_at) {
+                (Some(answer), Some(answered_at)) => Some(TranscriptEntry::Answer {
+                    at: answered_at.clone(),
+                    session_id: question.session_id.clone(),
+                    question: question.question.clone(),
+                    answer: answer.clone(),
+                }),
+                _ => None,
+            };
+            entries.push(TranscriptEntry::Question {
+                at: question.created_at,
+                session_id: question.session_id,
+                question: question.question,
+                options,
+            });
+            entries.extend(answered);
+        }
+
+        for comment in comments {
+            entries.push(TranscriptEntry::Comment {
+                at: comment.created_at,
+                author: comment.author,
+                content: comment.content,
+            });
+        }
+
+        for comment in review_comments {
+            let content = match (comment.file, comment.line) {
+                (Some(file), Some(line)) => {
+                    format!("On `{}` line {}: {}", file, line, comment.content)
+                }
+                (Some(file), None) => format!("On `{}`: {}", file, comment.content),
+                (None, _) => comment.content,
+            };
+            entries.push(TranscriptEntry::Comment {
+                at: comment.created_at,
+                author: comment.author,
+                content,
+            });
+        }
+
+        for (from_stage, to_stage, ai_status, at) in stages {
+            entries.push(TranscriptEntry::StageChange {
+                at,
+                from_stage,
+                to_stage,
+                ai_status,
+            });
+        }
+
+        // Stable sort: rows sharing a timestamp keep their per-source order.
+        entries.sort_by_key(|entry| {
+            DateTime::parse_from_rfc3339(entry.at())
+                .map(|t| t.timestamp_nanos_opt().unwrap_or_default())
+                .unwrap_or_default()
+        });
+
+        Ok(entries)
+    }
+
+    pub fn to_jsonl(entries: &[TranscriptEntry]) -> String {
+        let mut out = String::new();
+        for entry in entries {
+            if let Ok(line) = serde_json::to_string(entry) {
+                out.push_str(&line);
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    pub fn to_markdown(card: &Card, entries: &[TranscriptEntry]) -> String {
+        let mut out = format!("# AI transcript: {}\n\n", card.title);
+        out.push_str(&format!("- Card: `{}`\n", card.id));
+        out.push_str(&format!("- Stage: {} ({})\n", card.stage, card.ai_status));
+        if !card.branch_name.is_empty() {
+            out.push_str(&format!("- Branch: `{}`\n", card.branch_name));
+        }
+        if let Some(session_id) = &card.ai_session_id {
+            out.push_str(&format!("- Session: `{}`\n", session_id));
+        }
+        out.push('\n');
+
+        if entries.is_empty() {
+            out.push_str("_No AI activity recorded._\n");
+            return out;
+        }
+
+        for entry in entries {
+            match entry {
+                TranscriptEntry::Agent {
+                    at, agent, content, ..
+                } => {
+                    out.push_str(&format!(
+                        "**{}** · {}: {}\n\n",
+                        at,
+                        agent.as_deref().unwrap_or("agent"),
+                        content
+                    ));
+                }
+                TranscriptEntry::Subagent {
+                    at,
+                    session_id,
+                    agent_type,
+                    content,
+                    ..
+                } => {
+                    out.push_str(&format!(
+                        "> **{}** · {} sub-agent `{}`: {}\n\n",
+                        at,
+                        agent_type.as_deref().unwrap_or("unknown"),
+                        session_id,
+                        // The relay prefixes sub-agent logs with "↳ agent | ".
+                        content
+                            .strip_prefix("↳ ")
+                            .and_then(|c| c.split_once(" | "))
+                            .map_or(content.as_str(), |(_, body)| body)
+                    ));
+                }
+                TranscriptEntry::Question {
+                    at,
+                    question,
+                    options,
+                    ..
+                } => {
+                    out.push_str(&format!("**{}** · AI asked: {}\n", at, question));
+                    if let Some(options) = options.as_array().filter(|o| !o.is_empty()) {
+                        let labels: Vec<String> = options
+                            .iter()
+                            .map(|o| {
+                                o.get("label")
+                                    .and_then(|l| l.as_str())
+                                    .or_else(|| o.as_str())
+                                    .map(str::to_owned)
+                                    .unwrap_or_else(|| o.to_string())
+                            })
+                            .collect();
+                        out.push_str(&format!("  Options: {}\n", labels.join(", ")));
+                    }
+                    out.push('\n');
+                }
+                TranscriptEntry::Answer { at, answer, .. } => {
+                    out.push_str(&format!("**{}** · Human answered: {}\n\n", at, answer));
+                }
+                TranscriptEntry::Comment {
+                    at,
+                    author,
+                    content,
+                } => {
+                    out.push_str(&format!("**{}** · {} commented:\n\n", at, author));
+                    for line in content.lines() {
+                        out.push_str(&format!("> {}\n", line));
+                    }
+                    out.push('\n');
+                }
+                TranscriptEntry::StageChange {
+                    at,
+                    from_stage,
+                    to_stage,
+                    ai_status,
+                } => {
+                    out.push_str(&format!(
+                        "---\n\n**{}** · Stage {} → **{}** (AI: {})\n\n",
+                        at, from_stage, to_stage, ai_status
+                    ));
+                }
+            }
+        }
+
+        out
+    }
+}
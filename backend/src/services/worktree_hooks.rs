@@ -0,0 +1,599 @@
+use std::collections::VecDeque;
+use std::path::{Component, Path};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use uuid::Uuid;
+
+use crate::api::handlers::sse::WsEvent;
+use crate::domain::{AgentLog, Card, KanbanError};
+
+use super::{BoardVariableService, EventBus, GitWorktreeService, Redactor};
+
+/// `agent_logs.event_type` for setup, teardown and verification output.
+pub const SETUP_EVENT: &str = "worktree.setup";
+pub const TEARDOWN_EVENT: &str = "worktree.teardown";
+pub const VERIFY_EVENT: &str = "worktree.verify";
+
+const DEFAULT_TIMEOUT_SECS: u64 = 600;
+
+/// Only the tail of a command's output is logged; that is where errors are.
+const MAX_OUTPUT_CHARS: usize = 8_000;
+/// Bytes kept per pipe while a command runs: enough for `MAX_OUTPUT_CHARS`
+/// characters of any UTF-8 text.
+const MAX_OUTPUT_BYTES: usize = MAX_OUTPUT_CHARS * 4;
+
+/// `board_settings.worktree_hooks`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorktreeHooks {
+    #[serde(default)]
+    pub setup: Vec<HookStep>,
+    #[serde(default)]
+    pub teardown: Vec<HookStep>,
+    /// Commands that check finished work, e.g. the test suite. Only
+    /// `command` steps are allowed.
+    #[serde(default)]
+    pub verify: Vec<HookStep>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HookStep {
+    /// Shell command run in the worktree with the board's variables.
+    Command {
+        run: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout_secs: Option<u64>,
+    },
+    /// File or directory copied from the main checkout into the worktree.
+    Copy { path: String },
+    /// Symlink in the worktree pointing at the main checkout's copy, for
+    /// heavy directories like `node_modules`.
+    Symlink { path: String },
+}
+
+impl HookStep {
+    fn describe(&self) -> String {
+        match self {
+            HookStep::Command { run, .. } => format!("$ {}", run),
+            HookStep::Copy { path } => format!("copy {}", path),
+            HookStep::Symlink { path } => format!("symlink {}", path),
+        }
+    }
+}
+
+/// Result of running a worktree's `verify` steps.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Verification {
+    pub passed: bool,
+    pub steps: Vec<VerificationStep>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationStep {
+    pub step: String,
+    pub passed: bool,
+    /// Output tail on success, reason on failure; redacted.
+    pub output: String,
+    pub duration_ms: u64,
+}
+
+impl WorktreeHooks {
+    /// Parses and validates the stored JSON; an empty string means no hooks.
+    pub fn parse(raw: &str) -> Result<Self, KanbanError> {
+        if raw.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        let hooks: Self = serde_json::from_str(raw)
+            .map_err(|e| KanbanError::BadRequest(format!("Invalid worktree_hooks: {}", e)))?;
+
+        if hooks
+            .verify
+            .iter()
+            .any(|step| !matches!(step, HookStep::Command { .. }))
+        {
+            return Err(KanbanError::BadRequest(
+                "Invalid worktree_hooks: verify steps must be commands".into(),
+            ));
+        }
+        for step in hooks.setup.iter().chain(&hooks.teardown).chain(&hooks.verify) {
+            match step {
+                HookStep::Command { run, .. } if run.trim().is_empty() => {
+                    return Err(KanbanError::BadRequest(
+                        "Invalid worktree_hooks: command must not be empty".into(),
+                    ));
+                }
+                HookStep::Copy { path } | HookStep::Symlink { path } => validate_path(path)?,
+                HookStep::Command { .. } => {}
+            }
+        }
+        Ok(hooks)
+    }
+}
+
+/// Runs a board's worktree hooks. Every step's outcome is written to the
+/// card's agent log so a failed dispatch explains itself.
+pub struct WorktreeHookService;
+
+impl WorktreeHookService {
+    pub async fn load(db: &SqlitePool, board_id: Option<&str>) -> Result<WorktreeHooks, KanbanError> {
+        let Some(board_id) = board_id.filter(|id| !id.is_empty()) else {
+            return Ok(WorktreeHooks::default());
+        };
+        let raw: Option<String> =
+            sqlx::query_scalar("SELECT worktree_hooks FROM board_settings WHERE board_id = ?")
+                .bind(board_id)
+                .fetch_optional(db)
+                .await?;
+        WorktreeHooks::parse(raw.as_deref().unwrap_or_default())
+    }
+
+    /// Whether a new worktree on the board has setup steps to run. Hooks
+    /// that fail to load count, so the setup path reports the error.
+    pub async fn has_setup(db: &SqlitePool, board_id: Option<&str>) -> bool {
+        Self::load(db, board_id)
+            .await
+            .map_or(true, |hooks| !hooks.setup.is_empty())
+    }
+
+    /// Runs the setup steps in order and stops at the first failure, whose
+    /// reason is returned.
+    pub async fn run_setup(
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+        card: &Card,
+        codebase_path: &str,
+    ) -> Result<(), KanbanError> {
+        let hooks = Self::load(db, card.board_id.as_deref()).await?;
+        if hooks.setup.is_empty() {
+            return Ok(());
+        }
+        let env = BoardVariableService::env_for_card(db, card).await?;
+        let redactor = Redactor::for_board(db, card.board_id.as_deref()).await?;
+
+        for (index, step) in hooks.setup.iter().enumerate() {
+            let started = Instant::now();
+            let result = run_step(step, codebase_path, &card.worktree_path, &env).await;
+            let failed = result.is_err();
+            let detail = match &result {
+                Ok(output) => output.clone(),
+                Err(reason) => reason.clone(),
+            };
+            log_step(
+                db,
+                sse_tx,
+                card,
+                &redactor,
+                SETUP_EVENT,
+                step,
+                index,
+                &detail,
+                failed,
+                started.elapsed(),
+            )
+            .await;
+
+            if let Err(reason) = result {
+                return Err(KanbanError::Internal(redactor.redact(&format!(
+                    "Worktree setup step {} ({}) failed: {}",
+                    index + 1,
+                    step.describe(),
+                    first_line(&reason)
+                ))));
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs every teardown step; failures are logged and never block the
+    /// worktree's removal.
+    pub async fn run_teardown(db: &SqlitePool, sse_tx: &EventBus, card: &Card, codebase_path: &str) {
+        if card.worktree_path.is_empty() || !Path::new(&card.worktree_path).exists() {
+            return;
+        }
+        let hooks = match Self::load(db, card.board_id.as_deref()).await {
+            Ok(hooks) if !hooks.teardown.is_empty() => hooks,
+            Ok(_) => return,
+            Err(e) => {
+                tracing::warn!(card_id = card.id, error = %e, "Failed to load worktree teardown hooks");
+                return;
+            }
+        };
+        let env = BoardVariableService::env_for_card(db, card).await.unwrap_or_default();
+        let redactor = Redactor::for_board(db, card.board_id.as_deref())
+            .await
+            .unwrap_or_default();
+
+        for (index, step) in hooks.teardown.iter().enumerate() {
+            let started = Instant::now();
+            let result = run_step(step, codebase_path, &card.worktree_path, &env).await;
+            if let Err(reason) = &result {
+                tracing::warn!(card_id = card.id, step = step.describe(), reason, "Worktree teardown step failed");
+            }
+            let failed = result.is_err();
+            let detail = result.unwrap_or_else(|reason| reason);
+            log_step(
+                db,
+                sse_tx,
+                card,
+                &redactor,
+                TEARDOWN_EVENT,
+                step,
+                index,
+                &detail,
+                failed,
+                started.elapsed(),
+            )
+            .await;
+        }
+    }
+
+    /// Runs every verify step against the card's worktree, so a failing
+    /// step does not hide the others. `None` when the board has none.
+    pub async fn run_verify(
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+        card: &Card,
+        codebase_path: &str,
+    ) -> Result<Option<Verification>, KanbanError> {
+        let hooks = Self::load(db, card.board_id.as_deref()).await?;
+        if hooks.verify.is_empty() {
+            return Ok(None);
+        }
+        let env = BoardVariableService::env_for_card(db, card).await?;
+        let redactor = Redactor::for_board(db, card.board_id.as_deref()).await?;
+
+        let mut steps = Vec::with_capacity(hooks.verify.len());
+        for (index, step) in hooks.verify.iter().enumerate() {
+            let started = Instant::now();
+            let result = run_step(step, codebase_path, &card.worktree_path, &env).await;
+            let elapsed = started.elapsed();
+            let failed = result.is_err();
+            let detail = result.unwrap_or_else(|reason| reason);
+            log_step(
+                db,
+                sse_tx,
+                card,
+                &redactor,
+                VERIFY_EVENT,
+                step,
+                index,
+                &detail,
+                failed,
+                elapsed,
+            )
+            .await;
+            steps.push(VerificationStep {
+                step: redactor.redact(&step.describe()),
+                passed: !failed,
+                output: redactor.redact(&detail),
+                duration_ms: elapsed.as_millis() as u64,
+            });
+        }
+
+        Ok(Some(Verification {
+            passed: steps.iter().all(|step| step.passed),
+            steps,
+        }))
+    }
+
+    /// Teardown hooks, then [`GitWorktreeService::remove_worktree`].
+    pub async fn remove_worktree(
+        db: &SqlitePool,
+        sse_tx: &EventBus,
+        card: &Card,
+        codebase_path: &str,
+    ) -> Result<(), KanbanError> {
+        Self::run_teardown(db, sse_tx, card, codebase_path).await;
+        let (repo, worktree_path, branch_name) =
+            (codebase_path.to_string(), card.worktree_path.clone(), card.branch_name.clone());
+        GitWorktreeService::blocking(move || {
+            GitWorktreeService::remove_worktree(&repo, &worktree_path, &branch_name)
+        })
+        .await
+    }
+}
+
+/// Hook paths are relative to both checkouts and may not escape them.
+fn validate_path(path: &str) -> Result<(), KanbanError> {
+    let relative = Path::new(path);
+    let valid = !path.trim().is_empty()
+        && relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+        && relative.components().any(|component| matches!(component, Component::Normal(_)));
+    if valid {
+        Ok(())
+    } else {
+        Err(KanbanError::BadRequest(format!(
+            "Invalid worktree_hooks path '{}': use a path relative to the repository root",
+            path
+        )))
+    }
+}
+
+/// Output on success, reason on failure.
+async fn run_step(
+    step: &HookStep,
+    codebase_path: &str,
+    worktree_path: &str,
+    env: &[(String, String)],
+) -> Result<String, String> {
+    match step {
+        HookStep::Command { run, timeout_secs } => {
+            let timeout = Duration::from_secs(timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS));
+            run_command(run, worktree_path, env, timeout).await
+        }
+        HookStep::Copy { path } => {
+            let source = Path::new(codebase_path).join(path);
+            let target = Path::new(worktree_path).join(path);
+            if !source.exists() {
+                return Err(format!("{} does not exist in the main checkout", path));
+            }
+            // Directories can be large; copy them off the async workers.
+            tokio::task::spawn_blocking(move || {
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                copy_recursive(&source, &target)
+            })
+            .await
+            .map_err(|e| format!("Copy task failed: {}", e))?
+            .map_err(|e| format!("Failed to copy {}: {}", path, e))?;
+            exclude_untracked(worktree_path, path).await?;
+            Ok(format!("Copied {}", path))
+        }
+        HookStep::Symlink { path } => {
+            let source = Path::new(codebase_path).join(path);
+            let target = Path::new(worktree_path).join(path);
+            if !source.exists() {
+                return Err(format!("{} does not exist in the main checkout", path));
+            }
+            match std::fs::symlink_metadata(&target) {
+                Ok(meta) if meta.file_type().is_symlink() => {
+                    std::fs::remove_file(&target).map_err(|e| e.to_string())?;
+                }
+                Ok(_) => return Err(format!("{} already exists in the worktree", path)),
+                Err(_) => {}
+            }
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            symlink(&source, &target).map_err(|e| format!("Failed to link {}: {}", path, e))?;
+            exclude_untracked(worktree_path, path).await?;
+            Ok(format!("Linked {} -> {}", path, source.display()))
+        }
+    }
+}
+
+async fn run_command(
+    run: &str,
+    worktree_path: &str,
+    env: &[(String, String)],
+    timeout: Duration,
+) -> Result<String, String> {
+    let mut command = tokio::process::Command::new("sh");
+    command
+        .arg("-c")
+        .arg(run)
+        .current_dir(worktree_path)
+        .envs(env.iter().map(|(name, value)| (name, value)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    // The command leads its own process group so a timeout also stops
+    // whatever it started, not just the shell.
+    #[cfg(unix)]
+    command.process_group(0);
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Failed to start command: {}", e))?;
+    let pid = child.id();
+    let (stdout, stderr) = (child.stdout.take(), child.stderr.take());
+
+    // The pipes are drained as the command runs, keeping only their tails,
+    // so a noisy command cannot fill memory.
+    let run = async { tokio::join!(read_tail(stdout), read_tail(stderr), child.wait()) };
+    let ((stdout, stdout_dropped), (stderr, stderr_dropped), status) =
+        match tokio::time::timeout(timeout, run).await {
+            Ok((stdout, stderr, status)) => (
+                stdout,
+                stderr,
+                status.map_err(|e| format!("Failed to run command: {}", e))?,
+            ),
+            Err(_) => {
+                kill_process_group(pid).await;
+                return Err(format!("Timed out after {}s", timeout.as_secs()));
+            }
+        };
+
+    let mut combined = String::from_utf8_lossy(&stdout).into_owned();
+    combined.push_str(&String::from_utf8_lossy(&stderr));
+    let combined = tail(combined.trim_end(), stdout_dropped + stderr_dropped);
+
+    if status.success() {
+        Ok(combined)
+    } else {
+        let status = status
+            .code()
+            .map(|code| format!("exit code {}", code))
+            .unwrap_or_else(|| "terminated by signal".to_string());
+        if combined.is_empty() {
+            Err(status)
+        } else {
+            Err(format!("{}\n{}", status, combined))
+        }
+    }
+}
+
+#[cfg(unix)]
+async fn kill_process_group(pid: Option<u32>) {
+    if let Some(pid) = pid {
+        let _ = tokio::process::Command::new("kill")
+            .arg("-KILL")
+            .arg("--")
+            .arg(format!("-{}", pid))
+            .status()
+            .await;
+    }
+}
+
+#[cfg(not(unix))]
+async fn kill_process_group(_pid: Option<u32>) {}
+
+/// Copied files and symlinks are local setup, so keep them out of the
+/// card's auto-commit unless the repository already tracks them.
+async fn exclude_untracked(worktree_path: &str, path: &str) -> Result<(), String> {
+    let (worktree_path, path) = (worktree_path.to_string(), path.to_string());
+    GitWorktreeService::blocking(move || {
+        if GitWorktreeService::is_tracked(&worktree_path, &path) {
+            return Ok(());
+        }
+        let pattern = format!("/{}", path.trim_start_matches("./").trim_end_matches('/'));
+        GitWorktreeService::ensure_excluded(&worktree_path, &pattern)
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+fn copy_recursive(source: &Path, target: &Path) -> std::io::Result<()> {
+    if source.is_dir() {
+        std::fs::create_dir_all(target)?;
+        for entry in std::fs::read_dir(source)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &target.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        std::fs::copy(source, target).map(|_| ())
+    }
+}
+
+#[cfg(unix)]
+fn symlink(source: &Path, target: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(source, target)
+}
+
+#[cfg(not(unix))]
+fn symlink(_source: &Path, _target: &Path) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "symlink steps are only supported on Unix",
+    ))
+}
+
+/// Reads `pipe` to the end, keeping its last `MAX_OUTPUT_BYTES`. Returns
+/// them with the number of bytes dropped before them.
+async fn read_tail(pipe: Option<impl AsyncRead + Unpin>) -> (Vec<u8>, usize) {
+    let Some(mut pipe) = pipe else {
+        return (Vec::new(), 0);
+    };
+    let mut kept = VecDeque::new();
+    let mut dropped = 0;
+    let mut chunk = [0_u8; 8 * 1024];
+    loop {
+        match pipe.read(&mut chunk).await {
+            Ok(0) | Err(_) => break,
+            Ok(read) => {
+                kept.extend(&chunk[..read]);
+                let excess = kept.len().saturating_sub(MAX_OUTPUT_BYTES);
+                kept.drain(..excess);
+                dropped += excess;
+            }
+        }
+    }
+    (kept.into(), dropped)
+}
+
+/// The last `MAX_OUTPUT_CHARS` characters of `output`, whose pipes already
+/// dropped `dropped_bytes` while it was read.
+fn tail(output: &str, dropped_bytes: usize) -> String {
+    let count = output.chars().count();
+    if count <= MAX_OUTPUT_CHARS && dropped_bytes == 0 {
+        return output.to_string();
+    }
+    let skip = count.saturating_sub(MAX_OUTPUT_CHARS);
+    let kept: String = output.chars().skip(skip).collect();
+    if dropped_bytes == 0 {
+        format!("[... {} characters truncated]\n{}", skip, kept)
+    } else {
+        format!(
+            "[... {} bytes and {} characters truncated]\n{}",
+            dropped_bytes, skip, kept
+        )
+    }
+}
+
+fn first_line(reason: &str) -> &str {
+    reason.lines().next().unwrap_or(reason)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn log_step(
+    db: &SqlitePool,
+    sse_tx: &EventBus,
+    card: &Card,
+    redactor: &Redactor,
+    event_type: &str,
+    step: &HookStep,
+    index: usize,
+    detail: &str,
+    failed: bool,
+    elapsed: Duration,
+) {
+    let status = if failed { "failed" } else { "ok" };
+    let mut content = format!("[{} {}] {}", event_type, status, step.describe());
+    if !detail.is_empty() {
+        content.push('\n');
+        content.push_str(detail);
+    }
+    let metadata: Value = json!({
+        "step": index + 1,
+        "kind": step,
+        "status": status,
+        "duration_ms": elapsed.as_millis() as u64,
+    });
+
+    let log = AgentLog {
+        id: Uuid::new_v4().to_string(),
+        card_id: card.id.clone(),
+        session_id: card.ai_session_id.clone().unwrap_or_default(),
+        event_type: event_type.to_string(),
+        agent: None,
+        content: redactor.redact(&content),
+        metadata: redactor.redact(&metadata.to_string()),
+        created_at: Utc::now().to_rfc3339(),
+    };
+
+    let inserted = sqlx::query(
+        "INSERT INTO agent_logs (id, card_id, session_id, event_type, agent, content, metadata, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&log.id)
+    .bind(&log.card_id)
+    .bind(&log.session_id)
+    .bind(&log.event_type)
+    .bind(&log.agent)
+    .bind(&log.content)
+    .bind(&log.metadata)
+    .bind(&log.created_at)
+    .execute(db)
+    .await;
+    if let Err(e) = inserted {
+        tracing::warn!(card_id = card.id, error = %e, "Failed to store worktree hook log");
+        return;
+    }
+
+    let event = WsEvent::AgentLogCreated {
+        card_id: card.id.clone(),
+        log,
+    };
+    if let Ok(payload) = serde_json::to_string(&event) {
+        let _ = sse_tx.send(payload);
+    }
+}
@@ -0,0 +1,89 @@
+//! Git repositories and the review cards that point at them, for the merge
+//! and diff tests.
+
+use std::process::Command;
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+pub fn git(repo_path: &str, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(repo_path)
+        .output()
+        .expect("git command should execute");
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        panic!(
+            "git command failed in {}: git {}\n{}",
+            repo_path,
+            args.join(" "),
+            stderr.trim()
+        );
+    }
+
+    String::from_utf8(output.stdout).expect("git output should be valid UTF-8")
+}
+
+pub fn current_branch(repo_path: &str) -> String {
+    git(repo_path, &["rev-parse", "--abbrev-ref", "HEAD"])
+        .trim()
+        .to_string()
+}
+
+pub fn test_config() -> Arc<kanban_backend::config::Config> {
+    Arc::new(kanban_backend::config::Config {
+        port: 3000,
+        database_url: "sqlite::memory:".to_string(),
+        opencode_url: "http://localhost:4096".to_string(),
+        frontend_dir: "../frontend/dist".to_string(),
+        cors_origin: "http://localhost:5173".to_string(),
+        ..Default::default()
+    })
+}
+
+pub async fn test_app_with_pool() -> (axum::Router, String, sqlx::SqlitePool) {
+    let (pool, token) = super::setup_test_db().await;
+    let sse_tx = kanban_backend::services::EventBus::new(Some(pool.clone()), 10_000);
+    let http_client = reqwest::Client::new();
+    let config = test_config();
+
+    let state = kanban_backend::api::state::AppState::new(Some(pool.clone()), sse_tx, http_client, config.clone());
+
+    let app = kanban_backend::api::routes::create_router(state, &config);
+    (app, token, pool)
+}
+
+/// A card in review on `branch_name`, on the default board pointed at
+/// `repo_path`. `plan_path` may be empty.
+pub async fn seed_review_card(
+    pool: &sqlx::SqlitePool,
+    repo_path: &str,
+    branch_name: &str,
+    plan_path: &str,
+) -> String {
+    sqlx::query("INSERT OR REPLACE INTO board_settings (board_id, codebase_path, updated_at) VALUES ('default', ?, ?)")
+        .bind(repo_path)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .execute(pool)
+        .await
+        .expect("board settings should be inserted");
+
+    let card_id = Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().to_rfc3339();
+
+    sqlx::query("INSERT INTO cards (id, title, description, stage, position, priority, working_directory, ai_status, ai_progress, linked_documents, created_at, updated_at, board_id, branch_name, worktree_path, plan_path) VALUES (?, ?, ?, 'review', 1000, 'medium', '.', 'idle', '{}', '[]', ?, ?, 'default', ?, '', ?)")
+        .bind(&card_id)
+        .bind("Merge Conflict Test")
+        .bind("Testing merge conflict flow")
+        .bind(&now)
+        .bind(&now)
+        .bind(branch_name)
+        .bind(plan_path)
+        .execute(pool)
+        .await
+        .expect("review card should be inserted");
+
+    card_id
+}
@@ -1,3 +1,8 @@
+#![allow(dead_code)]
+
+pub mod git_repo;
+pub mod opencode_mock;
+
 use axum::body::Body;
 use axum::http::{Request, StatusCode};
 use axum::Router;
@@ -104,6 +109,20 @@ async fn setup_test_db_inner() -> (SqlitePool, String, String) {
         .await
         .expect("Failed to run test migrations");
 
+    let (user_id, token) = create_test_user(&pool, "test_user").await;
+
+    sqlx::query(
+        "INSERT OR IGNORE INTO boards (id, name, created_at, updated_at) VALUES ('default', 'Test Board', datetime('now'), datetime('now'))",
+    )
+    .execute(&pool)
+    .await
+    .expect("Failed to ensure default board");
+
+    (pool, token, user_id)
+}
+
+/// Inserts a user and returns its id and a valid access token.
+pub async fn create_test_user(pool: &SqlitePool, username: &str) -> (String, String) {
     let user_id = Uuid::new_v4().to_string();
     let tenant_id = Uuid::new_v4().to_string();
     let password_hash = kanban_backend::auth::password::hash_password("TestPass123")
@@ -115,7 +134,7 @@ async fn setup_test_db_inner() -> (SqlitePool, String, String) {
     )
     .bind(&user_id)
     .bind(&tenant_id)
-    .bind("test_user")
+    .bind(username)
     .bind("Test User")
     .bind("")
     .bind("")
@@ -123,24 +142,17 @@ async fn setup_test_db_inner() -> (SqlitePool, String, String) {
     .bind(&password_hash)
     .bind(&now)
     .bind(&now)
-    .execute(&pool)
+    .execute(pool)
     .await
     .expect("Failed to seed test user");
 
-    let signing_key = kanban_backend::auth::jwt::get_or_create_signing_key(&pool)
+    let signing_key = kanban_backend::auth::jwt::get_or_create_signing_key(pool)
         .await
         .expect("Failed to create JWT signing key");
     let token = kanban_backend::auth::jwt::create_token(&signing_key, &user_id, &tenant_id)
         .expect("Failed to create JWT token");
 
-    sqlx::query(
-        "INSERT OR IGNORE INTO boards (id, name, created_at, updated_at) VALUES ('default', 'Test Board', datetime('now'), datetime('now'))",
-    )
-    .execute(&pool)
-    .await
-    .expect("Failed to ensure default board");
-
-    (pool, token, user_id)
+    (user_id, token)
 }
 
 pub async fn setup_test_db() -> (SqlitePool, String) {
@@ -0,0 +1,359 @@
+//! Deterministic stand-in for the OpenCode HTTP API.
+//!
+//! Serves the endpoints the backend talks to (`/health`, `/session`,
+//! `/session/{id}`, `/session/{id}/message`, `/session/{id}/abort`,
+//! `/config/providers`) plus an
+//! `/event` SSE stream that tests drive with [`MockOpenCode::emit`] or a
+//! per-message script. Every request is recorded for later assertions.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures::Stream;
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::StreamExt;
+
+/// Builds the events emitted after a prompt is posted to a session.
+pub type MessageScript = Arc<dyn Fn(&str, &Value) -> Vec<Value> + Send + Sync>;
+
+/// Sentinel event that closes `/event` streams instead of being delivered.
+const DISCONNECT: &str = "mock.disconnect";
+
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub body: Value,
+}
+
+#[derive(Debug, Clone)]
+pub struct MockSession {
+    pub id: String,
+    pub parent_id: Option<String>,
+    pub status: String,
+    pub messages: Vec<Value>,
+}
+
+#[derive(Default)]
+struct MockInner {
+    sessions: HashMap<String, MockSession>,
+    requests: Vec<RecordedRequest>,
+    message_script: Option<MessageScript>,
+    fail_session_create: bool,
+}
+
+#[derive(Clone)]
+struct MockState {
+    inner: Arc<Mutex<MockInner>>,
+    events: broadcast::Sender<Value>,
+    next_id: Arc<AtomicUsize>,
+}
+
+pub struct MockOpenCode {
+    pub url: String,
+    state: MockState,
+    handle: JoinHandle<()>,
+}
+
+impl MockOpenCode {
+    pub async fn start() -> Self {
+        let (events, _) = broadcast::channel(256);
+        let state = MockState {
+            inner: Arc::new(Mutex::new(MockInner::default())),
+            events,
+            next_id: Arc::new(AtomicUsize::new(1)),
+        };
+
+        let app = Router::new()
+            .route("/health", get(health))
+            .route("/session", post(create_session))
+            .route("/session/{id}", get(get_session))
+            .route(
+                "/session/{id}/message",
+                get(list_messages).post(post_message),
+            )
+            .route("/session/{id}/abort", post(abort_session))
+            .route("/config/providers", get(config_providers))
+            .route("/event", get(event_stream))
+            .with_state(state.clone());
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind mock OpenCode listener");
+        let addr = listener.local_addr().expect("Mock OpenCode has no address");
+        let handle = tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+
+        Self {
+            url: format!("http://{}", addr),
+            state,
+            handle,
+        }
+    }
+
+    /// Broadcasts an OpenCode event to every connected `/event` subscriber.
+    pub fn emit(&self, event_type: &str, properties: Value) {
+        let _ = self
+            .state
+            .events
+            .send(json!({ "type": event_type, "properties": properties }));
+    }
+
+    /// Ends every open `/event` stream, as if the server restarted.
+    pub fn drop_subscribers(&self) {
+        let _ = self.state.events.send(json!({ "type": DISCONNECT }));
+    }
+
+    /// Waits until at least `count` clients are attached to `/event`.
+    pub async fn wait_for_subscribers(&self, count: usize) {
+        for _ in 0..200 {
+            if self.state.events.receiver_count() >= count {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(25)).await;
+        }
+        panic!("Timed out waiting for {} OpenCode event subscriber(s)", count);
+    }
+
+    /// Events returned by `script` are emitted whenever a prompt is posted.
+    pub fn on_message<F>(&self, script: F)
+    where
+        F: Fn(&str, &Value) -> Vec<Value> + Send + Sync + 'static,
+    {
+        self.lock().message_script = Some(Arc::new(script));
+    }
+
+    pub fn fail_session_create(&self, fail: bool) {
+        self.lock().fail_session_create = fail;
+    }
+
+    /// Registers a session directly, bypassing `POST /session`.
+    pub fn insert_session(&self, id: &str, status: &str) {
+        self.lock().sessions.insert(
+            id.to_string(),
+            MockSession {
+                id: id.to_string(),
+                parent_id: None,
+                status: status.to_string(),
+                messages: Vec::new(),
+            },
+        );
+    }
+
+    pub fn remove_session(&self, id: &str) {
+        self.lock().sessions.remove(id);
+    }
+
+    pub fn set_session_status(&self, id: &str, status: &str) {
+        if let Some(session) = self.lock().sessions.get_mut(id) {
+            session.status = status.to_string();
+        }
+    }
+
+    pub fn set_session_messages(&self, id: &str, messages: Vec<Value>) {
+        if let Some(session) = self.lock().sessions.get_mut(id) {
+            session.messages = messages;
+        }
+    }
+
+    pub fn session(&self, id: &str) -> Option<MockSession> {
+        self.lock().sessions.get(id).cloned()
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.lock().requests.clone()
+    }
+
+    pub fn requests_to(&self, method: &str, path_suffix: &str) -> Vec<RecordedRequest> {
+        self.requests()
+            .into_iter()
+            .filter(|r| r.method == method && r.path.ends_with(path_suffix))
+            .collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockInner> {
+        self.state.inner.lock().expect("Mock OpenCode state poisoned")
+    }
+}
+
+impl Drop for MockOpenCode {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+impl MockState {
+    fn record(&self, method: &str, path: String, body: Value) {
+        self.inner
+            .lock()
+            .expect("Mock OpenCode state poisoned")
+            .requests
+            .push(RecordedRequest {
+                method: method.to_string(),
+                path,
+                body,
+            });
+    }
+
+    fn emit(&self, event: Value) {
+        let _ = self.events.send(event);
+    }
+}
+
+async fn health(State(state): State<MockState>) -> Json<Value> {
+    state.record("GET", "/health".into(), Value::Null);
+    Json(json!({ "healthy": true }))
+}
+
+async fn create_session(
+    State(state): State<MockState>,
+    body: Option<Json<Value>>,
+) -> impl IntoResponse {
+    let body = body.map(|Json(b)| b).unwrap_or(Value::Null);
+    state.record("POST", "/session".into(), body.clone());
+
+    let mut inner = state.inner.lock().expect("Mock OpenCode state poisoned");
+    if inner.fail_session_create {
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": "mock failure" })));
+    }
+
+    let id = format!("ses_mock_{}", state.next_id.fetch_add(1, Ordering::SeqCst));
+    let parent_id = body
+        .get("parentID")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    inner.sessions.insert(
+        id.clone(),
+        MockSession {
+            id: id.clone(),
+            parent_id: parent_id.clone(),
+            status: "idle".into(),
+            messages: Vec::new(),
+        },
+    );
+    drop(inner);
+
+    let info = json!({ "id": id, "parentID": parent_id, "title": body.get("title") });
+    state.emit(json!({ "type": "session.created", "properties": { "info": info } }));
+
+    (StatusCode::OK, Json(info))
+}
+
+async fn get_session(State(state): State<MockState>, Path(id): Path<String>) -> impl IntoResponse {
+    state.record("GET", format!("/session/{}", id), Value::Null);
+
+    let inner = state.inner.lock().expect("Mock OpenCode state poisoned");
+    match inner.sessions.get(&id) {
+        Some(session) => (
+            StatusCode::OK,
+            Json(json!({
+                "id": session.id,
+                "parentID": session.parent_id,
+                "status": { "type": session.status },
+            })),
+        ),
+        None => (StatusCode::NOT_FOUND, Json(json!({ "error": "not found" }))),
+    }
+}
+
+async fn list_messages(State(state): State<MockState>, Path(id): Path<String>) -> impl IntoResponse {
+    state.record("GET", format!("/session/{}/message", id), Value::Null);
+
+    let inner = state.inner.lock().expect("Mock OpenCode state poisoned");
+    match inner.sessions.get(&id) {
+        Some(session) => (StatusCode::OK, Json(Value::Array(session.messages.clone()))),
+        None => (StatusCode::NOT_FOUND, Json(json!({ "error": "not found" }))),
+    }
+}
+
+async fn post_message(
+    State(state): State<MockState>,
+    Path(id): Path<String>,
+    Json(body): Json<Value>,
+) -> impl IntoResponse {
+    state.record("POST", format!("/session/{}/message", id), body.clone());
+
+    let script = {
+        let mut inner = state.inner.lock().expect("Mock OpenCode state poisoned");
+        let Some(session) = inner.sessions.get_mut(&id) else {
+            return (StatusCode::NOT_FOUND, Json(json!({ "error": "not found" })));
+        };
+        session.messages.push(json!({
+            "info": { "role": "user", "sessionID": id },
+            "parts": body.get("parts").cloned().unwrap_or_else(|| json!([])),
+        }));
+        inner.message_script.clone()
+    };
+
+    if let Some(script) = script {
+        for event in script(&id, &body) {
+            state.emit(event);
+        }
+    }
+
+    (
+        StatusCode::OK,
+        Json(json!({ "info": { "role": "assistant", "sessionID": id }, "parts": [] })),
+    )
+}
+
+async fn abort_session(State(state): State<MockState>, Path(id): Path<String>) -> impl IntoResponse {
+    state.record("POST", format!("/session/{}/abort", id), Value::Null);
+
+    let mut inner = state.inner.lock().expect("Mock OpenCode state poisoned");
+    match inner.sessions.get_mut(&id) {
+        Some(session) => {
+            session.status = "idle".into();
+            (StatusCode::OK, Json(json!(true)))
+        }
+        None => (StatusCode::NOT_FOUND, Json(json!(false))),
+    }
+}
+
+async fn config_providers(State(state): State<MockState>) -> Json<Value> {
+    state.record("GET", "/config/providers".into(), Value::Null);
+    Json(json!({
+        "providers": [
+            {
+                "id": "anthropic",
+                "name": "Anthropic",
+                "models": {
+                    "claude-sonnet": { "id": "claude-sonnet", "name": "Claude Sonnet" },
+                    "claude-opus": { "id": "claude-opus", "name": "Claude Opus" },
+                },
+            },
+            {
+                "id": "openai",
+                "name": "OpenAI",
+                "models": { "gpt-5": { "id": "gpt-5", "name": "GPT-5" } },
+            },
+        ],
+        "default": { "anthropic": "claude-sonnet", "openai": "gpt-5" },
+    }))
+}
+
+async fn event_stream(
+    State(state): State<MockState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let connected = json!({ "type": "server.connected", "properties": {} });
+    let stream = tokio_stream::once(connected)
+        .chain(BroadcastStream::new(state.events.subscribe()).filter_map(Result::ok))
+        .take_while(|event| event["type"] != DISCONNECT)
+        .map(|event| Ok(Event::default().data(event.to_string())));
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
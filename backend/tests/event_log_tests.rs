@@ -0,0 +1,561 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use tower::ServiceExt;
+
+use kanban_backend::api::AppState;
+use kanban_backend::config::Config;
+use kanban_backend::services::event_bus::{seq_of, EventAccess, EventFilter};
+use kanban_backend::services::EventBus;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::Message;
+
+async fn test_app(retention: i64) -> (axum::Router, String, SqlitePool, EventBus) {
+    let (pool, token) = common::setup_test_db().await;
+    let config = Arc::new(Config {
+        database_url: "sqlite::memory:".to_string(),
+        event_retention: retention,
+        ..Default::default()
+    });
+    let events = EventBus::new(Some(pool.clone()), retention);
+    let state = AppState::new(
+        Some(pool.clone()),
+        events.clone(),
+        reqwest::Client::new(),
+        config.clone(),
+    );
+    let app = kanban_backend::api::routes::create_router(state, &config);
+    (app, token, pool, events)
+}
+
+async fn create_card(app: &axum::Router, token: &str, title: &str) {
+    create_card_on(app, token, title, "default").await;
+}
+
+async fn create_card_on(app: &axum::Router, token: &str, title: &str, board_id: &str) {
+    let body = json!({ "title": title, "stage": "backlog", "board_id": board_id }).to_string();
+    let (status, resp) =
+        common::make_request(app.clone(), "POST", "/api/cards", Some(body), Some(token)).await;
+    assert_eq!(status, StatusCode::CREATED, "Create card failed: {}", resp);
+}
+
+async fn create_board(app: &axum::Router, token: &str, name: &str) -> String {
+    let body = json!({ "name": name }).to_string();
+    let (status, resp) =
+        common::make_request(app.clone(), "POST", "/api/boards", Some(body), Some(token)).await;
+    assert_eq!(status, StatusCode::CREATED, "Create board failed: {}", resp);
+    let board: Value = serde_json::from_str(&resp).unwrap();
+    board["id"].as_str().unwrap().to_string()
+}
+
+async fn wait_for_event_count(pool: &SqlitePool, count: i64) {
+    for _ in 0..200 {
+        let (stored,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM events")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        if stored >= count {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("Timed out waiting for {} stored events", count);
+}
+
+/// Opens `/api/events` and collects `count` SSE frames as (id, data).
+async fn read_sse(
+    app: &axum::Router,
+    token: &str,
+    uri: &str,
+    last_event_id: Option<&str>,
+    count: usize,
+) -> Vec<(Option<String>, Value)> {
+    let mut request = Request::builder()
+        .uri(uri)
+        .header("Authorization", format!("Bearer {}", token));
+    if let Some(id) = last_event_id {
+        request = request.header("Last-Event-ID", id);
+    }
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let mut stream = response.into_body().into_data_stream();
+    let mut buffer = String::new();
+    let mut frames = Vec::new();
+
+    while frames.len() < count {
+        let chunk = tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("Timed out waiting for SSE frame")
+            .expect("SSE stream ended")
+            .unwrap();
+        buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+
+        while let Some(end) = buffer.find("\n\n") {
+            let frame: String = buffer.drain(..end + 2).collect();
+            let mut id = None;
+            let mut data = None;
+            for line in frame.lines() {
+                if let Some(value) = line.strip_prefix("id: ") {
+                    id = Some(value.to_string());
+                } else if let Some(value) = line.strip_prefix("data: ") {
+                    data = Some(serde_json::from_str::<Value>(value).unwrap());
+                }
+            }
+            if let Some(data) = data {
+                frames.push((id, data));
+            }
+        }
+    }
+
+    frames
+}
+
+#[tokio::test]
+async fn test_events_are_persisted_with_increasing_seq() {
+    let (app, token, pool, _events) = test_app(100).await;
+
+    create_card(&app, &token, "First").await;
+    create_card(&app, &token, "Second").await;
+    wait_for_event_count(&pool, 2).await;
+
+    let rows: Vec<(i64, String)> =
+        sqlx::query_as("SELECT seq, event_type FROM events ORDER BY seq")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    assert_eq!(rows.len(), 2);
+    assert!(rows[0].0 < rows[1].0);
+    assert!(rows.iter().all(|(_, t)| t == "cardCreated"));
+}
+
+#[tokio::test]
+async fn test_sse_replays_after_last_event_id() {
+    let (app, token, pool, _events) = test_app(100).await;
+
+    for title in ["One", "Two", "Three"] {
+        create_card(&app, &token, title).await;
+    }
+    wait_for_event_count(&pool, 3).await;
+    let seqs: Vec<(i64,)> = sqlx::query_as("SELECT seq FROM events ORDER BY seq")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+
+    let frames = read_sse(&app, &token, "/api/events", Some(&seqs[0].0.to_string()), 2).await;
+    assert_eq!(frames[0].0.as_deref(), Some(seqs[1].0.to_string().as_str()));
+    assert_eq!(frames[0].1["card"]["title"], "Two");
+    assert_eq!(frames[1].1["card"]["title"], "Three");
+    assert_eq!(frames[1].1["seq"], seqs[2].0);
+
+    // `?since=` behaves the same for clients that cannot set headers.
+    let uri = format!("/api/events?since={}", seqs[1].0);
+    let frames = read_sse(&app, &token, &uri, None, 1).await;
+    assert_eq!(frames[0].1["card"]["title"], "Three");
+}
+
+#[tokio::test]
+async fn test_replay_beyond_retention_requests_resync() {
+    let (app, token, pool, _events) = test_app(2).await;
+
+    for i in 0..5 {
+        create_card(&app, &token, &format!("Card {}", i)).await;
+    }
+    wait_for_event_count(&pool, 5).await;
+
+    // Retention is enforced when the writer starts (as after a restart).
+    let _restarted = EventBus::new(Some(pool.clone()), 2);
+    let mut retained = i64::MAX;
+    for _ in 0..200 {
+        (retained,) = sqlx::query_as("SELECT COUNT(*) FROM events")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        if retained <= 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(retained, 2);
+
+    let frames = read_sse(&app, &token, "/api/events?since=0", None, 1).await;
+    assert_eq!(frames[0].1["type"], "resyncRequired");
+    let latest = frames[0].1["latest_seq"].as_i64().unwrap();
+    assert_eq!(frames[0].0, Some(latest.to_string()));
+}
+
+#[tokio::test]
+async fn test_lagging_subscriber_recovers_from_event_log() {
+    let (pool, _token) = common::setup_test_db().await;
+    let events = EventBus::new(Some(pool.clone()), 10_000);
+    let access = EventAccess::for_user(&pool, "nobody").await.unwrap();
+    let (_filter_tx, filter) = tokio::sync::watch::channel(EventFilter::default());
+    let mut stream = events.stream_from(Some(0), access, filter);
+
+    // Far more events than the broadcast channel holds, sent before the
+    // subscriber reads anything.
+    let total = 3000;
+    for i in 0..total {
+        events.send(json!({ "type": "cardDeleted", "card_id": i.to_string() }).to_string());
+    }
+
+    let mut previous = 0;
+    for _ in 0..total {
+        let msg = tokio::time::timeout(Duration::from_secs(10), stream.recv())
+            .await
+            .expect("Timed out waiting for event")
+            .expect("Event stream closed");
+        let seq = seq_of(&msg).expect("Event without seq");
+        assert_eq!(seq, previous + 1, "Gap or duplicate after seq {}", previous);
+        previous = seq;
+    }
+}
+
+#[tokio::test]
+async fn test_sse_filters_by_board_and_type() {
+    let (app, token, pool, _events) = test_app(100).await;
+    let other = create_board(&app, &token, "Other").await;
+
+    create_card_on(&app, &token, "On default", "default").await;
+    create_card_on(&app, &token, "On other", &other).await;
+    wait_for_event_count(&pool, 3).await;
+
+    let uri = format!("/api/events?since=0&board_ids={}&types=cardCreated", other);
+    let frames = read_sse(&app, &token, &uri, None, 1).await;
+    assert_eq!(frames[0].1["type"], "cardCreated");
+    assert_eq!(frames[0].1["card"]["title"], "On other");
+
+    let uri = "/api/events?since=0&board_ids=missing";
+    let (status, _) = common::make_request(app.clone(), "GET", uri, None, Some(&token)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_restricted_board_events_only_reach_members() {
+    let (app, token, pool, events) = test_app(100).await;
+    let (_outsider_id, outsider) = common::create_test_user(&pool, "outsider").await;
+    let private = create_board(&app, &token, "Private").await;
+
+    let (_, me) = common::make_request(app.clone(), "GET", "/api/auth/me", None, Some(&token)).await;
+    let me: Value = serde_json::from_str(&me).unwrap();
+    let body = json!({ "user_ids": [me["id"]] }).to_string();
+    let uri = format!("/api/boards/{}/members", private);
+    let (status, resp) =
+        common::make_request(app.clone(), "PUT", &uri, Some(body), Some(&token)).await;
+    assert_eq!(status, StatusCode::OK, "Set members failed: {}", resp);
+
+    create_card_on(&app, &token, "Secret", &private).await;
+    create_card_on(&app, &token, "Public", "default").await;
+    wait_for_event_count(&pool, 4).await;
+
+    let frames = read_sse(&app, &outsider, "/api/events?since=0&types=cardCreated", None, 1).await;
+    assert_eq!(frames[0].1["card"]["title"], "Public");
+
+    let frames = read_sse(&app, &token, "/api/events?since=0&types=cardCreated", None, 2).await;
+    assert_eq!(frames[0].1["card"]["title"], "Secret");
+    assert_eq!(frames[1].1["card"]["title"], "Public");
+
+    // A card whose board cannot be resolved may be on the private board.
+    events.send(json!({ "type": "cardMoved", "card_id": "unknown", "from_stage": "todo", "to_stage": "review" }).to_string());
+    create_card_on(&app, &token, "After", "default").await;
+    wait_for_event_count(&pool, 6).await;
+    let uri = "/api/events?since=0&types=cardMoved,cardCreated";
+    let frames = read_sse(&app, &outsider, uri, None, 2).await;
+    assert_eq!(frames[0].1["card"]["title"], "Public");
+    assert_eq!(frames[1].1["card"]["title"], "After");
+    let frames = read_sse(&app, &token, uri, None, 4).await;
+    assert_eq!(frames[2].1["card_id"], "unknown");
+
+    let uri = format!("/api/events?board_ids={}", private);
+    let (status, _) = common::make_request(app.clone(), "GET", &uri, None, Some(&outsider)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let uri = format!("/api/boards/{}/members", private);
+    let (status, _) = common::make_request(app.clone(), "GET", &uri, None, Some(&outsider)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+/// A review card queued for merge on `board_id`, with a directory standing
+/// in for its worktree.
+async fn enqueue_review_card(
+    app: &axum::Router,
+    token: &str,
+    pool: &SqlitePool,
+    board_id: &str,
+    worktree: &std::path::Path,
+) -> String {
+    let card_id = uuid::Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().to_rfc3339();
+    sqlx::query("INSERT INTO cards (id, title, description, stage, position, priority, working_directory, ai_status, ai_progress, linked_documents, created_at, updated_at, board_id, branch_name, worktree_path) VALUES (?, 'Queued', '', 'review', 1000, 'medium', '.', 'completed', '{}', '[]', ?, ?, ?, ?, ?)")
+        .bind(&card_id)
+        .bind(&now)
+        .bind(&now)
+        .bind(board_id)
+        .bind(format!("ai/{}", card_id))
+        .bind(worktree.to_string_lossy().to_string())
+        .execute(pool)
+        .await
+        .unwrap();
+    let uri = format!("/api/cards/{}/merge-queue", card_id);
+    let (status, resp) = common::make_request(app.clone(), "POST", &uri, None, Some(token)).await;
+    assert_eq!(status, StatusCode::CREATED, "Enqueue failed: {}", resp);
+    card_id
+}
+
+#[tokio::test]
+async fn test_merge_queue_is_scoped_to_each_board_sharing_a_repository() {
+    let (app, token, pool, _events) = test_app(100).await;
+    let (_outsider_id, outsider) = common::create_test_user(&pool, "outsider").await;
+    let private = create_board(&app, &token, "Private").await;
+    let (_, me) = common::make_request(app.clone(), "GET", "/api/auth/me", None, Some(&token)).await;
+    let me: Value = serde_json::from_str(&me).unwrap();
+    let uri = format!("/api/boards/{}/members", private);
+    let body = json!({ "user_ids": [me["id"]] }).to_string();
+    let (status, _) = common::make_request(app.clone(), "PUT", &uri, Some(body), Some(&token)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let repo = tempfile::TempDir::new().unwrap();
+    for board_id in ["default", private.as_str()] {
+        sqlx::query("INSERT OR REPLACE INTO board_settings (board_id, codebase_path, updated_at) VALUES (?, ?, ?)")
+            .bind(board_id)
+            .bind(repo.path().to_string_lossy().to_string())
+            .bind(chrono::Utc::now().to_rfc3339())
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+    let secret = enqueue_review_card(&app, &token, &pool, &private, repo.path()).await;
+    let public = enqueue_review_card(&app, &token, &pool, "default", repo.path()).await;
+
+    let (_, resp) =
+        common::make_request(app.clone(), "GET", "/api/boards/default/merge-queue", None, Some(&outsider)).await;
+    let listed: Vec<Value> = serde_json::from_str(&resp).unwrap();
+    assert_eq!(listed.len(), 1, "{}", resp);
+    assert_eq!(listed[0]["card_id"], public.as_str());
+    // Positions still follow the shared repository's merge order.
+    assert_eq!(listed[0]["position"], 2);
+
+    // Each enqueue updates both boards; the outsider only gets the public one.
+    let frames =
+        read_sse(&app, &outsider, "/api/events?since=0&types=mergeQueueUpdated", None, 2).await;
+    for (_, frame) in &frames {
+        assert_eq!(frame["board_id"], "default");
+        assert!(!frame["entries"].to_string().contains(&secret), "{}", frame);
+    }
+    let frames = read_sse(&app, &token, "/api/events?since=0&types=mergeQueueUpdated", None, 4).await;
+    assert!(frames
+        .iter()
+        .any(|(_, frame)| frame["board_id"] == private.as_str() && frame["entries"][0]["card_id"] == secret.as_str()));
+}
+
+#[tokio::test]
+async fn test_non_members_are_forbidden_on_every_board_and_card_route() {
+    let (app, token, pool, _events) = test_app(100).await;
+    let (outsider_id, outsider) = common::create_test_user(&pool, "outsider").await;
+    let b = create_board(&app, &token, "Private").await;
+
+    let body = json!({ "title": "Secret", "stage": "backlog", "board_id": b }).to_string();
+    let (_, resp) =
+        common::make_request(app.clone(), "POST", "/api/cards", Some(body), Some(&token)).await;
+    let c = serde_json::from_str::<Value>(&resp).unwrap()["id"].as_str().unwrap().to_string();
+
+    let body = json!({ "content": "note" }).to_string();
+    let uri = format!("/api/cards/{}/comments", c);
+    let (_, resp) = common::make_request(app.clone(), "POST", &uri, Some(body), Some(&token)).await;
+    let comment = serde_json::from_str::<Value>(&resp).unwrap()["id"].as_str().unwrap().to_string();
+
+    let body = json!({ "title": "step", "phase": "Phase 1", "phase_order": 1 }).to_string();
+    let uri = format!("/api/cards/{}/subtasks", c);
+    let (_, resp) = common::make_request(app.clone(), "POST", &uri, Some(body), Some(&token)).await;
+    let subtask = serde_json::from_str::<Value>(&resp).unwrap()["id"].as_str().unwrap().to_string();
+
+    let (_, me) = common::make_request(app.clone(), "GET", "/api/auth/me", None, Some(&token)).await;
+    let me: Value = serde_json::from_str(&me).unwrap();
+    let members = json!({ "user_ids": [me["id"]] }).to_string();
+    let uri = format!("/api/boards/{}/members", b);
+    let (status, resp) =
+        common::make_request(app.clone(), "PUT", &uri, Some(members.clone()), Some(&token)).await;
+    assert_eq!(status, StatusCode::OK, "Set members failed: {}", resp);
+
+    let template = json!({ "content": "Plan {{card.title}}" }).to_string();
+    let routes: Vec<(&str, String, Option<String>)> = vec![
+        ("PATCH", format!("/api/boards/{b}"), Some(json!({ "name": "Mine" }).to_string())),
+        ("DELETE", format!("/api/boards/{b}"), None),
+        ("PATCH", format!("/api/boards/{b}/reorder"), Some(json!({ "position": 1 }).to_string())),
+        ("GET", format!("/api/boards/{b}/members"), None),
+        ("PUT", format!("/api/boards/{b}/members"), Some(json!({ "user_ids": [outsider_id] }).to_string())),
+        ("GET", format!("/api/boards/{b}/logs/export"), None),
+        ("GET", format!("/api/boards/{b}/merge-queue"), None),
+        ("GET", format!("/api/boards/{b}/settings"), None),
+        ("PUT", format!("/api/boards/{b}/settings"), Some(json!({ "worktree_hooks": "{}" }).to_string())),
+        ("GET", format!("/api/boards/{b}/settings/secrets"), None),
+        ("PUT", format!("/api/boards/{b}/settings/secrets/TOKEN"), Some(json!({ "value": "x" }).to_string())),
+        ("DELETE", format!("/api/boards/{b}/settings/secrets/TOKEN"), None),
+        ("POST", format!("/api/boards/{b}/settings/auto-detect"), Some(json!({ "codebase_path": "/tmp" }).to_string())),
+        ("POST", format!("/api/boards/{b}/settings/clone-repo"), Some(json!({ "github_url": "https://github.com/a/b", "clone_path": "/tmp/b" }).to_string())),
+        ("GET", format!("/api/boards/{b}/settings/auto-detect-status"), None),
+        ("GET", format!("/api/boards/{b}/settings/auto-detect-logs?session_id=s"), None),
+        ("GET", format!("/api/boards/{b}/prompt-templates"), None),
+        ("GET", format!("/api/boards/{b}/prompt-templates/planning"), None),
+        ("PUT", format!("/api/boards/{b}/prompt-templates/planning"), Some(template)),
+        ("DELETE", format!("/api/boards/{b}/prompt-templates/planning"), None),
+        ("POST", format!("/api/boards/{b}/prompt-templates/planning/preview"), Some(json!({ "card_id": c }).to_string())),
+        ("GET", format!("/api/boards/{b}/prompt-templates/planning/versions"), None),
+        ("POST", format!("/api/boards/{b}/prompt-templates/planning/versions/1/restore"), None),
+        ("GET", format!("/api/board?board_id={b}"), None),
+        ("POST", "/api/cards".to_string(), Some(json!({ "title": "Mine", "board_id": b }).to_string())),
+        ("GET", format!("/api/cards/{c}"), None),
+        ("PATCH", format!("/api/cards/{c}"), Some(json!({ "title": "Mine" }).to_string())),
+        ("DELETE", format!("/api/cards/{c}"), None),
+        ("GET", format!("/api/cards/{c}/logs"), None),
+        ("POST", format!("/api/cards/{c}/messages"), Some(json!({ "content": "hi" }).to_string())),
+        ("GET", format!("/api/cards/{c}/messages"), None),
+        ("POST", format!("/api/cards/{c}/messages/ack"), Some(json!({ "last_seen_id": "m" }).to_string())),
+        ("GET", format!("/api/cards/{c}/attempts"), None),
+        ("POST", format!("/api/cards/{c}/attempts"), Some(json!({ "attempts": [{}, {}] }).to_string())),
+        ("GET", format!("/api/cards/{c}/attempts/a/diff"), None),
+        ("POST", format!("/api/cards/{c}/attempts/a/select"), None),
+        ("GET", format!("/api/cards/{c}/ai-reviews"), None),
+        ("POST", format!("/api/cards/{c}/ai-reviews"), Some(json!({ "session_id": "s", "verdict": "approve" }).to_string())),
+        ("GET", format!("/api/cards/{c}/review-threads"), None),
+        ("POST", format!("/api/cards/{c}/review-threads"), Some(json!({ "content": "why?" }).to_string())),
+        ("GET", format!("/api/cards/{c}/runs"), None),
+        ("GET", format!("/api/cards/{c}/runs/r/diff"), None),
+        ("GET", format!("/api/cards/{c}/runs/r/logs"), None),
+        ("POST", format!("/api/cards/{c}/logs/backfill"), None),
+        ("GET", format!("/api/cards/{c}/logs/export"), None),
+        ("GET", format!("/api/cards/{c}/agent-activity"), None),
+        ("GET", format!("/api/cards/{c}/transcript"), None),
+        ("GET", format!("/api/cards/{c}/versions"), None),
+        ("POST", format!("/api/cards/{c}/versions/v/restore"), None),
+        ("PATCH", format!("/api/cards/{c}/move"), Some(json!({ "stage": "todo" }).to_string())),
+        ("GET", format!("/api/cards/{c}/diff"), None),
+        ("POST", format!("/api/cards/{c}/merge"), None),
+        ("POST", format!("/api/cards/{c}/merge-partial"), Some(json!({ "files": [] }).to_string())),
+        ("POST", format!("/api/cards/{c}/merge-queue"), None),
+        ("DELETE", format!("/api/cards/{c}/merge-queue"), None),
+        ("GET", format!("/api/cards/{c}/conflicts"), None),
+        ("POST", format!("/api/cards/{c}/resolve-conflicts"), Some(json!({ "resolutions": [] }).to_string())),
+        ("POST", format!("/api/cards/{c}/complete-merge"), None),
+        ("POST", format!("/api/cards/{c}/abort-merge"), None),
+        ("POST", format!("/api/cards/{c}/ai-resolve-conflicts"), None),
+        ("POST", format!("/api/cards/{c}/create-pr"), Some(json!({}).to_string())),
+        ("POST", format!("/api/cards/{c}/reject"), Some(json!({}).to_string())),
+        ("POST", format!("/api/cards/{c}/generate-plan"), None),
+        ("POST", format!("/api/cards/{c}/stop-ai"), None),
+        ("POST", format!("/api/cards/{c}/resume-ai"), None),
+        ("POST", format!("/api/cards/{c}/conclude-ai"), None),
+        ("POST", format!("/api/cards/{c}/retry-ai"), None),
+        ("POST", format!("/api/cards/{c}/questions/q/answer"), Some(json!({ "answer": "yes" }).to_string())),
+        ("POST", format!("/api/cards/{c}/subtasks"), Some(json!({ "title": "x", "phase": "P", "phase_order": 1 }).to_string())),
+        ("GET", format!("/api/cards/{c}/comments"), None),
+        ("POST", format!("/api/cards/{c}/comments"), Some(json!({ "content": "x" }).to_string())),
+        ("POST", format!("/api/cards/{c}/labels/l"), None),
+        ("DELETE", format!("/api/cards/{c}/labels/l"), None),
+        ("GET", format!("/api/cards/{c}/files"), None),
+        ("PATCH", format!("/api/comments/{comment}"), Some(json!({ "content": "x" }).to_string())),
+        ("DELETE", format!("/api/comments/{comment}"), None),
+        ("PATCH", format!("/api/subtasks/{subtask}"), Some(json!({ "completed": true }).to_string())),
+        ("DELETE", format!("/api/subtasks/{subtask}"), None),
+    ];
+    for (method, uri, body) in routes {
+        let (status, resp) =
+            common::make_request(app.clone(), method, &uri, body, Some(&outsider)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{} {} returned {}", method, uri, resp);
+    }
+
+    // Restricted boards and their cards are left out of unfiltered listings.
+    let (_, resp) = common::make_request(app.clone(), "GET", "/api/boards", None, Some(&outsider)).await;
+    assert!(!resp.contains(&b));
+    let (_, resp) = common::make_request(app.clone(), "GET", "/api/board", None, Some(&outsider)).await;
+    assert!(!resp.contains(&c));
+
+    // A member cannot restrict the board to a list that leaves them out.
+    let uri = format!("/api/boards/{}/members", b);
+    let body = json!({ "user_ids": [outsider_id] }).to_string();
+    let (status, _) = common::make_request(app.clone(), "PUT", &uri, Some(body), Some(&token)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = common::make_request(app.clone(), "GET", &format!("/api/cards/{}", c), None, Some(&token)).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_ws_subscription_can_change_mid_connection() {
+    let (app, token, _pool, _events) = test_app(100).await;
+    let other = create_board(&app, &token, "Other").await;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server_app = app.clone();
+    let server = tokio::spawn(async move {
+        let _ = axum::serve(listener, server_app).await;
+    });
+
+    let mut request = format!("ws://{}/ws/events?types=cardCreated", addr)
+        .into_client_request()
+        .unwrap();
+    request
+        .headers_mut()
+        .insert("Authorization", format!("Bearer {}", token).parse().unwrap());
+    let (mut socket, _) = tokio_tungstenite::connect_async(request).await.unwrap();
+
+    async fn next_json(
+        socket: &mut tokio_tungstenite::WebSocketStream<
+            tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+        >,
+    ) -> Value {
+        loop {
+            let msg = tokio::time::timeout(Duration::from_secs(5), socket.next())
+                .await
+                .expect("Timed out waiting for WS message")
+                .expect("WS closed")
+                .unwrap();
+            if let Message::Text(text) = msg {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    assert_eq!(next_json(&mut socket).await["type"], "connected");
+
+    let subscribe = json!({ "action": "subscribe", "board_ids": [other] }).to_string();
+    socket.send(Message::Text(subscribe.into())).await.unwrap();
+    let reply = next_json(&mut socket).await;
+    assert_eq!(reply["type"], "subscribed");
+    assert_eq!(reply["filter"]["board_ids"], json!([other]));
+
+    create_card_on(&app, &token, "Filtered out", "default").await;
+    create_card_on(&app, &token, "Wanted", &other).await;
+    let event = next_json(&mut socket).await;
+    assert_eq!(event["card"]["title"], "Wanted");
+
+    let bad = json!({ "action": "subscribe", "board_ids": ["missing"] }).to_string();
+    socket.send(Message::Text(bad.into())).await.unwrap();
+    assert_eq!(next_json(&mut socket).await["type"], "subscriptionError");
+
+    socket
+        .send(Message::Text(json!({ "action": "reset" }).to_string().into()))
+        .await
+        .unwrap();
+    let reply = next_json(&mut socket).await;
+    assert_eq!(reply["type"], "subscribed");
+    assert_eq!(reply["filter"], json!({}));
+
+    create_card_on(&app, &token, "Any board", "default").await;
+    let event = next_json(&mut socket).await;
+    assert_eq!(event["card"]["title"], "Any board");
+
+    server.abort();
+}
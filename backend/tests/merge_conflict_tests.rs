@@ -1,43 +1,18 @@
 mod common;
 
-use std::collections::HashSet;
 use std::path::Path;
 use std::process::Command;
-use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use axum::http::StatusCode;
 use serde_json::json;
 use tempfile::TempDir;
 use uuid::Uuid;
 
+use common::git_repo::{current_branch, git, seed_review_card, test_app_with_pool, test_config};
+use kanban_backend::services::git::GitCancel;
 use kanban_backend::services::GitWorktreeService;
 
-fn git(repo_path: &str, args: &[&str]) -> String {
-    let output = Command::new("git")
-        .args(args)
-        .current_dir(repo_path)
-        .output()
-        .expect("git command should execute");
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        panic!(
-            "git command failed in {}: git {}\n{}",
-            repo_path,
-            args.join(" "),
-            stderr.trim()
-        );
-    }
-
-    String::from_utf8(output.stdout).expect("git output should be valid UTF-8")
-}
-
-fn current_branch(repo_path: &str) -> String {
-    git(repo_path, &["rev-parse", "--abbrev-ref", "HEAD"])
-        .trim()
-        .to_string()
-}
-
 fn abort_merge_if_needed(repo_path: &str) {
     if Path::new(repo_path).join(".git/MERGE_HEAD").exists() {
         let _ = Command::new("git")
@@ -84,59 +59,6 @@ fn create_test_repo_with_conflict() -> (TempDir, String, String) {
     (tmp, repo_path, branch_name.to_string())
 }
 
-fn test_config() -> Arc<kanban_backend::config::Config> {
-    Arc::new(kanban_backend::config::Config {
-        port: 3000,
-        database_url: "sqlite::memory:".to_string(),
-        opencode_url: "http://localhost:4096".to_string(),
-        frontend_dir: "../frontend/dist".to_string(),
-        cors_origin: "http://localhost:5173".to_string(),
-    })
-}
-
-async fn test_app_with_pool() -> (axum::Router, String, sqlx::SqlitePool) {
-    let (pool, token) = common::setup_test_db().await;
-    let (sse_tx, _) = tokio::sync::broadcast::channel(100);
-    let http_client = reqwest::Client::new();
-    let config = test_config();
-
-    let state = kanban_backend::api::state::AppState {
-        db: Some(pool.clone()),
-        sse_tx,
-        http_client,
-        config: config.clone(),
-        merge_locks: Arc::new(Mutex::new(HashSet::new())),
-    };
-
-    let app = kanban_backend::api::routes::create_router(state, &config);
-    (app, token, pool)
-}
-
-async fn seed_merge_ready_card(pool: &sqlx::SqlitePool, repo_path: &str, branch_name: &str) -> String {
-    sqlx::query("INSERT OR REPLACE INTO board_settings (board_id, codebase_path, updated_at) VALUES ('default', ?, ?)")
-        .bind(repo_path)
-        .bind(chrono::Utc::now().to_rfc3339())
-        .execute(pool)
-        .await
-        .expect("board settings should be inserted");
-
-    let card_id = Uuid::new_v4().to_string();
-    let now = chrono::Utc::now().to_rfc3339();
-
-    sqlx::query("INSERT INTO cards (id, title, description, stage, position, priority, working_directory, ai_status, ai_progress, linked_documents, created_at, updated_at, board_id, branch_name, worktree_path) VALUES (?, ?, ?, 'review', 1000, 'medium', '.', 'idle', '{}', '[]', ?, ?, 'default', ?, '')")
-        .bind(&card_id)
-        .bind("Merge Conflict Test")
-        .bind("Testing merge conflict flow")
-        .bind(&now)
-        .bind(&now)
-        .bind(branch_name)
-        .execute(pool)
-        .await
-        .expect("merge-ready card should be inserted");
-
-    card_id
-}
-
 async fn start_conflicting_merge(
     app: axum::Router,
     token: &str,
@@ -248,7 +170,7 @@ fn test_get_conflict_details_extracts_content() {
 async fn test_conflict_resolution_http_flow_e2e_moves_card_to_done() {
     let (app, token, pool) = test_app_with_pool().await;
     let (_tmp, repo_path, branch_name) = create_test_repo_with_conflict();
-    let card_id = seed_merge_ready_card(&pool, &repo_path, &branch_name).await;
+    let card_id = seed_review_card(&pool, &repo_path, &branch_name, "").await;
 
     let _merge_result = start_conflicting_merge(app.clone(), &token, &card_id).await;
 
@@ -319,7 +241,7 @@ async fn test_conflict_resolution_http_flow_e2e_moves_card_to_done() {
 async fn test_resolve_conflicts_endpoint_theirs_choice() {
     let (app, token, pool) = test_app_with_pool().await;
     let (_tmp, repo_path, branch_name) = create_test_repo_with_conflict();
-    let card_id = seed_merge_ready_card(&pool, &repo_path, &branch_name).await;
+    let card_id = seed_review_card(&pool, &repo_path, &branch_name, "").await;
 
     let _merge_result = start_conflicting_merge(app.clone(), &token, &card_id).await;
 
@@ -352,7 +274,7 @@ async fn test_resolve_conflicts_endpoint_theirs_choice() {
 async fn test_resolve_conflicts_endpoint_manual_choice() {
     let (app, token, pool) = test_app_with_pool().await;
     let (_tmp, repo_path, branch_name) = create_test_repo_with_conflict();
-    let card_id = seed_merge_ready_card(&pool, &repo_path, &branch_name).await;
+    let card_id = seed_review_card(&pool, &repo_path, &branch_name, "").await;
 
     let _merge_result = start_conflicting_merge(app.clone(), &token, &card_id).await;
 
@@ -386,7 +308,7 @@ async fn test_resolve_conflicts_endpoint_manual_choice() {
 async fn test_complete_merge_fails_with_unresolved_conflicts() {
     let (app, token, pool) = test_app_with_pool().await;
     let (_tmp, repo_path, branch_name) = create_test_repo_with_conflict();
-    let card_id = seed_merge_ready_card(&pool, &repo_path, &branch_name).await;
+    let card_id = seed_review_card(&pool, &repo_path, &branch_name, "").await;
 
     let _merge_result = start_conflicting_merge(app.clone(), &token, &card_id).await;
 
@@ -424,7 +346,8 @@ async fn test_complete_merge_fails_with_unresolved_conflicts() {
 async fn test_abort_merge_restores_clean_state() {
     let (app, token, pool) = test_app_with_pool().await;
     let (_tmp, repo_path, branch_name) = create_test_repo_with_conflict();
-    let card_id = seed_merge_ready_card(&pool, &repo_path, &branch_name).await;
+    let card_id = seed_review_card(&pool, &repo_path, &branch_name, "").await;
+    git(&repo_path, &["checkout", "-b", "wip"]);
 
     let _merge_result = start_conflicting_merge(app.clone(), &token, &card_id).await;
     assert!(Path::new(&repo_path).join(".git/MERGE_HEAD").exists());
@@ -440,6 +363,8 @@ async fn test_abort_merge_restores_clean_state() {
     assert_eq!(status, StatusCode::OK, "abort merge failed: {}", body);
     assert!(!Path::new(&repo_path).join(".git/MERGE_HEAD").exists());
     assert!(!GitWorktreeService::is_merge_in_progress(&repo_path));
+    assert_eq!(current_branch(&repo_path), "wip");
+    assert!(!Path::new(&repo_path).join(".git/KANBAN_PREVIOUS_BRANCH").exists());
 
     let (status, body) = common::make_request(
         app,
@@ -453,3 +378,815 @@ async fn test_abort_merge_restores_clean_state() {
     let card: serde_json::Value = serde_json::from_str(&body).expect("card response should be JSON");
     assert_eq!(card["stage"], "review");
 }
+
+#[test]
+fn test_conflict_details_classify_each_index_stage() {
+    let tmp = TempDir::new().unwrap();
+    let repo_path = tmp.path().to_string_lossy().to_string();
+    git(&repo_path, &["init"]);
+    git(&repo_path, &["config", "user.email", "test@test.com"]);
+    git(&repo_path, &["config", "user.name", "Test User"]);
+    std::fs::write(tmp.path().join("notes with spaces.txt"), "base\n").unwrap();
+    std::fs::write(tmp.path().join("gone.txt"), "base\n").unwrap();
+    std::fs::write(tmp.path().join("image.bin"), [0u8, 1, 2, 3]).unwrap();
+    git(&repo_path, &["add", "."]);
+    git(&repo_path, &["commit", "-m", "initial"]);
+    let default_branch = current_branch(&repo_path);
+
+    git(&repo_path, &["checkout", "-b", "ai/stages"]);
+    std::fs::write(tmp.path().join("notes with spaces.txt"), "branch\n").unwrap();
+    std::fs::write(tmp.path().join("image.bin"), [0u8, 9, 9, 9]).unwrap();
+    std::fs::write(tmp.path().join("new.txt"), "branch\n").unwrap();
+    git(&repo_path, &["rm", "-q", "gone.txt"]);
+    git(&repo_path, &["add", "."]);
+    git(&repo_path, &["commit", "-m", "branch"]);
+
+    git(&repo_path, &["checkout", &default_branch]);
+    std::fs::write(tmp.path().join("notes with spaces.txt"), "main\n").unwrap();
+    std::fs::write(tmp.path().join("gone.txt"), "main\n").unwrap();
+    std::fs::write(tmp.path().join("image.bin"), [0u8, 7, 7, 7]).unwrap();
+    std::fs::write(tmp.path().join("new.txt"), "main\n").unwrap();
+    git(&repo_path, &["add", "."]);
+    git(&repo_path, &["commit", "-m", "main"]);
+
+    let result = GitWorktreeService::merge_branch(&repo_path, "ai/stages", true, "", "").unwrap();
+    assert!(!result.success);
+    assert_eq!(
+        result.conflicts,
+        vec!["gone.txt", "image.bin", "new.txt", "notes with spaces.txt"]
+    );
+
+    let detail = result.conflict_detail.expect("conflicts should be detailed");
+    let kinds: Vec<(&str, &str, bool)> = detail
+        .files
+        .iter()
+        .map(|file| (file.path.as_str(), file.conflict_type.as_str(), file.is_binary))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            ("gone.txt", "deleted-by-them", false),
+            ("image.bin", "both-modified", true),
+            ("new.txt", "added-by-both", false),
+            ("notes with spaces.txt", "both-modified", false),
+        ]
+    );
+    let gone = &detail.files[0];
+    assert_eq!(gone.ours_content.as_deref(), Some("main\n"));
+    assert!(gone.theirs_content.is_none());
+    assert_eq!(detail.files[2].base_content, None);
+    assert_eq!(detail.files[3].theirs_content.as_deref(), Some("branch\n"));
+
+    let unmerged = GitWorktreeService::unmerged(&repo_path).unwrap();
+    assert_eq!(unmerged.len(), 4);
+    GitWorktreeService::abort_merge(&repo_path).unwrap();
+    assert!(GitWorktreeService::unmerged(&repo_path).unwrap().is_empty());
+}
+
+#[test]
+fn test_cancelling_git_kills_the_running_command() {
+    let (_tmp, repo_path, _) = create_test_repo_with_conflict();
+    let cancel = GitCancel::default();
+
+    let started = Instant::now();
+    let running = {
+        let cancel = cancel.clone();
+        std::thread::spawn(move || {
+            cancel.scope(|| {
+                GitWorktreeService::run_git(&repo_path, &["-c", "alias.slow=!sleep 5", "slow"])
+            })
+        })
+    };
+    std::thread::sleep(Duration::from_millis(200));
+    cancel.cancel();
+
+    let error = running.join().unwrap().expect_err("cancelled git should fail");
+    assert!(error.to_string().contains("cancelled"), "{}", error);
+    assert!(started.elapsed() < Duration::from_secs(3));
+}
+
+#[test]
+fn test_git_cancel_scope_is_restored_after_a_panic() {
+    let (_tmp, repo_path, _) = create_test_repo_with_conflict();
+    let cancel = GitCancel::default();
+    cancel.cancel();
+
+    let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        cancel.scope(|| panic!("work failed"))
+    }));
+    assert!(panicked.is_err());
+
+    // The cancelled token must not outlive its scope on this thread.
+    GitWorktreeService::run_git(&repo_path, &["status"]).expect("git should run unscoped");
+}
+
+#[tokio::test]
+async fn test_cancellable_git_runs_off_the_runtime() {
+    let (_tmp, repo_path, branch_name) = create_test_repo_with_conflict();
+    let diff = GitWorktreeService::cancellable(move || {
+        GitWorktreeService::get_diff(&repo_path, &branch_name)
+    })
+    .await
+    .unwrap();
+    assert_eq!(diff.stats.files_changed, 1);
+    assert_eq!(diff.files[0].status, "modified");
+    assert_eq!((diff.files[0].additions, diff.files[0].deletions), (1, 1));
+}
+
+fn create_test_repo_with_two_files() -> (TempDir, String, String) {
+    let tmp = TempDir::new().expect("temporary directory should be created");
+    let repo_path = tmp.path().to_string_lossy().to_string();
+
+    git(&repo_path, &["init"]);
+    git(&repo_path, &["config", "user.email", "test@test.com"]);
+    git(&repo_path, &["config", "user.name", "Test User"]);
+
+    let lines: Vec<String> = (1..=20).map(|n| format!("line{}", n)).collect();
+    std::fs::write(tmp.path().join("a.txt"), lines.join("\n") + "\n")
+        .expect("initial file should be written");
+    std::fs::write(tmp.path().join("b.txt"), "keep\n").expect("initial file should be written");
+    git(&repo_path, &["add", "."]);
+    git(&repo_path, &["commit", "-m", "initial"]);
+
+    let default_branch = current_branch(&repo_path);
+    let branch_name = "ai/partial-branch";
+    git(&repo_path, &["checkout", "-b", branch_name]);
+    let mut changed = lines.clone();
+    changed[1] = "good change".to_string();
+    changed[17] = "bad change".to_string();
+    std::fs::write(tmp.path().join("a.txt"), changed.join("\n") + "\n")
+        .expect("branch file update should be written");
+    std::fs::write(tmp.path().join("b.txt"), "nonsense\n")
+        .expect("branch file update should be written");
+    git(&repo_path, &["add", "."]);
+    git(&repo_path, &["commit", "-m", "branch change"]);
+    git(&repo_path, &["checkout", default_branch.as_str()]);
+
+    (tmp, repo_path, branch_name.to_string())
+}
+
+#[tokio::test]
+async fn test_partial_merge_lands_selected_hunks_and_keeps_remainder_on_branch() {
+    let (app, token, pool) = test_app_with_pool().await;
+    let (_tmp, repo_path, branch_name) = create_test_repo_with_two_files();
+    let card_id = seed_review_card(&pool, &repo_path, &branch_name, "").await;
+
+    let (status, body) = common::make_request(
+        app.clone(),
+        "POST",
+        &format!("/api/cards/{}/merge-partial", card_id),
+        Some(json!({"files": [{"path": "a.txt", "hunks": [5]}]}).to_string()),
+        Some(&token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+
+    let (status, body) = common::make_request(
+        app.clone(),
+        "POST",
+        &format!("/api/cards/{}/merge-partial", card_id),
+        Some(json!({"files": [{"path": "a.txt", "hunks": [0]}], "message": "Take the good part"}).to_string()),
+        Some(&token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "partial merge failed: {}", body);
+    let result: serde_json::Value = serde_json::from_str(&body).expect("partial merge response should be JSON");
+    assert_eq!(result["accepted_files"], json!(["a.txt"]));
+
+    let merged = std::fs::read_to_string(Path::new(&repo_path).join("a.txt")).unwrap();
+    assert!(merged.contains("good change"));
+    assert!(!merged.contains("bad change"));
+    assert_eq!(std::fs::read_to_string(Path::new(&repo_path).join("b.txt")).unwrap(), "keep\n");
+    assert_eq!(git(&repo_path, &["log", "-1", "--format=%s"]).trim(), "Take the good part");
+    assert_eq!(git(&repo_path, &["rev-parse", "HEAD"]).trim(), result["commit"]);
+
+    let remaining: Vec<&str> = result["remaining"]["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|file| file["path"].as_str().unwrap())
+        .collect();
+    assert_eq!(remaining, vec!["a.txt", "b.txt"]);
+    let remaining_a = result["remaining"]["files"][0]["diff"].as_str().unwrap();
+    assert!(remaining_a.contains("+bad change"));
+    assert!(!remaining_a.contains("+good change"));
+
+    let (status, body) = common::make_request(
+        app.clone(),
+        "GET",
+        &format!("/api/cards/{}", card_id),
+        None,
+        Some(&token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "get card failed: {}", body);
+    let card: serde_json::Value = serde_json::from_str(&body).expect("card response should be JSON");
+    assert_eq!(card["stage"], "review");
+
+    let (status, body) = common::make_request(
+        app.clone(),
+        "POST",
+        &format!("/api/cards/{}/merge-partial", card_id),
+        Some(json!({"files": [{"path": "a.txt"}, {"path": "b.txt"}]}).to_string()),
+        Some(&token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "second partial merge failed: {}", body);
+    let result: serde_json::Value = serde_json::from_str(&body).expect("partial merge response should be JSON");
+    assert_eq!(result["remaining"]["files"].as_array().map_or(0, Vec::len), 0);
+
+    let (status, body) = common::make_request(
+        app,
+        "GET",
+        &format!("/api/cards/{}", card_id),
+        None,
+        Some(&token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "get card failed: {}", body);
+    let card: serde_json::Value = serde_json::from_str(&body).expect("card response should be JSON");
+    assert_eq!(card["stage"], "done");
+}
+
+#[tokio::test]
+async fn test_partial_merge_leaves_base_branch_clean_when_the_commit_fails() {
+    let (app, token, pool) = test_app_with_pool().await;
+    let (_tmp, repo_path, branch_name) = create_test_repo_with_two_files();
+    let card_id = seed_review_card(&pool, &repo_path, &branch_name, "").await;
+    let default_branch = current_branch(&repo_path);
+    git(&repo_path, &["checkout", "-b", "wip"]);
+    let hook = Path::new(&repo_path).join(".git/hooks/pre-commit");
+    std::fs::write(&hook, "#!/bin/sh\nexit 1\n").unwrap();
+    let mut permissions = std::fs::metadata(&hook).unwrap().permissions();
+    std::os::unix::fs::PermissionsExt::set_mode(&mut permissions, 0o755);
+    std::fs::set_permissions(&hook, permissions).unwrap();
+
+    let (status, body) = common::make_request(
+        app,
+        "POST",
+        &format!("/api/cards/{}/merge-partial", card_id),
+        Some(json!({"files": [{"path": "b.txt"}]}).to_string()),
+        Some(&token),
+    )
+    .await;
+    assert!(!status.is_success(), "{}", body);
+
+    assert_eq!(current_branch(&repo_path), "wip");
+    assert_eq!(git(&repo_path, &["status", "--porcelain"]).trim(), "");
+    assert_eq!(std::fs::read_to_string(Path::new(&repo_path).join("b.txt")).unwrap(), "keep\n");
+    git(&repo_path, &["checkout", default_branch.as_str()]);
+    assert_eq!(git(&repo_path, &["status", "--porcelain"]).trim(), "");
+    assert_eq!(git(&repo_path, &["log", "-1", "--format=%s"]).trim(), "initial");
+}
+
+async fn test_app_with_locks() -> (
+    axum::Router,
+    String,
+    sqlx::SqlitePool,
+    kanban_backend::services::EventBus,
+    kanban_backend::services::merge_queue::MergeLocks,
+) {
+    let (pool, token) = common::setup_test_db().await;
+    let sse_tx = kanban_backend::services::EventBus::new(Some(pool.clone()), 10_000);
+    let config = test_config();
+
+    let state = kanban_backend::api::state::AppState::new(
+        Some(pool.clone()),
+        sse_tx.clone(),
+        reqwest::Client::new(),
+        config.clone(),
+    );
+    let merge_locks = state.merge_locks.clone();
+    let app = kanban_backend::api::routes::create_router(state, &config);
+    (app, token, pool, sse_tx, merge_locks)
+}
+
+/// A review card on its own branch, checked out in a worktree with `files`
+/// written and committed.
+async fn seed_worktree_card(
+    pool: &sqlx::SqlitePool,
+    repo_path: &str,
+    worktrees: &Path,
+    name: &str,
+    files: &[(&str, &str)],
+) -> String {
+    let branch_name = format!("ai/{}", name);
+    let worktree = worktrees.join(name);
+    let worktree_path = worktree.to_string_lossy().to_string();
+    git(repo_path, &["worktree", "add", "-b", &branch_name, &worktree_path]);
+    for (file, content) in files {
+        std::fs::write(worktree.join(file), content).expect("worktree file should be written");
+    }
+    git(&worktree_path, &["add", "."]);
+    git(&worktree_path, &["commit", "-m", name]);
+
+    let card_id = Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().to_rfc3339();
+    sqlx::query("INSERT INTO cards (id, title, description, stage, position, priority, working_directory, ai_status, ai_progress, linked_documents, created_at, updated_at, board_id, branch_name, worktree_path) VALUES (?, ?, '', 'review', 1000, 'medium', '.', 'completed', '{}', '[]', ?, ?, 'default', ?, ?)")
+        .bind(&card_id)
+        .bind(name)
+        .bind(&now)
+        .bind(&now)
+        .bind(&branch_name)
+        .bind(&worktree_path)
+        .execute(pool)
+        .await
+        .expect("worktree card should be inserted");
+    card_id
+}
+
+async fn merge_queue(app: &axum::Router, token: &str) -> Vec<serde_json::Value> {
+    let (status, body) = common::make_request(
+        app.clone(),
+        "GET",
+        "/api/boards/default/merge-queue",
+        None,
+        Some(token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "merge queue listing failed: {}", body);
+    serde_json::from_str::<serde_json::Value>(&body)
+        .expect("merge queue response should be JSON")
+        .as_array()
+        .cloned()
+        .unwrap_or_default()
+}
+
+#[tokio::test]
+async fn test_merge_queue_lands_cards_in_order_and_fails_conflicts_and_broken_verification() {
+    let (app, token, pool, sse_tx, merge_locks) = test_app_with_locks().await;
+    let tmp = TempDir::new().unwrap();
+    let repo = tmp.path().join("repo");
+    std::fs::create_dir(&repo).unwrap();
+    let repo_path = repo.to_string_lossy().to_string();
+    git(&repo_path, &["init"]);
+    git(&repo_path, &["config", "user.email", "test@test.com"]);
+    git(&repo_path, &["config", "user.name", "Test User"]);
+    std::fs::write(repo.join("shared.txt"), "one\ntwo\nthree\n").unwrap();
+    git(&repo_path, &["add", "."]);
+    git(&repo_path, &["commit", "-m", "initial"]);
+    let default_branch = current_branch(&repo_path);
+
+    let hooks = json!({"verify": [{"type": "command", "run": "test ! -f broken.txt"}]});
+    sqlx::query("INSERT OR REPLACE INTO board_settings (board_id, codebase_path, worktree_hooks, updated_at) VALUES ('default', ?, ?, ?)")
+        .bind(&repo_path)
+        .bind(hooks.to_string())
+        .bind(chrono::Utc::now().to_rfc3339())
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let worktrees = tmp.path().join("worktrees");
+    let a = seed_worktree_card(&pool, &repo_path, &worktrees, "a", &[("a.txt", "a\n")]).await;
+    let b = seed_worktree_card(&pool, &repo_path, &worktrees, "b", &[("shared.txt", "one\ntwo-b\nthree\n")]).await;
+    let c = seed_worktree_card(&pool, &repo_path, &worktrees, "c", &[("shared.txt", "one\ntwo-c\nthree\n")]).await;
+    let d = seed_worktree_card(&pool, &repo_path, &worktrees, "d", &[("broken.txt", "oops\n")]).await;
+    let e = seed_worktree_card(&pool, &repo_path, &worktrees, "e", &[("e.txt", "e\n")]).await;
+    let f = seed_worktree_card(&pool, &repo_path, &worktrees, "f", &[("f.txt", "f\n")]).await;
+
+    for card_id in [&a, &b, &c, &d, &f, &e] {
+        let (status, body) = common::make_request(
+            app.clone(),
+            "POST",
+            &format!("/api/cards/{}/merge-queue", card_id),
+            None,
+            Some(&token),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED, "enqueue failed: {}", body);
+    }
+    let (status, body) = common::make_request(
+        app.clone(),
+        "POST",
+        &format!("/api/cards/{}/merge-queue", a),
+        None,
+        Some(&token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT, "{}", body);
+
+    let (status, body) = common::make_request(
+        app.clone(),
+        "POST",
+        &format!("/api/cards/{}/merge", a),
+        None,
+        Some(&token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT, "manual merge of a queued card: {}", body);
+
+    let uri = format!("/api/cards/{}/merge-queue", e);
+    let (status, body) = common::make_request(app.clone(), "DELETE", &uri, None, Some(&token)).await;
+    assert_eq!(status, StatusCode::OK, "cancel failed: {}", body);
+    let (status, _) = common::make_request(app.clone(), "DELETE", &uri, None, Some(&token)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let queue = merge_queue(&app, &token).await;
+    let positions: Vec<(String, i64)> = queue
+        .iter()
+        .filter(|entry| entry["status"] == "queued")
+        .map(|entry| (entry["card_id"].as_str().unwrap().to_string(), entry["position"].as_i64().unwrap()))
+        .collect();
+    assert_eq!(
+        positions,
+        vec![(a.clone(), 1), (b.clone(), 2), (c.clone(), 3), (d.clone(), 4), (f.clone(), 5)]
+    );
+    // Its worktree goes away while it waits: nothing to rebase and verify in.
+    git(&repo_path, &["worktree", "remove", "--force", worktrees.join("f").to_str().unwrap()]);
+
+    tokio::spawn(
+        kanban_backend::services::MergeQueueProcessor {
+            db: pool.clone(),
+            sse_tx,
+            merge_locks,
+        }
+        .start(),
+    );
+
+    let mut queue = Vec::new();
+    for _ in 0..300 {
+        queue = merge_queue(&app, &token).await;
+        if queue.iter().all(|entry| entry["position"].is_null()) {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    let entry = |card_id: &str| {
+        queue
+            .iter()
+            .find(|entry| entry["card_id"] == card_id)
+            .cloned()
+            .expect("card should be listed")
+    };
+    assert_eq!(entry(&a)["status"], "merged", "{:?}", queue);
+    assert_eq!(entry(&b)["status"], "merged", "{:?}", queue);
+    assert_eq!(entry(&e)["status"], "cancelled");
+    let conflicted = entry(&c);
+    assert_eq!(conflicted["status"], "failed");
+    assert!(
+        conflicted["failure_reason"].as_str().unwrap().contains("shared.txt"),
+        "{}",
+        conflicted
+    );
+    let broken = entry(&d);
+    assert_eq!(broken["status"], "failed");
+    assert!(
+        broken["failure_reason"].as_str().unwrap().contains("Verification failed"),
+        "{}",
+        broken
+    );
+    assert!(broken["verification"].as_str().unwrap().contains("\"passed\":false"));
+    let unverified = entry(&f);
+    assert_eq!(unverified["status"], "failed");
+    assert!(
+        unverified["failure_reason"].as_str().unwrap().contains("no worktree"),
+        "{}",
+        unverified
+    );
+    assert!(!repo.join("f.txt").exists());
+
+    assert_eq!(current_branch(&repo_path), default_branch);
+    assert_eq!(
+        git(&repo_path, &["rev-parse", &default_branch]).trim(),
+        entry(&b)["merge_commit"]
+    );
+    assert_eq!(
+        std::fs::read_to_string(repo.join("shared.txt")).unwrap(),
+        "one\ntwo-b\nthree\n"
+    );
+    assert!(repo.join("a.txt").exists());
+    assert!(!repo.join("broken.txt").exists());
+
+    for (card_id, stage) in [(&a, "done"), (&b, "done"), (&c, "review"), (&d, "review"), (&f, "review")] {
+        let card_stage: String = sqlx::query_scalar("SELECT stage FROM cards WHERE id = ?")
+            .bind(card_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(card_stage, stage);
+    }
+    let failures: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM notifications WHERE notification_type = 'merge_failed'",
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(failures, 3);
+}
+
+#[tokio::test]
+async fn test_merge_queue_lands_a_card_after_ai_conflict_resolution() {
+    let (app, token, pool, sse_tx, merge_locks) = test_app_with_locks().await;
+    let tmp = TempDir::new().unwrap();
+    let repo = tmp.path().join("repo");
+    std::fs::create_dir(&repo).unwrap();
+    let repo_path = repo.to_string_lossy().to_string();
+    git(&repo_path, &["init"]);
+    git(&repo_path, &["config", "user.email", "test@test.com"]);
+    git(&repo_path, &["config", "user.name", "Test User"]);
+    std::fs::write(repo.join("shared.txt"), "one\ntwo\nthree\n").unwrap();
+    git(&repo_path, &["add", "."]);
+    git(&repo_path, &["commit", "-m", "initial"]);
+    sqlx::query("INSERT OR REPLACE INTO board_settings (board_id, codebase_path, updated_at) VALUES ('default', ?, ?)")
+        .bind(&repo_path)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let worktrees = tmp.path().join("worktrees");
+    let card_id =
+        seed_worktree_card(&pool, &repo_path, &worktrees, "resolved", &[("shared.txt", "one\ntwo-card\nthree\n")]).await;
+    let worktree = worktrees.join("resolved");
+    let worktree_path = worktree.to_string_lossy().to_string();
+    std::fs::write(repo.join("shared.txt"), "one\ntwo-main\nthree\n").unwrap();
+    git(&repo_path, &["commit", "-am", "main change"]);
+
+    // The agent resolves the conflict in its worktree, as after
+    // `ai-resolve-conflicts`.
+    let result = GitWorktreeService::merge_base_into_worktree(&repo_path, &worktree_path, "resolved").unwrap();
+    assert!(!result.success);
+    std::fs::write(worktree.join("shared.txt"), "one\ntwo-both\nthree\n").unwrap();
+    git(&worktree_path, &["add", "shared.txt"]);
+    git(&worktree_path, &["commit", "--no-edit"]);
+
+    // The base moves on again before the card's turn.
+    std::fs::write(repo.join("other.txt"), "other\n").unwrap();
+    git(&repo_path, &["add", "other.txt"]);
+    git(&repo_path, &["commit", "-m", "unrelated change"]);
+
+    let (status, body) = common::make_request(
+        app.clone(),
+        "POST",
+        &format!("/api/cards/{}/merge-queue", card_id),
+        None,
+        Some(&token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "enqueue failed: {}", body);
+    tokio::spawn(
+        kanban_backend::services::MergeQueueProcessor {
+            db: pool.clone(),
+            sse_tx,
+            merge_locks,
+        }
+        .start(),
+    );
+
+    let mut queue = Vec::new();
+    for _ in 0..300 {
+        queue = merge_queue(&app, &token).await;
+        if queue.iter().all(|entry| entry["position"].is_null()) {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert_eq!(queue[0]["status"], "merged", "{:?}", queue);
+    assert_eq!(
+        std::fs::read_to_string(repo.join("shared.txt")).unwrap(),
+        "one\ntwo-both\nthree\n"
+    );
+    assert!(repo.join("other.txt").exists());
+}
+
+fn create_test_repo_with_branch() -> (TempDir, String, String) {
+    let tmp = TempDir::new().expect("temporary directory should be created");
+    let repo_path = tmp.path().to_string_lossy().to_string();
+
+    git(&repo_path, &["init"]);
+    git(&repo_path, &["config", "user.email", "test@test.com"]);
+    git(&repo_path, &["config", "user.name", "Test User"]);
+
+    std::fs::write(tmp.path().join("file.txt"), "line1\nline2\nline3\n")
+        .expect("initial file should be written");
+    std::fs::write(tmp.path().join("untouched.txt"), "same\n")
+        .expect("untouched file should be written");
+    git(&repo_path, &["add", "."]);
+    git(&repo_path, &["commit", "-m", "initial"]);
+
+    let branch_name = "ai/review-threads";
+    git(&repo_path, &["checkout", "-b", branch_name]);
+    std::fs::write(tmp.path().join("file.txt"), "line1\nchanged\nline3\n")
+        .expect("branch file update should be written");
+    git(&repo_path, &["add", "."]);
+    git(&repo_path, &["commit", "-m", "branch change"]);
+    git(&repo_path, &["checkout", "-"]);
+
+    (tmp, repo_path, branch_name.to_string())
+}
+
+async fn post_json(
+    app: &axum::Router,
+    token: &str,
+    method: &str,
+    uri: &str,
+    body: serde_json::Value,
+) -> (StatusCode, serde_json::Value) {
+    let (status, body) =
+        common::make_request(app.clone(), method, uri, Some(body.to_string()), Some(token)).await;
+    (status, serde_json::from_str(&body).unwrap_or(serde_json::Value::Null))
+}
+
+#[tokio::test]
+async fn test_review_threads_anchor_to_diff_and_only_unresolved_are_redispatched() {
+    let (_tmp, repo_path, branch_name) = create_test_repo_with_branch();
+    let plan_dir = TempDir::new().unwrap();
+    let plan_path = plan_dir.path().join("plan.md");
+    std::fs::write(&plan_path, "# Plan\n").unwrap();
+    let (app, token, pool) = test_app_with_pool().await;
+    let card_id =
+        seed_review_card(&pool, &repo_path, &branch_name, &plan_path.to_string_lossy()).await;
+    let head = git(&repo_path, &["rev-parse", &branch_name]).trim().to_string();
+    let threads_uri = format!("/api/cards/{}/review-threads", card_id);
+
+    let (status, body) = post_json(
+        &app,
+        &token,
+        "POST",
+        &threads_uri,
+        json!({"file": "untouched.txt", "line": 1, "content": "Why?"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+
+    let (status, body) = post_json(
+        &app,
+        &token,
+        "POST",
+        &threads_uri,
+        json!({"file": "file.txt", "line": 9, "content": "Past the hunk"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+
+    std::fs::write(std::path::Path::new(&repo_path).join("base.txt"), "base\n").unwrap();
+    git(&repo_path, &["add", "."]);
+    git(&repo_path, &["commit", "-m", "base only"]);
+    let base_only = git(&repo_path, &["rev-parse", "HEAD"]).trim().to_string();
+    for commit in [base_only.as_str(), "0123456789abcdef"] {
+        let (status, body) = post_json(
+            &app,
+            &token,
+            "POST",
+            &threads_uri,
+            json!({"file": "file.txt", "line": 2, "commit": commit, "content": "Elsewhere"}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    }
+
+    let (status, old_side) = post_json(
+        &app,
+        &token,
+        "POST",
+        &threads_uri,
+        json!({"file": "file.txt", "line": 2, "side": "old", "commit": head, "content": "Was fine"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", old_side);
+    let (status, body) = post_json(
+        &app,
+        &token,
+        "PATCH",
+        &format!("/api/review-threads/{}", old_side["id"].as_str().unwrap()),
+        json!({"resolved": true}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let (status, open) = post_json(
+        &app,
+        &token,
+        "POST",
+        &threads_uri,
+        json!({"file": "file.txt", "line": 2, "content": "Rename this"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", open);
+    assert_eq!(open["commit_sha"], head);
+    assert_eq!(open["side"], "new");
+
+    let (status, fixed) = post_json(
+        &app,
+        &token,
+        "POST",
+        &threads_uri,
+        json!({"file": "file.txt", "content": "Typo in header"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", fixed);
+
+    let (status, replied) = post_json(
+        &app,
+        &token,
+        "POST",
+        &format!("/api/review-threads/{}/comments", open["id"].as_str().unwrap()),
+        json!({"content": "Use `renamed` instead"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", replied);
+    assert_eq!(replied["comments"].as_array().unwrap().len(), 2);
+
+    let (status, resolved) = post_json(
+        &app,
+        &token,
+        "PATCH",
+        &format!("/api/review-threads/{}", fixed["id"].as_str().unwrap()),
+        json!({"resolved": true}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", resolved);
+    assert_eq!(resolved["resolved"], true);
+
+    let (status, diff) = common::make_request(
+        app.clone(),
+        "GET",
+        &format!("/api/cards/{}/diff", card_id),
+        None,
+        Some(&token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", diff);
+    let diff: serde_json::Value = serde_json::from_str(&diff).unwrap();
+    assert_eq!(diff["head_commit"], head);
+    assert_eq!(diff["review_threads"].as_array().unwrap().len(), 3);
+    assert!(diff["files"].as_array().is_some());
+
+    let (status, unresolved) = common::make_request(
+        app.clone(),
+        "GET",
+        &format!("{}?unresolved=true", threads_uri),
+        None,
+        Some(&token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let unresolved: serde_json::Value = serde_json::from_str(&unresolved).unwrap();
+    assert_eq!(unresolved.as_array().unwrap().len(), 1);
+    assert_eq!(unresolved[0]["id"], open["id"]);
+
+    let (status, body) = post_json(
+        &app,
+        &token,
+        "PATCH",
+        &format!("/api/cards/{}/move", card_id),
+        json!({"stage": "todo"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let plan = std::fs::read_to_string(&plan_path).unwrap();
+    assert!(plan.contains("## Review Feedback"), "{}", plan);
+    assert!(
+        plan.contains(&format!("### 1. `file.txt` line 2 (at commit {})", &head[..12])),
+        "{}",
+        plan
+    );
+    assert!(plan.contains("Use `renamed` instead"), "{}", plan);
+    assert!(!plan.contains("Typo in header"), "{}", plan);
+    assert!(!plan.contains("### 2."), "{}", plan);
+}
+
+#[tokio::test]
+async fn test_reject_feedback_opens_general_review_thread() {
+    let (_tmp, repo_path, branch_name) = create_test_repo_with_branch();
+    let (app, token, pool) = test_app_with_pool().await;
+    let card_id = seed_review_card(&pool, &repo_path, &branch_name, "").await;
+
+    let (status, body) = post_json(
+        &app,
+        &token,
+        "POST",
+        &format!("/api/cards/{}/reject", card_id),
+        json!({"feedback": "Add tests"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let (status, threads) = common::make_request(
+        app.clone(),
+        "GET",
+        &format!("/api/cards/{}/review-threads", card_id),
+        None,
+        Some(&token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let threads: serde_json::Value = serde_json::from_str(&threads).unwrap();
+    assert_eq!(threads.as_array().unwrap().len(), 1);
+    assert!(threads[0]["file"].is_null());
+    assert_eq!(threads[0]["resolved"], false);
+    assert_eq!(threads[0]["comments"][0]["author"], "Reviewer");
+    assert_eq!(threads[0]["comments"][0]["content"], "Add tests");
+
+    let comments: Vec<(String, String)> =
+        sqlx::query_as("SELECT author, content FROM comments WHERE card_id = ?")
+            .bind(&card_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    assert_eq!(
+        comments,
+        vec![("Reviewer".to_string(), "**Review Feedback:** Add tests".to_string())]
+    );
+}
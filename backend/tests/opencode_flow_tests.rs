@@ -0,0 +1,2085 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use tempfile::TempDir;
+use tokio::task::JoinHandle;
+
+use common::opencode_mock::MockOpenCode;
+use kanban_backend::api::AppState;
+use kanban_backend::config::Config;
+use kanban_backend::domain::Card;
+use kanban_backend::services::opencode_supervisor::{ProcessState, RotatingLogFile};
+use kanban_backend::services::{
+    AiReviewService, EventBus, OpencodeManager, OpencodePool, OpencodeSupervisor, QueueProcessor, RedactorCache,
+    SseRelayService, StatusCoalescer,
+};
+
+struct Harness {
+    app: axum::Router,
+    token: String,
+    pool: SqlitePool,
+    mock: MockOpenCode,
+    extra_mocks: Vec<MockOpenCode>,
+    opencode: OpencodePool,
+    sse_tx: EventBus,
+    redactors: RedactorCache,
+    coalesce_window: Duration,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl Harness {
+    async fn new() -> Self {
+        Self::with_extra_endpoints(0).await
+    }
+
+    /// Starts one mock per endpoint; the first is the primary endpoint.
+    async fn with_extra_endpoints(extra: usize) -> Self {
+        let (pool, token) = common::setup_test_db().await;
+        let mock = MockOpenCode::start().await;
+        let mut extra_mocks = Vec::new();
+        for _ in 0..extra {
+            extra_mocks.push(MockOpenCode::start().await);
+        }
+        let sse_tx = EventBus::new(Some(pool.clone()), 10_000);
+        let opencode_urls = if extra_mocks.is_empty() {
+            Vec::new()
+        } else {
+            std::iter::once(&mock)
+                .chain(extra_mocks.iter())
+                .map(|m| m.url.clone())
+                .collect()
+        };
+        let config = Arc::new(Config {
+            database_url: "sqlite::memory:".to_string(),
+            opencode_url: mock.url.clone(),
+            opencode_urls,
+            ..Default::default()
+        });
+
+        let state = AppState::new(
+            Some(pool.clone()),
+            sse_tx.clone(),
+            reqwest::Client::new(),
+            config.clone(),
+        );
+        let opencode = state.opencode.clone();
+        let redactors = state.redactors.clone();
+        let app = kanban_backend::api::routes::create_router(state, &config);
+
+        Self {
+            app,
+            token,
+            pool,
+            mock,
+            extra_mocks,
+            opencode,
+            sse_tx,
+            redactors,
+            coalesce_window: Duration::from_millis(config.ai_status_coalesce_ms),
+            tasks: Vec::new(),
+        }
+    }
+
+    async fn start_relay(&mut self) {
+        let relay = SseRelayService {
+            opencode_url: self.mock.url.clone(),
+            db: self.pool.clone(),
+            sse_tx: self.sse_tx.clone(),
+            http_client: reqwest::Client::new(),
+            coalescer: StatusCoalescer::new(
+                self.pool.clone(),
+                self.sse_tx.clone(),
+                self.coalesce_window,
+            ),
+            redactors: self.redactors.clone(),
+        };
+        self.tasks.push(tokio::spawn(relay.start()));
+        self.mock.wait_for_subscribers(1).await;
+    }
+
+    fn start_queue_processor(&mut self) {
+        let processor = QueueProcessor {
+            db: self.pool.clone(),
+            http_client: reqwest::Client::new(),
+            opencode: self.opencode.clone(),
+            sse_tx: self.sse_tx.clone(),
+        };
+        self.tasks.push(tokio::spawn(processor.start()));
+    }
+
+    async fn create_card(&self, title: &str, stage: &str, working_directory: &str) -> String {
+        let body = json!({
+            "title": title,
+            "stage": stage,
+            "working_directory": working_directory,
+        })
+        .to_string();
+        let (status, resp) = common::make_request(
+            self.app.clone(),
+            "POST",
+            "/api/cards",
+            Some(body),
+            Some(&self.token),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED, "Create card failed: {}", resp);
+        let card: Value = serde_json::from_str(&resp).unwrap();
+        card["id"].as_str().unwrap().to_string()
+    }
+
+    /// Puts a card into an active AI state with the given session, as if it
+    /// had been dispatched earlier.
+    async fn attach_session(&self, card_id: &str, session_id: &str, stage: &str, status: &str) {
+        sqlx::query(
+            "UPDATE cards SET ai_session_id = ?, stage = ?, ai_status = ?, updated_at = ? WHERE id = ?",
+        )
+        .bind(session_id)
+        .bind(stage)
+        .bind(status)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(card_id)
+        .execute(&self.pool)
+        .await
+        .unwrap();
+        self.mock.insert_session(session_id, "busy");
+    }
+
+    async fn queue_card(&self, card_id: &str) {
+        sqlx::query("UPDATE cards SET stage = 'todo', ai_status = 'queued' WHERE id = ?")
+            .bind(card_id)
+            .execute(&self.pool)
+            .await
+            .unwrap();
+    }
+
+    async fn card(&self, card_id: &str) -> Card {
+        sqlx::query_as("SELECT * FROM cards WHERE id = ?")
+            .bind(card_id)
+            .fetch_one(&self.pool)
+            .await
+            .unwrap()
+    }
+
+    async fn wait_for_card<F>(&self, card_id: &str, what: &str, predicate: F) -> Card
+    where
+        F: Fn(&Card) -> bool,
+    {
+        for _ in 0..200 {
+            let card = self.card(card_id).await;
+            if predicate(&card) {
+                return card;
+            }
+            tokio::time::sleep(Duration::from_millis(25)).await;
+        }
+        let card = self.card(card_id).await;
+        panic!(
+            "Timed out waiting for card to be {}: stage={} ai_status={}",
+            what, card.stage, card.ai_status
+        );
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_dispatch_moves_card_through_in_progress_to_review() {
+    let mut h = Harness::new().await;
+    let workdir = TempDir::new().unwrap();
+    h.start_relay().await;
+
+    h.mock.on_message(|session_id, _| {
+        vec![json!({
+            "type": "session.status",
+            "properties": { "sessionID": session_id, "status": { "type": "busy" } },
+        })]
+    });
+
+    let card_id = h
+        .create_card("Mock dispatch", "plan", &workdir.path().to_string_lossy())
+        .await;
+    let (status, resp) = common::make_request(
+        h.app.clone(),
+        "PATCH",
+        &format!("/api/cards/{}/move", card_id),
+        Some(json!({ "stage": "todo" }).to_string()),
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "Move failed: {}", resp);
+    assert_eq!(h.card(&card_id).await.ai_status, "queued");
+
+    h.start_queue_processor();
+
+    let card = h
+        .wait_for_card(&card_id, "working in progress", |c| {
+            c.stage == "in_progress" && c.ai_status == "working"
+        })
+        .await;
+    let session_id = card.ai_session_id.clone().expect("card has a session");
+    let plan_path = card.plan_path.clone().expect("card has a plan file");
+    assert!(std::path::Path::new(&plan_path).exists());
+
+    let prompts = h.mock.requests_to("POST", &format!("/session/{}/message", session_id));
+    assert_eq!(prompts.len(), 1);
+    let prompt = prompts[0].body["parts"][0]["text"].as_str().unwrap();
+    assert!(prompt.contains(&plan_path));
+
+    h.mock.emit(
+        "session.idle",
+        json!({ "sessionID": session_id }),
+    );
+
+    let card = h
+        .wait_for_card(&card_id, "in review", |c| c.stage == "review")
+        .await;
+    assert_eq!(card.ai_status, "completed");
+
+    let (status, resp) = common::make_request(
+        h.app.clone(),
+        "GET",
+        "/api/notifications",
+        None,
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(resp.contains("AI completed: Mock dispatch"));
+}
+
+fn git(repo_path: &std::path::Path, args: &[&str]) -> String {
+    let output = std::process::Command::new("git")
+        .args(args)
+        .current_dir(repo_path)
+        .output()
+        .expect("git command should execute");
+    assert!(
+        output.status.success(),
+        "git {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+#[tokio::test]
+async fn test_dispatch_writes_board_variables_to_worktree_env() {
+    let mut h = Harness::new().await;
+    let repo = TempDir::new().unwrap();
+    git(repo.path(), &["init"]);
+    git(repo.path(), &["config", "user.email", "test@test.com"]);
+    git(repo.path(), &["config", "user.name", "Test User"]);
+    std::fs::write(repo.path().join("README.md"), "hello\n").unwrap();
+    git(repo.path(), &["add", "."]);
+    git(repo.path(), &["commit", "-m", "initial"]);
+
+    let card_id = h.create_card("Needs env", "todo", ".").await;
+    let board_id = h.card(&card_id).await.board_id.unwrap();
+    let (status, resp) = common::make_request(
+        h.app.clone(),
+        "PUT",
+        &format!("/api/boards/{}/settings", board_id),
+        Some(
+            json!({
+                "codebase_path": repo.path().to_string_lossy(),
+                "variables": "STAGING_URL=https://staging.example.com\nGREETING=hello world",
+            })
+            .to_string(),
+        ),
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", resp);
+    let (status, _) = common::make_request(
+        h.app.clone(),
+        "PUT",
+        &format!("/api/boards/{}/settings/secrets/API_TOKEN", board_id),
+        Some(json!({ "value": "tok$en\"quoted" }).to_string()),
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    h.queue_card(&card_id).await;
+    h.start_queue_processor();
+    let card = h
+        .wait_for_card(&card_id, "dispatched", |c| c.stage == "in_progress")
+        .await;
+    assert!(!card.worktree_path.is_empty());
+
+    let env = std::fs::read_to_string(std::path::Path::new(&card.worktree_path).join(".env"))
+        .expect("worktree has a .env");
+    assert!(env.contains("STAGING_URL=https://staging.example.com\n"));
+    assert!(env.contains("GREETING=\"hello world\"\n"));
+    assert!(env.contains("API_TOKEN=\"tok\\$en\\\"quoted\"\n"));
+
+    let worktree = std::path::Path::new(&card.worktree_path);
+    assert!(!git(worktree, &["status", "--porcelain"]).contains(".env"));
+
+    let prompt = kanban_backend::services::PromptTemplateService::render_for_card(
+        &h.pool,
+        kanban_backend::services::prompt_templates::PLANNING,
+        &card,
+        &[],
+    )
+    .await
+    .unwrap();
+    assert!(prompt.contains("- `API_TOKEN` (secret)"));
+    assert!(prompt.contains("- `STAGING_URL`"));
+    assert!(!prompt.contains("tok$en"));
+}
+
+fn init_repo(repo: &std::path::Path) {
+    git(repo, &["init"]);
+    git(repo, &["config", "user.email", "test@test.com"]);
+    git(repo, &["config", "user.name", "Test User"]);
+    std::fs::write(repo.join("README.md"), "hello\n").unwrap();
+    git(repo, &["add", "."]);
+    git(repo, &["commit", "-m", "initial"]);
+}
+
+async fn set_board_settings(h: &Harness, board_id: &str, settings: Value) {
+    let (status, resp) = common::make_request(
+        h.app.clone(),
+        "PUT",
+        &format!("/api/boards/{}/settings", board_id),
+        Some(settings.to_string()),
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", resp);
+}
+
+#[tokio::test]
+async fn test_worktree_hooks_run_setup_before_dispatch_and_teardown_on_delete() {
+    let mut h = Harness::new().await;
+    let repo = TempDir::new().unwrap();
+    init_repo(repo.path());
+    std::fs::write(repo.path().join(".env.local"), "LOCAL=1\n").unwrap();
+    std::fs::create_dir_all(repo.path().join("node_modules/left-pad")).unwrap();
+    std::fs::write(repo.path().join("node_modules/left-pad/index.js"), "// pad\n").unwrap();
+    let marker = TempDir::new().unwrap();
+    let marker_path = marker.path().join("torn-down");
+
+    let card_id = h.create_card("Needs setup", "todo", ".").await;
+    let board_id = h.card(&card_id).await.board_id.unwrap();
+    let hooks = json!({
+        "setup": [
+            { "type": "command", "run": "echo \"greeting=$GREETING\" > setup.txt && echo installed" },
+            { "type": "copy", "path": ".env.local" },
+            { "type": "symlink", "path": "node_modules" },
+            { "type": "command", "run": "head -c 200000 /dev/zero | tr '\\0' x; echo; echo noisy-done" },
+        ],
+        "teardown": [
+            { "type": "command", "run": format!("cat setup.txt > '{}'", marker_path.display()) },
+        ],
+    });
+    set_board_settings(
+        &h,
+        &board_id,
+        json!({
+            "codebase_path": repo.path().to_string_lossy(),
+            "variables": "GREETING=hi",
+            "worktree_hooks": hooks.to_string(),
+        }),
+    )
+    .await;
+
+    h.queue_card(&card_id).await;
+    h.start_queue_processor();
+    let card = h
+        .wait_for_card(&card_id, "dispatched", |c| c.stage == "in_progress")
+        .await;
+
+    let worktree = std::path::Path::new(&card.worktree_path);
+    assert_eq!(
+        std::fs::read_to_string(worktree.join("setup.txt")).unwrap(),
+        "greeting=hi\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(worktree.join(".env.local")).unwrap(),
+        "LOCAL=1\n"
+    );
+    let link = worktree.join("node_modules");
+    assert!(std::fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+    assert!(link.join("left-pad/index.js").exists());
+    let status = git(worktree, &["status", "--porcelain"]);
+    assert!(!status.contains("node_modules"), "{}", status);
+    assert!(!status.contains(".env.local"), "{}", status);
+
+    let logs: Vec<(String, String)> = sqlx::query_as(
+        "SELECT event_type, content FROM agent_logs WHERE card_id = ? ORDER BY created_at ASC",
+    )
+    .bind(&card_id)
+    .fetch_all(&h.pool)
+    .await
+    .unwrap();
+    let setup: Vec<&String> = logs
+        .iter()
+        .filter(|(event_type, _)| event_type == "worktree.setup")
+        .map(|(_, content)| content)
+        .collect();
+    assert_eq!(setup.len(), 4, "{:?}", logs);
+    assert!(setup[0].starts_with("[worktree.setup ok] $ echo"));
+    assert!(setup[0].contains("installed"));
+    // Only the tail of a noisy command is kept.
+    assert!(setup[3].ends_with("noisy-done"), "{}", &setup[3][setup[3].len() - 100..]);
+    assert!(setup[3].contains("bytes and"), "{}", &setup[3][..300]);
+    assert!(setup[3].len() < 9_000, "{}", setup[3].len());
+
+    let (status, _) = common::make_request(
+        h.app.clone(),
+        "DELETE",
+        &format!("/api/cards/{}", card_id),
+        None,
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(std::fs::read_to_string(&marker_path).unwrap(), "greeting=hi\n");
+    assert!(!worktree.exists());
+}
+
+#[tokio::test]
+async fn test_failed_worktree_setup_fails_dispatch_with_reason() {
+    let mut h = Harness::new().await;
+    let repo = TempDir::new().unwrap();
+    init_repo(repo.path());
+
+    let card_id = h.create_card("Broken setup", "todo", ".").await;
+    let board_id = h.card(&card_id).await.board_id.unwrap();
+    let hooks = json!({
+        "setup": [
+            { "type": "command", "run": "echo 'npm ERR! missing lockfile' >&2; exit 3" },
+            { "type": "copy", "path": "never-reached" },
+        ],
+    });
+    set_board_settings(
+        &h,
+        &board_id,
+        json!({
+            "codebase_path": repo.path().to_string_lossy(),
+            "worktree_hooks": hooks.to_string(),
+        }),
+    )
+    .await;
+
+    h.queue_card(&card_id).await;
+    h.start_queue_processor();
+    let card = h
+        .wait_for_card(&card_id, "failed", |c| c.ai_status == "failed")
+        .await;
+
+    assert_eq!(card.stage, "todo");
+    assert!(card.worktree_path.is_empty());
+    let progress: Value = serde_json::from_str(&card.ai_progress).unwrap();
+    let reason = progress["failure_reason"].as_str().unwrap();
+    assert!(reason.starts_with("Worktree setup step 1 ($ echo"), "{}", reason);
+    assert!(reason.ends_with("failed: exit code 3"), "{}", reason);
+    assert!(h.mock.requests_to("POST", "/session").is_empty());
+
+    let logs: Vec<String> = sqlx::query_scalar(
+        "SELECT content FROM agent_logs WHERE card_id = ? AND event_type = 'worktree.setup'",
+    )
+    .bind(&card_id)
+    .fetch_all(&h.pool)
+    .await
+    .unwrap();
+    assert_eq!(logs.len(), 1);
+    assert!(logs[0].starts_with("[worktree.setup failed]"));
+    assert!(logs[0].contains("npm ERR! missing lockfile"));
+}
+
+#[tokio::test]
+async fn test_slow_worktree_setup_runs_off_the_queue_and_times_out_its_process_group() {
+    let mut h = Harness::new().await;
+    let repo = TempDir::new().unwrap();
+    init_repo(repo.path());
+    let scratch = TempDir::new().unwrap();
+    let pid_file = scratch.path().join("sleep.pid");
+
+    let slow_id = h.create_card("Slow setup", "todo", ".").await;
+    let fast_id = h.create_card("Fast setup", "todo", ".").await;
+    let board_id = h.card(&slow_id).await.board_id.unwrap();
+    // Only the slow card's setup hangs, in a background child of the shell.
+    let run = format!(
+        "case \"$PWD\" in *{}*) sleep 60 & echo $! > '{}'; wait ;; esac",
+        slow_id,
+        pid_file.display()
+    );
+    let hooks = json!({ "setup": [{ "type": "command", "run": run, "timeout_secs": 2 }] });
+    set_board_settings(
+        &h,
+        &board_id,
+        json!({
+            "codebase_path": repo.path().to_string_lossy(),
+            "ai_concurrency": 2,
+            "worktree_hooks": hooks.to_string(),
+        }),
+    )
+    .await;
+
+    h.queue_card(&slow_id).await;
+    h.queue_card(&fast_id).await;
+    h.start_queue_processor();
+
+    h.wait_for_card(&fast_id, "dispatched", |c| c.stage == "in_progress")
+        .await;
+    assert_eq!(h.card(&slow_id).await.ai_status, "setting_up");
+
+    let card = h
+        .wait_for_card(&slow_id, "failed", |c| c.ai_status == "failed")
+        .await;
+    let progress: Value = serde_json::from_str(&card.ai_progress).unwrap();
+    let reason = progress["failure_reason"].as_str().unwrap();
+    assert!(reason.ends_with("failed: Timed out after 2s"), "{}", reason);
+
+    let pid = std::fs::read_to_string(&pid_file).unwrap().trim().to_string();
+    let mut alive = true;
+    for _ in 0..40 {
+        let ps = std::process::Command::new("ps")
+            .args(["-o", "stat=", "-p", &pid])
+            .output()
+            .unwrap();
+        let stat = String::from_utf8_lossy(&ps.stdout).trim().to_string();
+        alive = !stat.is_empty() && !stat.starts_with('Z');
+        if !alive {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    assert!(!alive, "the hook's background process outlived the timeout");
+}
+
+#[tokio::test]
+async fn test_worktree_hooks_reject_paths_outside_the_repository() {
+    let h = Harness::new().await;
+    let card_id = h.create_card("Hooks", "todo", ".").await;
+    let board_id = h.card(&card_id).await.board_id.unwrap();
+
+    for hooks in [
+        json!({ "setup": [{ "type": "copy", "path": "../secrets" }] }),
+        json!({ "teardown": [{ "type": "symlink", "path": "/etc" }] }),
+        json!({ "setup": [{ "type": "shell", "run": "true" }] }),
+    ] {
+        let (status, _) = common::make_request(
+            h.app.clone(),
+            "PUT",
+            &format!("/api/boards/{}/settings", board_id),
+            Some(json!({ "worktree_hooks": hooks.to_string() }).to_string()),
+            Some(&h.token),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", hooks);
+    }
+}
+
+#[tokio::test]
+async fn test_card_runs_keep_each_attempts_diff_and_usage() {
+    let mut h = Harness::new().await;
+    let repo = TempDir::new().unwrap();
+    init_repo(repo.path());
+    h.start_relay().await;
+
+    let card_id = h.create_card("Run history", "todo", ".").await;
+    let board_id = h.card(&card_id).await.board_id.unwrap();
+    set_board_settings(
+        &h,
+        &board_id,
+        json!({ "codebase_path": repo.path().to_string_lossy() }),
+    )
+    .await;
+
+    h.queue_card(&card_id).await;
+    h.start_queue_processor();
+    let card = h
+        .wait_for_card(&card_id, "dispatched", |c| c.stage == "in_progress")
+        .await;
+    let session_id = card.ai_session_id.clone().unwrap();
+    let worktree = std::path::Path::new(&card.worktree_path);
+    std::fs::write(worktree.join("first.txt"), "one\n").unwrap();
+
+    let finished = json!({
+        "id": "msg_1",
+        "sessionID": session_id,
+        "role": "assistant",
+        "finish": "stop",
+        "cost": 0.25,
+        "tokens": { "input": 100, "output": 40, "reasoning": 5, "cache": { "read": 10, "write": 2 } },
+    });
+    // A repeated event must not count the message twice.
+    h.mock.emit("message.updated", json!({ "info": finished }));
+    h.mock.emit("message.updated", json!({ "info": finished }));
+    h.mock.emit("session.idle", json!({ "sessionID": session_id }));
+    h.wait_for_card(&card_id, "in review", |c| c.stage == "review")
+        .await;
+
+    let runs_url = format!("/api/cards/{}/runs", card_id);
+    let (status, resp) =
+        common::make_request(h.app.clone(), "GET", &runs_url, None, Some(&h.token)).await;
+    assert_eq!(status, StatusCode::OK, "{}", resp);
+    let runs: Vec<Value> = serde_json::from_str(&resp).unwrap();
+    assert_eq!(runs.len(), 1, "{}", resp);
+    let first = &runs[0];
+    assert_eq!(first["run_number"], 1);
+    assert_eq!(first["kind"], "work");
+    assert_eq!(first["session_id"], session_id.as_str());
+    assert_eq!(first["outcome"], "completed");
+    assert!(first["files_changed"].as_i64().unwrap() >= 1, "{}", resp);
+    assert_eq!(first["input_tokens"], 100);
+    assert_eq!(first["output_tokens"], 40);
+    assert_eq!(first["cache_read_tokens"], 10);
+    assert_eq!(first["cost"], 0.25);
+    let first_id = first["id"].as_str().unwrap().to_string();
+
+    // A backfill counts the messages the relay missed, and only those.
+    let mut missed = history_message("msg_2", "assistant", 1_767_225_600_000, Vec::new());
+    missed["info"]["tokens"] = json!({ "input": 7, "output": 3 });
+    h.mock.set_session_messages(&session_id, vec![json!({ "info": finished }), missed]);
+    let (status, resp) = common::make_request(
+        h.app.clone(),
+        "POST",
+        &format!("/api/cards/{}/logs/backfill", card_id),
+        None,
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", resp);
+    let (_, resp) =
+        common::make_request(h.app.clone(), "GET", &runs_url, None, Some(&h.token)).await;
+    let runs: Vec<Value> = serde_json::from_str(&resp).unwrap();
+    assert_eq!(runs[0]["input_tokens"], 107, "{}", resp);
+    assert_eq!(runs[0]["output_tokens"], 43, "{}", resp);
+
+    sqlx::query("UPDATE cards SET stage = 'in_progress', ai_status = 'failed' WHERE id = ?")
+        .bind(&card_id)
+        .execute(&h.pool)
+        .await
+        .unwrap();
+    let (status, _) = common::make_request(
+        h.app.clone(),
+        "POST",
+        &format!("/api/cards/{}/retry-ai", card_id),
+        None,
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let card = h
+        .wait_for_card(&card_id, "redispatched", |c| {
+            c.stage == "in_progress" && c.ai_session_id.as_deref().is_some_and(|s| s != session_id)
+        })
+        .await;
+    std::fs::write(std::path::Path::new(&card.worktree_path).join("second.txt"), "two\n").unwrap();
+
+    let (_, resp) =
+        common::make_request(h.app.clone(), "GET", &runs_url, None, Some(&h.token)).await;
+    let runs: Vec<Value> = serde_json::from_str(&resp).unwrap();
+    assert_eq!(runs.len(), 2, "{}", resp);
+    assert_eq!(runs[1]["kind"], "retry");
+    assert_eq!(runs[1]["outcome"], "running");
+
+    let (status, resp) = common::make_request(
+        h.app.clone(),
+        "GET",
+        &format!("{}/{}/diff", runs_url, first_id),
+        None,
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", resp);
+    let diff: Value = serde_json::from_str(&resp).unwrap();
+    let files: Vec<&str> = diff["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f["path"].as_str().unwrap())
+        .collect();
+    assert!(files.contains(&"first.txt"), "{:?}", files);
+    assert!(!files.contains(&"second.txt"), "{:?}", files);
+
+    let (status, resp) = common::make_request(
+        h.app.clone(),
+        "GET",
+        &format!("{}/{}/logs", runs_url, first_id),
+        None,
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", resp);
+    let logs: Vec<Value> = serde_json::from_str(&resp).unwrap();
+    assert!(logs.iter().all(|log| log["session_id"] == session_id.as_str()), "{}", resp);
+
+    let (status, _) = common::make_request(
+        h.app.clone(),
+        "GET",
+        &format!("{}/missing/diff", runs_url),
+        None,
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+async fn attempts(h: &Harness, card_id: &str) -> Vec<Value> {
+    let (status, resp) = common::make_request(
+        h.app.clone(),
+        "GET",
+        &format!("/api/cards/{}/attempts", card_id),
+        None,
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", resp);
+    serde_json::from_str(&resp).unwrap()
+}
+
+async fn wait_for_attempts<F>(h: &Harness, card_id: &str, what: &str, predicate: F) -> Vec<Value>
+where
+    F: Fn(&[Value]) -> bool,
+{
+    for _ in 0..400 {
+        let attempts = attempts(h, card_id).await;
+        if predicate(&attempts) {
+            return attempts;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    panic!("Timed out waiting for attempts to be {}: {:?}", what, attempts(h, card_id).await);
+}
+
+#[tokio::test]
+async fn test_competing_attempts_share_the_concurrency_limit_and_merge_the_winner() {
+    let mut h = Harness::new().await;
+    let repo = TempDir::new().unwrap();
+    init_repo(repo.path());
+    h.start_relay().await;
+
+    let card_id = h.create_card("Race two agents", "todo", ".").await;
+    let board_id = h.card(&card_id).await.board_id.unwrap();
+    let hooks = json!({ "verify": [{ "type": "command", "run": "test -f answer.txt" }] });
+    set_board_settings(
+        &h,
+        &board_id,
+        json!({
+            "codebase_path": repo.path().to_string_lossy(),
+            "ai_concurrency": 1,
+            "worktree_hooks": hooks.to_string(),
+        }),
+    )
+    .await;
+
+    // Two racing requests must not both queue a set of attempts.
+    let attempts_uri = format!("/api/cards/{}/attempts", card_id);
+    let create = || {
+        common::make_request(
+            h.app.clone(),
+            "POST",
+            &attempts_uri,
+            Some(
+                json!({ "attempts": [
+                    { "agent": "sisyphus" },
+                    { "agent": "hephaestus", "model": "anthropic/claude-sonnet" },
+                ] })
+                .to_string(),
+            ),
+            Some(&h.token),
+        )
+    };
+    let ((first, first_resp), (second, second_resp)) = tokio::join!(create(), create());
+    let mut statuses = vec![first, second];
+    statuses.sort();
+    assert_eq!(
+        statuses,
+        vec![StatusCode::CREATED, StatusCode::CONFLICT],
+        "{} / {}",
+        first_resp,
+        second_resp
+    );
+    assert_eq!(attempts(&h, &card_id).await.len(), 2);
+    assert_eq!(h.card(&card_id).await.ai_status, "queued");
+
+    h.start_queue_processor();
+    let listed = wait_for_attempts(&h, &card_id, "first dispatched", |a| a[0]["status"] == "dispatched").await;
+    // One slot on the board: the second attempt waits for the first.
+    assert_eq!(listed[1]["status"], "queued");
+    assert_eq!(h.card(&card_id).await.stage, "in_progress");
+    let first_session = listed[0]["session_id"].as_str().unwrap().to_string();
+    let first_worktree = std::path::PathBuf::from(listed[0]["worktree_path"].as_str().unwrap());
+    assert!(listed[0]["branch_name"].as_str().unwrap().ends_with("-attempt-1"));
+    std::fs::write(first_worktree.join("answer.txt"), "42\n").unwrap();
+
+    h.mock.emit("session.idle", json!({ "sessionID": first_session }));
+    let listed = wait_for_attempts(&h, &card_id, "second dispatched", |a| {
+        a[0]["status"] == "completed" && a[1]["status"] == "dispatched"
+    })
+    .await;
+    let verification: Value = serde_json::from_str(listed[0]["verification"].as_str().unwrap()).unwrap();
+    assert_eq!(verification["passed"], true);
+    assert!(listed[0]["files_changed"].as_i64().unwrap() >= 1);
+
+    let second_session = listed[1]["session_id"].as_str().unwrap().to_string();
+    let second_worktree = std::path::PathBuf::from(listed[1]["worktree_path"].as_str().unwrap());
+    assert_ne!(first_worktree, second_worktree);
+    let message_path = format!("/session/{}/message", second_session);
+    for _ in 0..200 {
+        if !h.mock.requests_to("POST", &message_path).is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    let prompts = h.mock.requests_to("POST", &message_path);
+    assert_eq!(prompts[0].body["model"]["providerID"], "anthropic");
+    assert_eq!(prompts[0].body["model"]["modelID"], "claude-sonnet");
+    assert!(prompts[0].body["parts"][0]["text"].as_str().unwrap().contains("hephaestus"));
+
+    h.mock.emit("session.idle", json!({ "sessionID": second_session }));
+    h.wait_for_card(&card_id, "in review", |c| c.stage == "review").await;
+    let listed = attempts(&h, &card_id).await;
+    let verification: Value = serde_json::from_str(listed[1]["verification"].as_str().unwrap()).unwrap();
+    assert_eq!(verification["passed"], false);
+
+    let first_id = listed[0]["id"].as_str().unwrap().to_string();
+    let (status, resp) = common::make_request(
+        h.app.clone(),
+        "GET",
+        &format!("/api/cards/{}/attempts/{}/diff", card_id, first_id),
+        None,
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", resp);
+    assert!(resp.contains("answer.txt"));
+
+    // Of two racing selections only one claims the attempt.
+    let select_uri = format!("/api/cards/{}/attempts/{}/select", card_id, first_id);
+    let select = || common::make_request(h.app.clone(), "POST", &select_uri, None, Some(&h.token));
+    let ((first, first_resp), (second, second_resp)) = tokio::join!(select(), select());
+    let mut statuses = [first, second];
+    statuses.sort();
+    assert_eq!(statuses[0], StatusCode::OK, "{} / {}", first_resp, second_resp);
+    assert!(statuses[1].is_client_error(), "{} / {}", first_resp, second_resp);
+    let card = h.card(&card_id).await;
+    assert_eq!(card.worktree_path, first_worktree.to_string_lossy());
+    assert_eq!(card.ai_session_id.as_deref(), Some(first_session.as_str()));
+    assert!(!second_worktree.exists());
+    let listed = attempts(&h, &card_id).await;
+    assert_eq!(listed[0]["status"], "selected");
+    assert_eq!(listed[1]["status"], "discarded");
+    assert!(!git(repo.path(), &["branch", "--list"]).contains("-attempt-2"));
+
+    let (status, resp) = common::make_request(
+        h.app.clone(),
+        "POST",
+        &format!("/api/cards/{}/merge", card_id),
+        None,
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", resp);
+    assert!(repo.path().join("answer.txt").exists());
+}
+
+#[tokio::test]
+async fn test_board_and_card_models_are_validated_and_sent_with_prompts() {
+    let mut h = Harness::new().await;
+    let workdir = TempDir::new().unwrap();
+    let card_id = h
+        .create_card("Model choice", "plan", &workdir.path().to_string_lossy())
+        .await;
+    let board_id = h.card(&card_id).await.board_id.unwrap();
+
+    for model in ["anthropic/claude-haiku", "claude-opus"] {
+        let (status, resp) = common::make_request(
+            h.app.clone(),
+            "PUT",
+            &format!("/api/boards/{}/settings", board_id),
+            Some(json!({ "planning_model": model }).to_string()),
+            Some(&h.token),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{} accepted: {}", model, resp);
+    }
+    set_board_settings(
+        &h,
+        &board_id,
+        json!({
+            "planning_model": "anthropic/claude-opus",
+            "implementation_model": "anthropic/claude-sonnet",
+        }),
+    )
+    .await;
+
+    let (status, resp) = common::make_request(
+        h.app.clone(),
+        "PATCH",
+        &format!("/api/cards/{}", card_id),
+        Some(json!({ "implementation_model": "openai/gpt-5" }).to_string()),
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", resp);
+    let card: Value = serde_json::from_str(&resp).unwrap();
+    assert_eq!(card["implementation_model"], "openai/gpt-5");
+    assert_eq!(card["planning_model"], Value::Null);
+
+    let (status, resp) = common::make_request(
+        h.app.clone(),
+        "POST",
+        &format!("/api/cards/{}/generate-plan", card_id),
+        None,
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", resp);
+    let session_id = h.card(&card_id).await.ai_session_id.unwrap();
+    let message_path = format!("/session/{}/message", session_id);
+    for _ in 0..200 {
+        if !h.mock.requests_to("POST", &message_path).is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    let prompts = h.mock.requests_to("POST", &message_path);
+    assert_eq!(prompts.len(), 1);
+    assert_eq!(prompts[0].body["model"]["providerID"], "anthropic");
+    assert_eq!(prompts[0].body["model"]["modelID"], "claude-opus");
+
+    h.queue_card(&card_id).await;
+    h.start_queue_processor();
+    h.wait_for_card(&card_id, "dispatched", |c| c.stage == "in_progress")
+        .await;
+    let prompts = h.mock.requests_to("POST", &message_path);
+    assert_eq!(prompts.len(), 2);
+    assert_eq!(prompts[1].body["model"]["providerID"], "openai");
+    assert_eq!(prompts[1].body["model"]["modelID"], "gpt-5");
+
+    let (status, resp) = common::make_request(
+        h.app.clone(),
+        "GET",
+        "/api/opencode/models",
+        None,
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", resp);
+    let models: Vec<Value> = serde_json::from_str(&resp).unwrap();
+    assert_eq!(models.len(), 3);
+    assert!(models
+        .iter()
+        .any(|m| m["id"] == "anthropic/claude-sonnet" && m["is_default"] == true));
+}
+
+async fn ai_reviews(h: &Harness, card_id: &str) -> Vec<Value> {
+    let (status, resp) = common::make_request(
+        h.app.clone(),
+        "GET",
+        &format!("/api/cards/{}/ai-reviews", card_id),
+        None,
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", resp);
+    serde_json::from_str(&resp).unwrap()
+}
+
+async fn wait_for_ai_reviews<F>(h: &Harness, card_id: &str, what: &str, predicate: F) -> Vec<Value>
+where
+    F: Fn(&[Value]) -> bool,
+{
+    for _ in 0..200 {
+        let reviews = ai_reviews(h, card_id).await;
+        if predicate(&reviews) {
+            return reviews;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    panic!("Timed out waiting for AI reviews: {}", what);
+}
+
+#[tokio::test]
+async fn test_ai_review_sends_card_back_with_findings() {
+    let mut h = Harness::new().await;
+    let repo = TempDir::new().unwrap();
+    init_repo(repo.path());
+    h.start_relay().await;
+
+    let card_id = h.create_card("Reviewed work", "todo", ".").await;
+    let board_id = h.card(&card_id).await.board_id.unwrap();
+    let (status, _) = common::make_request(
+        h.app.clone(),
+        "PUT",
+        &format!("/api/boards/{}/settings", board_id),
+        Some(json!({ "ai_review_agent": " " }).to_string()),
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    set_board_settings(
+        &h,
+        &board_id,
+        json!({
+            "codebase_path": repo.path().to_string_lossy(),
+            "ai_review_enabled": true,
+            "review_model": "openai/gpt-5",
+        }),
+    )
+    .await;
+
+    h.queue_card(&card_id).await;
+    h.start_queue_processor();
+    let card = h
+        .wait_for_card(&card_id, "dispatched", |c| c.stage == "in_progress")
+        .await;
+    let session_id = card.ai_session_id.clone().unwrap();
+    let worktree = std::path::Path::new(&card.worktree_path);
+    std::fs::write(worktree.join("feature.rs"), "fn feature() {}\n").unwrap();
+    git(worktree, &["add", "."]);
+    git(worktree, &["commit", "-m", "feature"]);
+
+    h.mock.emit("session.idle", json!({ "sessionID": session_id }));
+    let reviews = wait_for_ai_reviews(&h, &card_id, "reviewer dispatched", |r| {
+        r.len() == 1 && r[0]["session_id"].is_string()
+    })
+    .await;
+    assert_eq!(reviews[0]["status"], "running");
+    assert_eq!(reviews[0]["agent"], "oracle");
+    let reviewer_session = reviews[0]["session_id"].as_str().unwrap().to_string();
+    assert_ne!(reviewer_session, session_id);
+    let reviewer_path = format!("/session/{}/message", reviewer_session);
+    for _ in 0..200 {
+        if !h.mock.requests_to("POST", &reviewer_path).is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    let prompts = h.mock.requests_to("POST", &reviewer_path);
+    assert_eq!(prompts.len(), 1);
+    assert_eq!(prompts[0].body["agent"], "oracle");
+    assert_eq!(prompts[0].body["model"]["modelID"], "gpt-5");
+    let prompt = prompts[0].body["parts"][0]["text"].as_str().unwrap();
+    assert!(prompt.contains("feature.rs"), "{}", prompt);
+    assert!(prompt.contains("kanban_submit_review"));
+    assert!(prompt.contains(&format!("session_id \"{}\"", reviewer_session)), "{}", prompt);
+
+    let submit_url = format!("/api/cards/{}/ai-reviews", card_id);
+    // Only the reviewer's own session may submit the review.
+    for session in [json!(session_id), json!(null)] {
+        let (status, _) = common::make_request(
+            h.app.clone(),
+            "POST",
+            &submit_url,
+            Some(json!({ "session_id": session, "verdict": "approve" }).to_string()),
+            Some(&h.token),
+        )
+        .await;
+        assert!(status.is_client_error(), "{}", status);
+    }
+    assert_eq!(ai_reviews(&h, &card_id).await[0]["status"], "running");
+    let (status, _) = common::make_request(
+        h.app.clone(),
+        "POST",
+        &submit_url,
+        Some(json!({ "session_id": reviewer_session, "verdict": "request_changes", "findings": [
+            { "file": "feature.rs", "line": 1, "severity": "fatal", "message": "x" },
+        ] }).to_string()),
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, resp) = common::make_request(
+        h.app.clone(),
+        "POST",
+        &submit_url,
+        Some(json!({
+            "session_id": reviewer_session,
+            "verdict": "request_changes",
+            "summary": "Untested",
+            "findings": [
+                { "file": "feature.rs", "line": 1, "severity": "error", "message": "feature() has no test" },
+                { "file": "README.md", "severity": "info", "message": "Document the feature" },
+            ],
+        }).to_string()),
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", resp);
+    let review: Value = serde_json::from_str(&resp).unwrap();
+    assert_eq!(review["status"], "changes_requested");
+    assert_eq!(review["findings"].as_array().unwrap().len(), 2);
+    assert_eq!(review["findings"][1]["line"], Value::Null);
+
+    let card = h.card(&card_id).await;
+    assert_eq!(card.stage, "in_progress");
+    assert_eq!(card.ai_session_id.as_deref(), Some(session_id.as_str()));
+    let message_path = format!("/session/{}/message", session_id);
+    for _ in 0..200 {
+        if h.mock.requests_to("POST", &message_path).len() == 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    let prompts = h.mock.requests_to("POST", &message_path);
+    assert_eq!(prompts.len(), 2);
+    let feedback = prompts[1].body["parts"][0]["text"].as_str().unwrap();
+    assert!(feedback.contains("- [error] feature.rs:1 — feature() has no test"), "{}", feedback);
+    assert!(prompts[1].body.get("agent").is_none());
+
+    // Findings are not comments.
+    let (_, resp) = common::make_request(
+        h.app.clone(),
+        "GET",
+        &format!("/api/cards/{}/comments", card_id),
+        None,
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(serde_json::from_str::<Vec<Value>>(&resp).unwrap().len(), 0);
+
+    // The second round's reviewer stops without a verdict.
+    h.mock.emit("session.idle", json!({ "sessionID": session_id }));
+    let reviews = wait_for_ai_reviews(&h, &card_id, "second round", |r| {
+        r.len() == 2 && r[1]["session_id"].is_string()
+    })
+    .await;
+    assert_eq!(reviews[1]["round"], 2);
+    h.mock.emit("session.idle", json!({ "sessionID": reviews[1]["session_id"] }));
+    let reviews = wait_for_ai_reviews(&h, &card_id, "second round failed", |r| {
+        r[1]["status"] == "failed"
+    })
+    .await;
+    assert!(reviews[1]["failure_reason"].as_str().unwrap().contains("without submitting"));
+    assert_eq!(h.card(&card_id).await.stage, "review");
+
+    let (status, _) = common::make_request(
+        h.app.clone(),
+        "POST",
+        &submit_url,
+        Some(json!({ "session_id": reviews[1]["session_id"], "verdict": "approve" }).to_string()),
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // With no agent on the card or the reviewer, both are OpenCode's default.
+    sqlx::query("UPDATE board_settings SET ai_review_agent = '', ai_review_max_rounds = 5 WHERE board_id = ?")
+        .bind(&board_id)
+        .execute(&h.pool)
+        .await
+        .unwrap();
+    let review = AiReviewService::start(&h.pool, &h.sse_tx, &reqwest::Client::new(), &h.mock.url, &card_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(review.status, "failed");
+    assert!(review.failure_reason.unwrap().contains("must differ"));
+}
+
+#[tokio::test]
+async fn test_agent_resolves_merge_conflicts_in_its_worktree() {
+    let mut h = Harness::new().await;
+    let repo = TempDir::new().unwrap();
+    init_repo(repo.path());
+    h.start_relay().await;
+
+    let card_id = h.create_card("Conflicting work", "todo", ".").await;
+    let board_id = h.card(&card_id).await.board_id.unwrap();
+    set_board_settings(
+        &h,
+        &board_id,
+        json!({ "codebase_path": repo.path().to_string_lossy() }),
+    )
+    .await;
+
+    h.queue_card(&card_id).await;
+    h.start_queue_processor();
+    let card = h
+        .wait_for_card(&card_id, "dispatched", |c| c.stage == "in_progress")
+        .await;
+    let session_id = card.ai_session_id.clone().unwrap();
+    let worktree = std::path::PathBuf::from(&card.worktree_path);
+    std::fs::write(worktree.join("README.md"), "hello from the card\n").unwrap();
+    git(&worktree, &["commit", "-am", "card change"]);
+    std::fs::write(repo.path().join("README.md"), "hello from main\n").unwrap();
+    git(repo.path(), &["commit", "-am", "main change"]);
+
+    h.mock.emit("session.idle", json!({ "sessionID": session_id }));
+    h.wait_for_card(&card_id, "in review", |c| c.stage == "review").await;
+    git(repo.path(), &["checkout", "-b", "wip"]);
+
+    let merge_url = format!("/api/cards/{}/merge", card_id);
+    let (status, resp) =
+        common::make_request(h.app.clone(), "POST", &merge_url, None, Some(&h.token)).await;
+    assert_eq!(status, StatusCode::OK, "{}", resp);
+    assert_eq!(serde_json::from_str::<Value>(&resp).unwrap()["success"], false);
+
+    let (status, resp) = common::make_request(
+        h.app.clone(),
+        "POST",
+        &format!("/api/cards/{}/ai-resolve-conflicts", card_id),
+        None,
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", resp);
+    let result: Value = serde_json::from_str(&resp).unwrap();
+    assert_eq!(result["success"], false);
+    assert_eq!(result["conflicts"], json!(["README.md"]));
+    // The main checkout is back to normal; the merge now lives in the worktree.
+    assert!(!repo.path().join(".git/MERGE_HEAD").exists());
+    assert_eq!(git(repo.path(), &["rev-parse", "--abbrev-ref", "HEAD"]).trim(), "wip");
+    assert_eq!(std::fs::read_to_string(repo.path().join("README.md")).unwrap(), "hello from main\n");
+    assert!(std::fs::read_to_string(worktree.join("README.md")).unwrap().contains("<<<<<<<"));
+
+    let card = h.card(&card_id).await;
+    assert_eq!(card.stage, "in_progress");
+    assert_eq!(card.ai_status, "dispatched");
+    let message_path = format!("/session/{}/message", session_id);
+    for _ in 0..200 {
+        if h.mock.requests_to("POST", &message_path).len() == 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    let prompts = h.mock.requests_to("POST", &message_path);
+    assert_eq!(prompts.len(), 2);
+    let prompt = prompts[1].body["parts"][0]["text"].as_str().unwrap();
+    assert!(prompt.contains("### README.md (both-modified)"), "{}", prompt);
+    assert!(prompt.contains("hello from the card"), "{}", prompt);
+    assert!(prompt.contains("hello from main"), "{}", prompt);
+    assert!(prompt.contains("git commit --no-edit"), "{}", prompt);
+
+    // The agent resolves and concludes the merge, then goes idle.
+    std::fs::write(worktree.join("README.md"), "hello from main and the card\n").unwrap();
+    git(&worktree, &["add", "README.md"]);
+    git(&worktree, &["commit", "--no-edit"]);
+    h.mock.emit("session.idle", json!({ "sessionID": session_id }));
+    h.wait_for_card(&card_id, "back in review", |c| c.stage == "review").await;
+
+    let (status, resp) = common::make_request(
+        h.app.clone(),
+        "GET",
+        &format!("/api/cards/{}/diff", card_id),
+        None,
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", resp);
+    let diff: Value = serde_json::from_str(&resp).unwrap();
+    let readme = diff["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|file| file["path"] == "README.md")
+        .and_then(|file| file["diff"].as_str())
+        .unwrap();
+    assert!(readme.contains("-hello from main"), "{}", readme);
+    assert!(readme.contains("+hello from main and the card"), "{}", readme);
+
+    let (status, resp) =
+        common::make_request(h.app.clone(), "POST", &merge_url, None, Some(&h.token)).await;
+    assert_eq!(status, StatusCode::OK, "{}", resp);
+    assert_eq!(serde_json::from_str::<Value>(&resp).unwrap()["success"], true);
+    assert_eq!(
+        std::fs::read_to_string(repo.path().join("README.md")).unwrap(),
+        "hello from main and the card\n"
+    );
+    assert_eq!(h.card(&card_id).await.stage, "done");
+}
+
+#[tokio::test]
+async fn test_dispatch_marks_card_failed_when_session_creation_fails() {
+    let mut h = Harness::new().await;
+    let workdir = TempDir::new().unwrap();
+    h.mock.fail_session_create(true);
+
+    let card_id = h
+        .create_card("Failing dispatch", "todo", &workdir.path().to_string_lossy())
+        .await;
+    sqlx::query("UPDATE cards SET ai_status = 'queued' WHERE id = ?")
+        .bind(&card_id)
+        .execute(&h.pool)
+        .await
+        .unwrap();
+
+    h.start_queue_processor();
+
+    let card = h
+        .wait_for_card(&card_id, "failed", |c| c.ai_status == "failed")
+        .await;
+    assert_eq!(card.stage, "todo");
+    assert!(card.ai_session_id.is_none());
+}
+
+#[tokio::test]
+async fn test_relay_correlates_subagent_sessions() {
+    let mut h = Harness::new().await;
+    h.start_relay().await;
+
+    let card_id = h.create_card("Sub-agent parent", "todo", ".").await;
+    h.attach_session(&card_id, "ses_parent", "in_progress", "working")
+        .await;
+
+    h.mock.emit(
+        "session.created",
+        json!({
+            "info": {
+                "id": "ses_child",
+                "parentID": "ses_parent",
+                "title": "Explore the codebase (@explore subagent)",
+            }
+        }),
+    );
+
+    let mut mapping: Option<(String, Option<String>)> = None;
+    for _ in 0..200 {
+        mapping = sqlx::query_as(
+            "SELECT card_id, agent_type FROM session_mappings WHERE child_session_id = 'ses_child'",
+        )
+        .fetch_optional(&h.pool)
+        .await
+        .unwrap();
+        if mapping.is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    let (mapped_card, agent_type) = mapping.expect("sub-agent session was mapped");
+    assert_eq!(mapped_card, card_id);
+    assert_eq!(agent_type.as_deref(), Some("explore"));
+
+    h.mock.emit("session.idle", json!({ "sessionID": "ses_child" }));
+
+    let mut logged = false;
+    for _ in 0..200 {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM agent_logs WHERE card_id = ? AND session_id = 'ses_child' AND content LIKE '%Sub-agent completed'",
+        )
+        .bind(&card_id)
+        .fetch_one(&h.pool)
+        .await
+        .unwrap();
+        if count > 0 {
+            logged = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    assert!(logged, "sub-agent completion was not logged");
+
+    // A sub-agent going idle must not complete the parent card.
+    let card = h.card(&card_id).await;
+    assert_eq!(card.stage, "in_progress");
+    assert_eq!(card.ai_status, "working");
+}
+
+#[tokio::test]
+async fn test_relay_coalesces_progress_until_terminal_state() {
+    let mut h = Harness::new().await;
+    // Long enough that only the terminal flush can write progress.
+    h.coalesce_window = Duration::from_secs(30);
+    h.start_relay().await;
+
+    let card_id = h.create_card("Chatty agent", "todo", ".").await;
+    h.attach_session(&card_id, "ses_chatty", "in_progress", "working")
+        .await;
+    let mut events = h.sse_tx.subscribe();
+
+    for done in 0..5 {
+        let todos: Vec<Value> = (0..5)
+            .map(|i| {
+                let status = if i < done { "completed" } else if i == done { "in_progress" } else { "pending" };
+                json!({ "content": format!("step {}", i), "status": status })
+            })
+            .collect();
+        h.mock.emit(
+            "todo.updated",
+            json!({ "sessionID": "ses_chatty", "todos": todos }),
+        );
+    }
+
+    let mut logged = 0;
+    for _ in 0..200 {
+        logged = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM agent_logs WHERE card_id = ? AND event_type = 'todo.updated'",
+        )
+        .bind(&card_id)
+        .fetch_one(&h.pool)
+        .await
+        .unwrap();
+        if logged == 5 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    assert_eq!(logged, 5, "every todo update is still logged");
+    let progress: Value = serde_json::from_str(&h.card(&card_id).await.ai_progress).unwrap();
+    assert!(progress.get("total_todos").is_none(), "progress written before the window closed");
+    // Progress written by someone else meanwhile survives the flush.
+    sqlx::query("UPDATE cards SET ai_progress = json_set(ai_progress, '$.review_status', 'pending') WHERE id = ?")
+        .bind(&card_id)
+        .execute(&h.pool)
+        .await
+        .unwrap();
+
+    h.mock.emit("session.idle", json!({ "sessionID": "ses_chatty" }));
+    let card = h
+        .wait_for_card(&card_id, "in review", |c| c.stage == "review")
+        .await;
+    let progress: Value = serde_json::from_str(&card.ai_progress).unwrap();
+    assert_eq!(progress["total_todos"], 5);
+    assert_eq!(progress["completed_todos"], 4);
+    assert_eq!(progress["current_task"], "step 4");
+    assert_eq!(progress["review_status"], "pending");
+
+    let mut status_events = Vec::new();
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    while status_events.is_empty() {
+        let event = tokio::time::timeout_at(deadline, events.recv())
+            .await
+            .expect("status change was broadcast")
+            .unwrap();
+        if event.event_type == "aiStatusChanged" {
+            status_events.push(event);
+        }
+    }
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    while let Ok(event) = events.try_recv() {
+        if event.event_type == "aiStatusChanged" {
+            status_events.push(event);
+        }
+    }
+    assert_eq!(status_events.len(), 1, "only the terminal transition is broadcast");
+    let payload: Value = serde_json::from_str(&status_events[0].message).unwrap();
+    assert_eq!(payload["progress"]["completed_todos"], 4);
+}
+
+fn history_message(id: &str, role: &str, created: i64, parts: Vec<Value>) -> Value {
+    let mut info = json!({
+        "id": id,
+        "role": role,
+        "agent": "build",
+        "time": { "created": created, "completed": created + 500 },
+    });
+    if role == "assistant" {
+        info["finish"] = json!("stop");
+    }
+    json!({ "info": info, "parts": parts })
+}
+
+async fn log_count(pool: &SqlitePool, card_id: &str) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM agent_logs WHERE card_id = ?")
+        .bind(card_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_backfill_recovers_session_history_idempotently() {
+    let mut h = Harness::new().await;
+    h.start_relay().await;
+
+    let card_id = h.create_card("Restarted run", "todo", ".").await;
+    h.attach_session(&card_id, "ses_parent", "in_progress", "working")
+        .await;
+    h.mock.insert_session("ses_child", "idle");
+    sqlx::query(
+        "INSERT INTO session_mappings (child_session_id, card_id, parent_session_id, agent_type, description, created_at) VALUES ('ses_child', ?, 'ses_parent', 'explore', '', ?)",
+    )
+    .bind(&card_id)
+    .bind(chrono::Utc::now().to_rfc3339())
+    .execute(&h.pool)
+    .await
+    .unwrap();
+
+    // The relay saw the first message finish before the "restart".
+    h.mock.emit(
+        "message.updated",
+        json!({ "info": { "id": "msg_1", "sessionID": "ses_parent", "agent": "build", "finish": "stop" } }),
+    );
+    for _ in 0..200 {
+        if log_count(&h.pool, &card_id).await == 1 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    assert_eq!(log_count(&h.pool, &card_id).await, 1);
+
+    h.mock.set_session_messages(
+        "ses_parent",
+        vec![
+            history_message("msg_0", "user", 1_767_225_600_000, vec![
+                json!({ "id": "prt_u1", "type": "text", "text": "Implement the plan" }),
+            ]),
+            history_message("msg_1", "assistant", 1_767_225_601_000, vec![
+                json!({ "id": "prt_a1", "type": "text", "text": "Reading the code" }),
+                json!({ "id": "prt_t1", "type": "tool", "tool": "bash", "state": { "status": "completed", "title": "cargo test" } }),
+            ]),
+        ],
+    );
+    h.mock.set_session_messages(
+        "ses_child",
+        vec![history_message("msg_c1", "assistant", 1_767_225_602_000, vec![
+            json!({ "id": "prt_c1", "type": "text", "text": "Found the module" }),
+        ])],
+    );
+
+    let uri = format!("/api/cards/{}/logs/backfill", card_id);
+    let (status, resp) =
+        common::make_request(h.app.clone(), "POST", &uri, None, Some(&h.token)).await;
+    assert_eq!(status, StatusCode::OK, "Backfill failed: {}", resp);
+    let report: Value = serde_json::from_str(&resp).unwrap();
+    assert_eq!(report["sessions"], 2);
+    // Only the child's finished message is new: msg_1's completion was
+    // already logged by the relay, and prompts and parts are never logged.
+    assert_eq!(report["inserted"], 1);
+
+    let (status, resp) =
+        common::make_request(h.app.clone(), "POST", &uri, None, Some(&h.token)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(serde_json::from_str::<Value>(&resp).unwrap()["inserted"], 0);
+    assert_eq!(log_count(&h.pool, &card_id).await, 2);
+
+    let part_rows: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM agent_logs WHERE card_id = ? AND event_type != 'message.updated'",
+    )
+    .bind(&card_id)
+    .fetch_one(&h.pool)
+    .await
+    .unwrap();
+    assert_eq!(part_rows, 0, "backfill must only write rows the relay writes");
+
+    let child_rows: Vec<(String, String)> = sqlx::query_as(
+        "SELECT content, metadata FROM agent_logs WHERE card_id = ? AND session_id = 'ses_child' ORDER BY created_at ASC",
+    )
+    .bind(&card_id)
+    .fetch_all(&h.pool)
+    .await
+    .unwrap();
+    assert_eq!(child_rows.len(), 1);
+    assert_eq!(child_rows[0].0, "↳ explore | Agent explore finished (stop)");
+    let metadata: Value = serde_json::from_str(&child_rows[0].1).unwrap();
+    assert_eq!(metadata["info"]["id"], "msg_c1");
+    assert_eq!(metadata["_subagent"], true);
+    assert_eq!(metadata["_agent_type"], "explore");
+    assert!(metadata.get("_backfilled").is_none());
+}
+
+#[tokio::test]
+async fn test_relay_reconnect_backfills_missed_messages() {
+    let mut h = Harness::new().await;
+    h.start_relay().await;
+
+    let card_id = h.create_card("Dropped stream", "todo", ".").await;
+    h.attach_session(&card_id, "ses_gap", "in_progress", "working")
+        .await;
+    h.mock.set_session_messages(
+        "ses_gap",
+        vec![history_message("msg_gap", "assistant", 1_767_225_600_000, vec![
+            json!({ "id": "prt_gap", "type": "text", "text": "Work done while disconnected" }),
+        ])],
+    );
+    assert_eq!(log_count(&h.pool, &card_id).await, 0);
+
+    h.mock.drop_subscribers();
+
+    let mut count = 0;
+    for _ in 0..200 {
+        count = log_count(&h.pool, &card_id).await;
+        if count == 1 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    assert_eq!(count, 1, "reconnect should backfill the finished message");
+}
+
+#[tokio::test]
+async fn test_relay_redacts_secrets_before_storing_logs() {
+    let mut h = Harness::new().await;
+    h.start_relay().await;
+
+    let card_id = h.create_card("Leaky agent", "todo", ".").await;
+    let board_id = h.card(&card_id).await.board_id.unwrap();
+    let (status, resp) = common::make_request(
+        h.app.clone(),
+        "PUT",
+        &format!("/api/boards/{}/settings", board_id),
+        Some(json!({ "variables": r#"{"DEPLOY_TOKEN": "hunter2-deploy", "REGION": "eu-west-1"}"# }).to_string()),
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", resp);
+    h.attach_session(&card_id, "ses_leak", "in_progress", "working")
+        .await;
+
+    let github_token = format!("ghp_{}", "a".repeat(36));
+    h.mock.emit(
+        "message.part.delta",
+        json!({
+            "part": { "sessionID": "ses_leak" },
+            "delta": format!("export DEPLOY_TOKEN=hunter2-deploy GH={} in eu-west-1", github_token),
+        }),
+    );
+
+    let mut row: Option<(String, String)> = None;
+    for _ in 0..200 {
+        row = sqlx::query_as("SELECT content, metadata FROM agent_logs WHERE card_id = ?")
+            .bind(&card_id)
+            .fetch_optional(&h.pool)
+            .await
+            .unwrap();
+        if row.is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    let (content, metadata) = row.expect("delta was logged");
+    assert_eq!(
+        content,
+        "export DEPLOY_TOKEN=[REDACTED:DEPLOY_TOKEN] GH=[REDACTED:github_token] in eu-west-1"
+    );
+    assert!(!metadata.contains("hunter2-deploy"));
+    assert!(!metadata.contains(&github_token));
+
+    // The relay caches the board's redactor; adding a secret must refresh it.
+    let (status, resp) = common::make_request(
+        h.app.clone(),
+        "PUT",
+        &format!("/api/boards/{}/settings/secrets/STRIPE", board_id),
+        Some(json!({ "value": "sk_live_rotated" }).to_string()),
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", resp);
+    h.mock.emit(
+        "message.part.delta",
+        json!({ "part": { "sessionID": "ses_leak" }, "delta": "charged with sk_live_rotated" }),
+    );
+
+    let mut rows: Vec<(String,)> = Vec::new();
+    for _ in 0..200 {
+        rows = sqlx::query_as("SELECT content FROM agent_logs WHERE card_id = ? ORDER BY created_at ASC")
+            .bind(&card_id)
+            .fetch_all(&h.pool)
+            .await
+            .unwrap();
+        if rows.len() == 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[1].0, "charged with [REDACTED:STRIPE]");
+}
+
+#[tokio::test]
+async fn test_question_answer_round_trip_on_active_session() {
+    let h = Harness::new().await;
+
+    let card_id = h.create_card("Needs input", "todo", ".").await;
+    h.attach_session(&card_id, "ses_question", "in_progress", "working")
+        .await;
+
+    let (status, resp) = common::make_request(
+        h.app.clone(),
+        "POST",
+        &format!("/api/cards/{}/questions", card_id),
+        Some(
+            json!({
+                "question": "Which database?",
+                "options": "[\"sqlite\", \"postgres\"]",
+            })
+            .to_string(),
+        ),
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "Create question failed: {}", resp);
+    let question: Value = serde_json::from_str(&resp).unwrap();
+    assert_eq!(question["session_id"], "ses_question");
+    assert_eq!(h.card(&card_id).await.ai_status, "waiting_input");
+
+    let (status, resp) = common::make_request(
+        h.app.clone(),
+        "POST",
+        &format!(
+            "/api/cards/{}/questions/{}/answer",
+            card_id,
+            question["id"].as_str().unwrap()
+        ),
+        Some(json!({ "answer": ["sqlite"] }).to_string()),
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "Answer failed: {}", resp);
+    assert_eq!(h.card(&card_id).await.ai_status, "working");
+}
+
+#[tokio::test]
+async fn test_human_message_reaches_running_session_and_flags_card() {
+    let h = Harness::new().await;
+    let workdir = TempDir::new().unwrap();
+    let card_id = h
+        .create_card("Chat with agent", "todo", &workdir.path().to_string_lossy())
+        .await;
+    h.attach_session(&card_id, "ses_chat", "in_progress", "completed")
+        .await;
+
+    let (status, resp) = common::make_request(
+        h.app.clone(),
+        "POST",
+        &format!("/api/cards/{}/messages", card_id),
+        Some(json!({ "content": "also update the README" }).to_string()),
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", resp);
+    let log: Value = serde_json::from_str(&resp).unwrap();
+    assert_eq!(log["event_type"], "human.message");
+    assert_eq!(log["agent"], "human");
+    assert_eq!(log["content"], "also update the README");
+
+    let card = h.card(&card_id).await;
+    assert_eq!(card.ai_status, "working");
+    assert!(card.human_message_pending);
+
+    for _ in 0..100 {
+        if !h.mock.requests_to("POST", "/session/ses_chat/message").is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let prompts = h.mock.requests_to("POST", "/session/ses_chat/message");
+    assert_eq!(prompts.len(), 1);
+    let text = prompts[0].body["parts"][0]["text"].as_str().unwrap();
+    assert!(text.contains("also update the README"), "{}", text);
+
+    let (status, resp) = common::make_request(
+        h.app.clone(),
+        "GET",
+        &format!("/api/cards/{}/messages", card_id),
+        None,
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let listed: Value = serde_json::from_str(&resp).unwrap();
+    assert_eq!(listed["pending"], true);
+    assert_eq!(listed["messages"].as_array().unwrap().len(), 1);
+
+    let first_id = listed["messages"][0]["id"].as_str().unwrap().to_string();
+
+    // A message that arrives after the agent read the list keeps the flag.
+    let (status, resp) = common::make_request(
+        h.app.clone(),
+        "POST",
+        &format!("/api/cards/{}/messages", card_id),
+        Some(json!({ "content": "and the CHANGELOG" }).to_string()),
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", resp);
+    let second_id = serde_json::from_str::<Value>(&resp).unwrap()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let ack_uri = format!("/api/cards/{}/messages/ack", card_id);
+    let ack = |last_seen_id: String| {
+        common::make_request(
+            h.app.clone(),
+            "POST",
+            &ack_uri,
+            Some(json!({ "last_seen_id": last_seen_id }).to_string()),
+            Some(&h.token),
+        )
+    };
+    let (status, resp) = ack(first_id).await;
+    assert_eq!(status, StatusCode::OK, "{}", resp);
+    assert_eq!(serde_json::from_str::<Value>(&resp).unwrap()["pending"], true);
+    assert!(h.card(&card_id).await.human_message_pending);
+
+    let (status, resp) = ack(second_id).await;
+    assert_eq!(status, StatusCode::OK, "{}", resp);
+    assert_eq!(serde_json::from_str::<Value>(&resp).unwrap()["pending"], false);
+    assert!(!h.card(&card_id).await.human_message_pending);
+
+    let (status, _) = ack("missing".to_string()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Review and Done cards keep their status when messaged.
+    sqlx::query("UPDATE cards SET stage = 'review', ai_status = 'completed' WHERE id = ?")
+        .bind(&card_id)
+        .execute(&h.pool)
+        .await
+        .unwrap();
+    let (status, resp) = common::make_request(
+        h.app.clone(),
+        "POST",
+        &format!("/api/cards/{}/messages", card_id),
+        Some(json!({ "content": "one more thing" }).to_string()),
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", resp);
+    let card = h.card(&card_id).await;
+    assert_eq!(card.ai_status, "completed");
+    assert!(card.human_message_pending);
+
+    let other = h.create_card("No session", "todo", ".").await;
+    let (status, _) = common::make_request(
+        h.app.clone(),
+        "POST",
+        &format!("/api/cards/{}/messages", other),
+        Some(json!({ "content": "hello?" }).to_string()),
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_stuck_card_recovery_uses_session_state() {
+    let mut h = Harness::new().await;
+    let stale = (chrono::Utc::now() - chrono::Duration::minutes(30)).to_rfc3339();
+
+    let idle_card = h.create_card("Stuck idle", "todo", ".").await;
+    h.attach_session(&idle_card, "ses_idle", "in_progress", "working")
+        .await;
+    h.mock.set_session_status("ses_idle", "idle");
+
+    let tool_card = h.create_card("Stuck on tool", "todo", ".").await;
+    h.attach_session(&tool_card, "ses_tool", "in_progress", "working")
+        .await;
+    h.mock.set_session_status("ses_tool", "idle");
+    h.mock.set_session_messages(
+        "ses_tool",
+        vec![json!({
+            "info": { "role": "assistant" },
+            "parts": [{ "type": "tool", "tool": "bash", "state": { "status": "running" } }],
+        })],
+    );
+
+    let gone_card = h.create_card("Session gone", "todo", ".").await;
+    h.attach_session(&gone_card, "ses_gone", "in_progress", "working")
+        .await;
+    h.mock.remove_session("ses_gone");
+
+    sqlx::query("UPDATE cards SET updated_at = ? WHERE id IN (?, ?, ?)")
+        .bind(&stale)
+        .bind(&idle_card)
+        .bind(&tool_card)
+        .bind(&gone_card)
+        .execute(&h.pool)
+        .await
+        .unwrap();
+
+    h.start_queue_processor();
+
+    let card = h
+        .wait_for_card(&idle_card, "failed", |c| c.ai_status == "failed")
+        .await;
+    let progress: Value = serde_json::from_str(&card.ai_progress).unwrap();
+    assert!(progress["failure_reason"]
+        .as_str()
+        .unwrap()
+        .starts_with("Session status: idle"));
+
+    let card = h
+        .wait_for_card(&gone_card, "failed", |c| c.ai_status == "failed")
+        .await;
+    let progress: Value = serde_json::from_str(&card.ai_progress).unwrap();
+    assert!(progress["failure_reason"]
+        .as_str()
+        .unwrap()
+        .starts_with("OpenCode session not found"));
+
+    let card = h
+        .wait_for_card(&tool_card, "waiting", |c| c.ai_status == "waiting")
+        .await;
+    let progress: Value = serde_json::from_str(&card.ai_progress).unwrap();
+    assert_eq!(progress["waiting_tool"], "bash");
+}
+
+#[tokio::test]
+async fn test_queue_dispatches_to_least_loaded_endpoint() {
+    let mut h = Harness::with_extra_endpoints(1).await;
+    let workdir = TempDir::new().unwrap();
+    let primary = h.mock.url.clone();
+    let secondary = h.extra_mocks[0].url.clone();
+
+    sqlx::query(
+        "INSERT INTO board_settings (board_id, ai_concurrency, created_at, updated_at) VALUES ('default', 0, datetime('now'), datetime('now'))
+         ON CONFLICT(board_id) DO UPDATE SET ai_concurrency = 0",
+    )
+    .execute(&h.pool)
+    .await
+    .unwrap();
+
+    let busy_card = h.create_card("Already running", "todo", ".").await;
+    h.attach_session(&busy_card, "ses_busy", "in_progress", "working")
+        .await;
+    sqlx::query("UPDATE cards SET opencode_url = ? WHERE id = ?")
+        .bind(&primary)
+        .bind(&busy_card)
+        .execute(&h.pool)
+        .await
+        .unwrap();
+
+    let card_id = h
+        .create_card("Balanced", "todo", &workdir.path().to_string_lossy())
+        .await;
+    h.queue_card(&card_id).await;
+
+    h.start_queue_processor();
+
+    let card = h
+        .wait_for_card(&card_id, "dispatched", |c| c.ai_session_id.is_some())
+        .await;
+    assert_eq!(card.opencode_url, secondary);
+    assert_eq!(h.extra_mocks[0].requests_to("POST", "/session").len(), 1);
+    assert!(h.mock.requests_to("POST", "/session").is_empty());
+}
+
+#[tokio::test]
+async fn test_queue_keeps_planning_session_endpoint_affinity() {
+    let mut h = Harness::with_extra_endpoints(1).await;
+    let workdir = TempDir::new().unwrap();
+    let secondary = h.extra_mocks[0].url.clone();
+
+    let card_id = h
+        .create_card("Planned elsewhere", "todo", &workdir.path().to_string_lossy())
+        .await;
+    h.extra_mocks[0].insert_session("ses_planned", "idle");
+    sqlx::query(
+        "UPDATE cards SET ai_session_id = 'ses_planned', opencode_url = ?, stage = 'todo', ai_status = 'queued' WHERE id = ?",
+    )
+    .bind(&secondary)
+    .bind(&card_id)
+    .execute(&h.pool)
+    .await
+    .unwrap();
+
+    h.start_queue_processor();
+
+    let card = h
+        .wait_for_card(&card_id, "dispatched", |c| c.stage == "in_progress")
+        .await;
+    assert_eq!(card.ai_session_id.as_deref(), Some("ses_planned"));
+    assert_eq!(card.opencode_url, secondary);
+    assert_eq!(
+        h.extra_mocks[0]
+            .requests_to("POST", "/session/ses_planned/message")
+            .len(),
+        1
+    );
+    assert!(h.mock.requests().iter().all(|r| r.method != "POST"));
+}
+
+#[tokio::test]
+async fn test_health_reports_endpoints_without_managed_process() {
+    let h = Harness::new().await;
+
+    // The public summary names no endpoint; details need a signed-in user.
+    let (status, body) = common::make_request(h.app.clone(), "GET", "/health", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let health: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(health["opencode"]["endpoints"], 1);
+    assert!(!body.contains(h.mock.url.as_str()), "{}", body);
+    let (status, _) =
+        common::make_request(h.app.clone(), "GET", "/api/opencode/status", None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, body) = common::make_request(
+        h.app.clone(),
+        "GET",
+        "/api/opencode/status",
+        None,
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let endpoints: Value = serde_json::from_str(&body).unwrap();
+    let endpoints = endpoints.as_array().unwrap();
+    assert_eq!(endpoints.len(), 1);
+    assert_eq!(endpoints[0]["url"], h.mock.url.as_str());
+    assert!(endpoints[0]["process"].is_null());
+
+    let (status, _) = common::make_request(
+        h.app.clone(),
+        "GET",
+        "/api/opencode/logs",
+        None,
+        Some(&h.token),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_supervisor_adopts_running_server_as_external() {
+    let h = Harness::new().await;
+    let logs = TempDir::new().unwrap();
+
+    let supervisor = OpencodeSupervisor::new(
+        OpencodeManager::new(&h.mock.url),
+        &logs.path().to_string_lossy(),
+    );
+    supervisor.start().await;
+    h.opencode.attach_supervisor(supervisor.clone());
+
+    let (_, body) = common::make_request(
+        h.app.clone(),
+        "GET",
+        "/api/opencode/status",
+        None,
+        Some(&h.token),
+    )
+    .await;
+    let endpoints: Value = serde_json::from_str(&body).unwrap();
+    let process = &endpoints[0]["process"];
+    assert_eq!(process["state"], "external");
+    assert!(process["pid"].is_null());
+    assert_eq!(process["restarts"], 0);
+
+    // Adopted servers are never stopped by the backend.
+    h.opencode.shutdown_processes().await;
+    assert_eq!(supervisor.status().await.state, ProcessState::External);
+
+    let uri = format!("/api/opencode/logs?endpoint={}", h.mock.url);
+    let (status, _) = common::make_request(h.app.clone(), "GET", &uri, None, Some(&h.token)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn test_rotating_log_file_keeps_bounded_generations() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("opencode.log");
+    let mut log = RotatingLogFile::new(&path, 64, 2);
+
+    for i in 0..20 {
+        log.write_line(&format!("line {:02} ................", i)).unwrap();
+    }
+
+    let current = std::fs::read_to_string(&path).unwrap();
+    assert!(current.len() <= 64);
+    assert!(current.contains("line 19"));
+    assert!(dir.path().join("opencode.log.1").exists());
+    assert!(dir.path().join("opencode.log.2").exists());
+    assert!(!dir.path().join("opencode.log.3").exists());
+
+    let previous = std::fs::read_to_string(dir.path().join("opencode.log.1")).unwrap();
+    assert!(!previous.contains("line 19"));
+}
@@ -330,7 +330,7 @@ const Sidebar: React.FC<SidebarProps> = ({
   const [settingsBoard, setSettingsBoard] = useState<Board | null>(null);
 
   const activeSessionCount = (Object.values(columns).flat() || []).filter((card) =>
-    card.ai_status === "dispatched" || card.ai_status === "working" || card.ai_status === "planning"
+    card.ai_status === "setting_up" || card.ai_status === "dispatched" || card.ai_status === "working" || card.ai_status === "planning"
   ).length;
 
   const userInitial = (getUser()?.username?.trim().charAt(0) || "").toUpperCase();
@@ -101,6 +101,7 @@ const statusPulse = keyframes`
 const AI_STATUS_CONFIG: Record<string, { color: string; bg: string; pulse: boolean }> = {
   planning: { color: "#42a5f5", bg: "rgba(66, 165, 245, 0.15)", pulse: true },
   working: { color: "#ffa726", bg: "rgba(255, 167, 38, 0.15)", pulse: true },
+  setting_up: { color: "#ffee58", bg: "rgba(255, 238, 88, 0.15)", pulse: true },
   dispatched: { color: "#ffee58", bg: "rgba(255, 238, 88, 0.15)", pulse: false },
   completed: { color: "#66bb6a", bg: "rgba(102, 187, 106, 0.15)", pulse: false },
   failed: { color: "#ef5350", bg: "rgba(239, 83, 80, 0.15)", pulse: false },
@@ -319,6 +320,12 @@ export const AgentLogViewer: React.FC<AgentLogViewerProps> = ({
             const newLog: AgentLog = message.log;
             setLogs((prev) => [...prev, newLog]);
             requestAnimationFrame(scrollToBottom);
+          } else if (message.type === "agentLogsBackfilled") {
+            // Recovered history lands in the middle of the log; reload it.
+            api
+              .getCardLogs(cardId)
+              .then((data) => setLogs(data))
+              .catch((err) => console.error("Failed to reload agent logs:", err));
           }
         } catch (err) {
           console.error("Failed to parse WebSocket message:", err);
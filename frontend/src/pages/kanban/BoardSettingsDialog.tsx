@@ -34,6 +34,7 @@ import {
 } from "@mui/icons-material";
 import type { BoardSettings, UpdateBoardSettingsRequest } from "../../types/kanban";
 import { api } from "../../services/api";
+import SecretVariablesEditor from "./SecretVariablesEditor";
 import { useDispatch } from "react-redux";
 import { updateBoard, setAutoDetectStatus } from "../../store/slices/kanbanSlice";
 import type { AppDispatch } from "../../redux/store";
@@ -755,7 +756,7 @@ export const BoardSettingsDialog: React.FC<BoardSettingsDialogProps> = ({
                   <Box>
                     <FieldLabel
                       label="Environment Variables"
-                      tooltip="Key-value pairs that AI should know about your project. These are NOT secrets - don't put passwords here. Use this for URLs, port numbers, service names, or any project constants that AI needs when writing code or configs."
+                      tooltip="Key-value pairs for your project, written to a .env file in each card's worktree. These are NOT secrets - use Secret Variables for passwords. Use this for URLs, port numbers, service names, or any project constants that AI needs when writing code or configs."
                     />
                     <TextField
                       fullWidth
@@ -770,6 +771,14 @@ export const BoardSettingsDialog: React.FC<BoardSettingsDialogProps> = ({
                       InputLabelProps={{ shrink: true }}
                     />
                   </Box>
+
+                  <Box>
+                    <FieldLabel
+                      label="Secret Variables"
+                      tooltip="API keys and passwords agents need. Values are encrypted at rest, never shown again after saving, and redacted from agent logs."
+                    />
+                    <SecretVariablesEditor boardId={boardId} />
+                  </Box>
                 </Box>
               )}
 
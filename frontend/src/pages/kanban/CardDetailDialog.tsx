@@ -249,6 +249,7 @@ export const CardDetailDialog: React.FC<CardDetailDialogProps> = ({ open, onClos
   const [workingDir, setWorkingDir] = useState(".");
   const [newSubtaskByPhase, setNewSubtaskByPhase] = useState<Record<string, string>>({});
   const [newComment, setNewComment] = useState("");
+  const [agentMessage, setAgentMessage] = useState("");
   const [linkedDocs, setLinkedDocs] = useState("");
   const [subtasks, setSubtasks] = useState<SubtaskItem[]>([]);
   const [newPhaseName, setNewPhaseName] = useState("");
@@ -640,6 +641,17 @@ export const CardDetailDialog: React.FC<CardDetailDialogProps> = ({ open, onClos
     }
   };
 
+  const handleSendAgentMessage = async () => {
+    if (!agentMessage.trim() || !card) return;
+    try {
+      await api.sendCardMessage(card.id, agentMessage);
+      setAgentMessage("");
+    } catch (err) {
+      console.error("Failed to message agent:", err);
+      setErrorMessage("Failed to send message to the agent.");
+    }
+  };
+
   const handleDelete = () => {
     if (!card) return;
     if (confirm("Are you sure you want to delete this card?")) {
@@ -658,7 +670,7 @@ export const CardDetailDialog: React.FC<CardDetailDialogProps> = ({ open, onClos
      }
    };
 
-  const isAiActive = card?.ai_status && ["planning", "dispatched", "working", "queued", "waiting_input", "waiting"].includes(card.ai_status);
+  const isAiActive = card?.ai_status && ["planning", "setting_up", "dispatched", "working", "queued", "waiting_input", "waiting"].includes(card.ai_status);
   const isAiFailed = card?.ai_status === "failed";
   const isAiWaiting = card?.ai_status === "waiting";
 
@@ -1406,6 +1418,26 @@ export const CardDetailDialog: React.FC<CardDetailDialogProps> = ({ open, onClos
           <Section>
             <SectionTitle variant="subtitle1">AI Agent Logs</SectionTitle>
             <AgentLogViewer cardId={card.id} sessionId={card.ai_session_id} aiStatus={card.ai_status} />
+            {card.ai_session_id && (
+              <Box sx={{ display: 'flex', gap: 1, mt: 1 }}>
+                <TextField
+                  size="small"
+                  fullWidth
+                  placeholder="Message the agent, e.g. also update the README"
+                  value={agentMessage}
+                  onChange={(e) => setAgentMessage(e.target.value)}
+                  onKeyDown={(e) => {
+                    if (e.key === "Enter" && !e.shiftKey) {
+                      e.preventDefault();
+                      handleSendAgentMessage();
+                    }
+                  }}
+                />
+                <Button size="small" onClick={handleSendAgentMessage} disabled={!agentMessage.trim()}>
+                  Send
+                </Button>
+              </Box>
+            )}
           </Section>
         )}
 
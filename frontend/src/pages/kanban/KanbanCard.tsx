@@ -170,7 +170,7 @@ export const KanbanCard: React.FC<KanbanCardProps> = ({
   };
 
   const isAiActive =
-    aiStatus === "planning" || aiStatus === "working" || aiStatus === "dispatched" || aiStatus === "waiting_input" || aiStatus === "waiting";
+    aiStatus === "planning" || aiStatus === "setting_up" || aiStatus === "working" || aiStatus === "dispatched" || aiStatus === "waiting_input" || aiStatus === "waiting";
 
   const handleClick = (e: React.MouseEvent) => {
     console.log('Card clicked!');
@@ -0,0 +1,80 @@
+import React, { useEffect, useState } from "react";
+import { Alert, Box, Button, IconButton, TextField, Typography } from "@mui/material";
+import { Delete as DeleteIcon } from "@mui/icons-material";
+import type { SecretVariable } from "../../types/kanban";
+import { api } from "../../services/api";
+
+interface SecretVariablesEditorProps {
+  boardId: string;
+}
+
+/** Secret values are encrypted server-side and never read back. */
+const SecretVariablesEditor: React.FC<SecretVariablesEditorProps> = ({ boardId }) => {
+  const [secrets, setSecrets] = useState<SecretVariable[]>([]);
+  const [name, setName] = useState("");
+  const [value, setValue] = useState("");
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    api
+      .getSecretVariables(boardId)
+      .then(setSecrets)
+      .catch(() => setSecrets([]));
+  }, [boardId]);
+
+  const handleSave = async () => {
+    setError(null);
+    try {
+      const saved = await api.setSecretVariable(boardId, name.trim(), value);
+      setSecrets((prev) =>
+        [...prev.filter((secret) => secret.name !== saved.name), saved].sort((a, b) => a.name.localeCompare(b.name))
+      );
+      setName("");
+      setValue("");
+    } catch (e) {
+      setError(e instanceof Error ? e.message : "Failed to save secret");
+    }
+  };
+
+  const handleDelete = async (secretName: string) => {
+    setError(null);
+    try {
+      await api.deleteSecretVariable(boardId, secretName);
+      setSecrets((prev) => prev.filter((secret) => secret.name !== secretName));
+    } catch (e) {
+      setError(e instanceof Error ? e.message : "Failed to delete secret");
+    }
+  };
+
+  return (
+    <Box sx={{ display: "grid", gap: 1 }}>
+      {error && <Alert severity="error">{error}</Alert>}
+      {secrets.map((secret) => (
+        <Box key={secret.name} sx={{ display: "flex", alignItems: "center", gap: 1 }}>
+          <Typography variant="body2" sx={{ fontFamily: "monospace", flex: 1 }}>
+            {secret.name} = {secret.value}
+          </Typography>
+          <IconButton size="small" aria-label={`Delete ${secret.name}`} onClick={() => handleDelete(secret.name)}>
+            <DeleteIcon fontSize="small" />
+          </IconButton>
+        </Box>
+      ))}
+      <Box sx={{ display: "flex", gap: 1 }}>
+        <TextField size="small" label="Name" value={name} onChange={(event) => setName(event.target.value)} />
+        <TextField
+          size="small"
+          type="password"
+          label="Value"
+          value={value}
+          onChange={(event) => setValue(event.target.value)}
+          sx={{ flex: 1 }}
+        />
+        <Button variant="outlined" disabled={!name.trim() || !value} onClick={handleSave}>
+          Save
+        </Button>
+      </Box>
+    </Box>
+  );
+};
+
+export default SecretVariablesEditor;
@@ -18,11 +18,21 @@ import type {
   AgentActivityResponse,
   CardVersion,
   BoardSettings,
+  AiReview,
   UpdateBoardSettingsRequest,
+  SecretVariable,
+  OpencodeModel,
   DiffResult,
+  CardDiff,
+  ReviewThread,
+  CardRun,
+  CardAttempt,
   ConflictDetail,
   FileResolution,
   MergeResult,
+  FileSelection,
+  PartialMergeResult,
+  MergeQueueEntry,
   Notification,
 } from "../types/kanban";
 import { API_BASE_URL } from "../constants";
@@ -198,6 +208,36 @@ export const api = {
 
   getCardLogs: (cardId: string) => fetchAPI<AgentLog[]>(`/api/cards/${cardId}/logs`),
 
+  sendCardMessage: (cardId: string, content: string) =>
+    fetchAPI<AgentLog>(`/api/cards/${cardId}/messages`, {
+      method: "POST",
+      body: JSON.stringify({ content }),
+    }),
+
+  getCardAttempts: (cardId: string) => fetchAPI<CardAttempt[]>(`/api/cards/${cardId}/attempts`),
+
+  createCardAttempts: (cardId: string, attempts: { agent?: string; model?: string }[]) =>
+    fetchAPI<CardAttempt[]>(`/api/cards/${cardId}/attempts`, {
+      method: "POST",
+      body: JSON.stringify({ attempts }),
+    }),
+
+  getCardAttemptDiff: (cardId: string, attemptId: string) =>
+    fetchAPI<DiffResult>(`/api/cards/${cardId}/attempts/${attemptId}/diff`),
+
+  selectCardAttempt: (cardId: string, attemptId: string) =>
+    fetchAPI<Card>(`/api/cards/${cardId}/attempts/${attemptId}/select`, { method: "POST" }),
+
+  getAiReviews: (cardId: string) => fetchAPI<AiReview[]>(`/api/cards/${cardId}/ai-reviews`),
+
+  getCardRuns: (cardId: string) => fetchAPI<CardRun[]>(`/api/cards/${cardId}/runs`),
+
+  getCardRunDiff: (cardId: string, runId: string) =>
+    fetchAPI<DiffResult>(`/api/cards/${cardId}/runs/${runId}/diff`),
+
+  getCardRunLogs: (cardId: string, runId: string) =>
+    fetchAPI<AgentLog[]>(`/api/cards/${cardId}/runs/${runId}/logs`),
+
   getAgentActivity: (cardId: string) =>
     fetchAPI<AgentActivityResponse>(`/api/cards/${cardId}/agent-activity`),
 
@@ -209,11 +249,52 @@ export const api = {
     }),
 
   getCardDiff: (cardId: string) =>
-    fetchAPI<DiffResult>(`/api/cards/${cardId}/diff`),
+    fetchAPI<CardDiff>(`/api/cards/${cardId}/diff`),
+
+  getReviewThreads: (cardId: string, unresolvedOnly = false) =>
+    fetchAPI<ReviewThread[]>(
+      `/api/cards/${cardId}/review-threads${unresolvedOnly ? "?unresolved=true" : ""}`
+    ),
+
+  createReviewThread: (
+    cardId: string,
+    thread: { file?: string; line?: number; side?: "new" | "old"; commit?: string; content: string }
+  ) =>
+    fetchAPI<ReviewThread>(`/api/cards/${cardId}/review-threads`, {
+      method: "POST",
+      body: JSON.stringify(thread),
+    }),
+
+  replyReviewThread: (threadId: string, content: string) =>
+    fetchAPI<ReviewThread>(`/api/review-threads/${threadId}/comments`, {
+      method: "POST",
+      body: JSON.stringify({ content }),
+    }),
+
+  setReviewThreadResolved: (threadId: string, resolved: boolean) =>
+    fetchAPI<ReviewThread>(`/api/review-threads/${threadId}`, {
+      method: "PATCH",
+      body: JSON.stringify({ resolved }),
+    }),
 
   mergeCard: (cardId: string) =>
     fetchAPI<MergeResult>(`/api/cards/${cardId}/merge`, { method: "POST" }),
 
+  mergeCardPartial: (cardId: string, files: FileSelection[], message?: string) =>
+    fetchAPI<PartialMergeResult>(`/api/cards/${cardId}/merge-partial`, {
+      method: "POST",
+      body: JSON.stringify({ files, message }),
+    }),
+
+  enqueueMerge: (cardId: string) =>
+    fetchAPI<MergeQueueEntry>(`/api/cards/${cardId}/merge-queue`, { method: "POST" }),
+
+  cancelQueuedMerge: (cardId: string) =>
+    fetchAPI<MergeQueueEntry>(`/api/cards/${cardId}/merge-queue`, { method: "DELETE" }),
+
+  getMergeQueue: (boardId: string) =>
+    fetchAPI<MergeQueueEntry[]>(`/api/boards/${boardId}/merge-queue`),
+
   getConflicts: (cardId: string) =>
     fetchAPI<ConflictDetail>(`/api/cards/${cardId}/conflicts`),
 
@@ -229,6 +310,9 @@ export const api = {
   abortMerge: (cardId: string) =>
     fetchAPI<void>(`/api/cards/${cardId}/abort-merge`, { method: "POST" }),
 
+  aiResolveConflicts: (cardId: string) =>
+    fetchAPI<MergeResult>(`/api/cards/${cardId}/ai-resolve-conflicts`, { method: "POST" }),
+
   createCardPr: (cardId: string, title?: string, body?: string) =>
     fetchAPI<{ url: string }>(`/api/cards/${cardId}/create-pr`, {
       method: "POST",
@@ -276,6 +360,22 @@ export const api = {
       body: JSON.stringify(data),
     }),
 
+  getOpencodeModels: () => fetchAPI<OpencodeModel[]>("/api/opencode/models"),
+
+  getSecretVariables: (boardId: string) =>
+    fetchAPI<SecretVariable[]>(`/api/boards/${boardId}/settings/secrets`),
+
+  setSecretVariable: (boardId: string, name: string, value: string) =>
+    fetchAPI<SecretVariable>(`/api/boards/${boardId}/settings/secrets/${encodeURIComponent(name)}`, {
+      method: "PUT",
+      body: JSON.stringify({ value }),
+    }),
+
+  deleteSecretVariable: (boardId: string, name: string) =>
+    fetchAPI<void>(`/api/boards/${boardId}/settings/secrets/${encodeURIComponent(name)}`, {
+      method: "DELETE",
+    }),
+
   autoDetectBoardSettings: (boardId: string, codebasePath: string) =>
     fetchAPI<{ status: string; session_id?: string }>(`/api/boards/${boardId}/settings/auto-detect`, {
       method: "POST",
@@ -10,6 +10,7 @@ import {
   removeCardSubtaskFromWS,
   updateCardCommentFromWS,
   removeCardCommentFromWS,
+  fetchBoard,
 } from "../store/slices/kanbanSlice";
 import { addNotificationFromWS } from "../store/slices/notificationSlice";
 import type { AppDispatch, RootState } from "../redux/store";
@@ -19,6 +20,8 @@ import { API_BASE_URL } from "../constants";
 /** Shape of WebSocket event payloads from the backend. */
 interface WsEventData {
   type: string;
+  /** Position in the server's event log; absent on control messages. */
+  seq?: number;
   card?: Card & { board_id?: string };
   card_id?: string;
   board?: Board;
@@ -42,6 +45,7 @@ interface WsEventData {
   notification?: Notification;
   conflict_count?: number;
   remaining_count?: number;
+  latest_seq?: number;
 }
 
 const WS_DEBUG = import.meta.env.DEV;
@@ -56,6 +60,8 @@ export class WebSocketManager {
   private maxReconnectDelay = 30000;
   private reconnectTimer: ReturnType<typeof setTimeout> | null = null;
   private isManualDisconnect = false;
+  /** Last event seq received; sent as `since` on reconnect to replay missed events. */
+  private lastSeq: number | null = null;
 
   constructor(dispatch: AppDispatch, getState: () => RootState) {
     this.dispatch = dispatch;
@@ -81,7 +87,8 @@ export class WebSocketManager {
 
     const apiUrl = new URL(API_BASE_URL);
     const protocol = apiUrl.protocol === "https:" ? "wss:" : "ws:";
-    const wsUrl = `${protocol}//${apiUrl.host}/ws/events`;
+    const since = this.lastSeq !== null ? `?since=${this.lastSeq}` : "";
+    const wsUrl = `${protocol}//${apiUrl.host}/ws/events${since}`;
 
     wsLog("[WS] Connecting to", wsUrl);
     this.ws = new WebSocket(wsUrl);
@@ -132,6 +139,10 @@ export class WebSocketManager {
     wsLog("[WS] Event:", event.type);
     const eventType = event.type;
 
+    if (typeof event.seq === "number") {
+      this.lastSeq = event.seq;
+    }
+
     switch (eventType) {
       case "cardCreated":
         if (event.card) {
@@ -303,6 +314,13 @@ export class WebSocketManager {
         wsLog("[WS] Server confirmed connection");
         break;
 
+      case "resyncRequired": {
+        wsLog("[WS] Missed events are no longer retained, reloading board");
+        const activeBoardId = this.getState().kanban.activeBoardId;
+        this.dispatch(fetchBoard(activeBoardId ?? undefined));
+        break;
+      }
+
       case "mergeConflictDetected":
         console.log("[WS] Merge conflict detected:", event.card_id, event.conflict_count);
         break;
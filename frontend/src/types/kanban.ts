@@ -44,6 +44,10 @@ export interface Card {
   updated_at: string;
   ai_agent: string | null;
   board_id?: string;
+  human_message_pending?: boolean;
+  /** `provider/model` overrides of the board defaults. */
+  planning_model?: string | null;
+  implementation_model?: string | null;
   subtask_count: number;
   subtask_completed: number;
   label_count: number;
@@ -76,6 +80,161 @@ export interface DiffResult {
   };
 }
 
+export interface ReviewThreadComment {
+  id: string;
+  thread_id: string;
+  author: string;
+  user_id: string | null;
+  content: string;
+  created_at: string;
+}
+
+export interface ReviewThread {
+  id: string;
+  card_id: string;
+  /** Null for feedback on the change as a whole. */
+  file: string | null;
+  /** Null for feedback on the whole file. */
+  line: number | null;
+  side: "new" | "old";
+  /** Branch head the thread was written against. */
+  commit_sha: string;
+  resolved: boolean;
+  resolved_by: string | null;
+  resolved_at: string | null;
+  created_at: string;
+  updated_at: string;
+  comments: ReviewThreadComment[];
+}
+
+export interface CardDiff extends DiffResult {
+  head_commit: string;
+  review_threads: ReviewThread[];
+}
+
+export interface FileSelection {
+  path: string;
+  /** 0-based indices of the `@@` hunks in the file's diff; whole file when omitted. */
+  hunks?: number[];
+}
+
+export interface PartialMergeResult {
+  commit: string;
+  accepted_files: string[];
+  remaining: DiffResult;
+}
+
+export type MergeQueueStatus = "queued" | "rebasing" | "verifying" | "merging" | "merged" | "failed" | "cancelled";
+
+export interface MergeQueueEntry {
+  id: string;
+  card_id: string;
+  board_id: string | null;
+  codebase_path: string;
+  status: MergeQueueStatus;
+  failure_reason: string | null;
+  verification: string | null;
+  merge_commit: string | null;
+  enqueued_by: string | null;
+  created_at: string;
+  started_at: string | null;
+  finished_at: string | null;
+  position: number | null;
+}
+
+export interface CardRun {
+  id: string;
+  card_id: string;
+  run_number: number;
+  kind: "plan" | "work" | "retry" | "resume";
+  session_id: string;
+  agent: string | null;
+  plan_path: string | null;
+  plan_version_id: string | null;
+  branch_name: string;
+  worktree_path: string;
+  base_commit: string;
+  head_commit: string;
+  started_at: string;
+  ended_at: string | null;
+  outcome: string;
+  files_changed: number;
+  additions: number;
+  deletions: number;
+  input_tokens: number;
+  output_tokens: number;
+  reasoning_tokens: number;
+  cache_read_tokens: number;
+  cache_write_tokens: number;
+  cost: number;
+}
+
+export interface AiReviewFinding {
+  id: string;
+  review_id: string;
+  card_id: string;
+  file: string;
+  /** Line in the new version of the file; null for the whole file. */
+  line: number | null;
+  severity: "info" | "warning" | "error";
+  message: string;
+  position: number;
+  created_at: string;
+}
+
+export interface AiReview {
+  id: string;
+  card_id: string;
+  round: number;
+  agent: string;
+  model: string | null;
+  session_id: string | null;
+  opencode_url: string;
+  status: "running" | "approved" | "changes_requested" | "failed";
+  summary: string;
+  failure_reason: string | null;
+  created_at: string;
+  finished_at: string | null;
+  findings: AiReviewFinding[];
+}
+
+export interface CardAttempt {
+  id: string;
+  card_id: string;
+  attempt_number: number;
+  agent: string | null;
+  model: string | null;
+  status:
+    | "queued"
+    | "setting_up"
+    | "dispatched"
+    | "working"
+    | "completed"
+    | "failed"
+    | "cancelled"
+    | "selected"
+    | "discarded";
+  session_id: string | null;
+  opencode_url: string;
+  branch_name: string;
+  worktree_path: string;
+  base_commit: string;
+  files_changed: number;
+  additions: number;
+  deletions: number;
+  /** Verification JSON; empty when no verify hooks ran. */
+  verification: string;
+  failure_reason: string | null;
+  created_at: string;
+  updated_at: string;
+  finished_at: string | null;
+}
+
+export interface AttemptVerification {
+  passed: boolean;
+  steps: { step: string; passed: boolean; output: string; duration_ms: number }[];
+}
+
 export interface MergeResult {
   success: boolean;
   message: string;
@@ -114,6 +273,8 @@ export interface UpdateCardRequest {
   working_directory?: string;
   linked_documents?: string;
   ai_agent?: string | null;
+  planning_model?: string;
+  implementation_model?: string;
 }
 
 export interface MoveCardRequest {
@@ -199,6 +360,18 @@ export interface CardVersion {
 export interface BoardSettings {
   board_id: string;
   ai_concurrency: number;
+  context_budget_chars: number;
+  log_retention_days: number;
+  log_done_summary_only: boolean;
+  log_archive: boolean;
+  worktree_hooks: string;
+  planning_model: string;
+  implementation_model: string;
+  auto_detect_model: string;
+  ai_review_enabled: boolean;
+  ai_review_agent: string;
+  review_model: string;
+  ai_review_max_rounds: number;
   codebase_path: string;
   github_repo: string;
   auto_detect_status: string;
@@ -218,7 +391,25 @@ export interface BoardSettings {
   updated_at: string;
 }
 
-export type NotificationType = "card_stage_changed" | "ai_completed" | "ai_question_pending" | "review_requested" | "ai_error";
+export interface OpencodeModel {
+  /** `provider/model`, as stored in settings and card overrides. */
+  id: string;
+  provider_id: string;
+  provider_name: string;
+  model_id: string;
+  name: string;
+  is_default: boolean;
+}
+
+export interface SecretVariable {
+  name: string;
+  /** Always masked; secret values are write-only. */
+  value: string;
+  created_at: string;
+  updated_at: string;
+}
+
+export type NotificationType = "card_stage_changed" | "ai_completed" | "ai_question_pending" | "review_requested" | "ai_error" | "merge_failed";
 
 export interface Notification {
   id: string;
@@ -234,6 +425,18 @@ export interface Notification {
 
 export interface UpdateBoardSettingsRequest {
   ai_concurrency?: number | string;
+  context_budget_chars?: number;
+  log_retention_days?: number;
+  log_done_summary_only?: boolean;
+  log_archive?: boolean;
+  worktree_hooks?: string;
+  planning_model?: string;
+  implementation_model?: string;
+  auto_detect_model?: string;
+  ai_review_enabled?: boolean;
+  ai_review_agent?: string;
+  review_model?: string;
+  ai_review_max_rounds?: number;
   codebase_path?: string;
   github_repo?: string;
   context_markdown?: string;